@@ -1,25 +1,4 @@
 use dragonfly::{FontManager, WebContext};
-// use raqote::*; // graphics library
-
-/*
-fn render_webcontext(ctx: &WebContext) {
-    let nodes = ctx.layout.nodes();
-
-    let mut dt = DrawTarget::new(512, 512);
-    for node in nodes {
-        dt.fill_rect(
-            node.pos.x,
-            node.pos.y,
-            100.0,
-            100.0,
-            &Source::Solid(SolidSource::from_unpremultiplied_argb(255, 255, 0, 0)),
-            &DrawOptions::new(),
-        )
-    }
-
-    dt.write_png("out.png").unwrap();
-}
-*/
 
 #[tokio::main]
 async fn main() {
@@ -32,4 +11,8 @@ async fn main() {
     )
     .unwrap();
     ctx.load().await.unwrap();
+
+    // render the page with the software rasterizer
+    let png = ctx.render_to_png_bytes().unwrap();
+    std::fs::write("out.png", png).unwrap();
 }
@@ -0,0 +1,406 @@
+use crate::{collapse_whitespace, BoxKind, DOMNodeKind, Layout, Rect, WebContext};
+use indextree::NodeId;
+use std::fmt;
+use strum_macros::Display;
+
+/// Role of an [`AccessNode`], from the `role` attribute of its element or its tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Role {
+    /// Root of the tree
+    Document,
+    /// Heading with its level, from 1 to 6
+    Heading(u8),
+    Link,
+    Button,
+    TextBox,
+    CheckBox,
+    Radio,
+    ComboBox,
+    Img,
+    List,
+    ListItem,
+    Table,
+    Row,
+    Cell,
+    ColumnHeader,
+    /// Element without a more specific role that is focusable or labelled
+    Generic,
+    /// Text of the page
+    Text,
+}
+
+impl Role {
+    /// Whether elements with this role are named by their content when they're not labelled.
+    pub fn is_named_by_content(self) -> bool {
+        matches!(
+            self,
+            Self::Heading(_)
+                | Self::Link
+                | Self::Button
+                | Self::CheckBox
+                | Self::Radio
+                | Self::Cell
+                | Self::ColumnHeader
+        )
+    }
+}
+
+/// A node of the accessibility tree of a page (see [`Layout::accessibility_tree`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessNode {
+    /// Element (or text node) of the layout tree
+    pub node: NodeId,
+    pub role: Role,
+    /// Accessible name
+    pub name: String,
+    pub disabled: bool,
+    /// Checked state of checkboxes and radio buttons, `None` for other roles
+    pub checked: Option<bool>,
+    /// Top-left corner and size of the bounding box of the node
+    pub rect: Rect,
+    pub children: Vec<AccessNode>,
+}
+
+impl AccessNode {
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.role, indent = depth * 2)?;
+        if let Role::Heading(level) = self.role {
+            write!(f, " level={level}")?;
+        }
+        if !self.name.is_empty() {
+            write!(f, " {:?}", self.name)?;
+        }
+        match self.checked {
+            Some(true) => write!(f, " checked")?,
+            Some(false) => write!(f, " unchecked")?,
+            None => (),
+        }
+        if self.disabled {
+            write!(f, " disabled")?;
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line per node, indented by depth: the role, level of headings, name and states.
+impl fmt::Display for AccessNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Bounding box of rectangles, ignoring empty ones if some are not empty.
+fn bounding_rect(rects: &[Rect]) -> Rect {
+    let first = rects.first().copied().unwrap_or_default();
+    rects.iter().fold(first, |bounds, rect| bounds.union(rect))
+}
+
+impl Layout {
+    /// Build a simplified accessibility tree of the laid-out document, for screen-reader
+    /// style audits and keyboard navigation.
+    ///
+    /// Elements get a role from their `role` attribute or their tag. Elements without a
+    /// role (`div`, `span`, `p`...) are left out and their children take their place, unless
+    /// they're focusable or have an `aria-label`. Hidden elements (`display: none` or
+    /// `aria-hidden="true"`) are left out with their subtree.
+    ///
+    /// Names are computed from `aria-label`, then the `alt` of images, then the labels of
+    /// controls, then the content of elements named by their content (see
+    /// [`Role::is_named_by_content`]), whose text is not repeated as child nodes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = r#"<title>Shop</title>
+    /// <h1>Fruits</h1>
+    /// <button aria-label="Close dialog">X</button>
+    /// <img src="apple.png" alt="An apple">
+    /// <div aria-hidden="true"><p>Decoration</p></div>
+    /// <ul><li><a href="/pear">Pears <img src="pear.png" alt="(new)"></a></li></ul>
+    /// <label><input type="checkbox" checked> Subscribe</label>
+    /// <input disabled aria-label="Search">
+    /// <p>Thanks</p>"#;
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// assert_eq!(
+    ///     layout.accessibility_tree().to_string(),
+    ///     r#"document "Shop"
+    ///   heading level=1 "Fruits"
+    ///   button "Close dialog"
+    ///     text "X"
+    ///   img "An apple"
+    ///   list
+    ///     listitem
+    ///       link "Pears (new)"
+    ///         img "(new)"
+    ///   checkbox "Subscribe" checked
+    ///   text "Subscribe"
+    ///   textbox "Search" disabled
+    ///   text "Thanks"
+    /// "#
+    /// );
+    ///
+    /// // names collapse whitespace like the laid-out text, Unicode spaces included
+    /// let html = "<title>\u{3000}Shop\n\tname </title><button aria-label=' Close\u{2003} dialog\u{3000}'>X</button>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let tree = layout.accessibility_tree();
+    /// assert_eq!(tree.name, "Shop name");
+    /// assert_eq!(tree.children[0].name, "Close dialog");
+    /// ```
+    pub fn accessibility_tree(&self) -> AccessNode {
+        let root = self.root_id();
+        let title = root
+            .descendants(&self.arena)
+            .find(|&id| self.arena[id].get().name() == "title")
+            .map(|id| {
+                collapse_whitespace(&self.text_content(id))
+                    .trim()
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let node = self.arena[root].get();
+        let mut tree = AccessNode {
+            node: root,
+            role: Role::Document,
+            name: title,
+            disabled: false,
+            checked: None,
+            rect: node.border_rect,
+            children: vec![],
+        };
+        for child in root.children(&self.arena) {
+            self.access_nodes(child, false, &mut tree.children);
+        }
+        tree
+    }
+
+    /// Add the accessibility nodes of a subtree to `out`. Text is left out if it's part of
+    /// the name of the parent node (`in_name`).
+    fn access_nodes(&self, id: NodeId, in_name: bool, out: &mut Vec<AccessNode>) {
+        let node = self.arena[id].get();
+        match &node.kind {
+            DOMNodeKind::Text(text) => {
+                let text = collapse_whitespace(text).trim().to_string();
+                if !in_name && !text.is_empty() {
+                    out.push(AccessNode {
+                        node: id,
+                        role: Role::Text,
+                        name: text,
+                        disabled: false,
+                        checked: None,
+                        rect: bounding_rect(&self.region_rects(id)),
+                        children: vec![],
+                    });
+                }
+                return;
+            }
+            DOMNodeKind::Comment(_) => return,
+            DOMNodeKind::Element(_) => (),
+        }
+        if self.is_access_hidden(id) {
+            return;
+        }
+
+        let Some(role) = self.role(id) else {
+            for child in id.children(&self.arena) {
+                self.access_nodes(child, in_name, out);
+            }
+            return;
+        };
+        let (name, from_content) = self.accessible_name(id, role);
+        let checked = match role {
+            Role::CheckBox | Role::Radio => Some(match node.name() {
+                "input" => node.has_bool_attr("checked"),
+                _ => node
+                    .attr("aria-checked")
+                    .is_some_and(|checked| checked.trim().eq_ignore_ascii_case("true")),
+            }),
+            _ => None,
+        };
+        let aria_disabled = node
+            .attr("aria-disabled")
+            .is_some_and(|disabled| disabled.trim().eq_ignore_ascii_case("true"));
+        let mut access = AccessNode {
+            node: id,
+            role,
+            name,
+            disabled: self.is_disabled(id).unwrap_or(false) || aria_disabled,
+            checked,
+            rect: bounding_rect(&self.region_rects(id)),
+            children: vec![],
+        };
+        for child in id.children(&self.arena) {
+            self.access_nodes(child, from_content, &mut access.children);
+        }
+        out.push(access);
+    }
+
+    /// Whether an element is left out of the accessibility tree with its subtree.
+    fn is_access_hidden(&self, id: NodeId) -> bool {
+        let aria_hidden = self.arena[id]
+            .get()
+            .attr("aria-hidden")
+            .is_some_and(|hidden| hidden.trim().eq_ignore_ascii_case("true"));
+        aria_hidden || self.box_kind(id) == BoxKind::Hidden
+    }
+
+    /// Role of an element, `None` if it's left out of the tree (but not its children).
+    fn role(&self, id: NodeId) -> Option<Role> {
+        let node = self.arena[id].get();
+        let heading_level = node
+            .name()
+            .strip_prefix('h')
+            .and_then(|level| level.parse::<u8>().ok())
+            .filter(|level| (1..=6).contains(level));
+
+        // the first role of the attribute that is supported
+        for role in node
+            .attr("role")
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+        {
+            let role = match role.to_ascii_lowercase().as_str() {
+                "heading" => Role::Heading(
+                    node.attr("aria-level")
+                        .and_then(|level| level.trim().parse().ok())
+                        .filter(|level| (1..=6).contains(level))
+                        .or(heading_level)
+                        .unwrap_or(2),
+                ),
+                "link" => Role::Link,
+                "button" => Role::Button,
+                "textbox" | "searchbox" => Role::TextBox,
+                "checkbox" | "switch" => Role::CheckBox,
+                "radio" => Role::Radio,
+                "combobox" => Role::ComboBox,
+                "img" | "image" => Role::Img,
+                "list" => Role::List,
+                "listitem" => Role::ListItem,
+                "table" | "grid" => Role::Table,
+                "row" => Role::Row,
+                "cell" | "gridcell" => Role::Cell,
+                "columnheader" => Role::ColumnHeader,
+                "generic" => Role::Generic,
+                "none" | "presentation" => return None,
+                _ => continue,
+            };
+            return Some(role);
+        }
+
+        let role = match node.name() {
+            _ if heading_level.is_some() => Role::Heading(heading_level.unwrap()),
+            "a" | "area" if node.attr("href").is_some() => Role::Link,
+            "button" => Role::Button,
+            "input" => match node.input_type() {
+                "checkbox" => Role::CheckBox,
+                "radio" => Role::Radio,
+                "button" | "submit" | "reset" | "image" => Role::Button,
+                "hidden" => return None,
+                "range" | "color" | "file" => Role::Generic,
+                _ => Role::TextBox,
+            },
+            "textarea" => Role::TextBox,
+            "select" => Role::ComboBox,
+            // images with an empty alt are decorative
+            "img" if node.attr("alt") == Some("") => return None,
+            "img" => Role::Img,
+            "ul" | "ol" | "menu" => Role::List,
+            "li" => Role::ListItem,
+            "table" => Role::Table,
+            "tr" => Role::Row,
+            "td" => Role::Cell,
+            "th" => Role::ColumnHeader,
+            _ if node.attr("aria-label").is_some() || node.attr("tabindex").is_some() => {
+                Role::Generic
+            }
+            _ => return None,
+        };
+        Some(role)
+    }
+
+    /// Accessible name of an element, and whether it was computed from its content.
+    fn accessible_name(&self, id: NodeId, role: Role) -> (String, bool) {
+        let node = self.arena[id].get();
+        let attr = |name: &str| {
+            node.attr(name)
+                .map(|value| collapse_whitespace(value).trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        if let Some(label) = attr("aria-label") {
+            return (label, false);
+        }
+        let is_input = node.name() == "input";
+        if matches!(node.name(), "img" | "area") || (is_input && node.input_type() == "image") {
+            if let Some(alt) = attr("alt") {
+                return (alt, false);
+            }
+        }
+        if matches!(node.name(), "input" | "select" | "textarea" | "button") {
+            let labels: Vec<String> = self
+                .root_id()
+                .descendants(&self.arena)
+                .filter(|&label| {
+                    self.arena[label].get().name() == "label"
+                        && self.labeled_control(label) == Some(id)
+                })
+                .map(|label| self.name_text(label))
+                .filter(|text| !text.is_empty())
+                .collect();
+            if !labels.is_empty() {
+                return (labels.join(" "), false);
+            }
+        }
+        if is_input {
+            match node.input_type() {
+                "submit" => return (attr("value").unwrap_or("Submit".to_string()), false),
+                "reset" => return (attr("value").unwrap_or("Reset".to_string()), false),
+                "button" => return (attr("value").unwrap_or_default(), false),
+                _ => (),
+            }
+        }
+        if role.is_named_by_content() {
+            let text = self.name_text(id);
+            if !text.is_empty() {
+                return (text, true);
+            }
+        }
+        (attr("title").unwrap_or_default(), false)
+    }
+
+    /// Text of an element that is not hidden, with the `alt` of its images.
+    fn name_text(&self, id: NodeId) -> String {
+        let mut parts = vec![];
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.arena[id].get();
+            match &node.kind {
+                DOMNodeKind::Text(text) => parts.push(text.as_str()),
+                DOMNodeKind::Element(_) if self.is_access_hidden(id) => (),
+                DOMNodeKind::Element(_) if node.name() == "img" => {
+                    parts.push(" ");
+                    parts.extend(node.attr("alt"));
+                    parts.push(" ");
+                }
+                DOMNodeKind::Element(_) => {
+                    let len = stack.len();
+                    stack.extend(id.children(&self.arena));
+                    stack[len..].reverse();
+                }
+                DOMNodeKind::Comment(_) => (),
+            }
+        }
+        collapse_whitespace(&parts.concat()).trim().to_string()
+    }
+}
+
+impl WebContext {
+    /// Build the accessibility tree of the page (see [`Layout::accessibility_tree`]).
+    pub fn accessibility_tree(&self) -> AccessNode {
+        self.layout.accessibility_tree()
+    }
+}
@@ -0,0 +1,106 @@
+use crate::DOMNode;
+use indextree::{Arena, NodeId};
+
+/// Builds a document tree in code, without parsing HTML.
+///
+/// Like a parsed document, the tree has a `html` root with a `head` and a `body`,
+/// new nodes are added to the body.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{DocumentBuilder, FontManager, Layout, Viewport};
+/// let document = DocumentBuilder::new()
+///     .element("div")
+///     .attr("class", "card")
+///     .style("color: red")
+///     .child(|b| b.element("p").child(|b| b.text("hello")));
+/// let layout = Layout::compute_from_nodes(document, &mut FontManager::default(), Viewport::default());
+/// assert_eq!(layout.select("div.card > p").len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DocumentBuilder {
+    arena: Arena<DOMNode>,
+    root: NodeId,
+    /// Node that new nodes are appended to
+    parent: NodeId,
+    /// Last element added to the parent, modified by `attr` and `child`
+    last: Option<NodeId>,
+}
+
+impl Default for DocumentBuilder {
+    fn default() -> Self {
+        let mut arena = Arena::new();
+        let root = arena.new_node(DOMNode::root());
+        root.append_value(DOMNode::new("head"), &mut arena);
+        let body = root.append_value(DOMNode::new("body"), &mut arena);
+        Self {
+            arena,
+            root,
+            parent: body,
+            last: None,
+        }
+    }
+}
+
+impl DocumentBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an element to the current parent.
+    pub fn element(mut self, name: &str) -> Self {
+        self.last = Some(
+            self.parent
+                .append_value(DOMNode::new(name), &mut self.arena),
+        );
+        self
+    }
+
+    /// Append a text node to the current parent.
+    pub fn text(mut self, text: &str) -> Self {
+        self.parent
+            .append_value(DOMNode::text_node(text), &mut self.arena);
+        self
+    }
+
+    /// Set an attribute of the last added element.
+    pub fn attr(mut self, name: &str, value: &str) -> Self {
+        match self.last {
+            Some(last) => {
+                if let Some(el) = self.arena[last].get_mut().as_element_mut() {
+                    el.set_attribute(name, value);
+                }
+            }
+            None => log::warn!("no element to set attribute '{name}' on"),
+        }
+        self
+    }
+
+    /// Set the inline style of the last added element.
+    #[inline]
+    pub fn style(self, style: &str) -> Self {
+        self.attr("style", style)
+    }
+
+    /// Add children to the last added element.
+    pub fn child(mut self, build: impl FnOnce(Self) -> Self) -> Self {
+        let Some(last) = self.last else {
+            log::warn!("no element to add children to");
+            return self;
+        };
+        let parent = self.parent;
+        self.parent = last;
+        self.last = None;
+
+        let mut builder = build(self);
+        builder.parent = parent;
+        builder.last = Some(last);
+        builder
+    }
+
+    /// The built node tree and its root node.
+    pub fn finish(self) -> (Arena<DOMNode>, NodeId) {
+        (self.arena, self.root)
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{DfError, DfResult};
+use futures_util::future::{self, Either};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    /// Tasks waiting for the cancellation, by the id of their [`Cancelled`] future
+    wakers: Mutex<Vec<(usize, Waker)>>,
+    next_id: AtomicUsize,
+}
+
+/// Cancels a page load from another task or thread.
+///
+/// Clones share the same state: cancelling one of them cancels all of them.
+/// A token stays cancelled, so a new one is needed for every load.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the operations using this token, waking up the tasks waiting for it.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        for (_, waker) in self.inner.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the token is cancelled.
+    pub async fn cancelled(&self) {
+        Cancelled {
+            inner: &self.inner,
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+        .await
+    }
+
+    /// Returns [`DfError::Cancelled`] if the token was cancelled.
+    pub fn check(&self) -> DfResult<()> {
+        match self.is_cancelled() {
+            true => Err(DfError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Run a future until it completes or the token is cancelled. The future is dropped
+    /// on cancellation, which aborts in-flight requests. The token doesn't keep the waker
+    /// of the task once the future completes.
+    ///
+    /// ```
+    /// use dragonfly::CancellationToken;
+    /// use std::future::Future;
+    /// use std::sync::Arc;
+    /// use std::task::{Context, Poll, Wake, Waker};
+    /// struct Task;
+    /// impl Wake for Task {
+    ///     fn wake(self: Arc<Self>) {}
+    /// }
+    /// let task = Arc::new(Task);
+    /// let waker = Waker::from(task.clone());
+    /// let mut cx = Context::from_waker(&waker);
+    ///
+    /// let token = CancellationToken::new();
+    /// let mut polls = 0;
+    /// let future = std::future::poll_fn(|_| {
+    ///     polls += 1;
+    ///     if polls == 1 { Poll::Pending } else { Poll::Ready(polls) }
+    /// });
+    /// let mut run = std::pin::pin!(token.run(future));
+    /// assert!(run.as_mut().poll(&mut cx).is_pending());
+    /// // the token holds a waker until the future completes
+    /// assert_eq!(Arc::strong_count(&task), 3);
+    /// assert!(matches!(run.as_mut().poll(&mut cx), Poll::Ready(Ok(2))));
+    /// assert_eq!(Arc::strong_count(&task), 2);
+    /// ```
+    pub async fn run<F: Future>(&self, future: F) -> DfResult<F::Output> {
+        self.check()?;
+        let future = std::pin::pin!(future);
+        let cancelled = std::pin::pin!(self.cancelled());
+        match future::select(future, cancelled).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(DfError::Cancelled),
+        }
+    }
+}
+
+/// Future of [`CancellationToken::cancelled`]. Its waker is removed from the token when it's
+/// dropped, so tokens that outlive many operations don't keep the wakers of finished ones.
+struct Cancelled<'a> {
+    inner: &'a Inner,
+    id: usize,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.inner.cancelled.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.inner.wakers.lock().unwrap();
+        // the token may have been cancelled before the lock was taken
+        if self.inner.cancelled.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+        match wakers.iter_mut().find(|(id, _)| *id == self.id) {
+            Some((_, waker)) => waker.clone_from(cx.waker()),
+            None => wakers.push((self.id, cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Cancelled<'_> {
+    fn drop(&mut self) {
+        let mut wakers = self
+            .inner
+            .wakers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        wakers.retain(|(id, _)| *id != self.id);
+    }
+}
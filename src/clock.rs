@@ -0,0 +1,63 @@
+//! Clocks used by the load and layout timers (monotonic) and the history (system time).
+//!
+//! `std::time::Instant` and `SystemTime::now` panic on `wasm32-unknown-unknown`, where the time
+//! has to come from the host: embedders register clocks (backed by `performance.now()` and
+//! `Date.now()` for example) with [`set_clock`] and [`set_system_clock`]. Without them, all
+//! durations are zero and the history timestamps are the Unix epoch.
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::time::Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use wasm::{system_time, Instant};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use wasm::{set_clock, set_system_clock};
+
+/// Current system time.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn system_time() -> std::time::SystemTime {
+    std::time::SystemTime::now()
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod wasm {
+    use std::sync::OnceLock;
+    use std::time::{Duration, SystemTime};
+
+    static CLOCK: OnceLock<fn() -> Duration> = OnceLock::new();
+    static SYSTEM_CLOCK: OnceLock<fn() -> Duration> = OnceLock::new();
+
+    /// Set the clock of the timers: a function returning the time elapsed since an arbitrary
+    /// fixed point (`performance.now()` for example). It can only be set once.
+    pub fn set_clock(now: fn() -> Duration) {
+        if CLOCK.set(now).is_err() {
+            log::warn!("the clock was already set");
+        }
+    }
+
+    /// Set the clock of the history timestamps: a function returning the time elapsed since the
+    /// Unix epoch (`Date.now()` for example). It can only be set once.
+    pub fn set_system_clock(now: fn() -> Duration) {
+        if SYSTEM_CLOCK.set(now).is_err() {
+            log::warn!("the system clock was already set");
+        }
+    }
+
+    pub(crate) fn system_time() -> SystemTime {
+        SystemTime::UNIX_EPOCH + SYSTEM_CLOCK.get().map_or(Duration::ZERO, |now| now())
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub(crate) struct Instant(Duration);
+
+    impl Instant {
+        pub(crate) fn now() -> Self {
+            Self(CLOCK.get().map_or(Duration::ZERO, |now| now()))
+        }
+
+        pub(crate) fn elapsed(&self) -> Duration {
+            Self::now().0.saturating_sub(self.0)
+        }
+    }
+}
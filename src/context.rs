@@ -1,123 +1,2076 @@
-use html5ever::tree_builder::QuirksMode;
+use encoding_rs::Encoding;
+use indextree::NodeId;
 use scraper::Html;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use url::Url;
 
+use crate::clock::Instant;
 use crate::*;
 
+/// Progress of a page load, given with every chunk of the page by
+/// [`WebContext::load_with_progress`].
+#[derive(Debug, Clone, Copy)]
+pub struct LoadProgress<'a> {
+    /// Progress of the pull of the page
+    pub pull: PullProgress<'a>,
+    /// Number of elements parsed so far
+    pub elements: usize,
+}
+
 /// Page loading timers
-#[derive(Debug, Copy, Clone, Default)]
+///
+/// With the `serde` feature, the timers serialize with one key per field, durations as
+/// `{"secs", "nanos"}` and resources as an array of [`ResourceTiming`].
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use dragonfly::{url::Url, ResourceKind, ResourceTiming, Timers};
+/// use std::time::Duration;
+/// let mut timers = Timers::default();
+/// timers.resources.push(ResourceTiming {
+///     url: Url::parse("https://example.com/style.css").unwrap(),
+///     kind: ResourceKind::BackgroundImage,
+///     duration: Duration::from_micros(1500),
+///     size: 10,
+///     from_cache: false,
+///     error: Some("\"not found\"".to_string()),
+/// });
+/// let json = serde_json::to_string(&timers).unwrap();
+/// for key in ["pull", "parse", "subresources", "style", "layout", "paint", "total"] {
+///     assert!(json.contains(&format!(r#""{key}":{{"secs":0,"nanos":0}}"#)));
+/// }
+/// assert!(json.contains(
+///     r#""resources":[{"url":"https://example.com/style.css","kind":"background-image","duration":{"secs":0,"nanos":1500000},"size":10,"from_cache":false,"error":"\"not found\""}]"#
+/// ));
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Timers {
     /// Time it took to pull the page (download/get from cache)
     pub pull: Duration,
     /// Time it took to parse the page
     pub parse: Duration,
+    /// Time it took to pull the subresources (stylesheets and images) of the page
+    pub subresources: Duration,
+    /// Timing of every subresource, in document order
+    pub resources: Vec<ResourceTiming>,
+    /// Time it took to match the stylesheet rules and compute the styles in the last layout
+    /// (included in `layout`)
+    pub style: Duration,
     /// Time it took to compute the last layout
     pub layout: Duration,
+    /// Time it took to build the last display list (see [`WebContext::display_list`])
+    pub paint: Duration,
     /// Total time elapsed
     pub total: Duration,
 }
 
+/// Whether a viewport size or scale factor is usable: finite and greater than zero.
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// Loading state of the page of a [`WebContext`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadState {
+    /// No page was loaded yet
+    #[default]
+    NotLoaded,
+    /// The page is being pulled, parsed or laid out
+    Loading,
+    Loaded,
+    /// The last load returned an error
+    Failed,
+}
+
 #[derive(Debug, Clone)]
 pub struct WebContext {
-    /// Page URL
-    url: Url,
-    html_str: Option<String>,
+    /// Page URL, after redirects
+    pub(crate) url: Url,
+    /// URL the page was requested at, before redirects
+    original_url: Url,
+    /// URLs the page was redirected to
+    redirect_chain: Vec<Url>,
+    /// URL that relative URLs are resolved against: the `href` of the first `<base>` element, or the page URL
+    pub(crate) base_url: Url,
+    /// `target` attribute of the `<base>` element
+    base_target: Option<String>,
+    /// URL that the markup set with [`WebContext::set_html`] is resolved against, instead of
+    /// the page URL
+    html_base: Option<Url>,
+    pub(crate) html_str: Option<String>,
     /// Page loading timers
     pub timers: Timers,
     /// Parsed page
-    pub document: Option<Html>,
+    document: Option<Html>,
+    pub(crate) load_state: LoadState,
     /// Computed page layout tree. This can be used for rendering
     pub layout: Layout,
+    /// Metadata of the loaded page
+    metadata: PageMetadata,
+    /// Encoding the page was decoded with
+    encoding: Option<&'static Encoding>,
+    doctype: Option<DocumentType>,
+    /// Scripts of the loaded page
+    pub(crate) scripts: Vec<ScriptInfo>,
+    /// Whether to pull external scripts with the subresources of the page (they're never executed)
+    pub pull_scripts: bool,
+    /// Whether to pull the images of the page. Without them, `img` elements are sized by
+    /// their attributes and styles, or by their alternative text.
+    ///
+    /// Changes apply to the next load:
+    ///
+    /// ```
+    /// use dragonfly::{ResourceKind, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<link rel=stylesheet href=test.css><img src=pixel.png alt='a photo'><p>text</p>";
+    /// let mut ctx = WebContext::builder().html(html).url("file:///tests/page.html").build()?;
+    /// block_on(ctx.load())?;
+    /// let pulled = |ctx: &WebContext| ctx.timers.resources.iter().map(|r| r.kind).collect::<Vec<_>>();
+    /// assert_eq!(pulled(&ctx), [ResourceKind::Stylesheet, ResourceKind::Image]);
+    /// let img_size = |ctx: &WebContext| ctx.layout.arena[ctx.layout.select("img")[0]].get().border_rect.size;
+    /// assert_eq!((img_size(&ctx).x, img_size(&ctx).y), (40.0, 30.0));
+    ///
+    /// ctx.load_images = false;
+    /// ctx.load_stylesheets = false;
+    /// block_on(ctx.reload(false))?;
+    /// assert!(pulled(&ctx).is_empty());
+    /// // the image is replaced by its alternative text
+    /// assert!(img_size(&ctx).x > 0.0 && img_size(&ctx).x != 40.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub load_images: bool,
+    /// Whether to pull the linked stylesheets of the page (`<style>` elements still apply)
+    pub load_stylesheets: bool,
+    /// Whether to pull the web fonts of `@font-face` rules. Web fonts are not supported yet,
+    /// so this has no effect: text is always shaped with the fonts of the [`FontManager`].
+    pub load_remote_fonts: bool,
+    /// Visited pages
+    history: History,
+    /// Entry of the page being navigated to, added to the history once it's loaded
+    navigation: Option<HistoryEntry>,
+    /// Language of the page if it's not declared by the `Content-Language` header
+    /// or a `lang` attribute on the root element
+    pub lang: Option<String>,
+    /// Whether to follow `<meta http-equiv="refresh">` tags without a delay when a page is loaded
+    pub follow_meta_refresh: bool,
+    /// Maximum number of refreshes followed in a row
+    pub max_refresh_hops: usize,
+    /// Limits on the resources used by page loads
+    pub limits: LoadLimits,
+    /// Non-fatal errors of the last load
+    load_errors: Vec<LoadError>,
+    /// Size of the page in bytes, counted in the byte budget
+    pub(crate) page_bytes: usize,
     /// Retrieves files and manages the file cache
     pub puller: Puller,
     /// Handles font storage and lookup
     pub font_manager: FontManager,
+    /// Decoded images
+    #[cfg(feature = "images")]
+    pub(crate) images: crate::ImageStore,
+    /// Notified of the progress of page loads
+    pub(crate) observer: Option<SharedObserver>,
 }
 
-impl WebContext {
-    pub fn new(url: &str, font_manager: FontManager) -> DfResult<Self> {
-        Ok(Self {
-            url: Url::parse(url)?,
-            html_str: None,
+/// Builder of a [`WebContext`].
+///
+/// The context loads the page at [`url`](WebContextBuilder::url), or the markup given to
+/// [`html`](WebContextBuilder::html). Settings that are not set keep their default value.
+///
+/// ```
+/// use dragonfly::WebContext;
+/// let ctx = WebContext::builder()
+///     .html("<p>hello</p>")
+///     .url("https://example.com/")
+///     .viewport(1024.0, 768.0)
+///     .scale_factor(2.0)
+///     .build()?;
+/// assert_eq!(ctx.viewport().size.x, 1024.0);
+/// # Ok::<(), dragonfly::DfError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WebContextBuilder {
+    url: Option<String>,
+    html: Option<String>,
+    font_manager: Option<FontManager>,
+    puller: Option<Puller>,
+    visited: Option<VisitedLinks>,
+    viewport: Viewport,
+    max_depth: Option<usize>,
+    lang: Option<String>,
+    follow_meta_refresh: bool,
+    max_refresh_hops: Option<usize>,
+    limits: LoadLimits,
+    observer: Option<SharedObserver>,
+    user_css: Option<String>,
+    load_images: Option<bool>,
+    load_stylesheets: Option<bool>,
+    load_remote_fonts: Option<bool>,
+}
+
+impl WebContextBuilder {
+    /// URL of the page. If markup is set with [`WebContextBuilder::html`], it's the URL
+    /// the page is shown at (`about:blank` by default).
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    /// Markup of the page, loaded instead of pulling the URL.
+    pub fn html(mut self, html: &str) -> Self {
+        self.html = Some(html.to_string());
+        self
+    }
+
+    pub fn font_manager(mut self, font_manager: FontManager) -> Self {
+        self.font_manager = Some(font_manager);
+        self
+    }
+
+    pub fn puller(mut self, puller: Puller) -> Self {
+        self.puller = Some(puller);
+        self
+    }
+
+    /// Store of the visited pages, shared with the contexts it's cloned to (see [`WebContext::visited`]).
+    pub fn visited(mut self, visited: VisitedLinks) -> Self {
+        self.visited = Some(visited);
+        self
+    }
+
+    /// Viewport size in CSS pixels. Sizes that are not positive numbers are ignored.
+    pub fn viewport(mut self, width: f32, height: f32) -> Self {
+        match is_positive(width) && is_positive(height) {
+            true => self.viewport.size = Vec2::new(width, height),
+            false => log::warn!("ignoring invalid viewport size {width}x{height}"),
+        }
+        self
+    }
+
+    /// Number of device pixels per CSS pixel. Scale factors that are not positive numbers are
+    /// ignored.
+    pub fn scale_factor(mut self, scale_factor: f32) -> Self {
+        match is_positive(scale_factor) {
+            true => self.viewport.scale_factor = scale_factor,
+            false => log::warn!("ignoring invalid scale factor {scale_factor}"),
+        }
+        self
+    }
+
+    /// Round the layout geometry to whole device pixels (see [`Viewport::pixel_snapping`]).
+    ///
+    /// The border, padding and content edges of every box land on whole device pixels, each
+    /// within half a pixel of where it was laid out, and the clip rectangles follow them:
+    ///
+    /// ```
+    /// use dragonfly::{Rect, Sides, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<!DOCTYPE html><body style='margin: 0'><div style='margin: 5.2px 0 0 10.3px;
+    ///     width: 50.4px; height: 20.1px; padding: 1.3px 2.2px; border: 1.2px solid;
+    ///     overflow: hidden'>text</div></body>";
+    /// let load = |snapping| -> Result<WebContext, Box<dyn std::error::Error>> {
+    ///     let mut ctx = WebContext::builder()
+    ///         .html(html)
+    ///         .scale_factor(2.0)
+    ///         .pixel_snapping(snapping)
+    ///         .build()?;
+    ///     block_on(ctx.load())?;
+    ///     Ok(ctx)
+    /// };
+    /// let (exact_ctx, snapped_ctx) = (load(false)?, load(true)?);
+    /// let div = |ctx: &WebContext| ctx.layout.arena[ctx.layout.select("div")[0]].get().clone();
+    /// let (exact, snapped) = (div(&exact_ctx), div(&snapped_ctx));
+    /// // geometry is in device pixels, twice the CSS pixels
+    /// assert!((exact.border_rect.pos.x - 20.6).abs() < 1e-3);
+    /// assert_eq!(snapped.border_rect.pos.x, 21.0);
+    ///
+    /// // (top, right, bottom, left) edges of the border, padding and content boxes
+    /// let edges = |rect: Rect, border: Sides<f32>, padding: Sides<f32>| {
+    ///     let inset = |rect: Rect, sides: Sides<f32>| {
+    ///         [rect.min().y + sides.top, rect.max().x - sides.right,
+    ///          rect.max().y - sides.bottom, rect.min().x + sides.left]
+    ///     };
+    ///     let mut edges = inset(rect, Sides::default()).to_vec();
+    ///     edges.extend(inset(rect, border));
+    ///     edges.extend(inset(rect.inset(border), padding));
+    ///     edges
+    /// };
+    /// let before = edges(exact.border_rect, exact.border, exact.padding);
+    /// let after = edges(snapped.border_rect, snapped.border, snapped.padding);
+    /// for (before, after) in before.iter().zip(&after) {
+    ///     assert_eq!(after.fract(), 0.0);
+    ///     assert!((after - before).abs() <= 0.5, "{before} snapped to {after}");
+    /// }
+    /// let clip = snapped.overflow_clip.unwrap();
+    /// assert_eq!(clip, snapped.border_rect.inset(snapped.border));
+    /// assert_eq!((clip.pos.x.fract(), clip.width().fract()), (0.0, 0.0));
+    /// let layout = &snapped_ctx.layout;
+    /// let text = layout.select("div")[0].children(&layout.arena).next().unwrap();
+    /// for fragment in &layout.arena[text].get().fragments {
+    ///     assert_eq!((fragment.pos.x.fract(), fragment.size.y.fract()), (0.0, 0.0));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pixel_snapping(mut self, pixel_snapping: bool) -> Self {
+        self.viewport.pixel_snapping = pixel_snapping;
+        self
+    }
+
+    /// Maximum element nesting depth (see [`Layout::max_depth`]).
+    ///
+    /// ```
+    /// use dragonfly::{LayoutError, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let depth = 5000;
+    /// let html = format!("<!DOCTYPE html>{}x{}", "<div>".repeat(depth), "</div>".repeat(depth));
+    ///
+    /// // deeper elements are flattened by default
+    /// let mut ctx = WebContext::builder().html(&html).build()?;
+    /// block_on(ctx.load())?;
+    /// assert!(ctx.layout.errors.contains(&LayoutError::MaxDepthExceeded(512)));
+    ///
+    /// // without a limit, the whole tree is laid out without overflowing the stack
+    /// let mut ctx = WebContext::builder().html(&html).max_depth(usize::MAX).build()?;
+    /// block_on(ctx.load())?;
+    /// assert!(ctx.layout.errors.is_empty());
+    /// let divs = ctx.layout.select("div");
+    /// assert_eq!(divs.len(), depth);
+    /// let rect = |id| ctx.layout.arena[id].get().border_rect;
+    /// assert_eq!(rect(divs[0]), rect(divs[depth - 1]));
+    /// assert!(rect(divs[0]).height() > 0.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Language of pages that don't declare one (see [`WebContext::lang`]).
+    pub fn lang(mut self, lang: &str) -> Self {
+        self.lang = Some(lang.to_string());
+        self
+    }
+
+    /// Follow refreshes without a delay (see [`WebContext::follow_meta_refresh`]).
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// use std::time::Duration;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let page = |refresh: &str, title: &str| {
+    ///     let html = format!(r#"<!DOCTYPE html><meta http-equiv="refresh" content="{refresh}"><title>{title}</title>"#);
+    ///     Some(Response::ok("text/html", html))
+    /// };
+    /// let base = serve(move |path| match path {
+    ///     "/start" => Some(Response::ok("text/html", "<!DOCTYPE html><title>Start</title>")),
+    ///     "/a" => page("0; url=b", "A"),
+    ///     "/b" => page("0;URL='/c'", "B"),
+    ///     "/c" => page("30; url=/a", "C"),
+    ///     _ => None,
+    /// });
+    ///
+    /// // two hops, the refreshed pages replace the history entry of the navigation
+    /// let start = format!("{base}start");
+    /// let mut ctx = WebContext::builder().url(&start).follow_meta_refresh(true).build()?;
+    /// block_on(ctx.load())?;
+    /// block_on(ctx.navigate("a"))?;
+    /// assert_eq!(ctx.url().path(), "/c");
+    /// assert_eq!(ctx.title().as_deref(), Some("C"));
+    /// let urls: Vec<_> = ctx.history().entries().iter().map(|entry| entry.url.path()).collect();
+    /// assert_eq!(urls, ["/start", "/c"]);
+    /// // refreshes with a delay are left to the caller
+    /// let (delay, next) = ctx.pending_refresh().unwrap();
+    /// assert_eq!((delay, next.path()), (Duration::from_secs(30), "/a"));
+    ///
+    /// // the chain stops after the maximum number of hops
+    /// let url = format!("{base}a");
+    /// let mut ctx = WebContext::builder()
+    ///     .url(&url)
+    ///     .follow_meta_refresh(true)
+    ///     .max_refresh_hops(1)
+    ///     .build()?;
+    /// block_on(ctx.load())?;
+    /// assert_eq!(ctx.title().as_deref(), Some("B"));
+    ///
+    /// // not followed by default
+    /// let mut ctx = WebContext::builder().url(&url).build()?;
+    /// block_on(ctx.load())?;
+    /// assert_eq!(ctx.title().as_deref(), Some("A"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn follow_meta_refresh(mut self, follow: bool) -> Self {
+        self.follow_meta_refresh = follow;
+        self
+    }
+
+    /// Maximum number of refreshes followed in a row, [`WebContext::DEFAULT_MAX_REFRESH_HOPS`]
+    /// by default.
+    pub fn max_refresh_hops(mut self, max_refresh_hops: usize) -> Self {
+        self.max_refresh_hops = Some(max_refresh_hops);
+        self
+    }
+
+    /// Limits on the resources used by page loads (see [`WebContext::limits`]).
+    pub fn limits(mut self, limits: LoadLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn load_observer(mut self, observer: impl LoadObserver + 'static) -> Self {
+        self.observer = Some(SharedObserver::new(observer));
+        self
+    }
+
+    /// User stylesheet of the context (see [`WebContext::set_user_stylesheet`]).
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut ctx = WebContext::builder()
+    ///     .html("<p>text</p>")
+    ///     .user_css("p { height: 30px; }")
+    ///     .build()?;
+    /// assert!(ctx.user_stylesheet().is_some());
+    /// block_on(ctx.load())?;
+    /// let p = ctx.layout.select("p")[0];
+    /// assert_eq!(ctx.layout.arena[p].get().border_rect.height(), 30.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn user_css(mut self, css: &str) -> Self {
+        self.user_css = Some(css.to_string());
+        self
+    }
+
+    /// Whether to pull the images of the page (see [`WebContext::load_images`]).
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<link rel=stylesheet href=test.css><img src=pixel.png alt='a photo'>";
+    /// let mut ctx = WebContext::builder()
+    ///     .html(html)
+    ///     .url("file:///tests/page.html")
+    ///     .load_images(false)
+    ///     .load_stylesheets(false)
+    ///     .load_remote_fonts(false)
+    ///     .build()?;
+    /// assert!(!ctx.load_images && !ctx.load_stylesheets && !ctx.load_remote_fonts);
+    /// block_on(ctx.load())?;
+    /// assert!(ctx.timers.resources.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_images(mut self, load_images: bool) -> Self {
+        self.load_images = Some(load_images);
+        self
+    }
+
+    /// Whether to pull the linked stylesheets of the page (see [`WebContext::load_stylesheets`]).
+    pub fn load_stylesheets(mut self, load_stylesheets: bool) -> Self {
+        self.load_stylesheets = Some(load_stylesheets);
+        self
+    }
+
+    /// Whether to pull web fonts (see [`WebContext::load_remote_fonts`]). This has no effect yet.
+    pub fn load_remote_fonts(mut self, load_remote_fonts: bool) -> Self {
+        self.load_remote_fonts = Some(load_remote_fonts);
+        self
+    }
+
+    /// Create the context. Fails if the URL is invalid, or if neither a URL
+    /// nor markup was set.
+    pub fn build(self) -> DfResult<WebContext> {
+        let url = match (&self.url, &self.html) {
+            (Some(url), _) => Url::parse(url)?,
+            (None, Some(_)) => Url::parse("about:blank")?,
+            (None, None) => return Err(DfError::NoPage),
+        };
+        let mut layout = Layout::default();
+        layout.viewport = self.viewport;
+        layout.max_depth = self.max_depth.unwrap_or(Layout::DEFAULT_MAX_DEPTH);
+        layout.visited = self.visited.unwrap_or_default();
+        layout.user_style = self.user_css.map(|css| {
+            let style = GlobalStyle::from_css(&css, ParserMode::Normal);
+            for diagnostic in &style.diagnostics {
+                log::warn!("user stylesheet: {diagnostic}");
+            }
+            style
+        });
+        Ok(WebContext {
+            base_url: url.clone(),
+            base_target: None,
+            html_base: None,
+            original_url: url.clone(),
+            redirect_chain: vec![],
+            url,
+            html_str: self.html,
             timers: Timers::default(),
             document: None,
-            layout: Layout::default(),
-            puller: Puller::default(),
-            font_manager,
+            load_state: LoadState::default(),
+            layout,
+            metadata: PageMetadata::default(),
+            encoding: None,
+            doctype: None,
+            scripts: vec![],
+            pull_scripts: false,
+            load_images: self.load_images.unwrap_or(true),
+            load_stylesheets: self.load_stylesheets.unwrap_or(true),
+            load_remote_fonts: self.load_remote_fonts.unwrap_or(true),
+            history: History::default(),
+            navigation: None,
+            lang: self.lang,
+            follow_meta_refresh: self.follow_meta_refresh,
+            max_refresh_hops: self
+                .max_refresh_hops
+                .unwrap_or(WebContext::DEFAULT_MAX_REFRESH_HOPS),
+            limits: self.limits,
+            load_errors: vec![],
+            page_bytes: 0,
+            puller: self.puller.unwrap_or_default(),
+            font_manager: self.font_manager.unwrap_or_default(),
+            #[cfg(feature = "images")]
+            images: crate::ImageStore::default(),
+            observer: self.observer,
         })
     }
+}
+
+/// Page of a [`WebContext`], moved out while another page loads and put back if the load
+/// is cancelled.
+#[derive(Debug)]
+struct PageState {
+    url: Url,
+    original_url: Url,
+    redirect_chain: Vec<Url>,
+    base_url: Url,
+    base_target: Option<String>,
+    html_base: Option<Url>,
+    html_str: Option<String>,
+    timers: Timers,
+    document: Option<Html>,
+    load_state: LoadState,
+    layout: Layout,
+    metadata: PageMetadata,
+    encoding: Option<&'static Encoding>,
+    doctype: Option<DocumentType>,
+    scripts: Vec<ScriptInfo>,
+    load_errors: Vec<LoadError>,
+    /// Current history entry, that the load updates
+    history_entry: Option<HistoryEntry>,
+}
+
+impl WebContext {
+    /// Default maximum number of refreshes followed in a row.
+    pub const DEFAULT_MAX_REFRESH_HOPS: usize = 5;
+
+    pub fn new(url: &str, font_manager: FontManager) -> DfResult<Self> {
+        Self::builder().url(url).font_manager(font_manager).build()
+    }
 
     pub fn new_from_html(html_str: &str, url: &str, font_manager: FontManager) -> DfResult<Self> {
-        Ok(Self {
-            url: Url::parse(url)?,
-            html_str: Some(html_str.to_string()),
-            timers: Timers::default(),
-            document: None,
-            layout: Layout::default(),
-            puller: Puller::default(),
-            font_manager,
-        })
+        Self::builder()
+            .html(html_str)
+            .url(url)
+            .font_manager(font_manager)
+            .build()
+    }
+
+    /// Configure a new context.
+    #[inline]
+    pub fn builder() -> WebContextBuilder {
+        WebContextBuilder::default()
+    }
+
+    /// Page URL, after redirects (see [`WebContext::final_url`])
+    #[inline]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// URL of the page after the redirects of the last load, the same as [`WebContext::url`].
+    /// A fragment of the requested URL is kept if the redirects don't set one.
+    #[inline]
+    pub fn final_url(&self) -> &Url {
+        &self.url
+    }
+
+    /// URL the page was requested at by the last load, before redirects.
+    #[inline]
+    pub fn original_url(&self) -> &Url {
+        &self.original_url
+    }
+
+    /// URLs the page was redirected to by the last load, in order: the last one is the
+    /// [`final_url`](WebContext::final_url). Empty if the page was not redirected.
+    ///
+    /// Relative URLs of the page are resolved against the final URL.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let base = serve(|path| match path {
+    ///     "/a" => Some(Response::redirect("/b")),
+    ///     "/b" => Some(Response::ok("text/html", "<!DOCTYPE html><link rel=stylesheet href=style.css><p>b</p>")),
+    ///     "/style.css" => Some(Response::ok("text/css", "p { font-size: 40px }")),
+    ///     _ => None,
+    /// });
+    /// let mut ctx = WebContext::builder().url(&format!("{base}a")).build()?;
+    /// block_on(ctx.load())?;
+    /// assert_eq!(ctx.original_url().path(), "/a");
+    /// assert_eq!(ctx.final_url().path(), "/b");
+    /// assert_eq!(ctx.redirect_chain(), [ctx.final_url().clone()]);
+    /// assert_eq!(ctx.resolve_url("c")?.as_str(), format!("{base}c"));
+    /// // the stylesheet was found next to the final URL
+    /// let p = ctx.layout.select("p")[0];
+    /// assert!(ctx.layout.arena[p].get().border_rect.height() > 40.0);
+    /// assert!(ctx.load_errors().is_empty());
+    ///
+    /// // a page that is not redirected has an empty chain
+    /// let mut ctx = WebContext::builder().url(&format!("{base}b")).build()?;
+    /// block_on(ctx.load())?;
+    /// assert!(ctx.redirect_chain().is_empty());
+    /// assert_eq!(ctx.original_url(), ctx.final_url());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn redirect_chain(&self) -> &[Url] {
+        &self.redirect_chain
+    }
+
+    /// Metadata (title, description, Open Graph tags, icons...) of the loaded page.
+    ///
+    /// ```
+    /// use dragonfly::{ViewportWidth, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut ctx = WebContext::builder().url("file:///tests/metadata.html").build()?;
+    /// block_on(ctx.load())?;
+    /// let meta = ctx.metadata();
+    /// assert_eq!(meta.title.as_deref(), Some("Example page"));
+    /// assert_eq!(meta.description.as_deref(), Some("A page with metadata"));
+    /// assert_eq!(meta.charset.as_deref(), Some("utf-8"));
+    /// let viewport = meta.viewport.as_ref().unwrap();
+    /// assert_eq!((viewport.width, viewport.initial_scale), (Some(ViewportWidth::DeviceWidth), Some(2.0)));
+    /// let og: Vec<_> = meta.open_graph.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    /// assert_eq!(og, [
+    ///     ("og:title", "Example"),
+    ///     ("og:image", "https://example.com/cover.png"),
+    ///     ("OG:type", "website"),
+    /// ]);
+    /// assert_eq!(meta.canonical.as_ref().unwrap().as_str(), "file:///canonical.html");
+    /// let icons: Vec<_> = meta.icons.iter().map(|icon| (icon.href.as_str(), icon.rel.as_str())).collect();
+    /// assert_eq!(icons, [
+    ///     ("file:///tests/icons/favicon-32.png", "icon"),
+    ///     ("file:///touch.png", "apple-touch-icon"),
+    /// ]);
+    /// assert_eq!(meta.icons[0].sizes, [(16, 16), (32, 32)]);
+    /// assert_eq!(meta.icons[0].kind.as_deref(), Some("image/png"));
+    /// assert!(meta.icons[1].sizes.is_empty());
+    ///
+    /// // tags outside of a `head` are found too
+    /// let html = r#"<!DOCTYPE html><body><p>text
+    ///     <title>No head</title>
+    ///     <meta property="og:title" content="Body">
+    ///     <link rel="icon" href="/body.ico"></p>"#;
+    /// let mut ctx = WebContext::builder().html(html).url("https://example.com/a/").build()?;
+    /// block_on(ctx.load())?;
+    /// assert!(ctx.layout.select("head > *").is_empty());
+    /// let meta = ctx.metadata();
+    /// assert_eq!(meta.title.as_deref(), Some("No head"));
+    /// assert_eq!(meta.open_graph, [("og:title".to_string(), "Body".to_string())]);
+    /// assert_eq!(meta.icons[0].href.as_str(), "https://example.com/body.ico");
+    /// assert_eq!(meta.description, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn metadata(&self) -> &PageMetadata {
+        &self.metadata
+    }
+
+    /// Refresh requested by a `<meta http-equiv="refresh">` tag of the page: the delay and
+    /// the URL to navigate to (the page URL if the tag doesn't have one).
+    pub fn pending_refresh(&self) -> Option<(Duration, Url)> {
+        let refresh = self.metadata.refresh.as_ref()?;
+        let url = refresh.url.clone().unwrap_or_else(|| self.url.clone());
+        Some((refresh.delay, url))
+    }
+
+    /// Refresh to follow after the page is loaded: only refreshes without a delay to another page
+    /// are followed, if [`WebContext::follow_meta_refresh`] is set.
+    fn refresh_target(&self) -> Option<Url> {
+        if !self.follow_meta_refresh {
+            return None;
+        }
+        let (delay, url) = self.pending_refresh()?;
+        let same_page = url[..url::Position::AfterQuery] == self.url[..url::Position::AfterQuery];
+        (delay.is_zero() && !same_page).then_some(url)
+    }
+
+    /// Title of the page: the whitespace-collapsed text of the first `<title>` element.
+    ///
+    /// The title is read when the page is loaded, and again from the layout tree if it was modified.
+    ///
+    /// ```
+    /// use dragonfly::{DOMNode, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<!DOCTYPE html><title>  Hello \n\t  World </title><title>Second</title>";
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// assert_eq!(ctx.title(), None);
+    /// block_on(ctx.load())?;
+    /// assert_eq!(ctx.title().as_deref(), Some("Hello World"));
+    ///
+    /// let title = ctx.layout.select("title")[0];
+    /// ctx.layout.replace_children(title, vec![DOMNode::text_node(" New   title ")]);
+    /// assert_eq!(ctx.title().as_deref(), Some("New title"));
+    ///
+    /// let mut ctx = WebContext::builder().html("<!DOCTYPE html><p>no title</p>").build()?;
+    /// block_on(ctx.load())?;
+    /// assert_eq!(ctx.title(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn title(&self) -> Option<String> {
+        match self.layout.is_modified() {
+            true => self.layout.title(),
+            false => self.metadata.title.clone(),
+        }
+    }
+
+    /// Character encoding the page was decoded with, if it's loaded.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut ctx = WebContext::builder().url("file:///tests/windows-1251.html").build()?;
+    /// assert_eq!(ctx.charset(), None);
+    /// block_on(ctx.load())?;
+    /// assert_eq!(ctx.charset(), Some(encoding_rs::WINDOWS_1251));
+    /// assert_eq!(ctx.title().as_deref(), Some("Привет, мир"));
+    /// let p = ctx.layout.select("p")[0];
+    /// assert!(ctx.layout.text_content(p).starts_with("Съешь же ещё"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn charset(&self) -> Option<&'static Encoding> {
+        self.encoding
+    }
+
+    /// Document type declaration of the page, if it has one.
+    #[inline]
+    pub fn doctype(&self) -> Option<&DocumentType> {
+        self.doctype.as_ref()
+    }
+
+    /// Rendering mode of the page, chosen from its doctype (see [`QuirksMode`]).
+    ///
+    /// ```
+    /// use dragonfly::{QuirksMode, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let body = r#"<body><div style="width: 100">x</div></body>"#;
+    /// let mut quirks = WebContext::builder().html(body).build()?;
+    /// block_on(quirks.load())?;
+    /// let mut standard = WebContext::builder().html(&format!("<!DOCTYPE html>{body}")).build()?;
+    /// block_on(standard.load())?;
+    /// assert_eq!(quirks.quirks_mode(), QuirksMode::Quirks);
+    /// assert_eq!(standard.quirks_mode(), QuirksMode::NoQuirks);
+    ///
+    /// // `width: 100` is 100px in quirks mode, and invalid in standard mode
+    /// let div_width = |ctx: &WebContext| ctx.layout.arena[ctx.layout.select("div")[0]].get().border_rect.width();
+    /// assert_eq!(div_width(&quirks), 100.0);
+    /// assert!(div_width(&standard) > 700.0);
+    ///
+    /// // the body is as tall as the viewport in quirks mode
+    /// let body_height = |ctx: &WebContext| ctx.layout.arena[ctx.layout.select("body")[0]].get().border_rect.height();
+    /// assert!(body_height(&quirks) >= 600.0 - 2.0 * 8.0);
+    /// assert!(body_height(&standard) < 100.0);
+    ///
+    /// // unitless font sizes and indents are ignored in standard mode too
+    /// let p = r#"<p style="font-size: 40; text-indent: 50">text</p>"#;
+    /// let mut quirks = WebContext::builder().html(p).build()?;
+    /// block_on(quirks.load())?;
+    /// let mut standard = WebContext::builder().html(&format!("<!DOCTYPE html>{p}")).build()?;
+    /// block_on(standard.load())?;
+    /// let p_height = |ctx: &WebContext| ctx.layout.arena[ctx.layout.select("p")[0]].get().border_rect.height();
+    /// let text_x = |ctx: &WebContext| {
+    ///     let text = ctx.layout.arena[ctx.layout.select("p")[0]].first_child().unwrap();
+    ///     ctx.layout.arena[text].get().fragments[0].pos.x
+    /// };
+    /// assert!(p_height(&quirks) > 40.0);
+    /// assert_eq!(text_x(&quirks), 58.0);
+    /// assert!(p_height(&standard) < 21.0);
+    /// assert_eq!(text_x(&standard), 8.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.layout.quirks_mode
+    }
+
+    /// URL that relative URLs of the page are resolved against: the `href` of the first
+    /// `<base>` element that has one, or the page URL.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let server = serve(|_| None);
+    /// let page = format!("{server}docs/page.html");
+    /// let load = |html: &str| -> Result<WebContext, Box<dyn std::error::Error>> {
+    ///     let mut ctx = WebContext::builder().url(&page).html(html).build()?;
+    ///     block_on(ctx.load())?;
+    ///     Ok(ctx)
+    /// };
+    /// let body = "<a href='guide.html'>guide</a><a href='/home'>home</a><img src='logo.png'>";
+    /// let resolved = |ctx: &WebContext| {
+    ///     let link = |i| ctx.attr_url(ctx.layout.select("a")[i], "href").unwrap().unwrap();
+    ///     let image = &ctx.timers.resources[0].url;
+    ///     [link(0).to_string(), link(1).to_string(), image.to_string()]
+    /// };
+    ///
+    /// // a relative base is resolved against the page URL
+    /// let ctx = load(&format!("<head><base href='../assets/'></head>{body}"))?;
+    /// assert_eq!(ctx.base_url().as_str(), format!("{server}assets/"));
+    /// assert_eq!(
+    ///     resolved(&ctx),
+    ///     [
+    ///         format!("{server}assets/guide.html"),
+    ///         format!("{server}home"),
+    ///         format!("{server}assets/logo.png"),
+    ///     ]
+    /// );
+    ///
+    /// // without `<base>`, URLs are resolved against the page URL
+    /// let ctx = load(body)?;
+    /// assert_eq!(ctx.base_url().as_str(), page);
+    /// assert_eq!(
+    ///     resolved(&ctx),
+    ///     [
+    ///         format!("{server}docs/guide.html"),
+    ///         format!("{server}home"),
+    ///         format!("{server}docs/logo.png"),
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Default browsing context of the links of the page (`target` attribute of the `<base>` element).
+    #[inline]
+    pub fn base_target(&self) -> Option<&str> {
+        self.base_target.as_deref()
+    }
+
+    /// Resolve a URL against the base URL of the page (see [`resolve_url`] for the rules).
+    /// Links, subresources and navigations are all resolved this way.
+    pub fn resolve_url(&self, raw: &str) -> Result<Url, UrlResolveError> {
+        resolve_url(&self.base_url, raw)
+    }
+
+    /// Resolve a URL against the base URL of the page, failing if no resource
+    /// can be pulled from it (see [`resolve_fetchable_url`]).
+    pub fn resolve_fetchable_url(&self, raw: &str) -> Result<Url, UrlResolveError> {
+        resolve_fetchable_url(&self.base_url, raw)
+    }
+
+    /// Read a URL attribute of an element and resolve it against the base URL of the page (see [`Layout::attr_url`]).
+    pub fn attr_url(&self, id: NodeId, name: &str) -> Option<Result<Url, UrlResolveError>> {
+        self.layout.attr_url(id, name, &self.base_url)
+    }
+
+    /// Find the base URL and target from the `<base>` elements of the page.
+    ///
+    /// Only the first `<base>` with an `href` sets the URL and only the first one with a `target`
+    /// sets the target, later ones are ignored. An invalid `href` is ignored too.
+    fn update_base(&mut self) {
+        let fallback = self.html_base.clone().unwrap_or_else(|| self.url.clone());
+        self.base_url = fallback.clone();
+        self.base_target = None;
+
+        let layout = &self.layout;
+        let bases: Vec<NodeId> = layout
+            .root_id()
+            .descendants(&layout.arena)
+            .filter(|&id| layout.arena[id].get().name() == "base")
+            .collect();
+        let attr = |name: &str| {
+            bases
+                .iter()
+                .find_map(|&id| layout.arena[id].get().attr(name))
+        };
+
+        if let Some(href) = attr("href") {
+            match resolve_url(&fallback, href) {
+                // `data:` and `javascript:` URLs can't be used as a base
+                Ok(base) if !base.cannot_be_a_base() => self.base_url = base,
+                Ok(_) => log::warn!("ignoring base URL '{href}'"),
+                Err(err) => log::warn!("ignoring invalid base URL '{href}': {err}"),
+            }
+        }
+        self.base_target = attr("target").map(str::to_string);
+    }
+
+    /// Session history of the context.
+    #[inline]
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    #[inline]
+    pub fn can_go_back(&self) -> bool {
+        self.history.can_go_back()
+    }
+
+    #[inline]
+    pub fn can_go_forward(&self) -> bool {
+        self.history.can_go_forward()
+    }
+
+    /// Navigate to a URL (relative URLs are resolved against the base URL of the page)
+    /// and add it to the session history, dropping the entries forward of the current one.
+    ///
+    /// If only the fragment of the URL changes, the page is not reloaded. If the page fails to
+    /// load, the history is left as it was and the URL is not marked visited.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let base = serve(|path| {
+    ///     let page = path.strip_prefix("/page-")?;
+    ///     Some(Response::ok("text/html", format!("<title>{page}</title>")))
+    /// });
+    /// let mut ctx = WebContext::new(&base, Default::default())?;
+    /// for page in ["page-a", "page-b", "page-c"] {
+    ///     block_on(ctx.navigate(page))?;
+    /// }
+    /// let titles = |ctx: &WebContext| -> Vec<_> {
+    ///     let entries = ctx.history().entries().iter();
+    ///     entries.map(|entry| entry.title.clone().unwrap()).collect()
+    /// };
+    /// assert_eq!(titles(&ctx), ["a", "b", "c"]);
+    ///
+    /// block_on(ctx.back())?;
+    /// block_on(ctx.back())?;
+    /// assert_eq!((ctx.history().index(), ctx.url().path()), (0, "/page-a"));
+    /// assert!(!ctx.can_go_back());
+    /// block_on(ctx.forward())?;
+    /// assert_eq!((ctx.history().index(), ctx.url().path()), (1, "/page-b"));
+    /// assert!(ctx.can_go_forward());
+    ///
+    /// // a failed navigation keeps the history
+    /// assert!(block_on(ctx.navigate("missing")).is_err());
+    /// assert_eq!((ctx.history().index(), titles(&ctx).len()), (1, 3));
+    /// assert!(!ctx.visited().contains(&format!("{base}missing").parse()?));
+    /// // navigating drops the entries forward of the current one
+    /// block_on(ctx.navigate(&format!("{base}page-d")))?;
+    /// assert_eq!(titles(&ctx), ["a", "b", "d"]);
+    /// assert!(!ctx.can_go_forward());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn navigate(&mut self, url: &str) -> DfResult<()> {
+        self.navigate_page(url, &CancellationToken::default()).await
+    }
+
+    /// Navigate to a URL like [`WebContext::navigate`], stopping as soon as `cancel` is cancelled.
+    ///
+    /// On cancellation, [`DfError::Cancelled`] is returned and the context is left as it was
+    /// before the navigation: the previous page and history are kept.
+    ///
+    /// ```
+    /// use dragonfly::{CancellationToken, DfError, WebContext};
+    /// use std::time::{Duration, Instant};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let base = serve(|path| match path {
+    ///     "/slow" => Some(Response::ok("text/html", "<p>slow</p>").delayed(Duration::from_secs(30))),
+    ///     _ => Some(Response::ok("text/html", "<title>fast</title><p>fast</p>")),
+    /// });
+    /// let mut ctx = WebContext::new(&format!("{base}fast"), Default::default())?;
+    /// block_on(ctx.load())?;
+    ///
+    /// let cancel = CancellationToken::new();
+    /// let token = cancel.clone();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(Duration::from_millis(100));
+    ///     token.cancel();
+    /// });
+    /// let start = Instant::now();
+    /// let result = block_on(ctx.navigate_cancellable("slow", &cancel));
+    /// assert!(matches!(result, Err(DfError::Cancelled)));
+    /// assert!(start.elapsed() < Duration::from_secs(10));
+    /// // the previous page is kept
+    /// assert_eq!(ctx.url().path(), "/fast");
+    /// assert_eq!(ctx.title().as_deref(), Some("fast"));
+    /// assert!(ctx.document().is_some());
+    /// assert_eq!(ctx.layout.select("p").len(), 1);
+    /// assert!(ctx.history().entries().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn navigate_cancellable(
+        &mut self,
+        url: &str,
+        cancel: &CancellationToken,
+    ) -> DfResult<()> {
+        self.navigate_page(url, cancel).await
+    }
+
+    async fn navigate_page(&mut self, url: &str, cancel: &CancellationToken) -> DfResult<()> {
+        let url = self.resolve_url(url)?;
+        // keep the page the context was loaded with
+        let initial = (self.history.current().is_none() && self.document.is_some()).then(|| {
+            let mut entry = HistoryEntry::new(self.url.clone(), self.html_str.clone());
+            entry.title = self.metadata.title.clone();
+            entry
+        });
+        // a page that wasn't pulled from its URL stays when only the fragment changes
+        let html_str = match self.is_same_document(&url) {
+            true => self.html_str.clone(),
+            false => None,
+        };
+        // the history and the visited pages only change once the page is loaded
+        self.navigation = Some(HistoryEntry::new(url.clone(), html_str.clone()));
+        let result = self.go_to(url.clone(), html_str, cancel).await;
+        let entry = self.navigation.take();
+        result?;
+        self.layout.visited.mark(&url);
+        self.history.extend(initial.into_iter().chain(entry));
+        Ok(())
+    }
+
+    /// History entry of the page being loaded: the entry of a navigation, or the current one.
+    fn history_entry_mut(&mut self) -> Option<&mut HistoryEntry> {
+        match &mut self.navigation {
+            Some(entry) => Some(entry),
+            None => self.history.current_mut(),
+        }
+    }
+
+    /// Go to the previous page of the session history. Does nothing if there is none.
+    pub async fn back(&mut self) -> DfResult<()> {
+        self.traverse(-1).await
+    }
+
+    /// Go to the next page of the session history. Does nothing if there is none.
+    pub async fn forward(&mut self) -> DfResult<()> {
+        self.traverse(1).await
     }
 
+    async fn traverse(&mut self, delta: isize) -> DfResult<()> {
+        match self.history.go(delta) {
+            Some(entry) => {
+                let (url, html_str) = (entry.url.clone(), entry.html_str.clone());
+                self.go_to(url, html_str, &CancellationToken::default())
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Whether a URL points to the loaded document, with a different fragment.
+    fn is_same_document(&self, url: &Url) -> bool {
+        self.document.is_some()
+            && url.fragment().is_some()
+            && url[..url::Position::AfterQuery] == self.url[..url::Position::AfterQuery]
+    }
+
+    /// Show the page at a URL: only update the URL if just the fragment changes,
+    /// otherwise load the page (from `html_str` if it's set).
+    async fn go_to(
+        &mut self,
+        url: Url,
+        html_str: Option<String>,
+        cancel: &CancellationToken,
+    ) -> DfResult<()> {
+        if html_str == self.html_str && self.is_same_document(&url) {
+            log::info!("navigating to fragment of '{url}'");
+            if self.base_url == self.url {
+                self.base_url = url.clone();
+            }
+            self.url = url;
+            let title = self.metadata.title.clone();
+            if let Some(entry) = self.history_entry_mut() {
+                entry.title = title;
+            }
+            return Ok(());
+        }
+
+        let previous = self.take_page();
+        self.url = url;
+        self.html_str = html_str;
+        self.html_base = None;
+        let result = self.load_page(false, |_| {}, cancel).await;
+        self.restore_if_cancelled(&result, previous);
+        result
+    }
+
+    /// Pull the page and its subresources, parse it and compute its layout.
+    ///
+    /// With the `tracing` feature, the load is recorded in a `load` span (with the `url`), with
+    /// `pull` spans for every resource (`url`, `bytes` and `from_cache`), a `parse` span and
+    /// `layout` spans (`node_count`, `viewport`). A `layout` span has a `style` span for selector
+    /// matching (`rules`, `nodes`) and a `boxes` span for the box geometry, with the amount of
+    /// text measured (`measured_texts`, `measured_bytes`). Building the display list is recorded
+    /// in a `display_list` span.
+    ///
+    /// ```
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # #[cfg(feature = "tracing")]
+    /// # {
+    /// # use std::sync::{Arc, Mutex};
+    /// # use tracing::{field::{Field, Visit}, span, Event, Metadata, Subscriber};
+    /// # // records "parent/span field=value ..." for every span
+    /// # #[derive(Default, Clone)]
+    /// # struct Spans { spans: Arc<Mutex<Vec<(String, String)>>>, stack: Arc<Mutex<Vec<u64>>> }
+    /// # struct Fields<'a>(&'a mut String);
+    /// # impl Visit for Fields<'_> {
+    /// #     fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+    /// #         self.0.push_str(&format!(" {}={:?}", field.name(), value));
+    /// #     }
+    /// # }
+    /// # impl Subscriber for Spans {
+    /// #     fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+    /// #     fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+    /// #         let mut spans = self.spans.lock().unwrap();
+    /// #         let parent = self.stack.lock().unwrap().last().map(|&id| spans[id as usize - 1].0.clone());
+    /// #         let name = match parent { Some(parent) => format!("{parent}/{}", attrs.metadata().name()), None => attrs.metadata().name().into() };
+    /// #         let mut fields = String::new();
+    /// #         attrs.record(&mut Fields(&mut fields));
+    /// #         spans.push((name, fields));
+    /// #         span::Id::from_u64(spans.len() as u64)
+    /// #     }
+    /// #     fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+    /// #         values.record(&mut Fields(&mut self.spans.lock().unwrap()[id.into_u64() as usize - 1].1));
+    /// #     }
+    /// #     fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+    /// #     fn event(&self, _: &Event<'_>) {}
+    /// #     fn enter(&self, id: &span::Id) { self.stack.lock().unwrap().push(id.into_u64()); }
+    /// #     fn exit(&self, _: &span::Id) { self.stack.lock().unwrap().pop(); }
+    /// # }
+    /// use dragonfly::WebContext;
+    /// let html = r#"<link rel="stylesheet" href="test.css"><p>Hello</p>"#;
+    /// let mut ctx = WebContext::builder().html(html).url("file:///tests/").build()?;
+    /// let subscriber = Spans::default();
+    /// tracing::subscriber::with_default(subscriber.clone(), || block_on(ctx.load()))?;
+    /// let spans = subscriber.spans.lock().unwrap();
+    /// let span = |name: &str| spans.iter().find(|(n, _)| n == name).map(|(_, fields)| fields.as_str());
+    /// assert_eq!(span("load"), Some(" url=file:///tests/"));
+    /// assert!(span("load/pull").unwrap().contains("url=file:///tests/test.css"));
+    /// assert!(span("load/pull").unwrap().contains("from_cache=false"));
+    /// assert!(span("load/parse").is_some());
+    /// assert!(span("load/layout").unwrap().contains("viewport="));
+    /// assert!(span("load/layout/style").unwrap().contains("rules="));
+    /// assert!(span("load/layout/boxes").unwrap().contains("measured_texts="));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn load(&mut self) -> DfResult<()> {
+        self.load_page(false, |_| {}, &CancellationToken::default())
+            .await
+    }
+
+    /// Load the page like [`WebContext::load`], stopping as soon as `cancel` is cancelled.
+    ///
+    /// On cancellation, [`DfError::Cancelled`] is returned and the context is left as it was
+    /// before the load: the previously loaded page is kept, or there is no document if
+    /// no page was loaded. The previous page is moved aside during the load rather than
+    /// copied, so it's dropped if the load fails for another reason.
+    pub async fn load_cancellable(&mut self, cancel: &CancellationToken) -> DfResult<()> {
+        let previous = self.take_page();
+        let result = self.load_page(false, |_| {}, cancel).await;
+        self.restore_if_cancelled(&result, previous);
+        result
+    }
+
+    /// Move the page out of the context before loading another one, leaving no document.
+    /// The URL and the markup stay, the load needs them.
+    fn take_page(&mut self) -> PageState {
+        PageState {
+            url: self.url.clone(),
+            original_url: self.original_url.clone(),
+            redirect_chain: std::mem::take(&mut self.redirect_chain),
+            base_url: self.base_url.clone(),
+            base_target: self.base_target.take(),
+            html_base: self.html_base.clone(),
+            html_str: self.html_str.clone(),
+            timers: std::mem::take(&mut self.timers),
+            document: self.document.take(),
+            load_state: self.load_state,
+            layout: self.layout.take_page(),
+            metadata: std::mem::take(&mut self.metadata),
+            encoding: self.encoding.take(),
+            doctype: self.doctype.take(),
+            scripts: std::mem::take(&mut self.scripts),
+            load_errors: std::mem::take(&mut self.load_errors),
+            history_entry: self.history.current().cloned(),
+        }
+    }
+
+    fn restore_if_cancelled(&mut self, result: &DfResult<()>, state: PageState) {
+        if !matches!(result, Err(DfError::Cancelled)) {
+            return;
+        }
+        log::info!("load of '{}' cancelled", self.url);
+        self.url = state.url;
+        self.original_url = state.original_url;
+        self.redirect_chain = state.redirect_chain;
+        self.base_url = state.base_url;
+        self.base_target = state.base_target;
+        self.html_base = state.html_base;
+        self.html_str = state.html_str;
+        self.timers = state.timers;
+        self.document = state.document;
+        self.load_state = state.load_state;
+        self.layout.restore_page(state.layout);
+        self.metadata = state.metadata;
+        self.encoding = state.encoding;
+        self.doctype = state.doctype;
+        self.scripts = state.scripts;
+        self.load_errors = state.load_errors;
+        if let (Some(entry), Some(previous)) = (self.history.current_mut(), state.history_entry) {
+            *entry = previous;
+        }
+    }
+
+    /// Load the page like [`WebContext::load`], calling `progress` every time a chunk of the
+    /// page is pulled. The page is parsed while it's pulled, and pages bigger than 1 MiB are
+    /// not kept in memory (nor cached) besides their parsed tree.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // 2 MiB, mostly comments
+    /// let item = format!("<li>item</li><!-- {} -->", "-".repeat(2000));
+    /// let html = format!("<ul>{}</ul>", item.repeat(1000));
+    /// let base = serve(move |_| Some(Response::ok("text/html", html.clone())));
+    /// let mut ctx = WebContext::new(&base, Default::default())?;
+    /// let mut steps = vec![];
+    /// block_on(ctx.load_with_progress(|progress| {
+    ///     steps.push((progress.pull.received, progress.elements));
+    /// }))?;
+    /// assert!(steps.len() > 1);
+    /// assert!(steps.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1));
+    /// // html, head, body, ul and the items
+    /// assert_eq!(steps.last().unwrap().1, 4 + 1000);
+    /// assert_eq!(ctx.layout.select("li").len(), 1000);
+    /// assert_eq!(ctx.puller.cache_stats().entries, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn load_with_progress(
+        &mut self,
+        progress: impl FnMut(&LoadProgress),
+    ) -> DfResult<()> {
+        self.load_page(false, progress, &CancellationToken::default())
+            .await
+    }
+
+    /// Load the current page again, recomputing its layout in place.
+    ///
+    /// If `force` is set, the page and its subresources are pulled again instead of
+    /// being served from the cache.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // local files are resolved from the working directory
+    /// let dir = format!("target/doctest-reload-{}", std::process::id());
+    /// std::fs::create_dir_all(&dir)?;
+    /// std::fs::write(format!("{dir}/page.html"), "<link rel=stylesheet href=style.css><h1>title</h1>")?;
+    /// std::fs::write(format!("{dir}/style.css"), "h1 { color: red; }")?;
+    /// let mut ctx = WebContext::new(&format!("file:///{dir}/page.html"), Default::default())?;
+    /// block_on(ctx.load())?;
+    /// let color = |ctx: &WebContext| {
+    ///     let color = ctx.layout.arena[ctx.layout.select("h1")[0]].get().style.as_ref()?.color?;
+    ///     Some((color.red, color.green, color.blue))
+    /// };
+    /// assert_eq!(color(&ctx), Some((1.0, 0.0, 0.0)));
+    ///
+    /// std::fs::write(format!("{dir}/style.css"), "h1 { color: blue; }")?;
+    /// // the warm cache serves the old stylesheet
+    /// let hits = ctx.puller.cache_stats().hits;
+    /// block_on(ctx.reload(false))?;
+    /// assert_eq!(ctx.puller.cache_stats().hits, hits + 2);
+    /// assert_eq!(color(&ctx), Some((1.0, 0.0, 0.0)));
+    /// // a forced reload pulls the new one
+    /// block_on(ctx.reload(true))?;
+    /// assert_eq!(color(&ctx), Some((0.0, 0.0, 1.0)));
+    /// std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn reload(&mut self, force: bool) -> DfResult<()> {
+        self.document = None;
+        self.load_page(force, |_| {}, &CancellationToken::default())
+            .await
+    }
+
+    async fn load_page(
+        &mut self,
+        bypass_cache: bool,
+        progress: impl FnMut(&LoadProgress),
+        cancel: &CancellationToken,
+    ) -> DfResult<()> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("load", url = %self.url);
+        let load = self.load_and_refresh(bypass_cache, progress, cancel);
+        #[cfg(feature = "tracing")]
+        let load = tracing::Instrument::instrument(load, span);
+        load.await
+    }
+
+    /// Load the page and follow its refreshes.
+    async fn load_and_refresh(
+        &mut self,
+        bypass_cache: bool,
+        mut progress: impl FnMut(&LoadProgress),
+        cancel: &CancellationToken,
+    ) -> DfResult<()> {
+        self.load_state = LoadState::Loading;
+        let mut result = self
+            .pull_and_lay_out(bypass_cache, &mut progress, cancel)
+            .await;
+
+        // follow refreshes, the refreshed page replaces the current history entry
+        let mut hops = 0;
+        while result.is_ok() {
+            let Some(url) = self.refresh_target() else {
+                break;
+            };
+            if hops == self.max_refresh_hops {
+                log::warn!("not following refresh to '{url}': too many refreshes");
+                break;
+            }
+            hops += 1;
+            log::info!("following refresh to '{url}'");
+            if let Some(entry) = self.history_entry_mut() {
+                *entry = HistoryEntry::new(url.clone(), None);
+            }
+            self.url = url;
+            self.html_str = None;
+            self.html_base = None;
+            self.document = None;
+            result = self
+                .pull_and_lay_out(bypass_cache, &mut progress, cancel)
+                .await;
+        }
+
+        if result.is_ok() {
+            self.observe(|o| o.on_load_finished(&self.timers));
+        }
+        self.load_state = match result {
+            Ok(()) => LoadState::Loaded,
+            Err(_) => LoadState::Failed,
+        };
+        result
+    }
+
+    async fn pull_and_lay_out(
+        &mut self,
+        bypass_cache: bool,
+        mut progress: impl FnMut(&LoadProgress),
+        cancel: &CancellationToken,
+    ) -> DfResult<()> {
         // pull page, measure time
         let start = Instant::now();
+        self.timers = Timers::default();
+        self.load_errors.clear();
+        self.original_url = self.url.clone();
+        self.redirect_chain.clear();
+        // resources of the previous page
+        self.layout.stylesheets.clear();
+        self.layout.image_sizes.clear();
+        self.layout.background_images.clear();
+        #[cfg(feature = "images")]
+        self.layout.image_handles.clear();
 
-        // if custom html str is available, prefer it over pulling the URL
-        let data = if self.html_str.is_some() {
-            self.html_str.clone().unwrap()
-        } else {
-            self.puller.pull_str(self.url.clone()).await?
+        // the page is parsed as it's pulled, chunk by chunk
+        log::info!("loading page at '{}'", self.url);
+        // a custom html str is already decoded, pulled pages are sniffed
+        let mut parser =
+            (self.html_str.is_some()).then(|| HtmlStreamParser::new(encoding_rs::UTF_8));
+        let mut parse_time = Duration::ZERO;
+        let mut bytes = 0;
+        let max_bytes = self.limits.max_bytes.unwrap_or(usize::MAX);
+        self.observe(|o| o.on_pull_start(&self.url));
+        let mut on_chunk = |chunk: &[u8], pull_progress: &PullProgress| {
+            // the page is cut at the byte budget
+            let chunk = &chunk[..chunk.len().min(max_bytes.saturating_sub(bytes))];
+            bytes = pull_progress.received.min(max_bytes);
+            let parse_start = Instant::now();
+            let parser = parser.get_or_insert_with(|| {
+                HtmlStreamParser::sniff(content_type_charset(pull_progress.content_type))
+            });
+            parser.feed(chunk);
+            parse_time += parse_start.elapsed();
+            progress(&LoadProgress {
+                pull: *pull_progress,
+                elements: parser.elements(),
+            });
         };
 
-        self.timers.pull = start.elapsed();
-        log::info!("pulled in {:?}", self.timers.pull);
+        // if custom html str is available, prefer it over pulling the URL
+        let (content_language, complete) = match &self.html_str {
+            Some(html_str) => {
+                let progress = PullProgress {
+                    content_type: None,
+                    received: html_str.len(),
+                    total: Some(html_str.len()),
+                };
+                on_chunk(html_str.as_bytes(), &progress);
+                (None, html_str.len() <= max_bytes)
+            }
+            None => {
+                let pull = self.puller.pull_limited(
+                    self.url.clone(),
+                    bypass_cache,
+                    max_bytes,
+                    Puller::MAX_STREAMED_LEN,
+                    &mut on_chunk,
+                );
+                match cancel.run(pull).await?? {
+                    Some(pulled) => {
+                        self.redirect_chain = pulled.redirects;
+                        (pulled.content_language, true)
+                    }
+                    None => (None, false),
+                }
+            }
+        };
+        // relative URLs are resolved against the final URL
+        if let Some(final_url) = self.redirect_chain.last() {
+            log::info!("page redirected to '{final_url}'");
+            self.url = final_url.clone();
+            let url = self.url.clone();
+            if let Some(entry) = self.history_entry_mut() {
+                entry.url = url;
+            }
+        }
+        self.page_bytes = bytes;
+        if !complete {
+            self.exceed_budget(
+                LoadPhase::Pull,
+                Some(self.url.clone()),
+                Budget::Bytes(max_bytes),
+            );
+        }
+        self.layout.default_lang = content_language
+            .or_else(|| self.lang.clone())
+            .unwrap_or_default();
 
-        // parse page, measure time
-        log::info!("parsing page at '{}'", self.url);
         let parse_start = Instant::now();
+        let parser = parser.unwrap_or_else(|| HtmlStreamParser::new(encoding_rs::UTF_8));
+        let (document, encoding) = parser.finish_with_encoding();
+        self.timers.parse = parse_time + parse_start.elapsed();
+        self.timers.pull = start.elapsed().saturating_sub(self.timers.parse);
+        log::info!(
+            "pulled in {:?}, parsed in {:?}",
+            self.timers.pull,
+            self.timers.parse
+        );
+        let error_count = document.errors.len();
+        self.observe(|o| {
+            o.on_pull_complete(&self.url, bytes, self.timers.pull);
+            o.on_parse_complete(self.timers.parse, error_count);
+        });
 
-        self.document = Some(Html::parse_document(&data));
+        cancel.check()?;
+        self.set_document(document, encoding);
 
-        self.timers.parse = parse_start.elapsed();
-        log::info!("parsed in {:?}", self.timers.parse);
+        // pull stylesheets and images, then lay out the page with them
+        self.load_subresources(bypass_cache, cancel).await?;
+        self.relayout();
+        self.update_metadata();
+
+        // measure page load time
+        self.timers.total = start.elapsed();
+        log::info!("loaded page in {:?}", self.timers.total);
+
+        Ok(())
+    }
 
+    /// Replace the document with a parsed one and build its layout tree. The layout is not
+    /// computed (see [`WebContext::relayout`]).
+    pub(crate) fn set_document(&mut self, document: Html, encoding: &'static Encoding) {
         // log quirks mode
-        match self.document().quirks_mode {
+        match QuirksMode::from(document.quirks_mode) {
             QuirksMode::Quirks => log::warn!("using quirks mode"),
             QuirksMode::LimitedQuirks => log::warn!("using limited quirks mode"),
             QuirksMode::NoQuirks => log::info!("using standard mode"),
         }
-        // log parser errors
-        for err in &self.document().errors {
+        // parser errors are recoverable
+        for err in &document.errors {
             log::warn!("HTML parser error: {:?}", err);
+            let error = LoadError::message(LoadPhase::Parse, Some(self.url.clone()), err.clone());
+            self.load_errors.push(error);
         }
 
-        // compute page layout
-        log::info!("computing layout for the first time");
-        self.recompute_layout();
+        self.encoding = Some(encoding);
+        self.doctype = DocumentType::of(&document);
+        self.document = Some(document);
 
-        // measure page load time
+        // the layout is computed once the subresources are loaded
+        self.build_tree();
+        self.update_base();
+        self.scripts = self.layout.scripts(&self.base_url);
+    }
+
+    /// Read the metadata of the page from its layout tree.
+    pub(crate) fn update_metadata(&mut self) {
+        self.metadata = self.layout.metadata(&self.base_url);
+        let title = self.metadata.title.clone();
+        if let Some(entry) = self.history_entry_mut() {
+            entry.title = title;
+        }
+    }
+
+    /// Replace the page with markup and lay it out, keeping the settings, fonts and cache of
+    /// the context. The stylesheets of `<style>` elements are applied, but no subresource
+    /// is pulled (see [`WebContext::set_html_and_load`]).
+    ///
+    /// The page URL and the history don't change. Relative URLs of the markup are resolved
+    /// against `url` (itself relative to the page URL), or the page URL if it's `None`.
+    ///
+    /// ```
+    /// use dragonfly::{LoadObserver, WebContext};
+    /// use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+    /// use std::time::Duration;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// struct Layouts(Arc<AtomicUsize>);
+    /// impl LoadObserver for Layouts {
+    ///     fn on_layout_complete(&self, _: Duration, _: usize) {
+    ///         self.0.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    /// }
+    /// let page_a = "<title>A</title><link rel=stylesheet href=test.css><p>page A</p>";
+    /// let mut ctx = WebContext::builder().html(page_a).url("file:///tests/a.html").build()?;
+    /// block_on(ctx.load())?;
+    /// let layouts = Arc::new(AtomicUsize::new(0));
+    /// ctx.set_load_observer(Layouts(layouts.clone()));
+    ///
+    /// let page_b = "<title>B</title><p>page B</p><a href=next.html>next</a>";
+    /// ctx.set_html(page_b, Some("https://b.example/docs/"))?;
+    /// assert_eq!(layouts.load(Ordering::Relaxed), 1);
+    /// let body = ctx.layout.select("body")[0];
+    /// assert_eq!(ctx.layout.text_content(body), "page Bnext");
+    /// assert_eq!(ctx.title().as_deref(), Some("B"));
+    /// // only the base URL changes
+    /// assert_eq!(ctx.url().as_str(), "file:///tests/a.html");
+    /// assert_eq!(ctx.base_url().as_str(), "https://b.example/docs/");
+    /// let link = ctx.attr_url(ctx.layout.select("a")[0], "href").unwrap()?;
+    /// assert_eq!(link.as_str(), "https://b.example/docs/next.html");
+    /// assert!(ctx.history().entries().is_empty());
+    ///
+    /// // the cache is still warm from page A
+    /// let hits = ctx.puller.cache_stats().hits;
+    /// block_on(ctx.set_html_and_load("<link rel=stylesheet href=test.css><p>page C</p>", None))?;
+    /// assert_eq!(ctx.puller.cache_stats().hits, hits + 1);
+    /// assert_eq!(ctx.base_url().as_str(), "file:///tests/a.html");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_html(&mut self, html: &str, url: Option<&str>) -> DfResult<()> {
+        let start = Instant::now();
+        self.replace_html(html, url)?;
+        self.set_author_stylesheets(vec![]);
+        self.relayout();
+        self.update_metadata();
+        self.load_state = LoadState::Loaded;
         self.timers.total = start.elapsed();
-        log::info!("loaded page in {:?}", self.timers.total);
+        Ok(())
+    }
 
+    /// Replace the page with markup like [`WebContext::set_html`], then pull its
+    /// stylesheets and images and lay it out with them.
+    pub async fn set_html_and_load(&mut self, html: &str, url: Option<&str>) -> DfResult<()> {
+        let start = Instant::now();
+        self.replace_html(html, url)?;
+        self.load_state = LoadState::Loading;
+        self.load_subresources(false, &CancellationToken::default())
+            .await?;
+        self.relayout();
+        self.update_metadata();
+        self.load_state = LoadState::Loaded;
+        self.timers.total = start.elapsed();
         Ok(())
     }
 
+    /// Parse markup and make it the document of the context.
+    fn replace_html(&mut self, html: &str, url: Option<&str>) -> DfResult<()> {
+        self.html_base = url.map(|url| self.url.join(url)).transpose()?;
+        self.original_url = self.url.clone();
+        self.redirect_chain.clear();
+        self.html_str = Some(html.to_string());
+
+        self.timers = Timers::default();
+        self.load_errors.clear();
+        self.page_bytes = html.len();
+        self.layout.stylesheets.clear();
+        self.layout.image_sizes.clear();
+        self.layout.background_images.clear();
+        #[cfg(feature = "images")]
+        self.layout.image_handles.clear();
+
+        let parse_start = Instant::now();
+        let document = parse_document(html);
+        self.timers.parse = parse_start.elapsed();
+        self.set_document(document, encoding_rs::UTF_8);
+        Ok(())
+    }
+
+    /// Rebuild the layout tree from the document and compute its layout.
+    ///
+    /// If the layout tree was modified (see [`Layout::is_modified`]), it no longer matches the
+    /// document, so it is not rebuilt: only its styles and geometry are recomputed to keep the changes.
     pub fn recompute_layout(&mut self) {
-        log::info!("recomputing layout...");
+        if self.layout.is_modified() {
+            self.relayout();
+            return;
+        }
+        self.build_layout();
+    }
+
+    /// Rebuild the layout tree from the document and compute its layout, discarding changes
+    /// made to the tree.
+    fn build_layout(&mut self) {
+        if self.build_tree() {
+            self.relayout();
+        }
+    }
+
+    /// Rebuild the layout tree from the document, without computing its layout. Returns
+    /// whether there is a document.
+    fn build_tree(&mut self) -> bool {
+        let Some(document) = &self.document else {
+            return false;
+        };
+        log::info!("building layout tree...");
+        // keep the layout settings (viewport, stylesheet, default language...)
+        self.layout.max_nodes = self.limits.max_nodes;
+        self.layout.build_document(document);
+        true
+    }
+
+    /// Recompute the styles and geometry of the current layout tree without rebuilding it
+    /// from the document, keeping changes made to the tree.
+    pub fn relayout(&mut self) {
+        log::info!("relayout...");
         let start = Instant::now();
+        self.layout.max_nodes = self.limits.max_nodes;
+        self.layout.max_layout_time = self.limits.max_layout_time;
+        self.layout.base_url = Some(self.base_url.clone());
 
-        let mut doc = self.document().clone();
-        self.layout = Layout::compute(&mut doc, &mut self.font_manager);
+        self.layout.relayout(&mut self.font_manager);
 
         self.timers.layout = start.elapsed();
+        self.timers.style = self.layout.style_time;
         log::info!("computed layout in {:?}", self.timers.layout);
+        self.layout_complete();
+    }
+
+    /// Non-fatal errors of the last load (or [`set_html`](WebContext::set_html)), in the order
+    /// they happened: subresources that couldn't be pulled, HTML parser errors, stylesheet
+    /// diagnostics, exceeded [`limits`](WebContext::limits), and the errors of the current layout.
+    /// They are cleared when a page starts loading.
+    ///
+    /// ```
+    /// use dragonfly::{DfError, LoadPhase, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = r#"<link rel="stylesheet" href="missing.css"><img src="missing.png">"#;
+    /// let mut ctx = WebContext::builder().html(html).url("file:///nonexistent/").build()?;
+    /// block_on(ctx.load())?;
+    /// let pull_errors: Vec<&str> = ctx
+    ///     .load_errors()
+    ///     .iter()
+    ///     .filter(|err| err.phase == LoadPhase::Pull)
+    ///     .filter_map(|err| Some(err.url.as_ref()?.as_str()))
+    ///     .collect();
+    /// assert_eq!(pull_errors, ["file:///nonexistent/missing.css", "file:///nonexistent/missing.png"]);
+    ///
+    /// // the underlying errors say which resource failed, and keep the error of the pull
+    /// let load_error = ctx.load_errors().iter().find(|err| err.phase == LoadPhase::Pull).unwrap();
+    /// assert!(load_error.to_string().contains("file:///nonexistent/missing.css"));
+    /// let error = load_error.error.as_ref().unwrap();
+    /// assert_eq!(error.to_string(), "pull error for file:///nonexistent/missing.css");
+    /// assert!(matches!(error.without_context(), DfError::IoError(_)));
+    /// let io_error = std::iter::successors(Some(error.as_ref() as &dyn std::error::Error), |err| err.source())
+    ///     .find_map(|err| err.downcast_ref::<std::io::Error>())
+    ///     .unwrap();
+    /// assert_eq!(io_error.kind(), std::io::ErrorKind::NotFound);
+    /// // the message of the load error has the messages of the sources, once
+    /// let io_message = io_error.to_string();
+    /// assert_eq!(load_error.message.matches(&io_message).count(), 1);
+    ///
+    /// // over HTTP, error responses are pull errors too
+    /// let server = serve(|_| None);
+    /// let mut ctx = WebContext::builder().html(html).url(&server).build()?;
+    /// block_on(ctx.load())?;
+    /// let statuses: Vec<_> = ctx
+    ///     .load_errors()
+    ///     .iter()
+    ///     .filter_map(|err| match err.error.as_deref()?.without_context() {
+    ///         DfError::HttpStatus { url, status } => Some((url.rsplit('/').next()?.to_string(), *status)),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(statuses, [("missing.css".to_string(), 404), ("missing.png".to_string(), 404)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn load_errors(&self) -> &[LoadError] {
+        &self.load_errors
+    }
+
+    pub(crate) fn add_load_error(&mut self, error: LoadError) {
+        self.load_errors.push(error);
+    }
+
+    /// Set the user stylesheet, that applies to every page loaded by the context (see
+    /// [`Layout::user_style`]). It overrides the user agent styles, and author styles
+    /// override it, except for its `!important` declarations.
+    ///
+    /// The stylesheet is parsed immediately: its diagnostics are added to the load errors of
+    /// the page, and the page is styled again.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// let html = "<style>p { height: 12px; color: blue; }</style><p>text</p>";
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// ctx.set_html(html, None)?;
+    /// let p = |ctx: &WebContext| ctx.layout.arena[ctx.layout.select("p")[0]].get().clone();
+    /// assert_eq!(p(&ctx).border_rect.height(), 12.0);
+    ///
+    /// ctx.set_user_stylesheet("p { height: 30px !important; color: red; }")?;
+    /// assert_eq!(p(&ctx).border_rect.height(), 30.0);
+    /// // the author color wins over the normal user declaration
+    /// let color = p(&ctx).style.and_then(|style| style.color);
+    /// assert_eq!(color.map(|c| (c.red, c.green, c.blue)), Some((0.0, 0.0, 1.0)));
+    ///
+    /// // the stylesheet applies to the next pages too
+    /// ctx.set_html("<p>next</p>", None)?;
+    /// assert_eq!(p(&ctx).border_rect.height(), 30.0);
+    /// # Ok::<(), dragonfly::DfError>(())
+    /// ```
+    pub fn set_user_stylesheet(&mut self, css: &str) -> DfResult<()> {
+        let style = GlobalStyle::from_css(css, ParserMode::Normal);
+        for diagnostic in &style.diagnostics {
+            log::warn!("user stylesheet: {diagnostic}");
+            self.add_load_error(LoadError::message(LoadPhase::Style, None, diagnostic));
+        }
+        self.layout.user_style = Some(style);
+        if self.document.is_some() {
+            self.relayout();
+        }
+        Ok(())
+    }
+
+    /// Remove the user stylesheet and style the page again.
+    pub fn clear_user_stylesheet(&mut self) {
+        if self.layout.user_style.take().is_some() && self.document.is_some() {
+            self.relayout();
+        }
+    }
+
+    /// The user stylesheet (see [`WebContext::set_user_stylesheet`]).
+    #[inline]
+    pub fn user_stylesheet(&self) -> Option<&GlobalStyle> {
+        self.layout.user_style.as_ref()
+    }
+
+    /// Build the display list of the page (see [`Layout::display_list`]), recording the time
+    /// it took in the timers.
+    pub fn display_list(&mut self) -> Vec<DisplayItem> {
+        let start = Instant::now();
+        let items = self.layout.display_list();
+        self.timers.paint = start.elapsed();
+        items
+    }
+
+    fn layout_complete(&mut self) {
+        // the errors of the previous layout are replaced
+        self.load_errors
+            .retain(|err| err.phase != LoadPhase::Layout);
+        for err in &self.layout.errors {
+            let error = LoadError::new(LoadPhase::Layout, None, DfError::Layout(err.clone()));
+            self.load_errors.push(error);
+        }
+        self.observe(|o| {
+            let node_count = self
+                .layout
+                .root_id()
+                .descendants(&self.layout.arena)
+                .count();
+            o.on_layout_complete(self.timers.layout, node_count);
+        });
+    }
+
+    /// Set the viewport size in CSS pixels. If the page is loaded, its layout is recomputed.
+    ///
+    /// Sizes that are not positive numbers (zero, negative, infinite or NaN) are ignored, the
+    /// viewport keeps its size.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let text = "word ".repeat(100);
+    /// let html = format!(r#"<p>{text}</p><div style="width: 50%">half</div>"#);
+    /// let mut ctx = WebContext::builder().html(&html).viewport(1200.0, 800.0).build()?;
+    /// block_on(ctx.load())?;
+    /// let lines = |ctx: &WebContext| {
+    ///     let text = ctx.layout.arena[ctx.layout.select("p")[0]].first_child().unwrap();
+    ///     ctx.layout.arena[text].get().fragments.len()
+    /// };
+    /// let div_width = |ctx: &WebContext| ctx.layout.arena[ctx.layout.select("div")[0]].get().border_rect.width();
+    /// let wide = lines(&ctx);
+    /// assert_eq!(div_width(&ctx), (1200.0 - 16.0) / 2.0);
+    ///
+    /// // resizing lays the page out again
+    /// ctx.set_viewport(400.0, 800.0);
+    /// assert!(lines(&ctx) > wide);
+    /// assert_eq!(div_width(&ctx), (400.0 - 16.0) / 2.0);
+    /// assert!(ctx.timers.layout > std::time::Duration::ZERO);
+    ///
+    /// // invalid sizes and scale factors are ignored
+    /// ctx.set_viewport(0.0, 800.0);
+    /// ctx.set_viewport(-100.0, 800.0);
+    /// ctx.set_viewport(f32::NAN, f32::INFINITY);
+    /// ctx.set_scale_factor(0.0);
+    /// ctx.set_scale_factor(f32::NAN);
+    /// assert_eq!((ctx.viewport().size.x, ctx.viewport().size.y), (400.0, 800.0));
+    /// assert_eq!(ctx.viewport().scale_factor, 1.0);
+    /// assert_eq!(div_width(&ctx), (400.0 - 16.0) / 2.0);
+    ///
+    /// ctx.set_scale_factor(2.0);
+    /// assert_eq!(div_width(&ctx), 400.0 - 16.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        if !is_positive(width) || !is_positive(height) {
+            log::warn!("ignoring invalid viewport size {width}x{height}");
+            return;
+        }
+        self.layout.viewport.size = Vec2::new(width, height);
+        self.viewport_changed();
+    }
+
+    /// Set the number of device pixels per CSS pixel. If the page is loaded, its layout is recomputed.
+    ///
+    /// Scale factors that are not positive numbers are ignored (see [`WebContext::set_viewport`]).
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        if !is_positive(scale_factor) {
+            log::warn!("ignoring invalid scale factor {scale_factor}");
+            return;
+        }
+        self.layout.viewport.scale_factor = scale_factor;
+        self.viewport_changed();
+    }
+
+    /// The viewport the page is laid out in.
+    #[inline]
+    pub fn viewport(&self) -> Viewport {
+        self.layout.viewport
+    }
+
+    fn viewport_changed(&mut self) {
+        // the node tree doesn't depend on the viewport, only the styles and geometry have to be recomputed
+        if self.document.is_some() {
+            self.relayout();
+        }
+    }
+
+    /// Pages visited by the context (and the other contexts the store is shared with).
+    /// Pages are marked visited when they're navigated to.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let next = "data:text/html,<p>next</p>";
+    /// let mut ctx = WebContext::builder().html(&format!("<a href='{next}'>next</a>")).build()?;
+    /// block_on(ctx.load())?;
+    /// let link_color = |ctx: &WebContext| {
+    ///     let color = ctx.layout.arena[ctx.layout.select("a")[0]].get().style.as_ref()?.color?;
+    ///     Some((color.red, color.green, color.blue))
+    /// };
+    /// let unvisited = link_color(&ctx);
+    ///
+    /// block_on(ctx.navigate(next))?;
+    /// block_on(ctx.back())?;
+    /// assert!(ctx.visited().contains(&next.parse()?));
+    /// assert_ne!(link_color(&ctx), unvisited);
+    /// // purple
+    /// assert_eq!(link_color(&ctx), Some((128.0 / 255.0, 0.0, 128.0 / 255.0)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn visited(&self) -> &VisitedLinks {
+        &self.layout.visited
+    }
+
+    /// Mark a URL as visited (relative URLs are resolved against the base URL of the page),
+    /// and update the styles of the links to it.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// // only the colors of `:visited` rules apply
+    /// let html = "<style>a:visited { color: red; display: none; }</style>\
+    ///             <a href='/a'>a</a> <a href='/b'>b</a>";
+    /// let mut ctx = WebContext::builder().html(html).url("https://example.com/").build()?;
+    /// ctx.set_html(html, None)?;
+    /// ctx.mark_visited("/b#top")?;
+    /// let links = ctx.layout.select("a");
+    /// assert_eq!(ctx.layout.select(":visited"), [links[1]]);
+    /// assert_eq!(ctx.layout.select(":link"), [links[0]]);
+    /// let visited = ctx.layout.arena[links[1]].get();
+    /// let color = visited.style.as_ref().and_then(|style| style.color);
+    /// assert_eq!(color.map(|c| (c.red, c.green, c.blue)), Some((1.0, 0.0, 0.0)));
+    /// assert!(visited.border_rect.width() > 0.0);
+    /// # Ok::<(), dragonfly::DfError>(())
+    /// ```
+    pub fn mark_visited(&mut self, url: &str) -> DfResult<()> {
+        let url = self.resolve_url(url)?;
+        self.layout.visited.mark(&url);
+        if self.document.is_some() {
+            self.relayout();
+        }
+        Ok(())
+    }
+
+    /// Set the element under the pointer and update the layout if the styles depend on it.
+    /// Returns whether the page has to be repainted (see [`Layout::set_hover`]).
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// let html = "<style>a:hover { background-color: yellow; }</style>\
+    ///             <a href='/'>link</a><div>text</div>";
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// ctx.set_html(html, None)?;
+    /// let (link, div) = (ctx.layout.select("a")[0], ctx.layout.select("div")[0]);
+    /// let background = |ctx: &WebContext| {
+    ///     let color = ctx.layout.arena[link].get().style.as_ref()?.background_color?;
+    ///     Some((color.red, color.green, color.blue))
+    /// };
+    ///
+    /// assert!(!ctx.set_hover(Some(div)), "no repaint needed");
+    /// assert_eq!(background(&ctx), None);
+    /// assert!(ctx.set_hover(Some(link)), "repaint needed");
+    /// assert_eq!(background(&ctx), Some((1.0, 1.0, 0.0)));
+    /// assert!(ctx.set_hover(None));
+    /// assert_eq!(background(&ctx), None);
+    /// # Ok::<(), dragonfly::DfError>(())
+    /// ```
+    pub fn set_hover(&mut self, id: Option<NodeId>) -> bool {
+        let repaint = self.layout.set_hover(id);
+        if repaint {
+            self.relayout();
+        }
+        repaint
+    }
+
+    /// Set the focused element and update the layout if the styles depend on it.
+    /// Returns whether the page has to be repainted (see [`Layout::set_focus`]).
+    pub fn set_focus(&mut self, id: Option<NodeId>) -> bool {
+        let repaint = self.layout.set_focus(id);
+        if repaint {
+            self.relayout();
+        }
+        repaint
+    }
+
+    /// Set an attribute on every element matching a selector and update the layout.
+    pub fn set_attribute(&mut self, selector: &str, name: &str, value: &str) {
+        for id in self.layout.select(selector) {
+            self.layout.set_attribute(id, name, value);
+        }
+        if self.layout.needs_relayout() {
+            self.relayout();
+        }
+    }
+
+    /// Remove an attribute from every element matching a selector and update the layout.
+    pub fn remove_attribute(&mut self, selector: &str, name: &str) {
+        for id in self.layout.select(selector) {
+            self.layout.remove_attribute(id, name);
+        }
+        if self.layout.needs_relayout() {
+            self.relayout();
+        }
+    }
+
+    /// Parsed page, if it's loaded.
+    #[inline]
+    pub fn document(&self) -> Option<&Html> {
+        self.document.as_ref()
+    }
+
+    #[inline]
+    pub fn document_mut(&mut self) -> Option<&mut Html> {
+        self.document.as_mut()
+    }
+
+    /// Parsed page, or [`DfError::NotLoaded`] if no page is loaded.
+    ///
+    /// ```
+    /// use dragonfly::{DfError, FontManager, LoadState, WebContext};
+    /// let mut ctx = WebContext::new_from_html("<p>hi</p>", "https://example.com/", FontManager::default())?;
+    /// // nothing to lay out yet
+    /// ctx.recompute_layout();
+    /// assert!(matches!(ctx.try_document(), Err(DfError::NotLoaded)));
+    /// assert_eq!(ctx.load_state(), LoadState::NotLoaded);
+    /// # Ok::<(), DfError>(())
+    /// ```
+    pub fn try_document(&self) -> DfResult<&Html> {
+        self.document.as_ref().ok_or(DfError::NotLoaded)
+    }
+
+    /// Whether a page is loaded.
+    #[inline]
+    pub fn is_loaded(&self) -> bool {
+        self.document.is_some()
     }
 
+    /// State of the last load.
     #[inline]
-    pub fn document(&mut self) -> &mut Html {
-        self.document.as_mut().unwrap()
+    pub fn load_state(&self) -> LoadState {
+        self.load_state
     }
 }
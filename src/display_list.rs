@@ -0,0 +1,660 @@
+use crate::{
+    image_size, BackgroundImage, BackgroundRepeat, BackgroundSize, BorderStyle, Dimension, Display,
+    Layout, ListMarker, ListStyleType, Pos2, Position, Rect, Repeat, Sides, TextStyle, Vec2,
+    Visibility,
+};
+use css_color::Srgb;
+use indextree::NodeId;
+
+/// A single paint operation, in page coordinates.
+#[derive(Debug, Clone)]
+pub enum DisplayItem {
+    /// Fill a rectangle with a solid color.
+    Rect {
+        node: NodeId,
+        pos: Pos2,
+        size: Vec2,
+        /// Radii of the rounded corners, see [`Border`](DisplayItem::Border)
+        radii: [Vec2; 4],
+        color: Srgb,
+    },
+    /// Draw the background image of a box, whose bytes are in [`Layout::background_images`]
+    /// by `url`. Its first tile is `tile` (see [`Layout::background_tile`]), the tiles are
+    /// repeated as `repeat` says and clipped to the border box (`pos`, `size` and `radii`).
+    BackgroundImage {
+        node: NodeId,
+        pos: Pos2,
+        size: Vec2,
+        radii: [Vec2; 4],
+        tile: Rect,
+        repeat: BackgroundRepeat,
+        url: String,
+    },
+    /// Draw the borders of a box: each side is drawn inside the border box, `widths` wide.
+    /// Sides without a border have a width of 0.
+    Border {
+        node: NodeId,
+        pos: Pos2,
+        size: Vec2,
+        /// Horizontal and vertical radii of the rounded corners of the border box: top-left,
+        /// top-right, bottom-right, bottom-left. They're scaled down so that the radii of
+        /// adjacent corners don't overlap
+        radii: [Vec2; 4],
+        widths: Sides<f32>,
+        styles: Sides<BorderStyle>,
+        colors: Sides<Srgb>,
+    },
+    /// Draw a shadow of a box. Outer shadows are drawn outside of the border box (`pos`, `size`
+    /// and `radii`) under the background, inset shadows inside of the padding box over the
+    /// background. The shadow shape is the box moved by `offset` and grown by `spread` (shrunk
+    /// for inset shadows), with edges blurred over `blur` pixels.
+    BoxShadow {
+        node: NodeId,
+        pos: Pos2,
+        size: Vec2,
+        radii: [Vec2; 4],
+        offset: Vec2,
+        blur: f32,
+        spread: f32,
+        color: Srgb,
+        inset: bool,
+    },
+    /// Draw an outline: a line `width` wide inside the rectangle (see
+    /// [`DOMNode::outline_rect`](crate::DOMNode::outline_rect)). Outlines are painted after
+    /// everything else.
+    Outline {
+        node: NodeId,
+        pos: Pos2,
+        size: Vec2,
+        width: f32,
+        style: BorderStyle,
+        color: Srgb,
+    },
+    /// Placeholder for the content of a replaced element (`img`, `svg` or `canvas`),
+    /// drawn by the embedder. The markup of an `svg` is available from [`Layout::svg_source`].
+    Replaced { node: NodeId, pos: Pos2, size: Vec2 },
+    /// Start a group of paint operations that are composited together with an `opacity`
+    /// from 0 to 1, when the matching [`PopOpacity`](DisplayItem::PopOpacity) is reached.
+    /// Groups can be nested: an element and its content is a group.
+    PushOpacity { node: NodeId, opacity: f32 },
+    /// End the group of the last [`PushOpacity`](DisplayItem::PushOpacity).
+    PopOpacity { node: NodeId },
+    /// Clip the paint operations to a rectangle until the matching
+    /// [`PopClip`](DisplayItem::PopClip), e.g. with a scissor rect. The content of a box with a
+    /// non-visible `overflow` is clipped to its [`overflow_clip`](crate::DOMNode::overflow_clip).
+    /// Clips can be nested, the content is clipped to all of them. Positioned elements painted
+    /// out of tree order are clipped again to the clips of their ancestors.
+    PushClip { node: NodeId, rect: Rect },
+    /// End the clip of the last [`PushClip`](DisplayItem::PushClip).
+    PopClip { node: NodeId },
+    /// Draw a decoded image (an `img` element), scaled to the size of its content box.
+    /// The pixels are resolved with [`WebContext::image`](crate::WebContext::image).
+    #[cfg(feature = "images")]
+    Image {
+        node: NodeId,
+        pos: Pos2,
+        size: Vec2,
+        image: crate::ImageHandle,
+    },
+    /// Draw a line fragment of text, and its decorations (see [`Decoration::offsets`](crate::Decoration::offsets)).
+    Text {
+        node: NodeId,
+        pos: Pos2,
+        size: Vec2,
+        /// Distance from `pos` to the baseline of the text
+        baseline: f32,
+        text: String,
+        style: TextStyle,
+    },
+}
+
+impl Layout {
+    /// Build the list of paint operations for the laid-out tree, in painting order.
+    pub fn display_list(&self) -> Vec<DisplayItem> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("display_list", items = tracing::field::Empty).entered();
+
+        let mut painter = Painter {
+            items: vec![],
+            outlines: vec![],
+            opacities: vec![1.0],
+            clips: vec![],
+        };
+        self.paint_stacking_context(self.root_id(), &mut painter);
+        let mut items = painter.items;
+        items.extend(painter.outlines);
+
+        #[cfg(feature = "tracing")]
+        span.record("items", items.len());
+        items
+    }
+
+    /// Whether a node is painted in the stacking order of its stacking context instead of in
+    /// tree order: positioned elements (with a `z-index` or not) are.
+    fn is_stacked(&self, id: NodeId) -> bool {
+        let style = self.arena[id].get().style.as_ref();
+        id != self.root_id()
+            && style.and_then(|s| s.position).unwrap_or_default() != Position::Static
+    }
+
+    /// Whether a node is painted as a whole in tree order, its positioned descendants can't be
+    /// painted under or over the rest of the page: elements with an opacity are.
+    fn is_group(&self, id: NodeId) -> bool {
+        let style = self.arena[id].get().style.as_ref();
+        style
+            .and_then(|s| s.opacity)
+            .is_some_and(|opacity| opacity < 1.0)
+    }
+
+    /// Paint a stacking context: the element at its root, the positioned descendants with a
+    /// negative `z-index`, the descendants in normal flow, then the other positioned descendants
+    /// (an `auto` z-index is 0). Descendants with the same z-index are painted in tree order.
+    fn paint_stacking_context(&self, id: NodeId, painter: &mut Painter) {
+        let node = self.arena[id].get();
+        let style = node.style.as_ref();
+        if let Some(Display::None) = style.and_then(|s| s.display) {
+            return;
+        }
+
+        let mut stacked = self.stacked_descendants(id);
+        stacked.sort_by_key(|&(z_index, _, _)| z_index);
+        let (negative, positive): (Vec<_>, Vec<_>) = stacked
+            .into_iter()
+            .partition(|&(z_index, _, _)| z_index < 0);
+
+        let group_opacity = style.and_then(|s| s.opacity).filter(|&o| o < 1.0);
+        if let Some(opacity) = group_opacity {
+            painter
+                .items
+                .push(DisplayItem::PushOpacity { node: id, opacity });
+            let compound = painter.opacities.last().unwrap_or(&1.0) * opacity;
+            painter.opacities.push(compound);
+        }
+        self.paint_own(id, painter);
+        if let Some(rect) = node.overflow_clip {
+            painter.push_clip(id, rect);
+        }
+
+        for (_, id, clip) in negative {
+            self.paint_stacked(id, clip, painter);
+        }
+        self.paint_flow(id, painter);
+        for (_, id, clip) in positive {
+            self.paint_stacked(id, clip, painter);
+        }
+
+        if node.overflow_clip.is_some() {
+            painter.pop_clip(id);
+        }
+        if group_opacity.is_some() {
+            painter.items.push(DisplayItem::PopOpacity { node: id });
+            painter.opacities.pop();
+        }
+    }
+
+    /// Positioned descendants of the root of a stacking context that belong to it, with their
+    /// z-index and the clip of their ancestors below the root, in tree order.
+    fn stacked_descendants(&self, root: NodeId) -> Vec<(i32, NodeId, Option<Rect>)> {
+        let mut stacked = vec![];
+        let mut stack: Vec<(NodeId, Option<Rect>)> = root
+            .children(&self.arena)
+            .map(|child| (child, None))
+            .collect();
+        stack.reverse();
+        while let Some((id, clip)) = stack.pop() {
+            let node = self.arena[id].get();
+            let style = node.style.as_ref();
+            if self.is_stacked(id) {
+                stacked.push((style.and_then(|s| s.z_index).unwrap_or(0), id, clip));
+                continue;
+            }
+            if self.is_group(id) {
+                continue;
+            }
+            let clip = match (clip, node.overflow_clip) {
+                (Some(outer), Some(rect)) => Some(outer.intersection(&rect).unwrap_or(Rect::ZERO)),
+                (clip, rect) => clip.or(rect),
+            };
+            let children: Vec<NodeId> = id.children(&self.arena).collect();
+            stack.extend(children.into_iter().rev().map(|child| (child, clip)));
+        }
+        stacked
+    }
+
+    /// Paint a positioned element out of tree order, clipped by its ancestors again.
+    fn paint_stacked(&self, id: NodeId, clip: Option<Rect>, painter: &mut Painter) {
+        if let Some(rect) = clip {
+            painter.push_clip(id, rect);
+        }
+        self.paint_stacking_context(id, painter);
+        if clip.is_some() {
+            painter.pop_clip(id);
+        }
+    }
+
+    /// Paint the descendants of a node in normal flow, in tree order. Positioned descendants
+    /// are skipped, they're painted by their stacking context.
+    fn paint_flow(&self, root: NodeId, painter: &mut Painter) {
+        let mut stack: Vec<Step> = root.children(&self.arena).map(Step::Paint).collect();
+        stack.reverse();
+        while let Some(step) = stack.pop() {
+            let id = match step {
+                Step::Paint(id) => id,
+                Step::EndClip(id) => {
+                    painter.pop_clip(id);
+                    continue;
+                }
+            };
+            if self.is_stacked(id) {
+                continue;
+            }
+            if self.is_group(id) {
+                self.paint_stacking_context(id, painter);
+                continue;
+            }
+            let node = self.arena[id].get();
+            if let Some(Display::None) = node.style.as_ref().and_then(|s| s.display) {
+                continue;
+            }
+
+            self.paint_own(id, painter);
+            if let Some(rect) = node.overflow_clip {
+                painter.push_clip(id, rect);
+                stack.push(Step::EndClip(id));
+            }
+
+            let children: Vec<NodeId> = id.children(&self.arena).collect();
+            stack.extend(children.into_iter().rev().map(Step::Paint));
+        }
+    }
+
+    /// Paint a visible node itself, with its outline clipped like its content.
+    fn paint_own(&self, id: NodeId, painter: &mut Painter) {
+        let style = self.arena[id].get().style.as_ref();
+        if style.and_then(|s| s.visibility).unwrap_or_default() != Visibility::Visible {
+            return;
+        }
+        let opacity = *painter.opacities.last().unwrap_or(&1.0);
+        let start = painter.outlines.len();
+        self.paint_node(id, opacity, &mut painter.items, &mut painter.outlines);
+        if let Some(&rect) = painter
+            .clips
+            .last()
+            .filter(|_| painter.outlines.len() > start)
+        {
+            painter
+                .outlines
+                .insert(start, DisplayItem::PushClip { node: id, rect });
+            painter.outlines.push(DisplayItem::PopClip { node: id });
+        }
+    }
+
+    /// Paint operations of a node itself, without its children. Its outline is added to
+    /// `outlines` with the compound `opacity` of the groups it's in.
+    fn paint_node(
+        &self,
+        id: NodeId,
+        opacity: f32,
+        items: &mut Vec<DisplayItem>,
+        outlines: &mut Vec<DisplayItem>,
+    ) {
+        let node = self.arena[id].get();
+        let style = node.style.as_ref();
+        let radii = clamp_radii(node.border_radius, node.border_rect.size);
+        let shadows = self.box_shadows(id, radii);
+        let (inset, outer): (Vec<_>, Vec<_>) = shadows
+            .into_iter()
+            .partition(|shadow| matches!(shadow, DisplayItem::BoxShadow { inset: true, .. }));
+        items.extend(outer);
+        let background = style.and_then(|s| s.background_color);
+        if let Some(color) = background.filter(|color| color.alpha > 0.0) {
+            items.push(DisplayItem::Rect {
+                node: id,
+                pos: node.border_rect.pos,
+                size: node.border_rect.size,
+                radii,
+                color,
+            });
+        }
+        if let (Some(BackgroundImage::Url(url)), Some(tile)) = (
+            style.and_then(|s| s.background_image.as_ref()),
+            self.background_tile(id),
+        ) {
+            items.push(DisplayItem::BackgroundImage {
+                node: id,
+                pos: node.border_rect.pos,
+                size: node.border_rect.size,
+                radii,
+                tile,
+                repeat: style.and_then(|s| s.background_repeat).unwrap_or_default(),
+                url: url.clone(),
+            });
+        }
+        items.extend(inset);
+
+        if let Some(style) = style.filter(|_| node.border.iter().any(|&w| w > 0.0)) {
+            let color = node.text_style().color;
+            items.push(DisplayItem::Border {
+                node: id,
+                pos: node.border_rect.pos,
+                size: node.border_rect.size,
+                radii,
+                widths: node.border,
+                styles: style.border.map(|side| side.style.unwrap_or_default()),
+                colors: style
+                    .border
+                    .map(|side| side.color.unwrap_or_default().resolve(color)),
+            });
+        }
+
+        if let (Some(style), Some(rect)) = (style, node.outline_rect()) {
+            outlines.push(DisplayItem::Outline {
+                node: id,
+                pos: rect.pos,
+                size: rect.size,
+                width: node.outline_width,
+                style: style.outline.style.unwrap_or_default(),
+                color: {
+                    let color = style.outline.color.unwrap_or_default();
+                    let mut color = color.resolve(node.text_style().color);
+                    color.alpha *= opacity;
+                    color
+                },
+            });
+        }
+
+        if let Some(marker) = &node.marker {
+            items.push(self.marker_item(id, marker));
+        }
+
+        if Layout::is_replaced(node) {
+            items.push(self.replaced_item(id));
+        }
+
+        for fragment in &node.fragments {
+            items.push(DisplayItem::Text {
+                node: id,
+                pos: fragment.pos,
+                size: fragment.size,
+                baseline: fragment.baseline,
+                text: fragment.text.clone(),
+                style: fragment.style.clone(),
+            });
+        }
+    }
+
+    /// Position and size of the first tile of the background image of a node, in device
+    /// pixels: `background-size` and `background-position` resolved in its padding box, for
+    /// the intrinsic size of the image. `None` if the image isn't loaded or its size is unknown.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<div style='width: 100px; height: 50px; padding: 10px;
+    ///     background: url(a.png) right 20% / 40px'></div>";
+    /// let mut layout = Layout::default();
+    /// layout.compute_document(&parse_document(html), &mut FontManager::default());
+    /// // a 4x2 PNG
+    /// let png = [b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".as_slice(), &[0, 0, 0, 4, 0, 0, 0, 2]];
+    /// layout.background_images.insert("a.png".to_string(), png.concat().into());
+    /// let div = layout.select("div")[0];
+    /// let tile = layout.background_tile(div).unwrap();
+    /// let padding_box = layout.arena[div].get().border_rect; // without borders
+    /// assert_eq!((tile.size.x, tile.size.y), (40.0, 20.0));
+    /// assert_eq!(tile.pos.x - padding_box.pos.x, 120.0 - 40.0);
+    /// assert_eq!(tile.pos.y - padding_box.pos.y, (70.0 - 20.0) * 0.2);
+    /// ```
+    pub fn background_tile(&self, id: NodeId) -> Option<Rect> {
+        let node = self.arena[id].get();
+        let style = node.style.as_ref()?;
+        let (width, height) = image_size(self.background_image(id)?)?;
+        let natural = Vec2::new(width as f32, height as f32) * self.viewport.scale_factor;
+        let area = node.border_rect.inset(node.border);
+        let ratio = |scale: fn(f32, f32) -> f32| match natural.x > 0.0 && natural.y > 0.0 {
+            true => natural * scale(area.size.x / natural.x, area.size.y / natural.y),
+            false => natural,
+        };
+        let mut size = match style.background_size.unwrap_or_default() {
+            BackgroundSize::Cover => ratio(f32::max),
+            BackgroundSize::Contain => ratio(f32::min),
+            BackgroundSize::Size(width, height) => {
+                let width = self.length(id, Some(width), Some(area.size.x));
+                let height = self.length(id, Some(height), Some(area.size.y));
+                // an `auto` side keeps the aspect ratio of the image
+                let scaled = |length: f32, from: f32, to: f32| match from > 0.0 {
+                    true => length * to / from,
+                    false => to,
+                };
+                match (width, height) {
+                    (Some(width), Some(height)) => Vec2::new(width, height),
+                    (Some(width), None) => Vec2::new(width, scaled(width, natural.x, natural.y)),
+                    (None, Some(height)) => Vec2::new(scaled(height, natural.y, natural.x), height),
+                    (None, None) => natural,
+                }
+            }
+        };
+
+        // `round` scales the image to fit a whole number of times
+        let repeat = style.background_repeat.unwrap_or_default();
+        let round = |length: f32, area: f32| match length > 0.0 {
+            true => area / (area / length).round().max(1.0),
+            false => length,
+        };
+        if repeat.x == Repeat::Round {
+            size.x = round(size.x, area.size.x);
+        }
+        if repeat.y == Repeat::Round {
+            size.y = round(size.y, area.size.y);
+        }
+
+        // percentages are of the space left around the image
+        let position = style.background_position.unwrap_or_default();
+        let offset =
+            |length: Dimension, free: f32| self.length(id, Some(length), Some(free)).unwrap_or(0.0);
+        let pos = area.pos
+            + Vec2::new(
+                offset(position.x, area.size.x - size.x),
+                offset(position.y, area.size.y - size.y),
+            );
+        Some(Rect::from_pos_size(pos, size))
+    }
+
+    /// Paint operation of the marker of a list item, in the color of its text.
+    fn marker_item(&self, id: NodeId, marker: &ListMarker) -> DisplayItem {
+        let color = self.arena[id].get().text_style().color;
+        let (pos, size) = match marker {
+            ListMarker::Bullet { rect, .. } | ListMarker::Image { rect, .. } => {
+                (rect.pos, rect.size)
+            }
+            ListMarker::Text(fragment) => (fragment.pos, fragment.size),
+        };
+        let round = [size / 2.0; 4];
+        match marker {
+            ListMarker::Bullet {
+                kind: ListStyleType::Circle,
+                ..
+            } => DisplayItem::Border {
+                node: id,
+                pos,
+                size,
+                radii: round,
+                widths: Sides::splat((size.x / 6.0).max(1.0)),
+                styles: Sides::splat(BorderStyle::Solid),
+                colors: Sides::splat(color),
+            },
+            ListMarker::Bullet { kind, .. } => DisplayItem::Rect {
+                node: id,
+                pos,
+                size,
+                radii: match kind {
+                    ListStyleType::Square => [Vec2::new(0.0, 0.0); 4],
+                    _ => round,
+                },
+                color,
+            },
+            ListMarker::Text(fragment) => DisplayItem::Text {
+                node: id,
+                pos,
+                size,
+                baseline: fragment.baseline,
+                text: fragment.text.clone(),
+                style: fragment.style.clone(),
+            },
+            ListMarker::Image { rect, url } => DisplayItem::BackgroundImage {
+                node: id,
+                pos,
+                size,
+                radii: [Vec2::new(0.0, 0.0); 4],
+                tile: *rect,
+                repeat: BackgroundRepeat {
+                    x: Repeat::NoRepeat,
+                    y: Repeat::NoRepeat,
+                },
+                url: url.clone(),
+            },
+        }
+    }
+
+    /// Shadows of a node with corner `radii`, in painting order (the last shadow first).
+    fn box_shadows(&self, id: NodeId, radii: [Vec2; 4]) -> Vec<DisplayItem> {
+        let node = self.arena[id].get();
+        let Some(shadows) = node
+            .style
+            .as_ref()
+            .and_then(|style| style.box_shadow.as_ref())
+        else {
+            return vec![];
+        };
+        let color = node.text_style().color;
+        let padding_rect = node.border_rect.inset(node.border);
+        let px = |length| self.length(id, Some(length), None).unwrap_or(0.0);
+        shadows
+            .iter()
+            .rev()
+            .map(|shadow| {
+                let (pos, size, radii) = match shadow.inset {
+                    true => (
+                        padding_rect.pos,
+                        padding_rect.size,
+                        inner_radii(radii, node.border),
+                    ),
+                    false => (node.border_rect.pos, node.border_rect.size, radii),
+                };
+                DisplayItem::BoxShadow {
+                    node: id,
+                    pos,
+                    size,
+                    radii,
+                    offset: Vec2::new(px(shadow.offset_x), px(shadow.offset_y)),
+                    blur: px(shadow.blur).max(0.0),
+                    spread: px(shadow.spread),
+                    color: shadow.color.resolve(color),
+                    inset: shadow.inset,
+                }
+            })
+            .collect()
+    }
+
+    /// Paint operation of a replaced element: its image if it was decoded, a placeholder otherwise.
+    fn replaced_item(&self, id: NodeId) -> DisplayItem {
+        let node = self.arena[id].get();
+        #[cfg(feature = "images")]
+        if let Some(&image) = node
+            .attr("src")
+            .filter(|_| node.name() == "img")
+            .and_then(|src| self.image_handles.get(src))
+        {
+            let rect = node.content_rect();
+            return DisplayItem::Image {
+                node: id,
+                pos: rect.pos,
+                size: rect.size,
+                image,
+            };
+        }
+        DisplayItem::Replaced {
+            node: id,
+            pos: node.border_rect.pos,
+            size: node.border_rect.size,
+        }
+    }
+}
+
+/// Step of the traversal of the tree in painting order.
+enum Step {
+    /// Paint a node, then its children
+    Paint(NodeId),
+    /// Close the overflow clip of a node, after its children
+    EndClip(NodeId),
+}
+
+/// Display list being built, with the opacity groups and clips that are open.
+struct Painter {
+    items: Vec<DisplayItem>,
+    /// Outlines are painted after everything else, outside of the groups
+    outlines: Vec<DisplayItem>,
+    /// Compound opacity of the open groups
+    opacities: Vec<f32>,
+    /// Intersection of the open clips, outlines of clipped nodes get their own
+    clips: Vec<Rect>,
+}
+
+impl Painter {
+    fn push_clip(&mut self, node: NodeId, rect: Rect) {
+        self.items.push(DisplayItem::PushClip { node, rect });
+        let clip = match self.clips.last() {
+            Some(outer) => outer.intersection(&rect).unwrap_or(Rect::ZERO),
+            None => rect,
+        };
+        self.clips.push(clip);
+    }
+
+    fn pop_clip(&mut self, node: NodeId) {
+        self.items.push(DisplayItem::PopClip { node });
+        self.clips.pop();
+    }
+}
+
+/// Radii of the corners of the inner edge of a border `widths` wide, given the radii of its
+/// outer edge.
+///
+/// ```
+/// use dragonfly::{inner_radii, Sides, Vec2};
+/// let radii = inner_radii([Vec2::new(10.0, 10.0); 4], Sides::new(4.0, 0.0, 20.0, 0.0));
+/// assert_eq!(radii[0], Vec2::new(10.0, 6.0));
+/// assert_eq!(radii[2], Vec2::new(10.0, 0.0));
+/// ```
+pub fn inner_radii(radii: [Vec2; 4], widths: Sides<f32>) -> [Vec2; 4] {
+    let insets = [
+        (widths.left, widths.top),
+        (widths.right, widths.top),
+        (widths.right, widths.bottom),
+        (widths.left, widths.bottom),
+    ];
+    std::array::from_fn(|i| {
+        let (x, y) = insets[i];
+        Vec2::new((radii[i].x - x).max(0.0), (radii[i].y - y).max(0.0))
+    })
+}
+
+/// Scale corner radii down so that the radii of adjacent corners fit on each side of a box
+/// of `size` (the radii of all corners are scaled by the same factor).
+///
+/// ```
+/// use dragonfly::{clamp_radii, Vec2};
+/// let radii = clamp_radii([Vec2::new(100.0, 10.0); 4], Vec2::new(100.0, 50.0));
+/// assert_eq!(radii[0], Vec2::new(50.0, 5.0));
+/// ```
+pub fn clamp_radii(radii: [Vec2; 4], size: Vec2) -> [Vec2; 4] {
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    let factor = [
+        (size.x, top_left.x + top_right.x),
+        (size.y, top_right.y + bottom_right.y),
+        (size.x, bottom_right.x + bottom_left.x),
+        (size.y, bottom_left.y + top_left.y),
+    ]
+    .into_iter()
+    .filter(|&(_, sum)| sum > 0.0)
+    .map(|(length, sum)| length / sum)
+    .fold(1.0f32, f32::min);
+    radii.map(|radius| radius * factor)
+}
@@ -1,41 +1,856 @@
-use crate::{Declaration, FontManager, Pos2, Vec2};
-use std::collections::HashMap;
+use crate::{
+    extract_charset, Declaration, Dimension, FontManager, ListMarker, Pos2, Rect, Sides, TextStyle,
+    Vec2,
+};
+use encoding_rs::Encoding;
+use html5ever::{driver::ParseOpts, tendril::TendrilSink, tree_builder::TreeBuilderOpts};
+use indexmap::IndexMap;
+use indextree::NodeId;
+use scraper::Html;
+use std::collections::BTreeMap;
+use std::ops::Range;
 
+/// Parser options of the documents rendered by dragonfly.
+///
+/// Scripts are never executed, so documents are parsed with scripting disabled: the content of
+/// `<noscript>` elements is parsed as markup, so it can be queried, but it isn't rendered.
+fn parse_opts() -> ParseOpts {
+    ParseOpts {
+        tree_builder: TreeBuilderOpts {
+            scripting_enabled: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Parse an HTML document the way dragonfly renders it (see [`HtmlStreamParser`]).
+///
+/// Elements with the `hidden` attribute, the content of `<template>` elements (which isn't
+/// even in the tree) and the content of `<noscript>` elements get no boxes or fragments, and
+/// their text is never found:
+///
+/// ```
+/// use dragonfly::{parse_document, FindOptions, FontManager, Layout};
+/// let html = "<p>shown</p><div hidden><p>secret one</p></div>
+///     <template><p>secret two</p></template>
+///     <noscript><p>secret three</p></noscript><p id=after>after</p>";
+/// let mut fonts = FontManager::default();
+/// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+/// let plain = "<p>shown</p><p id=after>after</p>";
+/// let plain = Layout::compute(&mut parse_document(plain), &mut fonts);
+///
+/// for selector in ["div[hidden]", "template", "noscript"] {
+///     let id = layout.select(selector)[0];
+///     for id in id.descendants(&layout.arena) {
+///         let node = layout.arena[id].get();
+///         assert!(node.border_rect.is_empty(), "{selector}: {}", node.border_rect);
+///         assert!(node.fragments.is_empty(), "{selector}");
+///     }
+/// }
+/// assert_eq!(layout.select("template p").len(), 0);
+/// assert_eq!(layout.select("noscript p").len(), 1);
+///
+/// // the hidden content takes no room
+/// let rect = |layout: &Layout, selector| layout.arena[layout.select(selector)[0]].get().border_rect;
+/// assert_eq!(rect(&layout, "#after"), rect(&plain, "#after"));
+/// let find = |query| layout.find_text(query, FindOptions::default(), &mut FontManager::default());
+/// assert!(find("secret").is_empty());
+/// assert_eq!((find("shown").len(), find("after").len()), (1, 1));
+/// ```
+pub fn parse_document(html: &str) -> Html {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("parse", bytes = html.len()).entered();
+    html5ever::parse_document(Html::new_document(), parse_opts()).one(html)
+}
+
+/// Number of bytes scanned for a `<meta charset>` declaration before parsing.
+const PRESCAN_LEN: usize = 1024;
+/// Number of bytes of a document that can be parsed again when it declares another
+/// encoding while it's parsed. Past them, the encoding is kept.
+const REPARSE_LEN: usize = 64 * 1024;
+
+/// Incremental HTML parser: the document is fed in chunks of bytes as they arrive,
+/// so it doesn't have to be buffered before parsing. The result is the same as
+/// [`parse_document`] on the whole decoded document.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{parse_document, HtmlStreamParser};
+/// let html = "<p>streamed <b>text</b> ✓</p>".repeat(100);
+/// let mut parser = HtmlStreamParser::new(encoding_rs::UTF_8);
+/// // chunks can split characters
+/// for chunk in html.as_bytes().chunks(7) {
+///     parser.feed(chunk);
+/// }
+/// assert_eq!(parser.finish().html(), parse_document(&html).html());
+/// ```
+///
+/// A large document streamed in chunks of 64 KiB, declaring its encoding after the prescanned
+/// bytes:
+///
+/// ```rust
+/// use dragonfly::{parse_document, HtmlStreamParser};
+/// let comments = "<!-- padding -->".repeat(100);
+/// let body = "<p>Привет, <b>мир</b></p>".repeat(50_000);
+/// let html = format!("<head>{comments}<meta charset=windows-1251></head><body>{body}");
+/// let (bytes, _, _) = encoding_rs::WINDOWS_1251.encode(&html);
+/// let mut parser = HtmlStreamParser::sniff(None);
+/// for chunk in bytes.chunks(64 * 1024) {
+///     parser.feed(chunk);
+/// }
+/// let expected = parse_document(&html);
+/// let elements = expected.tree.values().filter(|node| node.is_element()).count();
+/// assert_eq!(parser.elements(), elements);
+/// let (document, encoding) = parser.finish_with_encoding();
+/// assert_eq!(encoding, encoding_rs::WINDOWS_1251);
+/// assert_eq!(document.html(), expected.html());
+/// ```
+pub struct HtmlStreamParser {
+    /// Parser of the decoded document, `None` until the encoding is known
+    parser: Option<html5ever::driver::Parser<Html>>,
+    decoder: encoding_rs::Decoder,
+    /// Whether the encoding was guessed and can still change
+    tentative: bool,
+    /// Bytes fed while the encoding is tentative, to parse them again if it changes
+    buffer: Vec<u8>,
+    /// Number of bytes fed
+    bytes: usize,
+    /// Number of elements parsed
+    elements: usize,
+    /// Number of nodes of the document that were counted in `elements`
+    counted_nodes: usize,
+}
+
+impl HtmlStreamParser {
+    /// Create a parser for a document in an encoding. A byte order mark overrides the encoding.
+    pub fn new(encoding: &'static Encoding) -> Self {
+        Self {
+            parser: Some(html5ever::parse_document(
+                Html::new_document(),
+                parse_opts(),
+            )),
+            decoder: encoding.new_decoder(),
+            tentative: false,
+            buffer: vec![],
+            bytes: 0,
+            elements: 0,
+            counted_nodes: 0,
+        }
+    }
+
+    /// Create a parser that detects the encoding of the document like browsers do: from the
+    /// byte order mark, then the transport encoding (charset of the `Content-Type` header), then
+    /// a `<meta charset>` declaration in the first 1024 bytes, falling back to UTF-8.
+    ///
+    /// If the encoding was not declared before the document is parsed and a `meta` element
+    /// declares another one, the document is parsed again with it, once 64 KiB were parsed or
+    /// when it's finished. Only those first 64 KiB are kept to be parsed again.
+    pub fn sniff(transport: Option<&'static Encoding>) -> Self {
+        match transport {
+            Some(encoding) => Self::new(encoding),
+            None => Self {
+                parser: None,
+                decoder: encoding_rs::UTF_8.new_decoder(),
+                tentative: true,
+                buffer: vec![],
+                bytes: 0,
+                elements: 0,
+                counted_nodes: 0,
+            },
+        }
+    }
+
+    /// Decode and parse the next chunk of the document.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.bytes += chunk.len();
+        if self.tentative {
+            self.buffer.extend_from_slice(chunk);
+        }
+        match self.parser {
+            Some(_) => self.decode(chunk, false),
+            None if self.buffer.len() >= PRESCAN_LEN => self.start(),
+            None => {}
+        }
+        if self.tentative && self.buffer.len() > REPARSE_LEN {
+            self.confirm_encoding();
+        }
+    }
+
+    /// Number of bytes fed to the parser.
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Number of elements parsed so far.
+    #[inline]
+    pub fn elements(&self) -> usize {
+        self.elements
+    }
+
+    /// Encoding the document is decoded with (the encoding of the byte order mark if there is one).
+    /// A detected encoding can change until the parser is finished.
+    #[inline]
+    pub fn encoding(&self) -> &'static Encoding {
+        self.decoder.encoding()
+    }
+
+    /// Parse the end of the document.
+    pub fn finish(self) -> Html {
+        self.finish_with_encoding().0
+    }
+
+    /// Parse the end of the document, returning it with the encoding it was decoded with.
+    pub fn finish_with_encoding(mut self) -> (Html, &'static Encoding) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("parse").entered();
+        if self.parser.is_none() {
+            self.start();
+        }
+        self.decode(&[], true);
+        let document = self.parser.take().unwrap().finish();
+        let encoding = self.encoding();
+        if !self.tentative {
+            return (document, encoding);
+        }
+
+        match declared_charset(&document) {
+            Some(declared) if declared != encoding => {
+                log::info!("document declares {}, parsing it again", declared.name());
+                let mut parser = Self::new(declared);
+                parser.feed(&self.buffer);
+                parser.finish_with_encoding()
+            }
+            _ => (document, encoding),
+        }
+    }
+
+    /// Stop keeping the fed bytes, parsing them again now if the document declared another
+    /// encoding so far.
+    fn confirm_encoding(&mut self) {
+        self.tentative = false;
+        let buffer = std::mem::take(&mut self.buffer);
+        let Some(parser) = &self.parser else {
+            return;
+        };
+        match declared_charset(&parser.tokenizer.sink.sink) {
+            Some(declared) if declared != self.encoding() => {
+                log::info!("document declares {}, parsing it again", declared.name());
+                *self = Self::new(declared);
+                self.feed(&buffer);
+            }
+            _ => {}
+        }
+    }
+
+    /// Pick the encoding from the first bytes of the document and start parsing them.
+    fn start(&mut self) {
+        let encoding = match Encoding::for_bom(&self.buffer) {
+            Some((encoding, _)) => {
+                self.tentative = false;
+                encoding
+            }
+            None => prescan_charset(&self.buffer).unwrap_or(encoding_rs::UTF_8),
+        };
+        self.decoder = encoding.new_decoder();
+        self.parser = Some(html5ever::parse_document(
+            Html::new_document(),
+            parse_opts(),
+        ));
+        let buffer = std::mem::take(&mut self.buffer);
+        self.decode(&buffer, false);
+        if self.tentative {
+            self.buffer = buffer;
+        }
+    }
+
+    fn decode(&mut self, chunk: &[u8], last: bool) {
+        let Some(parser) = &mut self.parser else {
+            return;
+        };
+        let capacity = self
+            .decoder
+            .max_utf8_buffer_length(chunk.len())
+            .unwrap_or(chunk.len() * 3 + 16);
+        let mut text = String::with_capacity(capacity);
+        let _ = self.decoder.decode_to_string(chunk, &mut text, last);
+        if !text.is_empty() {
+            parser.process(text.into());
+        }
+        // the nodes are added to the end of the tree, only the new ones are counted
+        let nodes = parser.tokenizer.sink.sink.tree.values();
+        let len = nodes.len();
+        self.elements += nodes
+            .skip(self.counted_nodes)
+            .filter(|node| node.is_element())
+            .count();
+        self.counted_nodes = len;
+    }
+}
+
+/// Encoding declared by the attributes of a `meta` element (`charset`, or
+/// `http-equiv="content-type"` with a `content` charset).
+fn meta_charset<'a>(attr: impl Fn(&str) -> Option<&'a str>) -> Option<&'static Encoding> {
+    let label = match attr("charset") {
+        Some(charset) => charset,
+        None => {
+            let http_equiv = attr("http-equiv")?;
+            if !http_equiv.trim().eq_ignore_ascii_case("content-type") {
+                return None;
+            }
+            extract_charset(attr("content")?)?
+        }
+    };
+    let encoding = Encoding::for_label(label.trim().as_bytes())?;
+    // a document that can be parsed as ASCII can't be UTF-16
+    Some(match encoding {
+        _ if encoding == encoding_rs::UTF_16BE || encoding == encoding_rs::UTF_16LE => {
+            encoding_rs::UTF_8
+        }
+        _ if encoding == encoding_rs::X_USER_DEFINED => encoding_rs::WINDOWS_1252,
+        _ => encoding,
+    })
+}
+
+/// Encoding declared by the first `meta` element of a parsed document that declares one.
+fn declared_charset(document: &Html) -> Option<&'static Encoding> {
+    document
+        .tree
+        .nodes()
+        .filter_map(|node| node.value().as_element())
+        .filter(|el| el.name() == "meta")
+        .find_map(|el| meta_charset(|name| el.attr(name)))
+}
+
+/// Find a `<meta charset>` declaration in the first 1024 bytes of a document,
+/// before it's decoded (the "prescan" of the HTML encoding sniffing algorithm).
+///
+/// ```
+/// use dragonfly::prescan_charset;
+/// let html = b"<!-- <meta charset=utf-8> --><meta http-equiv=Content-Type content='text/html; charset=windows-1251'>";
+/// assert_eq!(prescan_charset(html), Some(encoding_rs::WINDOWS_1251));
+///
+/// // the bytes of a legacy encoding before the declaration are skipped as they are
+/// let html = include_bytes!("../tests/windows-1251.html");
+/// assert_eq!(prescan_charset(html), Some(encoding_rs::WINDOWS_1251));
+/// ```
+pub fn prescan_charset(data: &[u8]) -> Option<&'static Encoding> {
+    let data = &data[..data.len().min(PRESCAN_LEN)];
+    let mut i = 0;
+    while i < data.len() {
+        let rest = &data[i..];
+        if rest.starts_with(b"<!--") {
+            i += find_bytes(rest, b"-->").map_or(rest.len(), |end| end + 3);
+        } else if rest.len() > 5
+            && rest[..5].eq_ignore_ascii_case(b"<meta")
+            && (rest[5].is_ascii_whitespace() || rest[5] == b'/')
+        {
+            let (attrs, len) = prescan_attributes(&rest[5..]);
+            let attr = |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                    .map(|(_, v)| v.as_str())
+            };
+            if let Some(encoding) = meta_charset(attr) {
+                return Some(encoding);
+            }
+            i += 5 + len;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Read the attributes of a tag until its end (`>`). Returns the attributes
+/// and the number of bytes read.
+///
+/// The document is not decoded yet, so the bytes are scanned as they are: only ASCII
+/// delimiters matter, names and values are converted to text once they are read.
+fn prescan_attributes(data: &[u8]) -> (Vec<(String, String)>, usize) {
+    let skip = |i: &mut usize, skipped: fn(u8) -> bool| {
+        while data.get(*i).is_some_and(|&b| skipped(b)) {
+            *i += 1;
+        }
+    };
+    let mut attrs = vec![];
+    let mut i = 0;
+    loop {
+        // skip whitespace and slashes between attributes
+        skip(&mut i, |b| b.is_ascii_whitespace() || b == b'/');
+        match data.get(i) {
+            None => return (attrs, data.len()),
+            Some(b'>') => return (attrs, i + 1),
+            Some(_) => {}
+        }
+
+        let start = i;
+        skip(&mut i, |b| {
+            !b.is_ascii_whitespace() && !matches!(b, b'=' | b'>' | b'/')
+        });
+        let name = String::from_utf8_lossy(&data[start..i]).to_ascii_lowercase();
+        skip(&mut i, |b| b.is_ascii_whitespace());
+        let mut value = String::new();
+        if data.get(i) == Some(&b'=') {
+            i += 1;
+            skip(&mut i, |b| b.is_ascii_whitespace());
+            let range = match data.get(i) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let start = i + 1;
+                    let end = data[start..]
+                        .iter()
+                        .position(|&b| b == quote)
+                        .map_or(data.len(), |len| start + len);
+                    i = (end + 1).min(data.len());
+                    start..end
+                }
+                _ => {
+                    let start = i;
+                    skip(&mut i, |b| !b.is_ascii_whitespace() && b != b'>');
+                    start..i
+                }
+            };
+            value = String::from_utf8_lossy(&data[range]).into_owned();
+        }
+        if name.is_empty() {
+            // stray byte
+            i += 1;
+        } else if !attrs.iter().any(|(n, _)| *n == name) {
+            attrs.push((name, value));
+        }
+    }
+}
+
+/// Rendering mode of a document, chosen by the parser from its doctype.
+///
+/// Documents without a doctype (or with a legacy one) are rendered in quirks mode, which
+/// emulates old browsers. The quirks implemented by the layout are:
+/// - numbers without a unit are pixels in the author styles of the properties that take
+///   lengths, like `width`, `margin` or `font-size` (`width: 50` is `50px`), these
+///   declarations are ignored otherwise;
+/// - the `html` and `body` elements are at least as tall as the viewport.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuirksMode {
+    #[default]
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+impl From<html5ever::tree_builder::QuirksMode> for QuirksMode {
+    fn from(mode: html5ever::tree_builder::QuirksMode) -> Self {
+        use html5ever::tree_builder::QuirksMode as Mode;
+        match mode {
+            Mode::NoQuirks => Self::NoQuirks,
+            Mode::LimitedQuirks => Self::LimitedQuirks,
+            Mode::Quirks => Self::Quirks,
+        }
+    }
+}
+
+/// Document type declaration (`<!DOCTYPE html>`) of a parsed document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentType {
+    pub name: String,
+    pub public_id: String,
+    pub system_id: String,
+}
+
+impl DocumentType {
+    /// Find the document type declaration of a document.
+    pub fn of(document: &Html) -> Option<Self> {
+        document
+            .tree
+            .root()
+            .children()
+            .find_map(|node| node.value().as_doctype())
+            .map(|doctype| Self {
+                name: doctype.name().to_string(),
+                public_id: doctype.public_id().to_string(),
+                system_id: doctype.system_id().to_string(),
+            })
+    }
+}
+
+/// A piece of a text node that was laid out on a single line.
 #[derive(Debug, Clone)]
-pub struct DOMNode {
+pub struct TextFragment {
+    /// Byte range of the fragment in the text of the node
+    pub range: Range<usize>,
+    /// Fragment text, transformed by the `text-transform` of its style
+    pub text: String,
+    /// Top-left corner of the fragment
     pub pos: Pos2,
+    /// Fragment size (the height is the line height of the text)
+    pub size: Vec2,
+    /// Distance from the top of the fragment to the baseline of the text
+    pub baseline: f32,
+    /// Style of the fragment text, inherited from the element that wraps the text node.
+    ///
+    /// Bold text is measured with the bold face of its font, when the system has one:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<p>Hello</p><p><b>Hello</b></p><p><strong>Hello</strong></p>";
+    /// let mut fonts = FontManager::with_system_fonts();
+    /// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let fragment = |selector| {
+    ///     let text = layout.select(selector)[0].children(&layout.arena).next().unwrap();
+    ///     layout.arena[text].get().fragments[0].clone()
+    /// };
+    /// let (regular, b, strong) = (fragment("p"), fragment("b"), fragment("strong"));
+    /// assert_eq!(regular.style.font_weight, 400);
+    /// assert_eq!((b.style.font_weight, strong.style.font_weight), (700, 700));
+    /// assert_eq!(b.text, regular.text);
+    /// assert_ne!(b.size.x, regular.size.x);
+    /// assert_eq!(strong.size.x, b.size.x);
+    /// ```
+    pub style: TextStyle,
+}
+
+impl TextFragment {
+    #[inline]
+    pub fn rect(&self) -> Rect {
+        Rect::from_pos_size(self.pos, self.size)
+    }
+}
+
+/// Data of an element node.
+#[derive(Debug, Clone, Default)]
+pub struct ElementData {
+    /// Tag name
     pub name: String,
-    pub attrs: HashMap<String, String>,
+    /// Attributes, in source order
+    pub attrs: IndexMap<String, String>,
+    /// Classes from the `class` attribute
+    pub classes: Vec<String>,
+    /// Value of the `id` attribute
     pub id: String,
+}
+
+impl ElementData {
+    /// Set an attribute, updating the fields derived from it.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match name {
+            "id" => self.id = value.to_string(),
+            "class" => {
+                // duplicate classes are only kept once, in the order they first appear
+                self.classes.clear();
+                for class in value.split_whitespace() {
+                    if !self.has_class(class) {
+                        self.classes.push(class.to_string());
+                    }
+                }
+            }
+            _ => (),
+        }
+        self.attrs.insert(name.to_string(), value.to_string());
+    }
+
+    /// Remove an attribute, updating the fields derived from it. Returns the old value.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        match name {
+            "id" => self.id.clear(),
+            "class" => self.classes.clear(),
+            _ => (),
+        }
+        self.attrs.shift_remove(name)
+    }
+
+    /// Whether the element has a class (case-sensitive).
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes.iter().any(|c| c == class)
+    }
+
+    /// Add a class if the element doesn't have it yet.
+    pub fn add_class(&mut self, class: &str) {
+        if !self.has_class(class) {
+            self.classes.push(class.to_string());
+            self.sync_class_attr();
+        }
+    }
+
+    /// Remove a class. Returns whether the element had it.
+    pub fn remove_class(&mut self, class: &str) -> bool {
+        let len = self.classes.len();
+        self.classes.retain(|c| c != class);
+        let removed = self.classes.len() != len;
+        if removed {
+            self.sync_class_attr();
+        }
+        removed
+    }
+
+    /// Add the class if the element doesn't have it, remove it otherwise.
+    /// Returns whether the element has the class now.
+    ///
+    /// ```
+    /// use dragonfly::ElementData;
+    /// let mut el = ElementData::default();
+    /// el.set_attribute("class", "Foo bar");
+    /// // classes are case-sensitive: `foo` is another class than `Foo`
+    /// assert!(el.toggle_class("foo"));
+    /// assert_eq!(el.classes, ["Foo", "bar", "foo"]);
+    /// assert_eq!(el.attrs["class"], "Foo bar foo");
+    /// assert!(!el.toggle_class("foo"));
+    /// assert_eq!(el.attrs["class"], "Foo bar");
+    ///
+    /// assert!(!el.toggle_class("Foo"));
+    /// assert!(!el.has_class("Foo") && el.has_class("bar"));
+    /// assert!(el.toggle_class("Foo"));
+    /// assert_eq!(el.attrs["class"], "bar Foo");
+    /// ```
+    pub fn toggle_class(&mut self, class: &str) -> bool {
+        if self.remove_class(class) {
+            false
+        } else {
+            self.add_class(class);
+            true
+        }
+    }
+
+    /// Write the parsed classes back to the `class` attribute.
+    fn sync_class_attr(&mut self) {
+        self.attrs
+            .insert("class".to_string(), self.classes.join(" "));
+    }
+}
+
+/// Convert the suffix of a `data-*` attribute name to its dataset name:
+/// every `-` followed by a lowercase ASCII letter is removed and the letter is uppercased.
+fn dataset_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '-' && next.is_ascii_lowercase() => {
+                result.push(next.to_ascii_uppercase());
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Text direction from the `dir` attribute.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Dir {
+    #[default]
+    Ltr,
+    Rtl,
+    /// Direction from the first strong character of the text
+    Auto,
+}
+
+/// Valid values of the `type` attribute of `input` elements.
+const INPUT_TYPES: &[&str] = &[
+    "hidden",
+    "text",
+    "search",
+    "tel",
+    "url",
+    "email",
+    "password",
+    "date",
+    "month",
+    "week",
+    "time",
+    "datetime-local",
+    "number",
+    "range",
+    "color",
+    "checkbox",
+    "radio",
+    "file",
+    "submit",
+    "image",
+    "reset",
+    "button",
+];
+
+/// What a node of the document is.
+///
+/// The accessors of [`DOMNode`] return `None` or an empty value for the other kinds:
+///
+/// ```
+/// use dragonfly::{DOMNode, DOMNodeKind};
+/// let mut element = DOMNode::new("p");
+/// let data = element.as_element_mut().unwrap();
+/// data.set_attribute("id", "intro");
+/// data.set_attribute("class", "a b");
+/// let (mut text, mut comment) = (DOMNode::text_node("hi"), DOMNode::comment("note"));
+/// assert!(matches!(element.kind, DOMNodeKind::Element(_)));
+/// assert!(matches!(text.kind, DOMNodeKind::Text(_)));
+/// assert!(matches!(comment.kind, DOMNodeKind::Comment(_)));
+///
+/// let kinds = |node: &DOMNode| (node.is_element(), node.is_text(), node.is_comment());
+/// assert_eq!(kinds(&element), (true, false, false));
+/// assert_eq!(kinds(&text), (false, true, false));
+/// assert_eq!(kinds(&comment), (false, false, true));
+///
+/// assert_eq!(element.as_element().map(|el| el.name.as_str()), Some("p"));
+/// assert_eq!((element.name(), element.id(), element.text()), ("p", "intro", ""));
+/// assert_eq!(element.classes(), ["a", "b"]);
+/// assert_eq!(element.attr("id"), Some("intro"));
+///
+/// for (node, content) in [(&mut text, "hi"), (&mut comment, "note")] {
+///     assert!(node.as_element().is_none());
+///     assert!(node.as_element_mut().is_none());
+///     assert_eq!((node.name(), node.id(), node.text()), ("", "", content));
+///     assert!(node.classes().is_empty());
+///     assert_eq!(node.attr("id"), None);
+///     assert!(!node.has_class("a"));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub enum DOMNodeKind {
+    Element(ElementData),
+    /// Text with collapsed whitespace. This is NOT the inner text of an element,
+    /// this is a part of the inner text of its parent!!
+    Text(String),
+    Comment(String),
+}
+
+impl Default for DOMNodeKind {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DOMNode {
+    /// Border box of the node, computed by the layout
+    pub border_rect: Rect,
+    /// Resolved padding of the node in device pixels, computed by the layout.
+    ///
+    /// Only block-level and atomic inline boxes (inline blocks, images, ...) honour their padding
+    /// and borders: those of non-atomic inline boxes such as `span` are 0 and don't move the text.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout, Sides};
+    /// let plain = "<p><span>a</span>b</p><div style='display: inline-block'>c</div>";
+    /// let padded = "<p><span style='padding: 6px; border: 2px solid'>a</span>b</p>
+    ///     <div style='display: inline-block; padding: 6px; border: 2px solid'>c</div>";
+    /// let compute = |html| Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let (plain, padded) = (compute(plain), compute(padded));
+    /// let node = |layout: &Layout, selector| layout.arena[layout.select(selector)[0]].get().clone();
+    /// let text = |layout: &Layout| {
+    ///     let ids = layout.root_id().descendants(&layout.arena);
+    ///     let fragments = ids.flat_map(|id| layout.arena[id].get().fragments.clone());
+    ///     fragments.map(|f| (f.text, f.pos)).collect::<Vec<_>>()
+    /// };
+    /// // the span keeps no padding or border, and the text doesn't move
+    /// assert_eq!(node(&padded, "span").padding, Sides::splat(0.0));
+    /// assert_eq!(node(&padded, "span").border, Sides::splat(0.0));
+    /// assert_eq!(node(&padded, "span").border_rect, node(&plain, "span").border_rect);
+    /// assert_eq!(text(&padded)[..2], text(&plain)[..2]);
+    /// // the inline block grows by its padding and borders
+    /// assert_eq!(node(&padded, "div").padding, Sides::splat(6.0));
+    /// assert_eq!(node(&padded, "div").border, Sides::splat(2.0));
+    /// let (plain, padded) = (node(&plain, "div").border_rect, node(&padded, "div").border_rect);
+    /// assert_eq!(padded.width(), plain.width() + 16.0);
+    /// ```
+    pub padding: Sides<f32>,
+    /// Used border widths of the node in device pixels (0 for sides without a border, and for
+    /// non-atomic inline boxes, see [`padding`](DOMNode::padding)), computed by the layout
+    pub border: Sides<f32>,
+    /// Radii of the corners of the border box in device pixels (top-left, top-right,
+    /// bottom-right, bottom-left), computed by the layout. Radii that overlap are not scaled
+    /// down yet, see [`DisplayItem`](crate::DisplayItem)
+    pub border_radius: [Vec2; 4],
+    /// Used width of the outline in device pixels (0 without an outline), computed by the
+    /// layout. Outlines don't change the size of the box, see [`DOMNode::outline_rect`]
+    pub outline_width: f32,
+    /// Used distance between the border box and the outline in device pixels, computed by the
+    /// layout
+    pub outline_offset: f32,
+    /// Rectangle the content of the node is clipped to in device pixels, for nodes with a
+    /// non-visible `overflow`, computed by the layout. It's the padding box, unbounded along
+    /// an axis that isn't clipped. The overflow of the root element (or of `<body>`) applies
+    /// to the viewport, so they're never clipped:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<body style='overflow: hidden'>
+    ///     <div style='overflow-y: clip; height: 20px; padding: 5px; border: 1px solid'>
+    ///     <p style='margin: 0; height: 10px'></p><p style='margin: 0; height: 40px'></p></div></body>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let node = |selector| layout.arena[layout.select(selector)[0]].get();
+    /// assert_eq!(node("body").overflow_clip, None);
+    /// assert!(node("body").border_rect.height() > 0.0);
+    /// let clip = node("div").overflow_clip.unwrap();
+    /// assert_eq!((clip.pos.y, clip.height()), (8.0 + 1.0, 30.0));
+    /// assert!(clip.width() > 1e30); // only clipped vertically
+    /// // descendants outside of the clip are still laid out, but flagged
+    /// let div = layout.select("div")[0];
+    /// assert_eq!(node("p:first-child").clipped_by, None);
+    /// assert_eq!(node("p:last-child").clipped_by, Some(div));
+    /// ```
+    pub overflow_clip: Option<Rect>,
+    /// Closest ancestor whose [`overflow_clip`](DOMNode::overflow_clip) cuts off part of the
+    /// node (its border box or text), computed by the layout
+    pub clipped_by: Option<NodeId>,
+    /// Marker of a list item, computed by the layout. Outside markers are painted before the
+    /// start edge of the item, in the space its start margin has for them
+    pub marker: Option<ListMarker>,
+    pub kind: DOMNodeKind,
+    /// Computed style of the node (matched rules, inline style and inherited properties)
     pub style: Option<Declaration>,
-    /// Node text, if it is a text node. This is NOT the inner text of the node,
-    /// this is a part of the inner text of another node!!
-    pub text: String,
+    /// Line fragments of the node text, computed by the layout
+    pub fragments: Vec<TextFragment>,
+    /// Effective language (`lang` attribute of the closest ancestor), empty if unknown.
+    /// Computed by the layout
+    pub lang: String,
+    /// Effective text direction (`Ltr` or `Rtl`) from the computed `direction`, set by the layout
+    pub direction: Dir,
+    /// Whether the node or one of its descendants is under the pointer (see [`Layout::set_hover`](crate::Layout::set_hover))
+    pub hover: bool,
+    /// Whether the node is focused (see [`Layout::set_focus`](crate::Layout::set_focus))
+    pub focus: bool,
 }
 
 impl Default for DOMNode {
     fn default() -> Self {
         Self {
-            pos: Pos2::new(0.0, 0.0),
-            name: String::new(),
-            attrs: HashMap::new(),
-            id: String::new(),
+            border_rect: Rect::ZERO,
+            padding: Sides::splat(0.0),
+            border: Sides::splat(0.0),
+            border_radius: [Vec2::new(0.0, 0.0); 4],
+            outline_width: 0.0,
+            outline_offset: 0.0,
+            overflow_clip: None,
+            clipped_by: None,
+            marker: None,
+            kind: DOMNodeKind::default(),
             style: None,
-            text: String::new(),
+            fragments: vec![],
+            lang: String::new(),
+            direction: Dir::Ltr,
+            hover: false,
+            focus: false,
         }
     }
 }
 
 impl DOMNode {
-    /// Create a new node with an element name.
+    /// Create a new element node with a tag name.
     pub fn new(name: &str) -> Self {
         Self {
-            pos: Pos2::new(0.0, 0.0),
-            name: name.to_string(),
-            attrs: HashMap::new(),
-            id: String::new(),
-            style: None,
-            text: String::new(),
+            kind: DOMNodeKind::Element(ElementData {
+                name: name.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
         }
     }
 
@@ -45,46 +860,371 @@ impl DOMNode {
         node
     }
 
-    /// Updates the node text (string with no consecutive whitespace)
-    ///
-    /// Only do this if it is a text node. This is not meant for setting the inner text of the node.
-    pub fn set_text(&mut self, text: &str) {
-        self.text = String::new();
-        let get_char = |pos: usize| text[pos..].chars().next().unwrap();
+    /// Text node that keeps its whitespace, for the content of elements that are not
+    /// rendered as text (`script` and `style`).
+    pub fn raw_text_node(text: &str) -> Self {
+        Self {
+            kind: DOMNodeKind::Text(text.to_string()),
+            ..Default::default()
+        }
+    }
 
-        for i in 0..text.len() {
-            let c = get_char(i);
-            if i > 0 && c.is_whitespace() && get_char(i - 1).is_whitespace() {
-                continue;
-            }
-            self.text.push(if c.is_whitespace() { ' ' } else { c });
+    pub fn comment(text: &str) -> Self {
+        Self {
+            kind: DOMNodeKind::Comment(text.to_string()),
+            ..Default::default()
         }
-        log::debug!("set node text: '{}'", self.text);
+    }
+
+    /// Updates the node text
+    ///
+    /// This turns the node into a text node. This is not meant for setting the inner text of an element.
+    /// The text is stored as is: its whitespace is collapsed or preserved during layout, depending
+    /// on the `white-space` property of the parent element.
+    pub fn set_text(&mut self, text: &str) {
+        log::debug!("set node text: '{text}'");
+        self.kind = DOMNodeKind::Text(text.to_string());
     }
 
     /// Root `html` node.
     pub fn root() -> Self {
-        let mut node = Self::default();
-        node.name = String::from("html");
-        node
+        Self::new("html")
+    }
+
+    /// Whether this is an element node.
+    #[inline]
+    pub fn is_element(&self) -> bool {
+        matches!(self.kind, DOMNodeKind::Element(_))
+    }
+
+    /// Whether this is a text node.
+    #[inline]
+    pub fn is_text(&self) -> bool {
+        matches!(self.kind, DOMNodeKind::Text(_))
+    }
+
+    /// Whether this is a comment node.
+    #[inline]
+    pub fn is_comment(&self) -> bool {
+        matches!(self.kind, DOMNodeKind::Comment(_))
     }
 
-    pub fn bounds(&self, fonts: &mut FontManager) {
-        // calculate text size in node
-        let mut bounds = Vec2::new(0.0, 0.0);
-        for c in self.text.chars() {
-            let metrics = fonts.glyph_metrics(
-                c,
-                14.0,
-                self.style
-                    .clone()
-                    .unwrap_or_default()
-                    .font_family
-                    .unwrap_or_default(),
-            );
-            bounds.x += metrics.width as f32 + metrics.advance_width;
-            log::debug!("char '{c}' metrics: {metrics:?}");
+    /// Element data, if this is an element node.
+    #[inline]
+    pub fn as_element(&self) -> Option<&ElementData> {
+        match &self.kind {
+            DOMNodeKind::Element(el) => Some(el),
+            _ => None,
+        }
+    }
+
+    /// Mutable element data, if this is an element node.
+    #[inline]
+    pub fn as_element_mut(&mut self) -> Option<&mut ElementData> {
+        match &mut self.kind {
+            DOMNodeKind::Element(el) => Some(el),
+            _ => None,
+        }
+    }
+
+    /// Tag name of an element, empty for other nodes.
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.as_element().map_or("", |el| &el.name)
+    }
+
+    /// Value of the `id` attribute of an element, empty for other nodes.
+    #[inline]
+    pub fn id(&self) -> &str {
+        self.as_element().map_or("", |el| &el.id)
+    }
+
+    /// Classes of an element, empty for other nodes.
+    #[inline]
+    pub fn classes(&self) -> &[String] {
+        self.as_element().map_or(&[], |el| &el.classes)
+    }
+
+    /// Whether this is an element with a class (case-sensitive).
+    #[inline]
+    pub fn has_class(&self, class: &str) -> bool {
+        self.as_element().is_some_and(|el| el.has_class(class))
+    }
+
+    /// Value of an element attribute, `None` for other nodes.
+    #[inline]
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.as_element()?.attrs.get(name).map(String::as_str)
+    }
+
+    /// Whether a boolean attribute (`disabled`, `checked`, `hidden`...) is set.
+    ///
+    /// Boolean attributes are true when they are present, whatever their value:
+    /// `disabled="false"` still disables the element.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = r#"
+    ///     <button id="a" disabled="false">a</button>
+    ///     <button id="b" disabled="">b</button>
+    ///     <button id="c">c</button>
+    ///     <fieldset disabled="no"><input id="d"></fieldset>"#;
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// block_on(ctx.load())?;
+    /// let layout = &ctx.layout;
+    /// let a = layout.select("#a")[0];
+    /// assert!(layout.arena[a].get().has_bool_attr("disabled"));
+    /// assert!(!layout.arena[a].get().has_bool_attr("hidden"));
+    /// assert_eq!(layout.select(":disabled"), layout.select("#a, #b, fieldset, #d"));
+    /// assert_eq!(layout.select(":enabled"), layout.select("#c"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn has_bool_attr(&self, name: &str) -> bool {
+        self.attr(name).is_some()
+    }
+
+    /// Value of an enumerated attribute, matched case-insensitively against its keywords.
+    /// Returns `None` if the attribute is missing or its value is not a keyword.
+    ///
+    /// ```
+    /// use dragonfly::{Dir, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = r#"
+    ///     <p dir=" RTL ">a</p><p dir="Auto">b</p><p dir="sideways">c</p>
+    ///     <input type="CheckBox"><input type="Bogus"><input>"#;
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// block_on(ctx.load())?;
+    /// let node = |id| ctx.layout.arena[id].get();
+    /// let dirs: Vec<_> = ctx.layout.select("p").into_iter().map(|id| node(id).dir()).collect();
+    /// assert_eq!(dirs, [Some(Dir::Rtl), Some(Dir::Auto), None]);
+    /// let p = ctx.layout.select("p")[0];
+    /// assert_eq!(node(p).enumerated_attr("dir", &["ltr", "rtl"]), Some("rtl"));
+    /// let types: Vec<_> = ctx.layout.select("input").into_iter().map(|id| node(id).input_type()).collect();
+    /// assert_eq!(types, ["checkbox", "text", "text"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enumerated_attr<'a>(&self, name: &str, keywords: &[&'a str]) -> Option<&'a str> {
+        let value = self.attr(name)?.trim();
+        keywords
+            .iter()
+            .find(|keyword| keyword.eq_ignore_ascii_case(value))
+            .copied()
+    }
+
+    /// Value of the `dir` attribute, `None` if it's missing or invalid.
+    pub fn dir(&self) -> Option<Dir> {
+        match self.enumerated_attr("dir", &["ltr", "rtl", "auto"])? {
+            "ltr" => Some(Dir::Ltr),
+            "rtl" => Some(Dir::Rtl),
+            _ => Some(Dir::Auto),
+        }
+    }
+
+    /// Normalized `type` of an `input` element: lowercase, unknown or missing types are `text`.
+    pub fn input_type(&self) -> &'static str {
+        self.enumerated_attr("type", INPUT_TYPES).unwrap_or("text")
+    }
+
+    /// All `data-*` attributes of an element, by their dataset name: the name without the
+    /// `data-` prefix, converted to camelCase (`data-user-id` is `userId`).
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = r#"<p data-foo-bar="1" data-user-id="7" data-x-1="2" data-="empty" title="t">"#;
+    /// let mut layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let p = layout.select("p")[0];
+    /// let dataset = layout.arena[p].get().dataset();
+    /// let pairs: Vec<_> = dataset.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    /// // a dash followed by a digit is kept, a bare `data-` has an empty name
+    /// assert_eq!(pairs, [("", "empty"), ("fooBar", "1"), ("userId", "7"), ("x-1", "2")]);
+    ///
+    /// let node = layout.arena[p].get();
+    /// assert_eq!(node.data("userId"), Some("7"));
+    /// assert_eq!(node.data("user-id"), Some("7"));
+    /// assert_eq!(node.data(""), Some("empty"));
+    /// assert_eq!(node.data("title"), None);
+    ///
+    /// // attribute changes show up in the dataset
+    /// layout.set_attribute(p, "data-new-key", "v");
+    /// layout.remove_attribute(p, "data-foo-bar");
+    /// let node = layout.arena[p].get();
+    /// assert_eq!(node.data("newKey"), Some("v"));
+    /// assert_eq!(node.data("fooBar"), None);
+    /// let keys: Vec<_> = node.dataset().into_keys().collect();
+    /// assert_eq!(keys, ["", "newKey", "userId", "x-1"]);
+    /// ```
+    pub fn dataset(&self) -> BTreeMap<String, &str> {
+        let Some(el) = self.as_element() else {
+            return BTreeMap::new();
+        };
+        el.attrs
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((dataset_name(name.strip_prefix("data-")?), value.as_str()))
+            })
+            .collect()
+    }
+
+    /// Value of a `data-*` attribute, by its dataset name (`userId`) or attribute suffix (`user-id`).
+    pub fn data(&self, name: &str) -> Option<&str> {
+        let name = dataset_name(name);
+        self.as_element()?
+            .attrs
+            .iter()
+            .find(|(attr, _)| {
+                attr.strip_prefix("data-")
+                    .is_some_and(|attr| dataset_name(attr) == name)
+            })
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Text of a text or comment node, empty for elements.
+    #[inline]
+    pub fn text(&self) -> &str {
+        match &self.kind {
+            DOMNodeKind::Text(text) | DOMNodeKind::Comment(text) => text,
+            DOMNodeKind::Element(_) => "",
+        }
+    }
+
+    /// Content box of the node: the border box without the borders and the padding.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<div style='padding: 10px 20px; width: 100px; height: 50px'></div>
+    ///     <p style='border: 2px solid; border-left-width: 5px; width: 100px'></p>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let div = layout.arena[layout.select("div")[0]].get();
+    /// assert_eq!((div.border_rect.width(), div.border_rect.height()), (140.0, 70.0));
+    /// let content = div.content_rect();
+    /// assert_eq!((content.width(), content.height()), (100.0, 50.0));
+    /// assert_eq!(content.pos - div.border_rect.pos, dragonfly::Vec2::new(20.0, 10.0));
+    /// let p = layout.arena[layout.select("p")[0]].get();
+    /// assert_eq!((p.border_rect.width(), p.border_rect.height()), (107.0, 4.0));
+    /// assert_eq!(p.content_rect().pos - p.border_rect.pos, dragonfly::Vec2::new(5.0, 2.0));
+    /// ```
+    #[inline]
+    pub fn content_rect(&self) -> Rect {
+        self.border_rect.inset(self.border).inset(self.padding)
+    }
+
+    /// Outer edge of the outline of the node, drawn `outline_width` wide inside of it.
+    /// `None` if the node has no outline.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<div style='outline: 2px solid; outline-offset: 3px; height: 10px'></div>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let div = layout.arena[layout.select("div")[0]].get();
+    /// // the outline doesn't take space
+    /// assert_eq!(div.border_rect.height(), 10.0);
+    /// assert_eq!(div.outline_rect().unwrap().height(), 20.0);
+    /// ```
+    pub fn outline_rect(&self) -> Option<Rect> {
+        let outset = self.outline_offset + self.outline_width;
+        (self.outline_width > 0.0).then(|| self.border_rect.inset(Sides::splat(-outset)))
+    }
+
+    /// Resolved text style of the node, in its language.
+    ///
+    /// ```
+    /// use dragonfly::{fallback_families, parse_document, FontManager, Layout};
+    /// let html = r#"<html lang="en"><p>English <span lang="ja">日本語</span></p><p lang="">?</p>"#;
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let text = |selector| {
+    ///     let element = layout.select(selector)[0];
+    ///     layout.arena[layout.arena[element].first_child().unwrap()].get()
+    /// };
+    /// assert_eq!(text("p").text_style().lang, "en");
+    /// assert_eq!(text("span").lang, "ja");
+    /// // the characters the font doesn't have fall back to Japanese fonts
+    /// let style = text("span").text_style();
+    /// assert_eq!(fallback_families(&style.lang)[0], "Noto Sans CJK JP");
+    /// assert_eq!(text("p:last-child").text_style().lang, "");
+    /// ```
+    pub fn text_style(&self) -> TextStyle {
+        let mut style: TextStyle = self
+            .style
+            .as_ref()
+            .map(Declaration::text_style)
+            .unwrap_or_default();
+        style.lang = self.lang.clone();
+        style
+    }
+
+    /// Measure the size of a string laid out on a single line in the style of this node,
+    /// after its `text-transform` and with its letter and word spacing.
+    ///
+    /// The size is in device pixels, `scale_factor` is the number of device pixels per CSS pixel.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, FontStyle, Layout};
+    /// let html = r#"<div style="font-size: 20px"><span style="font-size: 2em">text</span></div>"#;
+    /// let mut fonts = FontManager::default();
+    /// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let span = layout.select("span")[0];
+    /// let text = layout.arena[span.children(&layout.arena).next().unwrap()].get();
+    /// assert_eq!(text.text_style().font_size, 40.0);
+    /// let size = text.measure_text("text", 1.0, &mut fonts);
+    /// let family = text.text_style().font_family;
+    /// let metrics = fonts.line_metrics(40.0, family, 400, FontStyle::Normal);
+    /// assert_eq!(size.y, metrics.new_line_size);
+    /// ```
+    pub fn measure_text(&self, text: &str, scale_factor: f32, fonts: &mut FontManager) -> Vec2 {
+        let style = self.text_style().scaled(scale_factor);
+        let text = &*style.render(text);
+        let spacing: f32 = text.chars().map(|c| style.spacing_after(c)).sum();
+        let width = (fonts.styled_text_width(text, &style) + spacing).max(0.0);
+        let (family, weight, font_style) = (style.font_family, style.font_weight, style.font_style);
+        let height = style.line_height.unwrap_or_else(|| {
+            fonts
+                .line_metrics(style.font_size, family, weight, font_style)
+                .new_line_size
+        });
+        log::debug!("measured text '{text}': {width}x{height}");
+        Vec2::new(width, height)
+    }
+
+    /// Measure the size of the node text laid out on a single line. A `width` or `height` set
+    /// in the style of the node is used instead of the size of the text, unless it's `auto` or a
+    /// percentage (there's no containing block to resolve it against). The size is clamped
+    /// between the `min-width`/`max-width` and `min-height`/`max-height` of the node, the minimum
+    /// wins over the maximum.
+    ///
+    /// ```
+    /// use dragonfly::{Declaration, DOMNode, FontManager};
+    /// let mut fonts = FontManager::default();
+    /// let mut node = DOMNode::text_node("Hello");
+    /// let text = node.bounds(&mut fonts);
+    /// node.style = Some(Declaration::from_inline("width: 300px; height: 2em"));
+    /// let bounds = node.bounds(&mut fonts);
+    /// assert_eq!((bounds.x, bounds.y), (300.0, 28.0));
+    /// node.style = Some(Declaration::from_inline("width: 50%; height: auto"));
+    /// assert_eq!(node.bounds(&mut fonts), text);
+    /// node.style = Some(Declaration::from_inline("width: 300px; max-width: 100px; min-width: 150px"));
+    /// assert_eq!(node.bounds(&mut fonts).x, 150.0);
+    /// ```
+    pub fn bounds(&self, fonts: &mut FontManager) -> Vec2 {
+        let mut bounds = self.measure_text(self.text(), 1.0, fonts);
+        if let Some(style) = &self.style {
+            let text_style = self.text_style();
+            let resolve = |d: Option<Dimension>| text_style.resolve(d?, None);
+            let clamp = |value: f32, min, max| {
+                let value = resolve(max).map_or(value, |max| value.min(max));
+                resolve(min).map_or(value, |min| value.max(min))
+            };
+            let width = resolve(style.width).unwrap_or(bounds.x);
+            let height = resolve(style.height).unwrap_or(bounds.y);
+            bounds.x = clamp(width, style.min_width, style.max_width);
+            bounds.y = clamp(height, style.min_height, style.max_height);
         }
         log::debug!("calculated node bounds: {bounds:?}");
+        bounds
     }
 }
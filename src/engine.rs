@@ -0,0 +1,93 @@
+use crate::{DfResult, FontManager, Puller, Viewport, VisitedLinks, WebContext, WebContextBuilder};
+use url::Url;
+
+/// Creates [`WebContext`]s (tabs of a browser for example) that share the same fonts and cache.
+///
+/// Fonts and pulled resources are shared: fonts loaded by one context and pages pulled by
+/// one context are reused by the others. The contexts are independent otherwise, and can be
+/// loaded and dropped separately.
+///
+/// ```
+/// use dragonfly::{BrowserEngine, FontManager};
+/// let mut engine = BrowserEngine::new(FontManager::default());
+/// engine.viewport.size.x = 1024.0;
+/// let a = engine.new_context_from_html("<p>a</p>", "https://a.example/")?;
+/// let b = engine.new_context("https://b.example/")?;
+/// assert_eq!(b.viewport().size.x, 1024.0);
+///
+/// // the contexts created after the user stylesheet is set use it
+/// engine.user_css = Some("p { height: 30px; }".to_string());
+/// let c = engine.new_context("https://c.example/")?;
+/// assert!(a.user_stylesheet().is_none());
+/// assert_eq!(c.user_stylesheet().unwrap().rules.len(), 1);
+///
+/// // the contexts share the fonts and the font cache of the engine
+/// assert!(std::sync::Arc::ptr_eq(&a.font_manager.serif, &c.font_manager.serif));
+/// let mut fonts = b.font_manager.clone();
+/// fonts.by_name("Dragonfly Missing Font");
+/// assert_eq!(c.font_manager.cache_stats().misses, 1);
+/// assert_eq!(engine.font_manager.cache_stats().entries, 1);
+/// # Ok::<(), dragonfly::DfError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BrowserEngine {
+    /// Fonts shared by the contexts
+    pub font_manager: FontManager,
+    /// Puller shared by the contexts: they share its cache
+    pub puller: Puller,
+    /// Pages visited by the contexts, that `:visited` links are matched against
+    pub visited: VisitedLinks,
+    /// Viewport of new contexts
+    pub viewport: Viewport,
+    /// Language of the pages that don't declare one (see [`WebContext::lang`])
+    pub lang: Option<String>,
+    /// Whether new contexts follow refreshes without a delay (see [`WebContext::follow_meta_refresh`])
+    pub follow_meta_refresh: bool,
+    /// User stylesheet of new contexts (see [`WebContext::set_user_stylesheet`])
+    pub user_css: Option<String>,
+}
+
+impl BrowserEngine {
+    pub fn new(font_manager: FontManager) -> Self {
+        Self {
+            font_manager,
+            ..Default::default()
+        }
+    }
+
+    /// Builder of a context that shares the resources and the settings of the engine.
+    pub fn context_builder(&self) -> WebContextBuilder {
+        let mut builder = WebContext::builder()
+            .font_manager(self.font_manager.clone())
+            .puller(self.puller.clone())
+            .visited(self.visited.clone())
+            .viewport(self.viewport.size.x, self.viewport.size.y)
+            .scale_factor(self.viewport.scale_factor)
+            .pixel_snapping(self.viewport.pixel_snapping)
+            .follow_meta_refresh(self.follow_meta_refresh);
+        if let Some(lang) = &self.lang {
+            builder = builder.lang(lang);
+        }
+        if let Some(css) = &self.user_css {
+            builder = builder.user_css(css);
+        }
+        builder
+    }
+
+    /// Mark a URL as visited in every context of the engine. The contexts that are loaded
+    /// update the styles of their links on their next layout.
+    pub fn mark_visited(&self, url: &str) -> DfResult<()> {
+        self.visited.mark(&Url::parse(url)?);
+        Ok(())
+    }
+
+    /// Create a context that loads the page at a URL.
+    pub fn new_context(&self, url: &str) -> DfResult<WebContext> {
+        self.context_builder().url(url).build()
+    }
+
+    /// Create a context that loads a page from markup, shown at a URL.
+    pub fn new_context_from_html(&self, html_str: &str, url: &str) -> DfResult<WebContext> {
+        self.context_builder().html(html_str).url(url).build()
+    }
+}
@@ -1,19 +1,207 @@
+/// Errors of dragonfly. The errors that wrap another one don't repeat its message, it's their
+/// [`source`](std::error::Error::source).
 #[derive(Debug, thiserror::Error)]
 pub enum DfError {
-    #[error("reqwest error: {0}")]
+    #[error("reqwest error")]
     ReqwestError(#[from] reqwest::Error),
-    #[error("io error: {0}")]
+    #[error("io error")]
     IoError(#[from] std::io::Error),
-    #[error("url parser error: {0}")]
+    #[error("url parser error")]
     UrlParseError(#[from] url::ParseError),
-    #[error("fontkit selection error: {0}")]
+    #[error("fontkit selection error")]
     FontSelectionError(#[from] font_kit::error::SelectionError),
     #[error("no filesystem present")]
     NoFilesystemError,
     #[error("failed to load font: {0}")]
     FontLoadingError(String),
+    #[error("failed to decode image: {0}")]
+    ImageDecodeError(String),
     #[error("unknown css property: {0}")]
     UnknownStyleProperty(String),
+    #[error("invalid selector: {0}")]
+    InvalidSelector(String),
+    #[error("url can't be fetched: {0}")]
+    UnfetchableUrl(String),
+    #[error("http status {status} for {url}")]
+    HttpStatus { url: String, status: u16 },
+    #[error("too many redirects from {0}")]
+    TooManyRedirects(String),
+    #[error("invalid data url: {0}")]
+    InvalidDataUrl(String),
+    #[error("no url or html to load")]
+    NoPage,
+    #[error("no page is loaded")]
+    NotLoaded,
+    #[error("operation cancelled")]
+    Cancelled,
+    #[error("{0} exceeded")]
+    BudgetExceeded(crate::Budget),
+    #[error("layout error")]
+    Layout(#[from] LayoutError),
+    #[error("invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    /// An error about a resource of the page, with the URL of the resource and the step of
+    /// the load it happened in (see [`DfResultExt::context`]). The underlying error is its
+    /// [`source`](std::error::Error::source)
+    #[error("{phase} error for {url}")]
+    Resource {
+        url: url::Url,
+        phase: LoadPhase,
+        source: Box<DfError>,
+    },
 }
 
 pub type DfResult<T> = Result<T, DfError>;
+
+impl DfError {
+    /// Wrap the error with the URL of the resource it's about and the step of the load it
+    /// happened in. Errors that already have the same context are not wrapped again.
+    pub fn context(self, phase: LoadPhase, url: &url::Url) -> Self {
+        match self {
+            Self::Resource {
+                url: ref inner_url,
+                phase: inner_phase,
+                ..
+            } if inner_url == url && inner_phase == phase => self,
+            err => Self::Resource {
+                url: url.clone(),
+                phase,
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// The message of the error followed by the messages of its sources, separated by `: `.
+    ///
+    /// ```
+    /// use dragonfly::{DfError, LoadPhase};
+    /// let url = "file:///missing.css".parse().unwrap();
+    /// let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+    /// let err = DfError::from(io_error).context(LoadPhase::Pull, &url);
+    /// assert_eq!(err.report(), "pull error for file:///missing.css: io error: not found");
+    /// ```
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(err) = source {
+            report.push_str(": ");
+            report.push_str(&err.to_string());
+            source = err.source();
+        }
+        report
+    }
+
+    /// The error without its resource contexts, to match on what went wrong.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use dragonfly::{DfError, LoadPhase};
+    /// let url = "https://example.com/style.css".parse().unwrap();
+    /// let err = DfError::NotLoaded.context(LoadPhase::Pull, &url);
+    /// assert_eq!(err.to_string(), "pull error for https://example.com/style.css");
+    /// let source = std::error::Error::source(&err).unwrap();
+    /// assert_eq!(source.to_string(), "no page is loaded");
+    /// assert!(matches!(err.without_context(), DfError::NotLoaded));
+    /// ```
+    pub fn without_context(&self) -> &DfError {
+        match self {
+            Self::Resource { source, .. } => source.without_context(),
+            err => err,
+        }
+    }
+}
+
+/// Add context to the errors of [`DfResult`]s.
+pub trait DfResultExt<T> {
+    /// Wrap the error with the URL of a resource and a load step (see [`DfError::context`]).
+    fn context(self, phase: LoadPhase, url: &url::Url) -> DfResult<T>;
+}
+
+impl<T> DfResultExt<T> for DfResult<T> {
+    #[inline]
+    fn context(self, phase: LoadPhase, url: &url::Url) -> DfResult<T> {
+        self.map_err(|err| err.context(phase, url))
+    }
+}
+
+/// Why a URL couldn't be resolved (see [`resolve_url`](crate::resolve_url)).
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum UrlResolveError {
+    #[error("invalid url: {0}")]
+    Invalid(#[from] url::ParseError),
+    /// The URL is valid, but no resource can be pulled from it (`javascript:`, `data:`, `mailto:`...)
+    #[error("url can't be fetched: {0}")]
+    Unfetchable(url::Url),
+}
+
+impl From<UrlResolveError> for DfError {
+    fn from(err: UrlResolveError) -> Self {
+        match err {
+            UrlResolveError::Invalid(err) => Self::UrlParseError(err),
+            UrlResolveError::Unfetchable(url) => Self::UnfetchableUrl(url.to_string()),
+        }
+    }
+}
+
+/// Non-fatal error encountered (and recovered from) while computing a layout.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LayoutError {
+    #[error("document is nested deeper than {0} elements, deeper elements were flattened")]
+    MaxDepthExceeded(usize),
+    #[error("{0} exceeded, the layout is incomplete")]
+    BudgetExceeded(crate::Budget),
+}
+
+/// Step of a page load in which a [`LoadError`] happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum LoadPhase {
+    /// Pulling the page or a subresource
+    Pull,
+    /// Parsing the HTML of the page
+    Parse,
+    /// Parsing and applying stylesheets
+    Style,
+    /// Loading fonts
+    Font,
+    /// Decoding images
+    Decode,
+    /// Computing the layout
+    Layout,
+}
+
+/// Non-fatal error encountered (and recovered from) while loading a page
+/// (see [`WebContext::load_errors`](crate::WebContext::load_errors)).
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{phase} error: {message}")]
+pub struct LoadError {
+    pub phase: LoadPhase,
+    /// URL of the resource the error is about, if any
+    pub url: Option<url::Url>,
+    /// Description of the error, with the messages of its sources
+    pub message: String,
+    /// Underlying error, if there is one
+    pub error: Option<std::sync::Arc<DfError>>,
+}
+
+impl LoadError {
+    pub fn new(phase: LoadPhase, url: Option<url::Url>, error: DfError) -> Self {
+        Self {
+            phase,
+            url,
+            message: error.report(),
+            error: Some(std::sync::Arc::new(error)),
+        }
+    }
+
+    /// An error described by a message only.
+    pub fn message(phase: LoadPhase, url: Option<url::Url>, message: impl Into<String>) -> Self {
+        Self {
+            phase,
+            url,
+            message: message.into(),
+            error: None,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{IconLink, WebContext};
+use bytes::Bytes;
+use url::Url;
+
+/// Icon size favored by [`WebContext::favicon_candidates`], in pixels.
+pub const PREFERRED_FAVICON_SIZE: u32 = 32;
+
+/// Icon pulled by [`WebContext::fetch_favicon`]. The data is not decoded.
+#[derive(Debug, Clone)]
+pub struct Favicon {
+    /// URL the icon was pulled from
+    pub url: Url,
+    pub data: Bytes,
+    /// `Content-Type` of the response, or else the declared type of the icon
+    pub content_type: Option<String>,
+}
+
+impl WebContext {
+    /// Icons of the page, most preferred first: `icon` links before `apple-touch-icon` ones,
+    /// then the ones with a declared size closest to [`PREFERRED_FAVICON_SIZE`], then document
+    /// order. Falls back to `/favicon.ico` of the origin of the page if no icon is declared.
+    pub fn favicon_candidates(&self) -> Vec<IconLink> {
+        let mut icons = self.metadata().icons.clone();
+        if icons.is_empty() {
+            let fallback = Some(self.url())
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+                .and_then(|url| url.join("/favicon.ico").ok());
+            icons.extend(fallback.map(|href| IconLink {
+                href,
+                rel: "icon".to_string(),
+                sizes: vec![],
+                kind: None,
+            }));
+        }
+        icons.sort_by_key(|icon| {
+            let touch = icon
+                .rel
+                .split_ascii_whitespace()
+                .all(|rel| !rel.eq_ignore_ascii_case("icon"));
+            let distance = icon
+                .sizes
+                .iter()
+                .map(|&(w, _)| w.abs_diff(PREFERRED_FAVICON_SIZE))
+                .min()
+                .unwrap_or(u32::MAX);
+            (touch, distance)
+        });
+        icons
+    }
+
+    /// Pull the first candidate icon (see [`favicon_candidates`](Self::favicon_candidates))
+    /// that can be pulled, through the cache of the puller. Error responses (like a missing
+    /// `/favicon.ico`) and empty ones are skipped. Returns `None` if no icon could be pulled.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let server = serve(|path| (path == "/icon.png").then(|| Response::ok("image/png", "png")));
+    /// let html = r#"<link rel=icon href=missing.png sizes=32x32><link rel=icon href=icon.png sizes=16x16>"#;
+    /// let mut ctx = WebContext::builder().html(html).url(&server).build()?;
+    /// block_on(ctx.load())?;
+    /// // the preferred icon is missing, the next one is used
+    /// let icon = block_on(ctx.fetch_favicon()).unwrap();
+    /// assert_eq!(icon.url.path(), "/icon.png");
+    /// assert_eq!(&icon.data[..], b"png");
+    ///
+    /// // the /favicon.ico fallback is missing too
+    /// let mut ctx = WebContext::builder().html("<p>no icons</p>").url(&server).build()?;
+    /// block_on(ctx.load())?;
+    /// assert_eq!(ctx.favicon_candidates()[0].href.path(), "/favicon.ico");
+    /// assert!(block_on(ctx.fetch_favicon()).is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_favicon(&self) -> Option<Favicon> {
+        let mut puller = self.puller.clone();
+        for icon in self.favicon_candidates() {
+            let resource = match puller.pull(icon.href.clone(), false).await {
+                Ok(resource) => resource,
+                Err(err) => {
+                    log::warn!("failed to pull icon '{}': {}", icon.href, err.report());
+                    continue;
+                }
+            };
+            if resource.data.is_empty() {
+                log::warn!("'{}' is not an icon", icon.href);
+                continue;
+            }
+            return Some(Favicon {
+                url: icon.href,
+                data: resource.data,
+                content_type: resource.content_type.or(icon.kind),
+            });
+        }
+        None
+    }
+}
@@ -1,29 +1,207 @@
-use crate::{DfError, DfResult, FontFamily};
+use crate::{DfError, DfResult, FontFamily, FontStyle, TextStyle};
 use font_kit::{
-    family_name::FamilyName, handle::Handle, properties::Properties, source::SystemSource,
+    family_name::FamilyName,
+    handle::Handle,
+    properties::{Properties, Style, Weight},
+    source::SystemSource,
 };
-use fontdue::{Font, Metrics};
+use fontdue::{Font, LineMetrics, Metrics};
+use std::collections::HashMap;
 use std::io::Read;
+use std::sync::{Arc, Mutex};
 
 /// Default fallback font (Cruft) data.
 pub const CRUFT_TTF_DATA: &[u8] = include_bytes!("./internal/cruft.ttf");
 
+/// Font size (in pixels) used when no other font size is specified.
+pub const DEFAULT_FONT_SIZE: f32 = 14.0;
+
+/// Weight of the regular faces of a [`FontManager`].
+pub const DEFAULT_FONT_WEIGHT: u16 = 400;
+
+/// Font storage and lookup.
+///
+/// Fonts are shared by the clones of a manager: cloning it is cheap, and fonts looked up
+/// by name by one clone are reused by the others.
+///
+/// **Breaking change:** the font fields are `Arc<Font>` (they used to be `Font`), so that
+/// the clones don't copy the font data. Wrap a font with `Arc::new` to set one.
+///
+/// ```
+/// use dragonfly::FontManager;
+/// use std::sync::Arc;
+/// let mut a = FontManager::default();
+/// let mut b = a.clone();
+/// assert!(Arc::ptr_eq(&a.serif, &b.serif));
+///
+/// // a font looked up by one clone is served to the other from the shared cache
+/// let found = a.by_name("Dragonfly Missing Font").is_some();
+/// assert_eq!(b.by_name("Dragonfly Missing Font").is_some(), found);
+/// let stats = b.cache_stats();
+/// assert_eq!((stats.entries, stats.misses, stats.hits), (1, 1, 1));
+///
+/// // fonts can be replaced by shared ones
+/// a.monospace = a.fallback_font.clone();
+/// assert!(Arc::ptr_eq(&a.monospace, &b.fallback_font));
+/// ```
 #[derive(Debug, Clone)]
 pub struct FontManager {
-    pub serif: Font,
-    pub sans_serif: Font,
-    pub monospace: Font,
-    pub cursive: Font,
+    pub serif: Arc<Font>,
+    pub sans_serif: Arc<Font>,
+    pub monospace: Arc<Font>,
+    pub cursive: Arc<Font>,
+    /// Whether fonts looked up by name are kept, so they're only looked up once
     pub cache_fonts: bool,
-    pub fantasy: Font,
+    pub fantasy: Arc<Font>,
     /// internal/cruft.ttf
-    pub fallback_font: Font,
-    cached_font: Option<(String, Font)>,
+    pub fallback_font: Arc<Font>,
+    /// Font looked up by name (and face) last
+    cached_font: Option<(FontKey, Arc<Font>)>,
+    /// Fonts looked up by name, shared by the clones of the manager
+    cache: Arc<Mutex<FontCache>>,
+    /// Whether the generic families were loaded from the system, so their other faces
+    /// and the fallback fonts can be looked up too
+    system_fonts: bool,
+    /// Fallback fonts found on the system, by language (see [`fallback_families`])
+    fallbacks: HashMap<String, Vec<Arc<Font>>>,
+    /// Number of strings measured, and their total length in bytes
+    pub(crate) measured_text: (usize, usize),
+}
+
+/// Statistics of the font cache of a [`FontManager`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FontCacheStats {
+    /// Number of font names looked up (including fonts that were not found)
+    pub entries: usize,
+    /// Number of lookups served from the cache
+    pub hits: usize,
+    /// Number of lookups made on the system
+    pub misses: usize,
+}
+
+/// Coverage bitmap of a line of text (see [`FontManager::rasterize_text`]).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextBitmap {
+    pub width: usize,
+    pub height: usize,
+    /// Distance from the top of the bitmap to the baseline, in pixels
+    pub ascent: usize,
+    /// Coverage of the pixels from 0 to 255, row by row
+    pub coverage: Vec<u8>,
+}
+
+/// Family name and face of a font looked up on the system.
+#[derive(Debug, Clone, PartialEq)]
+struct FontKey {
+    family: String,
+    weight: u16,
+    style: Style,
+}
+
+impl Eq for FontKey {}
+
+impl std::hash::Hash for FontKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.family.hash(state);
+        self.weight.hash(state);
+        (self.style as u8).hash(state);
+    }
+}
+
+impl FontKey {
+    fn new(family: String, weight: u16, style: FontStyle) -> Self {
+        let style = match style {
+            FontStyle::Normal => Style::Normal,
+            FontStyle::Italic => Style::Italic,
+            FontStyle::Oblique(_) => Style::Oblique,
+        };
+        Self {
+            family,
+            weight,
+            style,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FontCache {
+    /// Fonts by name and face, `None` if the font was not found
+    fonts: HashMap<FontKey, Option<Arc<Font>>>,
+    stats: FontCacheStats,
+}
+
+/// Fallback families of Japanese text.
+const JA_FAMILIES: &[&str] = &[
+    "Noto Sans CJK JP",
+    "Noto Sans JP",
+    "Hiragino Sans",
+    "Yu Gothic",
+    "MS Gothic",
+];
+
+/// Fallback families of Korean text.
+const KO_FAMILIES: &[&str] = &[
+    "Noto Sans CJK KR",
+    "Noto Sans KR",
+    "Apple SD Gothic Neo",
+    "Malgun Gothic",
+];
+
+/// Fallback families of simplified Chinese text.
+const ZH_HANS_FAMILIES: &[&str] = &[
+    "Noto Sans CJK SC",
+    "Noto Sans SC",
+    "PingFang SC",
+    "Microsoft YaHei",
+];
+
+/// Fallback families of traditional Chinese text.
+const ZH_HANT_FAMILIES: &[&str] = &[
+    "Noto Sans CJK TC",
+    "Noto Sans TC",
+    "PingFang TC",
+    "Microsoft JhengHei",
+];
+
+/// Fallback families of text in any language, after the families of its language.
+const COMMON_FAMILIES: &[&str] = &[
+    "Noto Sans",
+    "DejaVu Sans",
+    "Segoe UI",
+    "Arial Unicode MS",
+    "Noto Sans CJK SC",
+];
+
+/// Font families tried, in order, for the characters that the font of a text doesn't have,
+/// by the language of the text (a BCP 47 tag). Han characters are shared by Chinese,
+/// Japanese and Korean but drawn differently, so the fonts of the language come first.
+///
+/// ```
+/// use dragonfly::fallback_families;
+/// assert_eq!(fallback_families("ja-JP")[0], "Noto Sans CJK JP");
+/// assert_eq!(fallback_families("zh-Hant")[0], "Noto Sans CJK TC");
+/// assert_eq!(fallback_families("zh-TW")[0], "Noto Sans CJK TC");
+/// assert_eq!(fallback_families("zh")[0], "Noto Sans CJK SC");
+/// assert_eq!(fallback_families("en"), fallback_families(""));
+/// assert!(fallback_families("ko").ends_with(&fallback_families("en")));
+/// ```
+pub fn fallback_families(lang: &str) -> Vec<&'static str> {
+    let lang = lang.trim().to_ascii_lowercase();
+    let mut subtags = lang.split(['-', '_']);
+    let families = match subtags.next().unwrap_or_default() {
+        "ja" => JA_FAMILIES,
+        "ko" => KO_FAMILIES,
+        "zh" if subtags.any(|tag| matches!(tag, "hant" | "tw" | "hk" | "mo")) => ZH_HANT_FAMILIES,
+        "zh" => ZH_HANS_FAMILIES,
+        _ => &[],
+    };
+    families.iter().chain(COMMON_FAMILIES).copied().collect()
 }
 
 impl Default for FontManager {
     fn default() -> Self {
-        let fallback = Font::from_bytes(CRUFT_TTF_DATA, fontdue::FontSettings::default()).unwrap();
+        let fallback =
+            Arc::new(Font::from_bytes(CRUFT_TTF_DATA, fontdue::FontSettings::default()).unwrap());
         Self {
             serif: fallback.clone(),
             sans_serif: fallback.clone(),
@@ -32,7 +210,11 @@ impl Default for FontManager {
             fantasy: fallback.clone(),
             cache_fonts: true,
             cached_font: None,
+            cache: Arc::default(),
+            system_fonts: false,
+            fallbacks: HashMap::new(),
             fallback_font: fallback,
+            measured_text: (0, 0),
         }
     }
 }
@@ -72,14 +254,15 @@ fn get_font_data(family: FamilyName, properties: &Properties) -> DfResult<Font>
 
     // load the font with fontdue
     log::info!("loading font...");
-    let font = Font::from_bytes(data, fontdue::FontSettings::default());
-    if font.is_ok() {
-        log::info!("loaded font successfully");
-        Ok(font.unwrap())
-    } else {
-        let err_str = font.err().unwrap().to_string();
-        log::error!("failed to load font (fontdue): {err_str}");
-        Err(DfError::FontLoadingError(err_str))
+    match Font::from_bytes(data, fontdue::FontSettings::default()) {
+        Ok(font) => {
+            log::info!("loaded font successfully");
+            Ok(font)
+        }
+        Err(err) => {
+            log::error!("failed to load font (fontdue): {err}");
+            Err(DfError::FontLoadingError(err.to_string()))
+        }
     }
 }
 
@@ -97,62 +280,298 @@ impl FontManager {
 
     pub fn load_system_fonts(&mut self) {
         // TODO: load fonts in parallel
-        let start = std::time::Instant::now();
+        let start = crate::clock::Instant::now();
         log::info!("loading system fonts");
         let properties = Properties::new();
-        self.serif = get_font_data(FamilyName::Serif, &properties).unwrap();
-        self.sans_serif = get_font_data(FamilyName::SansSerif, &properties).unwrap();
-        self.monospace = get_font_data(FamilyName::Monospace, &properties).unwrap();
-        self.cursive = get_font_data(FamilyName::Cursive, &properties).unwrap();
-        self.fantasy = get_font_data(FamilyName::Fantasy, &properties).unwrap();
+        let load = |family| Arc::new(get_font_data(family, &properties).unwrap());
+        self.serif = load(FamilyName::Serif);
+        self.sans_serif = load(FamilyName::SansSerif);
+        self.monospace = load(FamilyName::Monospace);
+        self.cursive = load(FamilyName::Cursive);
+        self.fantasy = load(FamilyName::Fantasy);
+        self.system_fonts = true;
         log::info!("loaded fonts in {:?}", start.elapsed());
     }
 
     /// Get font by name. If the font is already present in the font cache, no font lookup is made.
-    pub fn by_name(&mut self, name: &str) -> Option<Font> {
-        // check if we cached the font already
-        // TODO: add an option to cache multiple fonts
+    pub fn by_name(&mut self, name: &str) -> Option<Arc<Font>> {
+        self.face_by_name(name, DEFAULT_FONT_WEIGHT, FontStyle::Normal)
+    }
+
+    /// Get the face of a font with the closest weight to `weight` (from 1 to 1000) and the
+    /// closest style to `style`. If the face is already present in the font cache, no font
+    /// lookup is made.
+    pub fn face_by_name(&mut self, name: &str, weight: u16, style: FontStyle) -> Option<Arc<Font>> {
+        let family = FamilyName::Title(name.to_string());
+        self.lookup(family, FontKey::new(name.to_string(), weight, style))
+    }
+
+    fn lookup(&mut self, family: FamilyName, key: FontKey) -> Option<Arc<Font>> {
+        // check if we used the font last
         if let Some(cached_font) = &self.cached_font {
-            if cached_font.0 == name {
-                log::info!("found cached font '{name}'");
+            if cached_font.0 == key {
                 return Some(cached_font.1.clone());
             }
         }
 
+        // then if any clone of the manager looked it up already
+        if self.cache_fonts {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(font) = cache.fonts.get(&key).cloned() {
+                log::info!("found cached font {key:?}");
+                cache.stats.hits += 1;
+                drop(cache);
+                if let Some(font) = &font {
+                    self.cached_font = Some((key, font.clone()));
+                }
+                return font;
+            }
+        }
+
         // otherwise, load the font
-        log::info!("looking up font '{name}'");
-        let data =
-            get_font_data(FamilyName::Title(name.to_string()), &Properties::default()).ok()?;
-        self.cached_font = Some((name.to_string(), data.clone())); // update cached font data
-        Some(data.clone())
+        log::info!("looking up font {key:?}");
+        let mut properties = Properties::new();
+        properties
+            .weight(Weight(key.weight as f32))
+            .style(key.style);
+        let font = get_font_data(family, &properties).ok().map(Arc::new);
+        if self.cache_fonts {
+            let mut cache = self.cache.lock().unwrap();
+            cache.fonts.insert(key.clone(), font.clone());
+            cache.stats.misses += 1;
+            cache.stats.entries = cache.fonts.len();
+        }
+        if let Some(font) = &font {
+            self.cached_font = Some((key, font.clone()));
+        }
+        font
+    }
+
+    /// Statistics of the cache of fonts looked up by name.
+    pub fn cache_stats(&self) -> FontCacheStats {
+        self.cache.lock().unwrap().stats
     }
 
-    pub fn get_font(&mut self, family: FontFamily) -> &Font {
-        match family {
-            FontFamily::Serif => &self.serif,
+    /// Get the font of a family, in the face with the closest weight to `weight` and the
+    /// closest style to `style`.
+    ///
+    /// Other faces than the regular one of the generic families are only looked up if the
+    /// system fonts were loaded, otherwise the regular face is used.
+    pub fn get_font(&mut self, family: FontFamily, weight: u16, style: FontStyle) -> &Font {
+        self.font(family, weight, style)
+    }
+
+    /// Font of a family and face, see [`FontManager::get_font`].
+    fn font(&mut self, family: FontFamily, weight: u16, style: FontStyle) -> &Arc<Font> {
+        let generic = match family {
+            FontFamily::SystemUi
+            | FontFamily::UiSerif
+            | FontFamily::UiRounded
+            | FontFamily::Math
+            | FontFamily::Emoji
+            | FontFamily::Fangsong => FontFamily::Serif,
+            FontFamily::UiSansSerif => FontFamily::SansSerif,
+            FontFamily::UiMonospace => FontFamily::Monospace,
+            FontFamily::Custom(s) => {
+                if self.face_by_name(&s, weight, style).is_none() {
+                    log::warn!("could not find system font '{s}'");
+                    return &self.fallback_font;
+                }
+                return &self.cached_font.as_ref().unwrap().1;
+            }
+            generic => generic,
+        };
+        let name = match generic {
+            FontFamily::SansSerif => FamilyName::SansSerif,
+            FontFamily::Monospace => FamilyName::Monospace,
+            FontFamily::Cursive => FamilyName::Cursive,
+            FontFamily::Fantasy => FamilyName::Fantasy,
+            _ => FamilyName::Serif,
+        };
+        // keyed by the css name of the family, which always parses as the generic family
+        let key = FontKey::new(generic.to_string(), weight, style);
+        if (weight != DEFAULT_FONT_WEIGHT || style != FontStyle::Normal)
+            && self.system_fonts
+            && self.lookup(name, key).is_some()
+        {
+            return &self.cached_font.as_ref().unwrap().1;
+        }
+        match generic {
             FontFamily::SansSerif => &self.sans_serif,
             FontFamily::Monospace => &self.monospace,
             FontFamily::Cursive => &self.cursive,
             FontFamily::Fantasy => &self.fantasy,
-            FontFamily::SystemUi => &self.serif,
-            FontFamily::UiSerif => &self.serif,
-            FontFamily::UiSansSerif => &self.sans_serif,
-            FontFamily::UiMonospace => &self.monospace,
-            FontFamily::UiRounded => &self.serif,
-            FontFamily::Math => &self.serif,
-            FontFamily::Emoji => &self.serif,
-            FontFamily::Fangsong => &self.serif,
-            FontFamily::Custom(s) => {
-                if let None = self.by_name(&s) {
-                    log::warn!("could not find system font '{s}'");
-                    return &self.fallback_font;
+            _ => &self.serif,
+        }
+    }
+
+    pub fn glyph_metrics(
+        &mut self,
+        glyph: char,
+        px: f32,
+        family: FontFamily,
+        weight: u16,
+        style: FontStyle,
+    ) -> Metrics {
+        self.get_font(family, weight, style).metrics(glyph, px)
+    }
+
+    /// Measure the advance width of a string laid out on a single line.
+    pub fn text_width(
+        &mut self,
+        text: &str,
+        px: f32,
+        family: FontFamily,
+        weight: u16,
+        style: FontStyle,
+    ) -> f32 {
+        self.measured_text.0 += 1;
+        self.measured_text.1 += text.len();
+        let font = self.get_font(family, weight, style);
+        text.chars()
+            .map(|c| font.metrics(c, px).advance_width)
+            .sum()
+    }
+
+    /// Measure the advance width of a string laid out on a single line in a text style. The
+    /// characters that the font of the style doesn't have are measured in the fallback font
+    /// of the language of the style that has them (see [`FontManager::font_for_char`]).
+    pub fn styled_text_width(&mut self, text: &str, style: &TextStyle) -> f32 {
+        self.measured_text.0 += 1;
+        self.measured_text.1 += text.len();
+        let font = self
+            .font(
+                style.font_family.clone(),
+                style.font_weight,
+                style.font_style,
+            )
+            .clone();
+        let mut width = 0.0;
+        for c in text.chars() {
+            let metrics = match font.lookup_glyph_index(c) {
+                0 => self
+                    .fallback_for_char(c, &font, &style.lang)
+                    .metrics(c, style.font_size),
+                _ => font.metrics(c, style.font_size),
+            };
+            width += metrics.advance_width;
+        }
+        width
+    }
+
+    /// Font a character of a text in a style is drawn with: the font of the style, or if it
+    /// doesn't have the character, the first fallback font of the language of the text that
+    /// has it (see [`fallback_families`]).
+    ///
+    /// Fallback fonts are only looked up if the system fonts were loaded, so the fonts of
+    /// a [`FontManager::with_fallback_font`] manager don't depend on the system.
+    ///
+    /// ```
+    /// use dragonfly::{FontManager, TextStyle};
+    /// use std::sync::Arc;
+    /// let mut fonts = FontManager::default();
+    /// let style = TextStyle { lang: "ja".to_string(), ..Default::default() };
+    /// assert!(Arc::ptr_eq(&fonts.font_for_char('日', &style), &fonts.serif));
+    /// ```
+    pub fn font_for_char(&mut self, c: char, style: &TextStyle) -> Arc<Font> {
+        let font = self
+            .font(
+                style.font_family.clone(),
+                style.font_weight,
+                style.font_style,
+            )
+            .clone();
+        match font.lookup_glyph_index(c) {
+            0 => self.fallback_for_char(c, &font, &style.lang),
+            _ => font,
+        }
+    }
+
+    /// First fallback font of a language that has a character `font` doesn't have, or `font`
+    /// itself if there's none.
+    fn fallback_for_char(&mut self, c: char, font: &Arc<Font>, lang: &str) -> Arc<Font> {
+        if !self.system_fonts || c.is_whitespace() || c.is_control() {
+            return font.clone();
+        }
+        let lang = lang.trim().to_ascii_lowercase();
+        if !self.fallbacks.contains_key(&lang) {
+            let fonts = fallback_families(&lang)
+                .into_iter()
+                .filter_map(|family| self.by_name(family))
+                .collect();
+            self.fallbacks.insert(lang.clone(), fonts);
+        }
+        self.fallbacks[&lang]
+            .iter()
+            .find(|fallback| fallback.lookup_glyph_index(c) != 0)
+            .unwrap_or(font)
+            .clone()
+    }
+
+    /// Rasterize a string laid out on a single line in a text style, with glyphs advanced like
+    /// in [`FontManager::text_width`], plus the letter and word spacing of the style.
+    pub fn rasterize_text(&mut self, text: &str, style: &TextStyle) -> TextBitmap {
+        let (px, family) = (style.font_size, style.font_family.clone());
+        let (weight, font_style) = (style.font_weight, style.font_style);
+        let metrics = self.line_metrics(px, family.clone(), weight, font_style);
+        let spacing: f32 = text.chars().map(|c| style.spacing_after(c)).sum();
+        let width = self.styled_text_width(text, style) + spacing;
+        let width = width.max(0.0).ceil() as usize;
+        let ascent = metrics.ascent.ceil() as usize;
+        let height = ascent + (-metrics.descent).ceil() as usize;
+        let mut bitmap = TextBitmap {
+            width,
+            height,
+            ascent,
+            coverage: vec![0; width * height],
+        };
+
+        let font = self.font(family, weight, font_style).clone();
+        let mut pen = 0.0;
+        for c in text.chars() {
+            let font = match font.lookup_glyph_index(c) {
+                0 => self.fallback_for_char(c, &font, &style.lang),
+                _ => font.clone(),
+            };
+            let (glyph, coverage) = font.rasterize(c, px);
+            let left = (pen + glyph.xmin as f32).round() as isize;
+            let top = ascent as isize - (glyph.ymin + glyph.height as i32) as isize;
+            for (row, line) in coverage.chunks(glyph.width.max(1)).enumerate() {
+                let y = top + row as isize;
+                if y < 0 || y >= height as isize {
+                    continue;
+                }
+                for (col, &value) in line.iter().enumerate() {
+                    let x = left + col as isize;
+                    if x < 0 || x >= width as isize {
+                        continue;
+                    }
+                    let pixel = &mut bitmap.coverage[y as usize * width + x as usize];
+                    *pixel = pixel.saturating_add(value);
                 }
-                &self.cached_font.as_ref().unwrap().1
             }
+            pen += glyph.advance_width + style.spacing_after(c);
         }
+        bitmap
     }
 
-    pub fn glyph_metrics(&mut self, glyph: char, px: f32, family: FontFamily) -> Metrics {
-        self.get_font(family).metrics(glyph, px)
+    /// Get the horizontal line metrics (ascent, descent, line gap) of a font family and face.
+    ///
+    /// If the font doesn't provide line metrics, they are approximated from the font size.
+    pub fn line_metrics(
+        &mut self,
+        px: f32,
+        family: FontFamily,
+        weight: u16,
+        style: FontStyle,
+    ) -> LineMetrics {
+        self.get_font(family, weight, style)
+            .horizontal_line_metrics(px)
+            .unwrap_or(LineMetrics {
+                ascent: px * 0.8,
+                descent: -px * 0.2,
+                line_gap: px * 0.2,
+                new_line_size: px * 1.2,
+            })
     }
 }
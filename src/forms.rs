@@ -0,0 +1,236 @@
+use crate::{resolve_url, Layout, WebContext};
+use indextree::NodeId;
+use strum_macros::{Display, EnumString};
+use url::Url;
+
+/// HTTP method a form is submitted with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum FormMethod {
+    #[default]
+    #[strum(serialize = "get")]
+    Get,
+    #[strum(serialize = "post")]
+    Post,
+}
+
+/// A control (`input`, `select`, `textarea` or `button`) associated with a form.
+#[derive(Debug, Clone)]
+pub struct FormControl {
+    pub node: NodeId,
+    /// Value of the `name` attribute
+    pub name: String,
+    /// Control type: the `type` attribute of inputs and buttons (`text` and `submit` by default),
+    /// `select`, `select-multiple` or `textarea`
+    pub kind: String,
+    /// Current values: the selected options of a `select`, the value of other controls
+    pub values: Vec<String>,
+    /// Checked state of checkboxes and radio buttons
+    pub checked: bool,
+    /// Whether the control is disabled (directly or by a disabled `fieldset`)
+    pub disabled: bool,
+}
+
+impl FormControl {
+    /// Whether the control contributes its values when the form is submitted.
+    pub fn is_submittable(&self) -> bool {
+        let ignored = matches!(
+            self.kind.as_str(),
+            "submit" | "button" | "reset" | "image" | "file"
+        );
+        let unchecked = matches!(self.kind.as_str(), "checkbox" | "radio") && !self.checked;
+        !self.name.is_empty() && !self.disabled && !ignored && !unchecked
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Form {
+    pub node: NodeId,
+    /// Resolved submission URL (the document URL if there is no `action` attribute)
+    pub action: Url,
+    pub method: FormMethod,
+    /// Controls associated with the form, in document order
+    pub controls: Vec<FormControl>,
+}
+
+impl Form {
+    /// Name/value pairs submitted by the form, in document order.
+    ///
+    /// `overrides` replace the values of the controls with the same name.
+    pub fn payload(&self, overrides: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut pairs = vec![];
+        for control in self.controls.iter().filter(|c| c.is_submittable()) {
+            match overrides.iter().find(|(name, _)| *name == control.name) {
+                // an overridden name is only submitted once
+                Some((name, value)) => {
+                    if !pairs.iter().any(|(n, _): &(String, String)| n == name) {
+                        pairs.push((name.to_string(), value.to_string()));
+                    }
+                }
+                None => pairs.extend(
+                    control
+                        .values
+                        .iter()
+                        .map(|value| (control.name.clone(), value.clone())),
+                ),
+            }
+        }
+        pairs
+    }
+}
+
+impl Layout {
+    /// Find all forms of the document with their controls. Relative actions are resolved against `base`.
+    pub fn forms(&self, base: &Url) -> Vec<Form> {
+        let form_ids = self.select("form");
+        let controls = self.select("input, select, textarea, button");
+
+        form_ids
+            .iter()
+            .map(|&form_id| {
+                let node = self.arena[form_id].get();
+                let action = node
+                    .attr("action")
+                    .map(str::trim)
+                    .filter(|action| !action.is_empty())
+                    .and_then(|action| resolve_url(base, action).ok())
+                    .unwrap_or_else(|| base.clone());
+                let method = node
+                    .attr("method")
+                    .and_then(|method| method.trim().parse().ok())
+                    .unwrap_or_default();
+
+                Form {
+                    node: form_id,
+                    action,
+                    method,
+                    controls: controls
+                        .iter()
+                        .filter(|&&id| self.form_owner(id) == Some(form_id))
+                        .map(|&id| self.form_control(id))
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// The form a control belongs to: the form referenced by its `form` attribute,
+    /// or its closest `form` ancestor.
+    fn form_owner(&self, id: NodeId) -> Option<NodeId> {
+        match self.arena[id].get().attr("form") {
+            Some(form_id) => self
+                .select("form")
+                .into_iter()
+                .find(|&form| self.arena[form].get().id() == form_id),
+            None => self.closest(id, "form"),
+        }
+    }
+
+    fn form_control(&self, id: NodeId) -> FormControl {
+        let node = self.arena[id].get();
+        let attr = |name: &str| node.attr(name).map(str::to_string);
+        let kind = match node.name() {
+            "select" if node.has_bool_attr("multiple") => "select-multiple",
+            "select" | "textarea" => node.name(),
+            "button" => node
+                .enumerated_attr("type", &["submit", "reset", "button"])
+                .unwrap_or("submit"),
+            _ => node.input_type(),
+        }
+        .to_string();
+
+        let values = match node.name() {
+            "select" => self.selected_options(id, kind == "select-multiple"),
+            "textarea" => vec![self.text_content(id)],
+            _ => vec![attr("value").unwrap_or_else(|| match kind.as_str() {
+                "checkbox" | "radio" => "on".to_string(),
+                _ => String::new(),
+            })],
+        };
+
+        FormControl {
+            node: id,
+            name: attr("name").unwrap_or_default(),
+            checked: node.has_bool_attr("checked"),
+            disabled: self.is_disabled(id).unwrap_or_default(),
+            kind,
+            values,
+        }
+    }
+
+    /// Values of the selected options of a `select`. Without a selected option,
+    /// the first one is selected, unless multiple options can be selected.
+    fn selected_options(&self, id: NodeId, multiple: bool) -> Vec<String> {
+        let options: Vec<NodeId> = self
+            .select("option")
+            .into_iter()
+            .filter(|&option| self.closest(option, "select") == Some(id))
+            .collect();
+        let value = |option: NodeId| {
+            let node = self.arena[option].get();
+            node.attr("value")
+                .map(str::to_string)
+                .unwrap_or_else(|| self.text_content(option).trim().to_string())
+        };
+
+        let mut selected = options
+            .iter()
+            .copied()
+            .filter(|&option| self.arena[option].get().has_bool_attr("selected"));
+        match (multiple, options.first()) {
+            (true, _) => selected.map(value).collect(),
+            (false, first) => selected
+                .next_back()
+                .or(first.copied())
+                .map(value)
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl WebContext {
+    /// Find all forms of the page, with actions resolved against the base URL of the page.
+    pub fn forms(&self) -> Vec<Form> {
+        self.layout.forms(self.base_url())
+    }
+
+    /// URL-encode the values submitted by a form (see [`Form::payload`]),
+    /// as a query string for `GET` forms or a request body for `POST` forms.
+    ///
+    /// ```
+    /// use dragonfly::{FormMethod, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut ctx = WebContext::builder().url("file:///tests/login.html").build()?;
+    /// block_on(ctx.load())?;
+    /// let forms = ctx.forms();
+    /// let (login, search) = (&forms[0], &forms[1]);
+    /// assert_eq!(login.method, FormMethod::Post);
+    /// assert_eq!(login.action.as_str(), "file:///session?next=/home");
+    /// let names: Vec<_> = login.controls.iter().map(|c| c.name.as_str()).collect();
+    /// assert_eq!(names, ["user", "password", "remember", "newsletter", "lang", "token", "go", "csrf"]);
+    ///
+    /// // unchecked checkboxes, disabled controls and buttons are not submitted
+    /// assert_eq!(
+    ///     ctx.submit_payload(login, &[]),
+    ///     "user=ada+lovelace&password=p%26ss%3Dw0rd&remember=on&lang=fr&csrf=abc"
+    /// );
+    /// assert_eq!(
+    ///     ctx.submit_payload(login, &[("password", "secret"), ("lang", "en")]),
+    ///     "user=ada+lovelace&password=secret&remember=on&lang=en&csrf=abc"
+    /// );
+    ///
+    /// // GET to the document URL by default
+    /// assert_eq!(search.method, FormMethod::Get);
+    /// assert_eq!(search.action, *ctx.url());
+    /// assert_eq!(ctx.submit_payload(search, &[]), "q=x");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn submit_payload(&self, form: &Form, overrides: &[(&str, &str)]) -> String {
+        url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form.payload(overrides))
+            .finish()
+    }
+}
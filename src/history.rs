@@ -0,0 +1,93 @@
+use std::time::SystemTime;
+use url::Url;
+
+/// A page visited by a [`WebContext`](crate::WebContext).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub url: Url,
+    /// Title of the page, once it's loaded
+    pub title: Option<String>,
+    /// When the page was visited. On `wasm32-unknown-unknown`, the time comes from the clock
+    /// set with `set_system_clock`
+    pub timestamp: SystemTime,
+    /// Markup of pages that were not pulled from their URL
+    pub(crate) html_str: Option<String>,
+}
+
+impl HistoryEntry {
+    pub(crate) fn new(url: Url, html_str: Option<String>) -> Self {
+        Self {
+            url,
+            title: None,
+            timestamp: crate::clock::system_time(),
+            html_str,
+        }
+    }
+}
+
+/// Session history: the visited pages and the position of the current one.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    /// Index of the current entry
+    index: usize,
+}
+
+impl History {
+    /// All entries, oldest first.
+    #[inline]
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Index of the current entry in [`History::entries`].
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[inline]
+    pub fn current(&self) -> Option<&HistoryEntry> {
+        self.entries.get(self.index)
+    }
+
+    pub(crate) fn current_mut(&mut self) -> Option<&mut HistoryEntry> {
+        self.entries.get_mut(self.index)
+    }
+
+    #[inline]
+    pub fn can_go_back(&self) -> bool {
+        self.index > 0
+    }
+
+    #[inline]
+    pub fn can_go_forward(&self) -> bool {
+        self.index + 1 < self.entries.len()
+    }
+
+    /// Add an entry after the current one, dropping the entries that were forward of it.
+    pub(crate) fn push(&mut self, entry: HistoryEntry) {
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.index + 1);
+        }
+        self.entries.push(entry);
+        self.index = self.entries.len() - 1;
+    }
+
+    /// Add entries after the current one like [`History::push`], the last one becoming current.
+    pub(crate) fn extend(&mut self, entries: impl IntoIterator<Item = HistoryEntry>) {
+        for entry in entries {
+            self.push(entry);
+        }
+    }
+
+    /// Move the current entry by `delta` entries, returns the new current entry.
+    pub(crate) fn go(&mut self, delta: isize) -> Option<&HistoryEntry> {
+        let index = self.index.checked_add_signed(delta)?;
+        if index >= self.entries.len() {
+            return None;
+        }
+        self.index = index;
+        self.entries.get(index)
+    }
+}
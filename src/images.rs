@@ -0,0 +1,286 @@
+use crate::{DfError, DfResult, WebContext};
+use indexmap::IndexMap;
+use url::Url;
+
+/// Handle of a decoded image in the [`ImageStore`] of a context, resolved with
+/// [`WebContext::image`](crate::WebContext::image).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageHandle(u64);
+
+/// An image decoded to 8-bit RGBA pixels, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl DecodedImage {
+    /// Decode a PNG, GIF or JPEG image. Animated GIFs are decoded to their first frame.
+    ///
+    /// ```
+    /// use dragonfly::DecodedImage;
+    /// let image = DecodedImage::decode(&std::fs::read("tests/pixel.png").unwrap())?;
+    /// assert_eq!((image.width, image.height), (40, 30));
+    /// assert_eq!(image.data.len(), 40 * 30 * 4);
+    /// let gif = DecodedImage::decode(&std::fs::read("tests/frames.gif").unwrap())?;
+    /// assert_eq!((gif.width, gif.height), (2, 2));
+    /// assert_eq!(gif.pixel(1, 0), Some([0, 255, 0, 255]));
+    /// assert!(DecodedImage::decode(b"GIF89a").is_err());
+    /// # Ok::<(), dragonfly::DfError>(())
+    /// ```
+    pub fn decode(data: &[u8]) -> DfResult<Self> {
+        Self::decode_limited(data, usize::MAX)
+    }
+
+    /// Decode an image like [`DecodedImage::decode`], failing without decoding it if its
+    /// pixels take more than `max_bytes`.
+    ///
+    /// ```
+    /// use dragonfly::DecodedImage;
+    /// let data = std::fs::read("tests/pixel.png").unwrap();
+    /// assert!(DecodedImage::decode_limited(&data, 40 * 30 * 4).is_ok());
+    /// assert!(DecodedImage::decode_limited(&data, 40 * 30 * 4 - 1).is_err());
+    ///
+    /// // a 30000x30000 PNG is rejected from its header, without inflating it
+    /// let bomb = std::fs::read("tests/bomb.png").unwrap();
+    /// let err = DecodedImage::decode_limited(&bomb, 64 << 20).unwrap_err();
+    /// assert!(err.to_string().contains("30000x30000"));
+    /// ```
+    pub fn decode_limited(data: &[u8], max_bytes: usize) -> DfResult<Self> {
+        let error = |err: image::ImageError| DfError::ImageDecodeError(err.to_string());
+        let reader = image::io::Reader::new(std::io::Cursor::new(data))
+            .with_guessed_format()
+            .map_err(|err| DfError::ImageDecodeError(err.to_string()))?;
+        let format = reader.format();
+        // the size is checked from the header, before anything is decompressed
+        let (width, height) = reader.into_dimensions().map_err(error)?;
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .filter(|&len| len <= max_bytes)
+            .ok_or_else(|| {
+                DfError::ImageDecodeError(format!(
+                    "{width}x{height} image doesn't fit in {max_bytes} bytes"
+                ))
+            })?;
+
+        let mut reader = image::io::Reader::new(std::io::Cursor::new(data));
+        if let Some(format) = format {
+            reader.set_format(format);
+        }
+        let mut limits = image::io::Limits::default();
+        limits.max_alloc = Some(len.saturating_mul(2) as u64);
+        reader.limits(limits);
+        let image = reader.decode().map_err(error)?.into_rgba8();
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            data: image.into_raw(),
+        })
+    }
+
+    /// RGBA value of a pixel, `None` outside of the image.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        Some(self.data[i..i + 4].try_into().unwrap())
+    }
+}
+
+/// Decoded images of a context, by URL.
+///
+/// The total size of the pixels is limited to [`max_bytes`](ImageStore::max_bytes): when an image
+/// doesn't fit, the least recently used images are evicted, and their handles no longer resolve.
+#[derive(Debug, Clone)]
+pub struct ImageStore {
+    images: IndexMap<Url, (ImageHandle, DecodedImage)>,
+    max_bytes: usize,
+    bytes: usize,
+    next_handle: u64,
+}
+
+impl Default for ImageStore {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_BYTES)
+    }
+}
+
+impl ImageStore {
+    /// Default budget of decoded bytes (256 MiB).
+    pub const DEFAULT_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            images: IndexMap::new(),
+            max_bytes,
+            bytes: 0,
+            next_handle: 0,
+        }
+    }
+
+    /// Maximum total size of the decoded images in bytes.
+    #[inline]
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Change the budget of decoded bytes, evicting images that no longer fit.
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        self.evict(0);
+    }
+
+    /// Total size of the decoded images in bytes.
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Decoded image of a handle, `None` if it was evicted.
+    pub fn get(&self, handle: ImageHandle) -> Option<&DecodedImage> {
+        self.images
+            .values()
+            .find(|(h, _)| *h == handle)
+            .map(|(_, image)| image)
+    }
+
+    /// Handle of the image decoded from a URL, marking it as recently used.
+    pub fn handle(&mut self, url: &Url) -> Option<ImageHandle> {
+        let index = self.images.get_index_of(url)?;
+        let last = self.images.len() - 1;
+        self.images.move_index(index, last);
+        Some(self.images[last].0)
+    }
+
+    /// Store the image decoded from a URL, replacing the previous image of the URL.
+    /// Returns `None` if the image is bigger than the whole budget.
+    pub fn insert(&mut self, url: Url, image: DecodedImage) -> Option<ImageHandle> {
+        self.remove(&url);
+        let len = image.data.len();
+        if len > self.max_bytes {
+            log::warn!("image '{url}' is bigger than the image budget ({len} bytes)");
+            return None;
+        }
+        self.evict(len);
+        let handle = ImageHandle(self.next_handle);
+        self.next_handle += 1;
+        self.bytes += len;
+        self.images.insert(url, (handle, image));
+        Some(handle)
+    }
+
+    /// Remove the image decoded from a URL.
+    pub fn remove(&mut self, url: &Url) -> Option<DecodedImage> {
+        let (_, image) = self.images.shift_remove(url)?;
+        self.bytes -= image.data.len();
+        Some(image)
+    }
+
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.bytes = 0;
+    }
+
+    /// Evict the least recently used images until `len` more bytes fit in the budget.
+    fn evict(&mut self, len: usize) {
+        while self.bytes + len > self.max_bytes {
+            let Some((url, (_, image))) = self.images.shift_remove_index(0) else {
+                break;
+            };
+            log::info!("evicting decoded image '{url}'");
+            self.bytes -= image.data.len();
+        }
+    }
+}
+
+impl WebContext {
+    /// Decoded image of a handle from the display list (see [`DisplayItem::Image`](crate::DisplayItem::Image)).
+    /// Returns `None` if the image was evicted from the [`ImageStore`].
+    ///
+    /// ```
+    /// use dragonfly::{DisplayItem, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<img src=pixel.png>";
+    /// let mut ctx = WebContext::builder().html(html).url("file:///tests/page.html").build()?;
+    /// block_on(ctx.load())?;
+    /// let handle = ctx
+    ///     .display_list()
+    ///     .iter()
+    ///     .find_map(|item| match item {
+    ///         DisplayItem::Image { image, size, .. } => Some((*image, *size)),
+    ///         _ => None,
+    ///     });
+    /// let (handle, size) = handle.unwrap();
+    /// assert_eq!((size.x, size.y), (40.0, 30.0));
+    /// let image = ctx.image(handle).unwrap();
+    /// assert_eq!((image.width, image.height), (40, 30));
+    /// assert_eq!(image.pixel(0, 0), Some([64, 128, 192, 255]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn image(&self, handle: ImageHandle) -> Option<&DecodedImage> {
+        self.images.get(handle)
+    }
+
+    /// Decoded images of the context, shared by the pages it loads.
+    #[inline]
+    pub fn images(&self) -> &ImageStore {
+        &self.images
+    }
+
+    /// Decoded images of the context, e.g. to change their budget. Images that can't be
+    /// decoded, or that don't fit in the budget, are shown as their `alt` text and reported
+    /// in the [load errors](WebContext::load_errors).
+    ///
+    /// ```
+    /// use dragonfly::{DisplayItem, LoadPhase, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<img src=pixel.png alt=pixel><img src=garbage.html alt=garbage>";
+    /// let mut ctx = WebContext::builder().html(html).url("file:///tests/page.html").build()?;
+    /// ctx.images_mut().set_max_bytes(1024);
+    /// block_on(ctx.load())?;
+    /// let errors = ctx.load_errors().iter().filter(|err| err.phase == LoadPhase::Decode);
+    /// let urls: Vec<_> = errors.filter_map(|err| err.url.as_ref()).map(|url| url.path()).collect();
+    /// assert_eq!(urls, ["/tests/pixel.png", "/tests/garbage.html"]);
+    /// assert!(ctx.images().is_empty());
+    /// let list = ctx.display_list();
+    /// assert!(!list.iter().any(|item| matches!(item, DisplayItem::Image { .. })));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn images_mut(&mut self) -> &mut ImageStore {
+        &mut self.images
+    }
+
+    /// Decode a loaded image for the `img` elements with a `src`, unless it was already
+    /// decoded. Images bigger than the budget of the [`ImageStore`] are not decoded.
+    pub(crate) fn decode_image(&mut self, src: &str, url: &Url, data: &[u8]) -> DfResult<()> {
+        let handle = match self.images.handle(url) {
+            Some(handle) => handle,
+            None => {
+                let image = DecodedImage::decode_limited(data, self.images.max_bytes())?;
+                log::info!("decoded image '{url}' ({}x{})", image.width, image.height);
+                let Some(handle) = self.images.insert(url.clone(), image) else {
+                    return Ok(());
+                };
+                handle
+            }
+        };
+        self.layout.image_handles.insert(src.to_string(), handle);
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{resolve_url, BoxKind, Layout, Rect, TextFragment, WebContext};
+use indextree::NodeId;
+use url::Url;
+
+/// Kind of an interactive element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionKind {
+    /// `a` or `area` with an `href`
+    Link,
+    Button,
+    Input,
+    Select,
+    TextArea,
+    Label,
+    /// Any other element with a non-negative `tabindex`
+    Focusable,
+}
+
+/// What activating an interactive element points to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegionTarget {
+    /// Resolved `href` of a link
+    Href(Url),
+    /// Control associated with a label
+    Control(NodeId),
+}
+
+/// An interactive element and the area it covers on the page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub node: NodeId,
+    pub kind: RegionKind,
+    pub target: Option<RegionTarget>,
+    pub disabled: bool,
+    /// Value of the `tabindex` attribute
+    pub tab_index: Option<i32>,
+    /// Boxes and line fragments of the element, in paint order
+    pub rects: Vec<Rect>,
+}
+
+impl Region {
+    /// Whether the element can be reached with keyboard navigation.
+    pub fn is_tabbable(&self) -> bool {
+        !self.disabled && self.kind != RegionKind::Label && self.tab_index.is_none_or(|i| i >= 0)
+    }
+}
+
+impl Layout {
+    /// Find all interactive elements (links, form controls, labels and elements with a
+    /// non-negative `tabindex`) that are rendered, with the areas they cover.
+    /// Link targets are resolved against `base`.
+    ///
+    /// Regions are sorted in tab order: positive `tabindex` values first, then the other
+    /// tabbable elements, then the elements that can't be reached with the keyboard,
+    /// in document order.
+    ///
+    /// ```
+    /// use dragonfly::{RegionKind, RegionTarget, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut ctx = WebContext::builder().url("file:///tests/interactive.html").build()?;
+    /// block_on(ctx.load())?;
+    /// let regions = ctx.interactive_regions();
+    /// let node = |id| ctx.layout.arena[id].get();
+    /// // hidden inputs, links without `href`, negative `tabindex` and elements that are
+    /// // not rendered are skipped
+    /// let ids: Vec<_> = regions.iter().map(|region| node(region.node).id()).collect();
+    /// assert_eq!(ids, [
+    ///     "first", "later", // positive tabindex
+    ///     "link", "name", "agree", "choice", "card", // tabbable, in document order
+    ///     "for-label", "wrap-label", "text", "off", // not tabbable
+    /// ]);
+    ///
+    /// let region = |id: &str| regions.iter().find(|region| node(region.node).id() == id).unwrap();
+    /// let link = region("link");
+    /// assert_eq!(link.kind, RegionKind::Link);
+    /// assert!(matches!(&link.target, Some(RegionTarget::Href(url)) if url.path() == "/tests/next.html"));
+    /// assert_eq!(region("for-label").target, Some(RegionTarget::Control(region("name").node)));
+    /// assert_eq!(region("wrap-label").target, Some(RegionTarget::Control(region("agree").node)));
+    /// assert_eq!(region("choice").kind, RegionKind::Select);
+    /// assert!(region("text").disabled && region("off").disabled);
+    /// assert_eq!(region("card").kind, RegionKind::Focusable);
+    /// // every region has a rect, the ones with text cover it
+    /// assert!(regions.iter().all(|region| !region.rects.is_empty()));
+    /// for id in ["first", "later", "link", "for-label", "wrap-label", "off", "card"] {
+    ///     let rects = &region(id).rects;
+    ///     assert!(rects.iter().all(|rect| rect.width() > 0.0 && rect.height() > 0.0), "{id}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn interactive_regions(&self, base: &Url) -> Vec<Region> {
+        let mut regions = vec![];
+
+        for id in self.root_id().descendants(&self.arena) {
+            let node = self.arena[id].get();
+            let tab_index = node.attr("tabindex").and_then(|i| i.trim().parse().ok());
+            let (kind, target) = match node.name() {
+                "a" | "area" => match node.attr("href") {
+                    Some(href) => {
+                        let target = resolve_url(base, href).ok().map(RegionTarget::Href);
+                        (RegionKind::Link, target)
+                    }
+                    None => continue,
+                },
+                "button" => (RegionKind::Button, None),
+                "input" if node.input_type() != "hidden" => (RegionKind::Input, None),
+                "select" => (RegionKind::Select, None),
+                "textarea" => (RegionKind::TextArea, None),
+                "label" => (
+                    RegionKind::Label,
+                    self.labeled_control(id).map(RegionTarget::Control),
+                ),
+                _ if tab_index.is_some_and(|i: i32| i >= 0) => (RegionKind::Focusable, None),
+                _ => continue,
+            };
+
+            let rendered = id
+                .ancestors(&self.arena)
+                .all(|ancestor| self.box_kind(ancestor) != BoxKind::Hidden);
+            if !rendered {
+                continue;
+            }
+
+            regions.push(Region {
+                node: id,
+                kind,
+                target,
+                disabled: self.is_disabled(id).unwrap_or(false),
+                tab_index,
+                rects: self.region_rects(id),
+            });
+        }
+
+        regions.sort_by_key(|region| match region.tab_index {
+            _ if !region.is_tabbable() => (2, 0),
+            Some(i) if i > 0 => (0, i),
+            _ => (1, 0),
+        });
+        regions
+    }
+
+    /// Control labeled by a `label`: the element referenced by the `for` attribute,
+    /// or the first labelable descendant.
+    pub fn labeled_control(&self, label: NodeId) -> Option<NodeId> {
+        let labelable = |id: NodeId| {
+            let node = self.arena[id].get();
+            match node.name() {
+                "input" => node.input_type() != "hidden",
+                name => matches!(
+                    name,
+                    "button" | "meter" | "output" | "progress" | "select" | "textarea"
+                ),
+            }
+        };
+
+        match self.arena[label].get().attr("for") {
+            Some(for_id) => self
+                .root_id()
+                .descendants(&self.arena)
+                .find(|&id| self.arena[id].get().id() == for_id)
+                .filter(|&id| labelable(id)),
+            None => label
+                .descendants(&self.arena)
+                .skip(1)
+                .find(|&id| labelable(id)),
+        }
+    }
+
+    /// Boxes covered by an element: its own box, or the line fragments and
+    /// atomic boxes of an inline element.
+    pub(crate) fn region_rects(&self, id: NodeId) -> Vec<Rect> {
+        if self.box_kind(id) != BoxKind::Inline {
+            let node = self.arena[id].get();
+            return vec![node.border_rect];
+        }
+
+        let mut rects = vec![];
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.arena[id].get();
+            match self.box_kind(id) {
+                BoxKind::Hidden => {}
+                BoxKind::Inline => {
+                    rects.extend(node.fragments.iter().map(TextFragment::rect));
+                    let len = stack.len();
+                    stack.extend(id.children(&self.arena));
+                    stack[len..].reverse();
+                }
+                _ => rects.push(node.border_rect),
+            }
+        }
+        if rects.is_empty() {
+            // empty inline element (an input for example)
+            let node = self.arena[id].get();
+            rects.push(node.border_rect);
+        }
+        rects
+    }
+}
+
+impl WebContext {
+    /// Find all interactive elements of the page (see [`Layout::interactive_regions`]).
+    pub fn interactive_regions(&self) -> Vec<Region> {
+        self.layout.interactive_regions(self.base_url())
+    }
+}
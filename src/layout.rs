@@ -1,14 +1,116 @@
-use crate::{DOMNode, Declaration, FontManager, GlobalStyle};
-use ego_tree::NodeRef as EgoNodeRef;
+use crate::clock::Instant;
+use crate::{
+    first_strong_direction, resolve_fetchable_url, segment_text_with, BorderSide, BoxSizing,
+    Budget, Clear, DOMNode, DOMNodeKind, Declaration, Decoration, Dimension, Dir, Direction,
+    Display, DocumentBuilder, ElementData, ElementState, Float, FontManager, GlobalStyle,
+    LayoutError, Overflow, OverflowWrap, ParserMode, Pos2, Position, QuirksMode, Rect, Sides,
+    TextAlign, TextFragment, TextStyle, Unit, UrlResolveError, Vec2, VerticalAlign, VisitedLinks,
+    WordBreak,
+};
+use bytes::Bytes;
 use indextree::{Arena, NodeId};
-use scraper::{node::Element, Html};
+use scraper::{node::Element, ElementRef, Html};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::time::Duration;
+use url::Url;
 
 #[derive(Debug, Clone)]
 pub struct Layout {
     /// DOM node arena. Has a root node by default.
     pub arena: Arena<DOMNode>,
     root_id: NodeId,
+    /// User agent stylesheet
     pub style: GlobalStyle,
+    /// User stylesheet, between the user agent and the author stylesheets in the cascade
+    pub user_style: Option<GlobalStyle>,
+    /// Author stylesheets of the page (`<style>` elements and linked stylesheets), in document order
+    pub stylesheets: Vec<GlobalStyle>,
+    /// Intrinsic sizes of the loaded images in CSS pixels, by `src` attribute
+    pub image_sizes: HashMap<String, Vec2>,
+    /// Pulled background images, by URL as written in the stylesheets (see
+    /// [`Layout::background_image`])
+    pub background_images: HashMap<String, Bytes>,
+    /// Decoded pixels of the loaded images, by `src` attribute
+    #[cfg(feature = "images")]
+    pub image_handles: HashMap<String, crate::ImageHandle>,
+    /// Area the document is laid out in
+    pub viewport: Viewport,
+    /// Maximum element nesting depth. Elements nested deeper than this are flattened
+    /// into their closest ancestor, so the layout can't overflow the stack.
+    pub max_depth: usize,
+    /// Maximum number of nodes. Further nodes of the document are dropped.
+    pub max_nodes: Option<usize>,
+    /// Maximum duration of a layout. When it runs out, the boxes that were not laid out yet
+    /// keep their previous geometry.
+    pub max_layout_time: Option<Duration>,
+    /// Language of the document when the root element has no `lang` attribute
+    /// (from the `Content-Language` header for example), empty if unknown
+    pub default_lang: String,
+    /// Rendering mode of the document, from its doctype
+    pub quirks_mode: QuirksMode,
+    /// URL the `href` of links is resolved against to match `:visited`
+    pub base_url: Option<Url>,
+    /// Visited pages, that `:visited` links point to
+    pub visited: VisitedLinks,
+    /// Non-fatal errors encountered while computing the layout
+    pub errors: Vec<LayoutError>,
+    /// Outer markup of the `svg` elements, by node
+    svg_sources: HashMap<NodeId, String>,
+    /// Inner markup of the elements whose children are not in the tree (`template`, `svg`
+    /// and `canvas`), by node, so they can be serialized
+    inert_markup: HashMap<NodeId, String>,
+    /// Whether the node tree changed since the layout was computed
+    dirty: bool,
+    /// Whether the node tree was changed since it was built from the document
+    modified: bool,
+    /// Element under the pointer
+    hovered: Option<NodeId>,
+    /// Focused element
+    focused: Option<NodeId>,
+    /// Time it took to compute the styles in the last layout
+    pub(crate) style_time: Duration,
+    /// Start of the current layout, to check its time budget
+    layout_start: Option<Instant>,
+    /// Absolutely positioned boxes waiting to be laid out, with their static position
+    absolutes: Vec<(NodeId, Pos2)>,
+}
+
+/// Layout input describing the area the document is laid out in.
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    /// Viewport size in CSS pixels. The root box is laid out at the viewport width.
+    pub size: Vec2,
+    /// Number of device pixels per CSS pixel.
+    ///
+    /// All layout geometry (node boxes, text fragments and font sizes of text styles) is computed
+    /// in device pixels, so text is measured at the size it is drawn at on a hidpi surface.
+    pub scale_factor: f32,
+    /// Round box and fragment edges to whole device pixels after layout,
+    /// so thin boxes don't smear across two pixels.
+    pub pixel_snapping: bool,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            size: Self::DEFAULT_SIZE,
+            scale_factor: 1.0,
+            pixel_snapping: false,
+        }
+    }
+}
+
+impl Viewport {
+    /// Viewport size used when no other size is specified.
+    pub const DEFAULT_SIZE: Vec2 = Vec2::new(800.0, 600.0);
+
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            size: Vec2::new(width, height),
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for Layout {
@@ -19,104 +121,2546 @@ impl Default for Layout {
             arena,
             root_id,
             style: GlobalStyle::default_css(),
+            user_style: None,
+            stylesheets: vec![],
+            image_sizes: HashMap::new(),
+            background_images: HashMap::new(),
+            #[cfg(feature = "images")]
+            image_handles: HashMap::new(),
+            viewport: Viewport::default(),
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_nodes: None,
+            max_layout_time: None,
+            default_lang: String::new(),
+            base_url: None,
+            visited: VisitedLinks::default(),
+            quirks_mode: QuirksMode::default(),
+            errors: vec![],
+            svg_sources: HashMap::new(),
+            inert_markup: HashMap::new(),
+            dirty: false,
+            modified: false,
+            hovered: None,
+            focused: None,
+            style_time: Duration::ZERO,
+            layout_start: None,
+            absolutes: vec![],
+        }
+    }
+}
+
+/// How a node takes part in the layout of its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BoxKind {
+    /// `display: none`, the node and its subtree are not laid out.
+    Hidden,
+    /// Text nodes and inline elements, laid out in line boxes.
+    Inline,
+    /// Inline-level box that is laid out as a single unit (inline blocks and images).
+    Atomic,
+    /// Block-level box.
+    Block,
+    /// Box taken out of the normal flow and floated to one side.
+    Float(Float),
+    /// Box taken out of the normal flow and placed against its containing block (`position:
+    /// absolute` or `fixed`).
+    Absolute,
+}
+
+/// A floating box, in page coordinates (including margins).
+#[derive(Debug, Clone, Copy)]
+struct FloatBox {
+    pos: Pos2,
+    size: Vec2,
+}
+
+impl FloatBox {
+    #[inline]
+    fn bottom(&self) -> f32 {
+        self.pos.y + self.size.y
+    }
+}
+
+/// Floats placed in a block formatting context.
+#[derive(Debug, Clone, Default)]
+struct FloatContext {
+    left: Vec<FloatBox>,
+    right: Vec<FloatBox>,
+}
+
+impl FloatContext {
+    /// The horizontal range between `min_x` and `max_x` that is not covered
+    /// by floats in the band from `y` to `y + height`.
+    fn available(&self, y: f32, height: f32, min_x: f32, max_x: f32) -> (f32, f32) {
+        let bottom = y + height.max(f32::EPSILON);
+        let overlaps = |f: &&FloatBox| f.pos.y < bottom && f.bottom() > y;
+        let left = self
+            .left
+            .iter()
+            .filter(overlaps)
+            .map(|f| f.pos.x + f.size.x)
+            .fold(min_x, f32::max);
+        let right = self
+            .right
+            .iter()
+            .filter(overlaps)
+            .map(|f| f.pos.x)
+            .fold(max_x, f32::min);
+        (left, right)
+    }
+
+    /// The closest float bottom edge below `y`.
+    fn next_bottom(&self, y: f32) -> Option<f32> {
+        self.left
+            .iter()
+            .chain(&self.right)
+            .map(FloatBox::bottom)
+            .filter(|&bottom| bottom > y)
+            .reduce(f32::min)
+    }
+
+    /// The y position a box with the `clear` property has to be moved down to.
+    fn clearance(&self, clear: Clear) -> Option<f32> {
+        let bottom = |floats: &[FloatBox]| floats.iter().map(FloatBox::bottom).reduce(f32::max);
+        match clear {
+            Clear::None => None,
+            Clear::Left => bottom(&self.left),
+            Clear::Right => bottom(&self.right),
+            Clear::Both => match (bottom(&self.left), bottom(&self.right)) {
+                (Some(left), Some(right)) => Some(left.max(right)),
+                (left, right) => left.or(right),
+            },
+        }
+    }
+
+    /// Bottom edge of the lowest float.
+    #[inline]
+    fn bottom(&self) -> Option<f32> {
+        self.clearance(Clear::Both)
+    }
+
+    /// Find a position for a new float at or below `y` and add it to the context.
+    fn place(&mut self, side: Float, size: Vec2, y: f32, min_x: f32, max_x: f32) -> Pos2 {
+        // a float can't be placed higher than the top of an earlier float
+        let mut y = self
+            .left
+            .iter()
+            .chain(&self.right)
+            .map(|f| f.pos.y)
+            .fold(y, f32::max);
+
+        loop {
+            let (left, right) = self.available(y, size.y, min_x, max_x);
+            let fits = right - left >= size.x;
+            match self.next_bottom(y) {
+                // move down past the floats that are in the way
+                Some(bottom) if !fits => y = bottom,
+                _ => {
+                    let pos = match side {
+                        Float::Right => Pos2::new(right - size.x, y),
+                        _ => Pos2::new(left, y),
+                    };
+                    let float = FloatBox { pos, size };
+                    match side {
+                        Float::Right => self.right.push(float),
+                        _ => self.left.push(float),
+                    }
+                    return pos;
+                }
+            }
         }
     }
 }
 
+/// Block-level box laid out by [`Layout::layout_block`], with what's needed to size it once
+/// its content is laid out.
+#[derive(Debug, Clone)]
+struct BlockBox {
+    margin: Sides<f32>,
+    /// Position of the border box
+    pos: Pos2,
+    /// Sizes of the borders and paddings
+    inner: Vec2,
+    specified_width: Option<f32>,
+    specified_height: Option<f32>,
+    /// Width of the containing block
+    containing_width: f32,
+    /// Width available to the content
+    available: f32,
+    shrink_to_fit: bool,
+}
+
+/// Block container whose children are being laid out. Nested block boxes are laid out
+/// with an explicit stack of these, so deeply nested documents can't overflow the call stack.
+#[derive(Debug)]
+struct BlockFrame {
+    id: NodeId,
+    /// The box itself, if it's laid out too and not only its content
+    block: Option<BlockBox>,
+    /// Position and width of the content box
+    origin: Pos2,
+    width: f32,
+    /// Whether the box establishes a block formatting context, with its own float context
+    own_floats: bool,
+    children: std::vec::IntoIter<NodeId>,
+    y: f32,
+    used_width: f32,
+    /// Consecutive inline-level children that are laid out in the same line boxes
+    run: Vec<NodeId>,
+    /// Indentation of the first line, a block child before it indents its own first line
+    indent: f32,
+}
+
+/// Block containers being laid out by [`Layout::layout_flow`], innermost last.
+#[derive(Debug, Default)]
+struct FlowStack {
+    frames: Vec<BlockFrame>,
+    /// Float contexts of the frames that establish block formatting contexts
+    contexts: Vec<FloatContext>,
+}
+
+/// A word, a collapsible space or an atomic box in an inline formatting context.
+#[derive(Debug, Clone)]
+struct InlinePiece {
+    node: NodeId,
+    /// Byte range in the node text (empty for atomic boxes)
+    range: Range<usize>,
+    width: f32,
+    /// Height above the baseline
+    ascent: f32,
+    /// Height below the baseline
+    descent: f32,
+    space: bool,
+    /// Whether a line can break right before this piece (without a space between them)
+    break_before: bool,
+    /// Whether this is a preserved newline, which ends the line
+    newline: bool,
+    /// Whether lines can't break at this space (`white-space: nowrap`)
+    nowrap: bool,
+    /// Whether a line can break right before this piece when its word doesn't fit on a line
+    /// by itself (`overflow-wrap`)
+    overflow_break: bool,
+    /// `Top` or `Bottom` for pieces aligned to an edge of the line box, otherwise `Baseline`
+    align: VerticalAlign,
+    /// Height of the baseline of the piece above the baseline of the line
+    shift: f32,
+}
+
+/// Bounding box of two (min, max) corner pairs.
+fn union(a: (Pos2, Pos2), b: (Pos2, Pos2)) -> (Pos2, Pos2) {
+    (
+        Pos2::new(a.0.x.min(b.0.x), a.0.y.min(b.0.y)),
+        Pos2::new(a.1.x.max(b.1.x), a.1.y.max(b.1.y)),
+    )
+}
+
+/// Clamp a size between an optional minimum and maximum. The minimum wins over the maximum.
+fn clamp(value: f32, min: Option<f32>, max: Option<f32>) -> f32 {
+    let value = max.map_or(value, |max| value.min(max));
+    min.map_or(value, |min| value.max(min))
+}
+
 impl Layout {
-    pub fn compute(document: &mut Html, fonts: &mut FontManager) -> Self {
-        let mut layout = Self::default();
-        let root = document.tree.root();
+    /// Default maximum element nesting depth.
+    pub const DEFAULT_MAX_DEPTH: usize = 512;
 
-        // compute all nodes recursively
-        layout.compute_node(root, 0, layout.root_id, fonts);
+    pub fn compute(document: &mut Html, fonts: &mut FontManager) -> Self {
+        Self::compute_with_viewport(document, fonts, Viewport::default())
+    }
 
-        log::debug!("computed layout tree:\n{:?}", layout.arena);
+    pub fn compute_with_viewport(
+        document: &mut Html,
+        fonts: &mut FontManager,
+        viewport: Viewport,
+    ) -> Self {
+        let mut layout = Self {
+            viewport,
+            ..Default::default()
+        };
+        layout.compute_document(document, fonts);
         layout
     }
 
-    fn compute_node(
-        &mut self,
-        html_node: EgoNodeRef<'_, scraper::Node>,
-        depth: usize,
-        parent: NodeId,
+    /// Compute the layout of a document built in code with a [`DocumentBuilder`].
+    /// The nodes go through the same style and layout passes as parsed documents.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, DocumentBuilder, FontManager, Layout, Viewport};
+    /// let mut fonts = FontManager::default();
+    /// let document = DocumentBuilder::new()
+    ///     .element("p")
+    ///     .child(|b| b.text("first paragraph"))
+    ///     .element("p")
+    ///     .style("padding: 4px; font-size: 24px")
+    ///     .child(|b| b.text("second paragraph"));
+    /// let built = Layout::compute_from_nodes(document, &mut fonts, Viewport::default());
+    /// // built documents are in no-quirks mode, like HTML with a doctype
+    /// let html = r#"<!DOCTYPE html><p>first paragraph</p><p style="padding: 4px; font-size: 24px">second paragraph</p>"#;
+    /// let parsed = Layout::compute(&mut parse_document(html), &mut fonts);
+    ///
+    /// let nodes = |layout: &Layout| -> Vec<_> {
+    ///     let body = layout.select("body")[0];
+    ///     body.descendants(&layout.arena)
+    ///         .map(|id| {
+    ///             let node = layout.arena[id].get();
+    ///             let fragments: Vec<_> = node.fragments.iter().map(|f| (f.pos, f.size)).collect();
+    ///             (node.name().to_string(), node.border_rect, fragments)
+    ///         })
+    ///         .collect()
+    /// };
+    /// assert_eq!(nodes(&built).len(), 5);
+    /// assert_eq!(nodes(&built), nodes(&parsed));
+    /// ```
+    pub fn compute_from_nodes(
+        document: DocumentBuilder,
         fonts: &mut FontManager,
-    ) {
-        if html_node.value().is_element() {
-            log::info!(
-                "compute node {}, recurse depth {depth}",
-                html_node.value().as_element().unwrap().name()
-            );
+        viewport: Viewport,
+    ) -> Self {
+        let mut layout = Self {
+            viewport,
+            ..Default::default()
+        };
+        (layout.arena, layout.root_id) = document.finish();
+        layout.relayout(fonts);
+        layout
+    }
+
+    /// Rebuild the node tree from a parsed document and compute its layout,
+    /// using the current settings of the layout (viewport, maximum depth and stylesheet).
+    pub fn compute_document(&mut self, document: &Html, fonts: &mut FontManager) {
+        self.build_document(document);
+        self.relayout(fonts);
+    }
+
+    /// Rebuild the node tree from a parsed document, without computing its layout
+    /// (see [`Layout::relayout`]).
+    pub(crate) fn build_document(&mut self, document: &Html) {
+        self.arena = Arena::new();
+        self.root_id = self.arena.new_node(DOMNode::root());
+        self.errors.clear();
+        self.svg_sources.clear();
+        self.inert_markup.clear();
+        self.modified = false;
+        self.hovered = None;
+        self.focused = None;
+        self.quirks_mode = document.quirks_mode.into();
+
+        // compute all nodes
+        self.compute_nodes(document);
+        self.dirty = true;
+    }
+
+    /// Move the page out of the layout: its node tree and the resources it loaded. An empty
+    /// tree is left with the same settings and stylesheets, until the page is put back with
+    /// [`Layout::restore_page`] or another one is built.
+    pub(crate) fn take_page(&mut self) -> Layout {
+        let mut arena = Arena::new();
+        let root_id = arena.new_node(DOMNode::root());
+        let empty = Layout {
+            arena,
+            root_id,
+            style: std::mem::take(&mut self.style),
+            user_style: self.user_style.take(),
+            stylesheets: vec![],
+            image_sizes: HashMap::new(),
+            background_images: HashMap::new(),
+            #[cfg(feature = "images")]
+            image_handles: HashMap::new(),
+            viewport: self.viewport,
+            max_depth: self.max_depth,
+            max_nodes: self.max_nodes,
+            max_layout_time: self.max_layout_time,
+            default_lang: self.default_lang.clone(),
+            quirks_mode: self.quirks_mode,
+            base_url: self.base_url.clone(),
+            visited: self.visited.clone(),
+            errors: vec![],
+            svg_sources: HashMap::new(),
+            inert_markup: HashMap::new(),
+            dirty: false,
+            modified: false,
+            hovered: None,
+            focused: None,
+            style_time: Duration::ZERO,
+            layout_start: None,
+            absolutes: vec![],
+        };
+        std::mem::replace(self, empty)
+    }
+
+    /// Put back a page moved out with [`Layout::take_page`], keeping the current settings.
+    pub(crate) fn restore_page(&mut self, page: Layout) {
+        let current = std::mem::replace(self, page);
+        self.style = current.style;
+        self.user_style = current.user_style;
+        self.viewport = current.viewport;
+        self.max_depth = current.max_depth;
+        self.max_nodes = current.max_nodes;
+        self.max_layout_time = current.max_layout_time;
+        self.visited = current.visited;
+    }
+
+    /// Recompute the styles and the geometry of the node tree, e.g. after its attributes changed.
+    pub fn relayout(&mut self, fonts: &mut FontManager) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "layout",
+            node_count = self.arena.len(),
+            viewport = %format_args!("{}x{}", self.viewport.size.x, self.viewport.size.y),
+            scale_factor = self.viewport.scale_factor,
+        )
+        .entered();
+
+        // match stylesheet rules and compute box geometry
+        let start = Instant::now();
+        self.layout_start = Some(start);
+        self.errors
+            .retain(|err| !matches!(err, LayoutError::BudgetExceeded(Budget::LayoutTime(_))));
+        self.compute_styles();
+        self.style_time = start.elapsed();
+        self.compute_boxes(fonts);
+        self.layout_start = None;
+        if self.viewport.pixel_snapping {
+            self.snap_to_pixels();
+        }
+        self.compute_clips();
+        self.dirty = false;
+
+        log::debug!("computed layout tree:\n{:?}", self.arena);
+    }
+
+    /// Whether the node tree changed since the layout was last computed,
+    /// so [`Layout::relayout`] has to be called.
+    #[inline]
+    pub fn needs_relayout(&self) -> bool {
+        self.dirty
+    }
+
+    /// Whether the node tree was changed (attributes or nodes) since it was built from the document.
+    ///
+    /// A modified tree no longer matches the parsed document, rebuilding it
+    /// with [`Layout::compute_document`] discards the changes.
+    #[inline]
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Mark the node tree as changed, its layout is updated on the next [`Layout::relayout`].
+    #[inline]
+    fn invalidate(&mut self) {
+        self.dirty = true;
+        self.modified = true;
+    }
+
+    /// Append a node to the children of `parent`. Returns the id of the new node.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, DOMNode, FontManager, Layout};
+    /// let mut fonts = FontManager::default();
+    /// let html = r#"<!DOCTYPE html><div id="a" style="height: 10px"></div>"#;
+    /// let mut layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let body = layout.select("body")[0];
+    /// let div = layout.append_child(body, DOMNode::new("div"));
+    /// layout.set_attribute(div, "style", "height: 20px");
+    /// layout.append_child(div, DOMNode::text_node("new"));
+    /// assert!(layout.needs_relayout());
+    /// layout.relayout(&mut fonts);
+    ///
+    /// let rect = |layout: &Layout, id| layout.arena[id].get().border_rect;
+    /// let a = layout.select("#a")[0];
+    /// assert_eq!(rect(&layout, div).min().y, rect(&layout, a).max().y);
+    /// assert_eq!(rect(&layout, div).height(), 20.0);
+    /// assert_eq!(rect(&layout, body).height(), 30.0);
+    /// assert_eq!(
+    ///     layout.inner_html(body),
+    ///     r#"<div id="a" style="height: 10px"></div><div style="height: 20px">new</div>"#
+    /// );
+    /// ```
+    pub fn append_child(&mut self, parent: NodeId, node: DOMNode) -> NodeId {
+        self.invalidate();
+        parent.append_value(node, &mut self.arena)
+    }
+
+    /// Insert a node right before `sibling`. Returns the id of the new node.
+    pub fn insert_before(&mut self, sibling: NodeId, node: DOMNode) -> NodeId {
+        self.invalidate();
+        sibling.insert_before_value(node, &mut self.arena)
+    }
+
+    /// Remove a node and its whole subtree. The root node can't be removed.
+    ///
+    /// The hovered and focused elements are cleared if they are in the subtree.
+    ///
+    /// ```
+    /// use dragonfly::{Layout, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = r#"<!DOCTYPE html>
+    ///     <style>#a { height: 10px } #b { height: 20px } #b:hover { height: 50px } #c { height: 30px }</style>
+    ///     <div id="a"></div><div id="b"><span>b</span></div><div id="c"></div>"#;
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// block_on(ctx.load())?;
+    /// let rect = |layout: &Layout, selector| layout.arena[layout.select(selector)[0]].get().border_rect;
+    /// assert_eq!(rect(&ctx.layout, "#c").min().y, 8.0 + 10.0 + 20.0);
+    ///
+    /// // hover the span, the removed subtree holds it
+    /// let (b, span) = (ctx.layout.select("#b")[0], ctx.layout.select("span")[0]);
+    /// ctx.layout.set_hover(Some(span));
+    /// ctx.relayout();
+    /// assert_eq!(rect(&ctx.layout, "#c").min().y, 8.0 + 10.0 + 50.0);
+    /// ctx.layout.remove_node(b);
+    /// assert!(ctx.layout.is_modified() && ctx.layout.needs_relayout());
+    /// assert_eq!(ctx.layout.hovered(), None);
+    /// ctx.relayout();
+    ///
+    /// // the following sibling moves up, no element is hovered anymore
+    /// let layout = &ctx.layout;
+    /// assert_eq!(rect(layout, "#a").height(), 10.0);
+    /// assert_eq!(rect(layout, "#c").min().y, 8.0 + 10.0);
+    /// assert_eq!(rect(layout, "body").height(), 40.0);
+    /// let ids: Vec<_> = layout.select("body > *").into_iter().map(|id| layout.arena[id].get().id()).collect();
+    /// assert_eq!(ids, ["a", "c"]);
+    /// assert!(layout.select("span, :hover").is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_node(&mut self, id: NodeId) {
+        if id == self.root_id {
+            log::warn!("can't remove the root node");
+            return;
+        }
+        let in_subtree = |node: Option<NodeId>, arena: &Arena<DOMNode>| {
+            node.is_some_and(|node| node.ancestors(arena).any(|ancestor| ancestor == id))
+        };
+        if in_subtree(self.hovered, &self.arena) {
+            self.set_hover(None);
+        }
+        if in_subtree(self.focused, &self.arena) {
+            self.set_focus(None);
+        }
+        for descendant in id.descendants(&self.arena) {
+            self.svg_sources.remove(&descendant);
+            self.inert_markup.remove(&descendant);
+        }
+        id.remove_subtree(&mut self.arena);
+        self.invalidate();
+    }
+
+    /// Replace all children of `parent` with new nodes. Returns the ids of the new nodes.
+    pub fn replace_children(&mut self, parent: NodeId, nodes: Vec<DOMNode>) -> Vec<NodeId> {
+        let children: Vec<NodeId> = parent.children(&self.arena).collect();
+        for child in children {
+            self.remove_node(child);
+        }
+        self.invalidate();
+        nodes
+            .into_iter()
+            .map(|node| parent.append_value(node, &mut self.arena))
+            .collect()
+    }
+
+    /// Element data of a node, `None` (with a warning) if the node was removed, isn't
+    /// in the tree or isn't an element.
+    fn element_mut(&mut self, id: NodeId, action: &str) -> Option<&mut ElementData> {
+        let Some(node) = self.arena.get_mut(id).filter(|node| !node.is_removed()) else {
+            log::warn!("can't {action} on node {id}: it isn't in the tree");
+            return None;
+        };
+        let el = node.get_mut().as_element_mut();
+        if el.is_none() {
+            log::warn!("can't {action} on a non-element node");
+        }
+        el
+    }
+
+    /// Set an attribute of an element. The layout is updated on the next [`Layout::relayout`].
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<style>.big { font-size: 32px; }</style><p>text</p>";
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// block_on(ctx.load())?;
+    /// let p = ctx.layout.select("p")[0];
+    /// let height = |ctx: &WebContext| ctx.layout.arena[p].get().border_rect.height();
+    /// let before = height(&ctx);
+    ///
+    /// ctx.layout.set_attribute(p, "class", "big");
+    /// assert!(ctx.layout.needs_relayout());
+    /// ctx.relayout();
+    /// assert_eq!(ctx.layout.select(".big"), [p]);
+    /// assert!(height(&ctx) > before);
+    ///
+    /// assert_eq!(ctx.layout.remove_attribute(p, "class").as_deref(), Some("big"));
+    /// ctx.relayout();
+    /// assert_eq!(height(&ctx), before);
+    ///
+    /// // removed nodes are ignored
+    /// ctx.layout.remove_node(p);
+    /// ctx.layout.set_attribute(p, "class", "big");
+    /// assert_eq!(ctx.layout.remove_attribute(p, "class"), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) {
+        let Some(el) = self.element_mut(id, &format!("set attribute '{name}'")) else {
+            return;
+        };
+        el.set_attribute(name, value);
+        self.invalidate();
+    }
+
+    /// Remove an attribute of an element. The layout is updated on the next [`Layout::relayout`].
+    pub fn remove_attribute(&mut self, id: NodeId, name: &str) -> Option<String> {
+        let value = self
+            .element_mut(id, &format!("remove attribute '{name}'"))?
+            .remove_attribute(name);
+        if value.is_some() {
+            self.invalidate();
+        }
+        value
+    }
+
+    /// Add a class to an element. The layout is updated on the next [`Layout::relayout`].
+    pub fn add_class(&mut self, id: NodeId, class: &str) {
+        if let Some(el) = self.element_mut(id, "add a class") {
+            el.add_class(class);
+            self.invalidate();
         }
+    }
+
+    /// Remove a class from an element. Returns whether the element had it.
+    pub fn remove_class(&mut self, id: NodeId, class: &str) -> bool {
+        let removed = self
+            .element_mut(id, "remove a class")
+            .is_some_and(|el| el.remove_class(class));
+        if removed {
+            self.invalidate();
+        }
+        removed
+    }
+
+    /// Add a class to an element if it doesn't have it, remove it otherwise.
+    /// Returns whether the element has the class now.
+    pub fn toggle_class(&mut self, id: NodeId, class: &str) -> bool {
+        let Some(el) = self.element_mut(id, "toggle a class") else {
+            return false;
+        };
+        let added = el.toggle_class(class);
+        self.invalidate();
+        added
+    }
+
+    /// Element under the pointer (see [`Layout::set_hover`]).
+    #[inline]
+    pub fn hovered(&self) -> Option<NodeId> {
+        self.hovered
+    }
+
+    /// Focused element (see [`Layout::set_focus`]).
+    #[inline]
+    pub fn focused(&self) -> Option<NodeId> {
+        self.focused
+    }
+
+    /// Set the element under the pointer: it and its ancestors match `:hover`, the previously
+    /// hovered ones no longer do.
+    ///
+    /// Returns whether the styles of the page depend on the change, in which case the layout
+    /// has to be updated with [`Layout::relayout`] (see [`Layout::needs_relayout`]).
+    pub fn set_hover(&mut self, id: Option<NodeId>) -> bool {
+        let id = id.filter(|&id| self.is_in_tree(id));
+        let previous = std::mem::replace(&mut self.hovered, id);
+        self.set_state(ElementState::Hover, previous, id)
+    }
+
+    /// Set the focused element, that matches `:focus`. Returns whether the styles of the page
+    /// depend on the change (see [`Layout::set_hover`]).
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<style>a:focus { color: red } p:hover { color: blue }</style>\
+    ///     <p><a href=x id=l>link</a></p><div id=other tabindex=0>other</div>";
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// block_on(ctx.load())?;
+    /// let layout = &mut ctx.layout;
+    /// let (p, a) = (layout.select("p")[0], layout.select("#l")[0]);
+    /// assert!(layout.set_focus(Some(a)));
+    /// assert!(layout.set_hover(Some(a)));
+    /// assert_eq!(layout.select(":focus"), [a]);
+    ///
+    /// // removing the focused and hovered elements clears them
+    /// layout.remove_node(p);
+    /// assert_eq!((layout.focused(), layout.hovered()), (None, None));
+    /// assert!(layout.select(":hover").is_empty());
+    /// assert!(!layout.set_focus(None));
+    /// let other = layout.select("#other")[0];
+    /// layout.set_focus(Some(other));
+    /// assert_eq!(layout.select(":focus"), [other]);
+    /// // a removed node can't be focused
+    /// layout.set_focus(Some(a));
+    /// assert_eq!(layout.focused(), None);
+    /// assert!(layout.select(":focus").is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_focus(&mut self, id: Option<NodeId>) -> bool {
+        let id = id.filter(|&id| self.is_in_tree(id));
+        let previous = std::mem::replace(&mut self.focused, id);
+        self.set_state(ElementState::Focus, previous, id)
+    }
+
+    /// Whether a node is in the arena and wasn't removed.
+    fn is_in_tree(&self, id: NodeId) -> bool {
+        self.arena.get(id).is_some_and(|node| !node.is_removed())
+    }
 
-        let parent = match html_node.value() {
-            scraper::Node::Element(el) => self.handle_element(el, parent, fonts),
-            scraper::Node::Text(text) => {
-                log::debug!("adding text to parent node {parent:?}",);
-                parent.append_value(DOMNode::text_node(text), &mut self.arena);
-                parent
+    /// Move a state from an element to another, and mark the layout as changed if the
+    /// stylesheet rules that depend on the state match differently.
+    fn set_state(&mut self, state: ElementState, from: Option<NodeId>, to: Option<NodeId>) -> bool {
+        // hover applies to the ancestors too, removed nodes are skipped
+        let with_state = |id: Option<NodeId>| -> Vec<NodeId> {
+            let id = id.filter(|&id| self.is_in_tree(id));
+            match id {
+                Some(id) if state == ElementState::Hover => id.ancestors(&self.arena).collect(),
+                Some(id) => vec![id],
+                None => vec![],
             }
-            _ => {
-                log::warn!("unhandled html node {:?}", html_node.value());
-                parent
+        };
+        let (old, new) = (with_state(from), with_state(to));
+        let changed: Vec<NodeId> = old
+            .iter()
+            .filter(|id| !new.contains(id))
+            .chain(new.iter().filter(|id| !old.contains(id)))
+            .copied()
+            .collect();
+        if changed.is_empty() {
+            return false;
+        }
+
+        // the changed elements can affect their descendants and following siblings
+        let mut affected = HashSet::new();
+        for &id in &changed {
+            // following_siblings() starts with the node itself
+            for sibling in id.following_siblings(&self.arena) {
+                affected.extend(sibling.descendants(&self.arena));
             }
+        }
+        let affected: Vec<NodeId> = affected.into_iter().collect();
+        let matches = |layout: &Self| -> Vec<bool> {
+            std::iter::once(&layout.style)
+                .chain(&layout.user_style)
+                .chain(&layout.stylesheets)
+                .flat_map(|sheet| &sheet.rules)
+                .filter(|(selector, _)| selector.depends_on(state))
+                .flat_map(|(selector, _)| {
+                    affected
+                        .iter()
+                        .map(|&id| layout.matches_selector(id, selector))
+                })
+                .collect()
         };
 
-        for child in html_node.children() {
-            self.compute_node(child, depth + 1, parent, fonts);
+        let before = matches(self);
+        for &id in &changed {
+            let node = self.arena[id].get_mut();
+            match state {
+                ElementState::Hover => node.hover = new.contains(&id),
+                ElementState::Focus => node.focus = new.contains(&id),
+                ElementState::Visited => unreachable!("visited links are not set on nodes"),
+            }
+        }
+        let restyle = matches(self) != before;
+        self.dirty |= restyle;
+        restyle
+    }
+
+    /// Read a URL attribute (`href`, `src`, `poster`...) of an element and resolve it against `base`.
+    ///
+    /// Returns `None` if the element doesn't have the attribute, and an error if the URL
+    /// is invalid or can't be fetched (e.g. `javascript:` links).
+    pub fn attr_url(
+        &self,
+        id: NodeId,
+        name: &str,
+        base: &Url,
+    ) -> Option<Result<Url, UrlResolveError>> {
+        let raw = self.arena[id].get().attr(name)?;
+        Some(resolve_fetchable_url(base, raw))
+    }
+
+    /// Id of the root (`html`) node.
+    #[inline]
+    pub fn root_id(&self) -> NodeId {
+        self.root_id
+    }
+
+    /// Raw outer markup of an `svg` element, for embedders that rasterize SVG themselves.
+    ///
+    /// `svg` and `canvas` elements are laid out as replaced boxes, without their children. A
+    /// canvas is 300x150 by default, an svg takes its missing width or height from the aspect
+    /// ratio of its `viewBox`, and a CSS size on one axis keeps the aspect ratio:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<canvas id=a></canvas><canvas id=b width=100></canvas>
+    ///     <svg id=c viewBox='0 0 4 3' width=200><text>inside</text></svg>
+    ///     <svg id=d viewBox='0 0 4 3'></svg><svg id=e viewBox='0 0 4 3' height=90></svg>
+    ///     <svg id=f viewBox='0 0 4 3' width=50 height=60></svg><svg id=g></svg>
+    ///     <svg id=h viewBox='0 0 4 3' style='width: 80px'></svg>
+    ///     <canvas id=i style='width: 60px'></canvas>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let size = |selector| {
+    ///     let rect = layout.arena[layout.select(selector)[0]].get().border_rect;
+    ///     (rect.width(), rect.height())
+    /// };
+    /// assert_eq!(size("#a"), (300.0, 150.0));
+    /// assert_eq!(size("#b"), (100.0, 150.0));
+    /// // viewBox with a width or a height, without both, and ignored with both
+    /// assert_eq!(size("#c"), (200.0, 150.0));
+    /// assert_eq!(size("#d"), (300.0, 225.0));
+    /// assert_eq!(size("#e"), (120.0, 90.0));
+    /// assert_eq!(size("#f"), (50.0, 60.0));
+    /// assert_eq!(size("#g"), (300.0, 150.0));
+    /// // CSS widths keep the aspect ratio
+    /// assert_eq!(size("#h"), (80.0, 60.0));
+    /// assert_eq!(size("#i"), (60.0, 30.0));
+    ///
+    /// // the svg content isn't laid out, but its markup is kept
+    /// let svg = layout.select("#c")[0];
+    /// assert_eq!(svg.children(&layout.arena).count(), 0);
+    /// assert!(!layout.text_content(layout.root_id()).contains("inside"));
+    /// assert_eq!(
+    ///     layout.svg_source(svg),
+    ///     Some(r#"<svg id="c" viewBox="0 0 4 3" width="200"><text>inside</text></svg>"#)
+    /// );
+    /// assert_eq!(layout.svg_source(layout.select("#a")[0]), None);
+    /// ```
+    pub fn svg_source(&self, id: NodeId) -> Option<&str> {
+        self.svg_sources.get(&id).map(String::as_str)
+    }
+
+    /// Inner markup of an element whose children are not in the tree (`template`, `svg` or
+    /// `canvas`).
+    pub(crate) fn inert_markup(&self, id: NodeId) -> Option<&str> {
+        self.inert_markup.get(&id).map(String::as_str)
+    }
+
+    /// Children of a node in the order they're laid out and painted: by their `order` for flex
+    /// containers (tree order among equal values), otherwise in tree order. The arena keeps
+    /// the tree order.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<div style='display: flex'><p id=a style='order: 1'></p><p id=b></p>
+    ///     <p id=c style='order: -1'></p><p id=d style='order: 1'></p></div>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let div = layout.select("div")[0];
+    /// let ids = |children: Vec<_>| -> Vec<&str> {
+    ///     let elements = children.into_iter().filter(|&id| layout.arena[id].get().is_element());
+    ///     elements.map(|id| layout.arena[id].get().attr("id").unwrap()).collect()
+    /// };
+    /// assert_eq!(ids(layout.ordered_children(div)), ["c", "b", "a", "d"]);
+    /// assert_eq!(ids(div.children(&layout.arena).collect()), ["a", "b", "c", "d"]);
+    /// ```
+    pub fn ordered_children(&self, id: NodeId) -> Vec<NodeId> {
+        let mut children: Vec<NodeId> = id.children(&self.arena).collect();
+        let display = self.node_style(id).and_then(|s| s.display);
+        if let Some(Display::Flex | Display::InlineFlex) = display {
+            children
+                .sort_by_key(|&child| self.node_style(child).and_then(|s| s.order).unwrap_or(0));
+        }
+        children
+    }
+
+    /// Build the node tree from the document tree. Elements nested deeper than
+    /// `max_depth` are flattened: their children are added to their closest laid out ancestor.
+    fn compute_nodes(&mut self, document: &Html) {
+        // (document node, parent node, element depth), walked with an explicit stack
+        // so deeply nested documents can't overflow the call stack
+        let mut stack = vec![(document.tree.root(), self.root_id, 0)];
+        // the root is counted with the `html` element that replaces it
+        let mut node_count = 0;
+
+        while let Some((html_node, parent, depth)) = stack.pop() {
+            if let Some(max) = self.max_nodes {
+                let adds_node = matches!(
+                    html_node.value(),
+                    scraper::Node::Element(_) | scraper::Node::Text(_) | scraper::Node::Comment(_)
+                );
+                if adds_node && node_count >= max {
+                    log::warn!("document has more than {max} nodes, dropping the rest");
+                    self.errors
+                        .push(LayoutError::BudgetExceeded(Budget::Nodes(max)));
+                    break;
+                }
+                node_count += adds_node as usize;
+            }
+            let (parent, depth) = match html_node.value() {
+                scraper::Node::Element(el) if depth >= self.max_depth => {
+                    log::warn!("element '{}' is nested too deep, flattening", el.name());
+                    let error = LayoutError::MaxDepthExceeded(self.max_depth);
+                    if !self.errors.contains(&error) {
+                        self.errors.push(error);
+                    }
+                    (parent, depth)
+                }
+                scraper::Node::Element(el)
+                    if matches!(el.name(), "template" | "svg" | "canvas") =>
+                {
+                    // the children of these elements are never laid out: template contents are inert,
+                    // svg internals are drawn by the embedder and canvas fallback content is not shown
+                    let id = self.handle_element(el, parent);
+                    if let Some(element) = ElementRef::wrap(html_node) {
+                        if el.name() == "svg" {
+                            self.svg_sources.insert(id, element.html());
+                        }
+                        self.inert_markup.insert(id, element.inner_html());
+                    }
+                    continue;
+                }
+                scraper::Node::Element(el) => {
+                    log::info!("compute node {}, depth {depth}", el.name());
+                    (self.handle_element(el, parent), depth + 1)
+                }
+                scraper::Node::Text(text) => {
+                    log::debug!("adding text to parent node {parent:?}",);
+                    let node = match self.arena[parent].get().name() {
+                        "script" | "style" => DOMNode::raw_text_node(text),
+                        _ => DOMNode::text_node(text),
+                    };
+                    parent.append_value(node, &mut self.arena);
+                    (parent, depth)
+                }
+                scraper::Node::Comment(comment) => {
+                    parent.append_value(DOMNode::comment(comment), &mut self.arena);
+                    (parent, depth)
+                }
+                _ => {
+                    log::warn!("unhandled html node {:?}", html_node.value());
+                    (parent, depth)
+                }
+            };
+
+            // push in reverse, so children are popped in document order
+            let children: Vec<_> = html_node.children().collect();
+            for child in children.into_iter().rev() {
+                stack.push((child, parent, depth));
+            }
         }
     }
 
-    fn handle_element(&mut self, el: &Element, parent: NodeId, fonts: &mut FontManager) -> NodeId {
+    fn handle_element(&mut self, el: &Element, parent: NodeId) -> NodeId {
         let el_name = el.name();
         log::debug!("layout element '{}'", el_name);
 
-        // create new node
-        let mut node = DOMNode::new(el_name);
+        // create new element
+        let mut data = ElementData {
+            name: el_name.to_string(),
+            ..Default::default()
+        };
 
-        // process node attrs
+        // process element attrs
         for attr in el.attrs() {
-            node.attrs.insert(attr.0.to_string(), attr.1.to_string());
             log::debug!("parsing attribute: {:?}", attr);
-
-            match attr.0 {
-                "style" => node.style = Some(Declaration::from_inline(attr.1)),
-                _ => log::warn!("unhandled attribute '{}'", attr.0),
+            // inline styles are parsed when computing styles
+            if !matches!(attr.0, "id" | "class" | "style") {
+                log::warn!("unhandled attribute '{}'", attr.0);
             }
+            data.set_attribute(attr.0, attr.1);
         }
 
         // add node to document
-        self.add_node(node, parent, fonts)
+        let node = DOMNode {
+            kind: DOMNodeKind::Element(data),
+            ..Default::default()
+        };
+        self.add_node(node, parent)
     }
 
-    fn add_node(&mut self, node: DOMNode, parent: NodeId, fonts: &mut FontManager) -> NodeId {
-        let node_id = match node.name.as_str() {
+    fn add_node(&mut self, node: DOMNode, parent: NodeId) -> NodeId {
+        match node.name() {
             "html" => {
                 log::debug!("update root node");
-                *self.arena.get_mut(self.root_id).unwrap().get_mut() = node.clone();
+                *self.arena.get_mut(self.root_id).unwrap().get_mut() = node;
                 self.root_id
             }
+            // return node id (will be used as a parent of children nodes)
             _ => parent.append_value(node, &mut self.arena),
+        }
+    }
+
+    /// Compute the style of every node: match stylesheet rules, apply inline styles
+    /// and inherit properties from the parent node.
+    pub(crate) fn compute_styles(&mut self) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "style",
+            rules = std::iter::once(&self.style)
+                .chain(&self.user_style)
+                .chain(&self.stylesheets)
+                .map(|sheet| sheet.rules.len())
+                .sum::<usize>(),
+            nodes = self.arena.len(),
+        )
+        .entered();
+
+        // descendants are visited in tree order, so parents are always computed first
+        let ids: Vec<NodeId> = self.root_id.descendants(&self.arena).collect();
+        for id in ids {
+            if self.out_of_time() {
+                break;
+            }
+            let node = self.arena[id].get();
+            let user = self.user_style.as_ref();
+            let mut style = self.style.cascade(
+                user,
+                &self.stylesheets,
+                node,
+                self.author_mode(),
+                |complex| self.matches_complex_selector(id, complex),
+            );
+            // the dir attribute is a presentational hint, style sheets override it
+            let (lang, dir) = self.lang_and_direction(id);
+            if node.dir().is_some() && style.direction.is_none() {
+                style.direction = Some(match dir {
+                    Dir::Rtl => Direction::Rtl,
+                    Dir::Ltr | Dir::Auto => Direction::Ltr,
+                });
+            }
+            if let Some(parent_style) = self.arena[id]
+                .parent()
+                .and_then(|parent| self.arena[parent].get().style.as_ref())
+            {
+                style.inherit(parent_style);
+            }
+            let node = self.arena[id].get_mut();
+            node.direction = style.direction.unwrap_or_default().into();
+            node.style = Some(style);
+            node.lang = lang;
+        }
+    }
+
+    /// Mode the author styles of the document are parsed in.
+    pub(crate) fn author_mode(&self) -> ParserMode {
+        match self.quirks_mode {
+            QuirksMode::Quirks => ParserMode::Quirks,
+            QuirksMode::NoQuirks | QuirksMode::LimitedQuirks => ParserMode::Normal,
+        }
+    }
+
+    /// Effective language and direction of a node, its parent must already be computed.
+    fn lang_and_direction(&self, id: NodeId) -> (String, Dir) {
+        let node = self.arena[id].get();
+        let (parent_lang, parent_direction) = match self.arena[id].parent() {
+            Some(parent) => {
+                let parent = self.arena[parent].get();
+                (parent.lang.as_str(), parent.direction)
+            }
+            None => (self.default_lang.as_str(), Dir::Ltr),
+        };
+
+        let lang = node.attr("lang").unwrap_or(parent_lang).trim().to_string();
+        let direction = match node.dir() {
+            Some(Dir::Auto) => self.auto_direction(id).unwrap_or(Dir::Ltr),
+            Some(dir) => dir,
+            None => parent_direction,
+        };
+        (lang, direction)
+    }
+
+    /// Direction of the first strong character in the text of an element, skipping
+    /// descendants with their own `dir` attribute and elements without rendered text.
+    fn auto_direction(&self, id: NodeId) -> Option<Dir> {
+        let mut stack: Vec<NodeId> = id.children(&self.arena).collect();
+        stack.reverse();
+        while let Some(id) = stack.pop() {
+            let node = self.arena[id].get();
+            match &node.kind {
+                DOMNodeKind::Text(text) => {
+                    if let Some(dir) = first_strong_direction(text) {
+                        return Some(dir);
+                    }
+                }
+                DOMNodeKind::Element(el)
+                    if node.dir().is_none()
+                        && !matches!(el.name.as_str(), "bdi" | "script" | "style" | "textarea") =>
+                {
+                    let len = stack.len();
+                    stack.extend(id.children(&self.arena));
+                    stack[len..].reverse();
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    #[inline]
+    fn node_style(&self, id: NodeId) -> Option<&Declaration> {
+        self.arena[id].get().style.as_ref()
+    }
+
+    /// Whether the node is a replaced element (its content is not laid out from its children).
+    pub(crate) fn is_replaced(node: &DOMNode) -> bool {
+        matches!(node.name(), "img" | "svg" | "canvas")
+    }
+
+    /// Intrinsic size of a replaced element.
+    fn replaced_size(&self, node: &DOMNode, fonts: &mut FontManager) -> Vec2 {
+        let attr = |name: &str| {
+            node.attr(name)
+                .and_then(|v| v.trim().trim_end_matches("px").parse::<f32>().ok())
+        };
+
+        let (width, height) = match node.name() {
+            "canvas" => (
+                attr("width").unwrap_or(300.0),
+                attr("height").unwrap_or(150.0),
+            ),
+            "svg" => {
+                // height / width ratio from the `viewBox="min-x min-y width height"` attribute
+                let ratio = node.attr("viewBox").and_then(|v| {
+                    let v: Vec<f32> = v
+                        .split(|c: char| c.is_whitespace() || c == ',')
+                        .filter_map(|n| n.parse().ok())
+                        .collect();
+                    (v.len() == 4 && v[2] > 0.0 && v[3] > 0.0).then(|| v[3] / v[2])
+                });
+                match (attr("width"), attr("height"), ratio) {
+                    (Some(width), Some(height), _) => (width, height),
+                    (None, Some(height), Some(ratio)) => (height / ratio, height),
+                    (width, height, ratio) => {
+                        let width = width.unwrap_or(300.0);
+                        (width, height.unwrap_or(ratio.map_or(150.0, |r| width * r)))
+                    }
+                }
+            }
+            _ => {
+                // missing dimensions come from the loaded image, keeping its aspect ratio
+                let natural = node.attr("src").and_then(|src| self.image_sizes.get(src));
+                match (attr("width"), attr("height"), natural) {
+                    (None, None, Some(size)) => (size.x, size.y),
+                    (Some(width), None, Some(size)) if size.x > 0.0 => {
+                        (width, width * size.y / size.x)
+                    }
+                    (None, Some(height), Some(size)) if size.y > 0.0 => {
+                        (height * size.x / size.y, height)
+                    }
+                    // images that are not loaded show their alternative text
+                    (None, None, None) if node.attr("alt").is_some_and(|alt| !alt.is_empty()) => {
+                        let size = node.measure_text(node.attr("alt").unwrap(), 1.0, fonts);
+                        (size.x, size.y)
+                    }
+                    (width, height, _) => (width.unwrap_or(0.0), height.unwrap_or(0.0)),
+                }
+            }
+        };
+        Vec2::new(width, height) * self.viewport.scale_factor
+    }
+
+    pub(crate) fn box_kind(&self, id: NodeId) -> BoxKind {
+        let node = self.arena[id].get();
+        match node.kind {
+            DOMNodeKind::Text(_) => return BoxKind::Inline,
+            DOMNodeKind::Comment(_) => return BoxKind::Hidden,
+            DOMNodeKind::Element(_) => (),
+        }
+
+        let style = node.style.as_ref();
+        let display = style.and_then(|s| s.display).unwrap_or(Display::Inline);
+        let float = style.and_then(|s| s.float).unwrap_or_default();
+        if let Display::None = display {
+            return BoxKind::Hidden;
+        }
+        let position = style.and_then(|s| s.position).unwrap_or_default();
+        if matches!(position, Position::Absolute | Position::Fixed) && id != self.root_id {
+            return BoxKind::Absolute;
+        }
+        if float != Float::None && id != self.root_id {
+            return BoxKind::Float(float);
+        }
+
+        match display {
+            Display::Inline if Self::is_replaced(node) => BoxKind::Atomic,
+            Display::Inline => BoxKind::Inline,
+            Display::InlineBlock | Display::InlineFlex | Display::InlineGrid => BoxKind::Atomic,
+            _ => BoxKind::Block,
+        }
+    }
+
+    /// Whether the node establishes a new block formatting context: floats inside it
+    /// don't affect the outside, and it grows to contain its floats.
+    fn establishes_bfc(&self, id: NodeId) -> bool {
+        if id == self.root_id {
+            return true;
+        }
+        match self.box_kind(id) {
+            BoxKind::Atomic | BoxKind::Float(_) | BoxKind::Absolute => true,
+            _ => {
+                let (x, y) = self.used_overflow(id);
+                x.scrolls()
+                    || y.scrolls()
+                    || matches!(
+                        self.node_style(id).and_then(|s| s.display),
+                        Some(Display::FlowRoot | Display::Flex | Display::Grid)
+                    )
+            }
+        }
+    }
+
+    /// Used horizontal and vertical overflow of a node. The overflow of the root element, or
+    /// of `<body>` if the root's is visible, applies to the viewport instead of the box.
+    fn used_overflow(&self, id: NodeId) -> (Overflow, Overflow) {
+        let overflow = |id| {
+            self.node_style(id)
+                .map_or((Overflow::Visible, Overflow::Visible), |s| {
+                    (
+                        s.overflow_x.unwrap_or_default(),
+                        s.overflow_y.unwrap_or_default(),
+                    )
+                })
         };
+        let visible = (Overflow::Visible, Overflow::Visible);
+        let propagated = id == self.root_id
+            || (self.arena[id].get().name() == "body"
+                && self.arena[id].parent() == Some(self.root_id)
+                && overflow(self.root_id) == visible);
+        // inline boxes don't clip their content
+        if propagated || self.box_kind(id) == BoxKind::Inline {
+            return visible;
+        }
+        // an axis can't stay visible next to one that scrolls
+        let used = |axis: Overflow, other: Overflow| match axis {
+            Overflow::Visible if other.scrolls() => Overflow::Auto,
+            Overflow::Clip if other.scrolls() => Overflow::Hidden,
+            axis => axis,
+        };
+        let (x, y) = overflow(id);
+        (used(x, y), used(y, x))
+    }
 
-        // get mutable node ref of parent
-        let node = self.arena.get_mut(node_id).unwrap().get_mut();
+    /// Rectangle the content of a node is clipped to: its padding box, unbounded along the
+    /// axes that aren't clipped. `None` if the overflow is visible.
+    fn overflow_clip(&self, id: NodeId) -> Option<Rect> {
+        let (x, y) = self.used_overflow(id);
+        if !x.clips() && !y.clips() {
+            return None;
+        }
+        let node = self.arena[id].get();
+        let mut rect = node.border_rect.inset(node.border);
+        if !x.clips() {
+            (rect.pos.x, rect.size.x) = (f32::MIN / 2.0, f32::MAX);
+        }
+        if !y.clips() {
+            (rect.pos.y, rect.size.y) = (f32::MIN / 2.0, f32::MAX);
+        }
+        Some(rect)
+    }
+
+    /// Resolve a length of a node to device pixels, with percentages of `percent_base` (in device
+    /// pixels). `auto` lengths are `None`.
+    pub(crate) fn length(
+        &self,
+        id: NodeId,
+        d: Option<Dimension>,
+        percent_base: Option<f32>,
+    ) -> Option<f32> {
+        let d = d?;
+        let scale = self.viewport.scale_factor;
+        let style = self.arena[id].get().text_style();
+        // percentages are already in device pixels
+        match d.unit {
+            Unit::Percent(_) => style.resolve(d, percent_base),
+            _ => Some(style.resolve(d, None)? * scale),
+        }
+    }
 
-        // compute node bounds
-        node.bounds(fonts);
+    /// Resolved margins of a node in device pixels, in a containing block `width` wide. The
+    /// start margin of list items includes the space of their outside marker.
+    fn margins(&self, id: NodeId, width: f32) -> Sides<f32> {
+        let mut margins = match self.node_style(id) {
+            Some(style) => style
+                .margin
+                .map(|m| self.length(id, m, Some(width)).unwrap_or(0.0)),
+            None => Sides::splat(0.0),
+        };
+        match self.arena[id].get().direction {
+            Dir::Rtl => margins.right += self.marker_gutter(id),
+            _ => margins.left += self.marker_gutter(id),
+        }
+        margins
+    }
 
-        /*
-        log::debug!(
-            "node attrs: {:?}; computing node bounds",
-            node.value().attrs
+    /// Resolved padding of a node in device pixels, in a containing block `width` wide.
+    /// Negative paddings are invalid.
+    fn paddings(&self, id: NodeId, width: f32) -> Sides<f32> {
+        match self.node_style(id) {
+            Some(style) => style
+                .padding
+                .map(|p| self.length(id, p, Some(width)).unwrap_or(0.0).max(0.0)),
+            None => Sides::splat(0.0),
+        }
+    }
+
+    /// Used border widths of a node in device pixels, 0 for the sides without a border.
+    fn borders(&self, id: NodeId) -> Sides<f32> {
+        match self.node_style(id) {
+            Some(style) => style
+                .border
+                .map(|side| match side.style.unwrap_or_default() {
+                    style if style.is_visible() => {
+                        let width = side.width.unwrap_or(Dimension::px(BorderSide::MEDIUM));
+                        self.length(id, Some(width), None).unwrap_or(0.0).max(0.0)
+                    }
+                    _ => 0.0,
+                }),
+            None => Sides::splat(0.0),
+        }
+    }
+
+    /// Used width and offset of the outline of a node in device pixels, the width is 0 without
+    /// an outline.
+    fn outline(&self, id: NodeId) -> (f32, f32) {
+        let Some(outline) = self.node_style(id).map(|style| style.outline) else {
+            return (0.0, 0.0);
+        };
+        if !outline.style.unwrap_or_default().is_visible() {
+            return (0.0, 0.0);
+        }
+        let width = outline.width.unwrap_or(Dimension::px(BorderSide::MEDIUM));
+        let width = self.length(id, Some(width), None).unwrap_or(0.0).max(0.0);
+        let offset = self.length(id, outline.offset, None).unwrap_or(0.0);
+        (width, offset)
+    }
+
+    /// Resolved radii of the corners of a node in device pixels, for a border box of `size`.
+    fn border_radius(&self, id: NodeId, size: Vec2) -> [Vec2; 4] {
+        let radii = self.node_style(id).map(|style| style.border_radius);
+        radii.unwrap_or_default().map(|radius| match radius {
+            Some(radius) => Vec2::new(
+                self.length(id, Some(radius.x), Some(size.x)).unwrap_or(0.0),
+                self.length(id, Some(radius.y), Some(size.y)).unwrap_or(0.0),
+            ),
+            None => Vec2::new(0.0, 0.0),
+        })
+    }
+
+    /// Resolved `width` and `height` of the content box of a node in device pixels, if they are
+    /// specified (not `auto`), in a containing block `width` wide. `inner` is the size of its
+    /// paddings and borders. Percentage heights only apply if the height of the containing
+    /// block doesn't depend on its content.
+    fn specified_size(&self, id: NodeId, width: f32, inner: Vec2) -> (Option<f32>, Option<f32>) {
+        match self.node_style(id) {
+            Some(style) => (
+                self.content_length(id, style.width, Some(width), inner.x),
+                self.content_length(id, style.height, self.definite_height(id), inner.y),
+            ),
+            None => (None, None),
+        }
+    }
+
+    /// Resolve a size of a node to the size of its content box: with `box-sizing: border-box`
+    /// the paddings and borders (`inner`) are part of the specified size.
+    fn content_length(
+        &self,
+        id: NodeId,
+        d: Option<Dimension>,
+        percent_base: Option<f32>,
+        inner: f32,
+    ) -> Option<f32> {
+        let length = self.length(id, d, percent_base)?;
+        match self.node_style(id).and_then(|s| s.box_sizing) {
+            Some(BoxSizing::BorderBox) => Some((length - inner).max(0.0)),
+            _ => Some(length),
+        }
+    }
+
+    /// Clamp the content width of a node between its `min-width` and `max-width`, in a
+    /// containing block `width` wide. When the minimum is bigger than the maximum, the
+    /// minimum wins.
+    fn clamp_width(&self, id: NodeId, width: f32, inner: f32, value: f32) -> f32 {
+        let Some(style) = self.node_style(id) else {
+            return value;
+        };
+        let max = self.content_length(id, style.max_width, Some(width), inner);
+        let min = self.content_length(id, style.min_width, Some(width), inner);
+        clamp(value, min, max)
+    }
+
+    /// Clamp the content height of a node between its `min-height` and `max-height`.
+    fn clamp_height(&self, id: NodeId, inner: f32, value: f32) -> f32 {
+        let Some(style) = self.node_style(id) else {
+            return value;
+        };
+        let height = self.definite_height(id);
+        let max = self.content_length(id, style.max_height, height, inner);
+        let min = self.content_length(id, style.min_height, height, inner);
+        clamp(value, min, max)
+    }
+
+    /// Height of the containing block of a node, if it doesn't depend on its content: the
+    /// specified height of the parent, or the viewport height for the root element.
+    fn definite_height(&self, id: NodeId) -> Option<f32> {
+        // percentage heights of the ancestors, resolved from the closest ancestor with a
+        // height that doesn't depend on its own containing block
+        let mut percents = vec![];
+        let mut height = None;
+        for parent in id.ancestors(&self.arena).skip(1) {
+            if parent == self.root_id {
+                height = Some(self.viewport.size.y * self.viewport.scale_factor);
+                break;
+            }
+            let Some(d) = self.node_style(parent).and_then(|style| style.height) else {
+                break;
+            };
+            if let Unit::Percent(_) = d.unit {
+                percents.push((parent, d));
+                continue;
+            }
+            height = self.length(parent, Some(d), None);
+            break;
+        }
+        percents
+            .into_iter()
+            .rev()
+            .fold(height, |height, (parent, d)| {
+                self.length(parent, Some(d), height)
+            })
+    }
+
+    /// Text style of a node, with the font size in device pixels.
+    pub(crate) fn text_style(&self, id: NodeId) -> TextStyle {
+        self.arena[id]
+            .get()
+            .text_style()
+            .scaled(self.viewport.scale_factor)
+    }
+
+    /// Lay out the whole tree, starting from the root box at the viewport width.
+    fn compute_boxes(&mut self, fonts: &mut FontManager) {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "boxes",
+            measured_texts = tracing::field::Empty,
+            measured_bytes = tracing::field::Empty,
+        )
+        .entered();
+        #[cfg(feature = "tracing")]
+        let measured = fonts.measured_text;
+
+        let origin = Pos2::new(0.0, 0.0);
+        let width = self.viewport.size.x * self.viewport.scale_factor;
+        self.absolutes.clear();
+        self.layout_block(
+            self.root_id,
+            origin,
+            width,
+            &mut FloatContext::default(),
+            false,
+            fonts,
         );
-        log::debug!(
-            "character metrics: {:?}",
-            fonts.sans_serif().metrics('R', 16.0),
+        self.shift_relative(self.root_id);
+        // absolutely positioned boxes are laid out after their containing blocks, and can
+        // contain more of them
+        while !self.absolutes.is_empty() {
+            for (id, static_pos) in std::mem::take(&mut self.absolutes) {
+                self.layout_absolute(id, static_pos, fonts);
+                self.shift_relative(id);
+            }
+        }
+
+        self.compute_markers(fonts);
+
+        // outlines don't take space, they're resolved separately
+        let ids: Vec<NodeId> = self.root_id.descendants(&self.arena).collect();
+        for id in ids {
+            let (width, offset) = self.outline(id);
+            let node = self.arena[id].get_mut();
+            node.outline_width = width;
+            node.outline_offset = offset;
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("measured_texts", fonts.measured_text.0 - measured.0);
+            span.record("measured_bytes", fonts.measured_text.1 - measured.1);
+        }
+    }
+
+    /// Whether the current layout ran out of time (see [`Layout::max_layout_time`]).
+    fn out_of_time(&mut self) -> bool {
+        let (Some(start), Some(max)) = (self.layout_start, self.max_layout_time) else {
+            return false;
+        };
+        if start.elapsed() <= max {
+            return false;
+        }
+        let error = LayoutError::BudgetExceeded(Budget::LayoutTime(max));
+        if !self.errors.contains(&error) {
+            log::warn!("layout took longer than {max:?}, stopping");
+            self.errors.push(error);
+        }
+        true
+    }
+
+    /// Lay out a block-level box with its margin box at `origin`.
+    ///
+    /// If `shrink_to_fit` is set, the box is only as wide as its content (used for floats and inline blocks).
+    /// Returns the size of the margin box.
+    fn layout_block(
+        &mut self,
+        id: NodeId,
+        origin: Pos2,
+        width: f32,
+        floats: &mut FloatContext,
+        shrink_to_fit: bool,
+        fonts: &mut FontManager,
+    ) -> Vec2 {
+        let (block, content_pos) = self.block_box(id, origin, width, shrink_to_fit);
+        let available = block.available;
+        self.layout_flow(id, Some(block), content_pos, available, floats, fonts)
+    }
+
+    /// Resolve the edges and the available width of a block-level box with its margin box at
+    /// `origin`, in a containing block `width` wide. Returns the box and its content position.
+    fn block_box(
+        &mut self,
+        id: NodeId,
+        origin: Pos2,
+        width: f32,
+        shrink_to_fit: bool,
+    ) -> (BlockBox, Pos2) {
+        let margin = self.margins(id, width);
+        let padding = self.paddings(id, width);
+        let border = self.borders(id);
+        let node = self.arena[id].get_mut();
+        node.padding = padding;
+        node.border = border;
+        let pos = Pos2::new(origin.x + margin.left, origin.y + margin.top);
+        let content_pos = Pos2::new(
+            pos.x + border.left + padding.left,
+            pos.y + border.top + padding.top,
+        );
+        // sizes of the borders and paddings
+        let inner = Vec2::new(
+            border.horizontal() + padding.horizontal(),
+            border.vertical() + padding.vertical(),
         );
-        */
+        let (specified_width, specified_height) = self.specified_size(id, width, inner);
+        let edges = margin.horizontal() + inner.x;
+        let available = specified_width.unwrap_or((width - edges).max(0.0));
+        let available = self.clamp_width(id, width, inner.x, available);
+        let block = BlockBox {
+            margin,
+            pos,
+            inner,
+            specified_width,
+            specified_height,
+            containing_width: width,
+            available,
+            shrink_to_fit,
+        };
+        (block, content_pos)
+    }
+
+    /// Size a block-level box once its content (`content` wide and tall) is laid out.
+    /// Returns the size of the margin box.
+    fn finish_block(
+        &mut self,
+        id: NodeId,
+        block: BlockBox,
+        content_pos: Pos2,
+        content: Vec2,
+        floats: &mut FloatContext,
+        fonts: &mut FontManager,
+    ) -> Vec2 {
+        let BlockBox {
+            margin,
+            pos,
+            inner,
+            available,
+            ..
+        } = block;
+        let edges = margin.vertical() + inner.y;
+        let height = block
+            .specified_height
+            .unwrap_or(content.y.max(self.quirks_min_height(id, edges)));
+        let height = self.clamp_height(id, inner.y, height);
+        // width used by the content, the width of shrink-to-fit boxes
+        let used_width = block.specified_width.unwrap_or(content.x).min(available);
+        let used_width = self.clamp_width(id, block.containing_width, inner.x, used_width);
+        let box_width = match block.shrink_to_fit {
+            true => used_width,
+            false => available,
+        };
+        // lines that are not aligned to the left edge are aligned again in the narrower box.
+        // they're already broken in the same places, as none of them is wider than the box
+        if box_width < available && self.has_aligned_lines(id) {
+            self.layout_content(id, content_pos, box_width, floats, fonts);
+        }
+        let size = Vec2::new(box_width + inner.x, height + inner.y);
+        let border_radius = self.border_radius(id, size);
+        let node = self.arena[id].get_mut();
+        node.border_rect = Rect::from_pos_size(pos, size);
+        node.border_radius = border_radius;
+        log::debug!("block '{}' at {}", node.name(), node.border_rect);
+
+        Vec2::new(
+            used_width + inner.x + margin.horizontal(),
+            size.y + margin.vertical(),
+        )
+    }
+
+    /// Lay out the content of a block box at `content_pos`, `width` wide.
+    ///
+    /// Returns the width used by the content and the content height.
+    fn layout_content(
+        &mut self,
+        id: NodeId,
+        content_pos: Pos2,
+        width: f32,
+        floats: &mut FloatContext,
+        fonts: &mut FontManager,
+    ) -> Vec2 {
+        self.layout_flow(id, None, content_pos, width, floats, fonts)
+    }
+
+    /// Lay out the content of a block box at `content_pos`, `width` wide, and the box itself
+    /// if `block` is set. The block-level boxes nested in it are laid out with an explicit
+    /// stack instead of recursion.
+    ///
+    /// Returns the size of the margin box of `block`, or the width used by the content and
+    /// the content height.
+    fn layout_flow(
+        &mut self,
+        id: NodeId,
+        block: Option<BlockBox>,
+        content_pos: Pos2,
+        width: f32,
+        floats: &mut FloatContext,
+        fonts: &mut FontManager,
+    ) -> Vec2 {
+        let mut stack = FlowStack::default();
+        // size of the box laid out last
+        let mut size = self.enter_block(id, block, content_pos, width, &mut stack, fonts);
+
+        loop {
+            let Some(frame) = stack.frames.last_mut() else {
+                return size.unwrap_or_default();
+            };
+            if let Some(size) = size.take() {
+                frame.y += size.y;
+                frame.used_width = frame.used_width.max(size.x);
+            }
+            let current = stack.contexts.last_mut().unwrap_or(&mut *floats);
+            if let Some((child, pos)) = self.next_block_child(frame, current, fonts) {
+                let (block, content_pos) = self.block_box(child, pos, frame.width, false);
+                let available = block.available;
+                size = self.enter_block(
+                    child,
+                    Some(block),
+                    content_pos,
+                    available,
+                    &mut stack,
+                    fonts,
+                );
+                continue;
+            }
+
+            // all the children are laid out
+            let frame = stack.frames.pop().unwrap();
+            let mut content = Vec2::new(frame.used_width, frame.y - frame.origin.y);
+            if frame.own_floats {
+                let own = stack.contexts.pop().unwrap();
+                if let Some(bottom) = own.bottom() {
+                    content.y = content.y.max(bottom - frame.origin.y);
+                }
+            }
+            let current = stack.contexts.last_mut().unwrap_or(&mut *floats);
+            size = Some(match frame.block {
+                Some(block) => {
+                    self.finish_block(frame.id, block, frame.origin, content, current, fonts)
+                }
+                None => content,
+            });
+        }
+    }
+
+    /// Start laying out a block box (or only its content if `block` is `None`), with its
+    /// content box at `content_pos`, `width` wide.
+    ///
+    /// Replaced elements are laid out right away and their size is returned, otherwise a
+    /// frame is pushed to lay out the children of the box.
+    fn enter_block(
+        &mut self,
+        id: NodeId,
+        block: Option<BlockBox>,
+        content_pos: Pos2,
+        width: f32,
+        stack: &mut FlowStack,
+        fonts: &mut FontManager,
+    ) -> Option<Vec2> {
+        if Self::is_replaced(self.arena[id].get()) {
+            let mut content = self.replaced_size(self.arena[id].get(), fonts);
+            let Some(block) = block else {
+                return Some(content);
+            };
+            // a size set on one axis only keeps the intrinsic aspect ratio
+            if content.x > 0.0 && content.y > 0.0 {
+                match (block.specified_width, block.specified_height) {
+                    (Some(width), None) => {
+                        content = Vec2::new(width, width * content.y / content.x)
+                    }
+                    (None, Some(height)) => {
+                        content = Vec2::new(height * content.x / content.y, height)
+                    }
+                    _ => (),
+                }
+            }
+            // replaced elements don't contain floats, the context isn't used
+            let mut floats = FloatContext::default();
+            return Some(self.finish_block(id, block, content_pos, content, &mut floats, fonts));
+        }
+
+        let own_floats = self.establishes_bfc(id);
+        if own_floats {
+            stack.contexts.push(FloatContext::default());
+        }
+        let text_indent = self.node_style(id).and_then(|style| style.text_indent);
+        let mut indent = self.length(id, text_indent, Some(width)).unwrap_or(0.0);
+        indent += self.inside_marker_width(id, fonts);
+        let children: Vec<NodeId> = id.children(&self.arena).collect();
+        stack.frames.push(BlockFrame {
+            id,
+            block,
+            origin: content_pos,
+            width,
+            own_floats,
+            children: children.into_iter(),
+            y: content_pos.y,
+            used_width: 0.0,
+            run: vec![],
+            indent,
+        });
+        None
+    }
+
+    /// Whether the lines of a box or of any of its descendants are not aligned to the left.
+    fn has_aligned_lines(&self, id: NodeId) -> bool {
+        id.descendants(&self.arena)
+            .any(|id| self.text_align(id) != TextAlign::Left)
+    }
+
+    /// Minimum content height of a box in quirks mode: the root and the `body` element are at
+    /// least as tall as the viewport, with their vertical margins and paddings (`edges`).
+    fn quirks_min_height(&self, id: NodeId, edges: f32) -> f32 {
+        if self.quirks_mode != QuirksMode::Quirks {
+            return 0.0;
+        }
+        let viewport_height = self.viewport.size.y * self.viewport.scale_factor;
+        let is_body =
+            self.arena[id].get().name() == "body" && self.arena[id].parent() == Some(self.root_id);
+        match id == self.root_id || is_body {
+            true => (viewport_height - edges).max(0.0),
+            false => 0.0,
+        }
+    }
+
+    /// Lay out the children of a block box up to its next block-level child, which is
+    /// returned with the position of its margin box. Inline-level children are laid out in
+    /// line boxes, and floats are laid out and placed.
+    ///
+    /// Returns `None` once all the children are laid out.
+    fn next_block_child(
+        &mut self,
+        frame: &mut BlockFrame,
+        floats: &mut FloatContext,
+        fonts: &mut FontManager,
+    ) -> Option<(NodeId, Pos2)> {
+        let (origin, width) = (frame.origin, frame.width);
+        while let Some(child) = frame.children.next() {
+            if self.out_of_time() {
+                frame.children = Vec::new().into_iter();
+                break;
+            }
+            let kind = self.box_kind(child);
+            match kind {
+                BoxKind::Hidden => continue,
+                BoxKind::Absolute => {
+                    self.defer_absolute(child, Pos2::new(origin.x, frame.y));
+                    continue;
+                }
+                BoxKind::Inline | BoxKind::Atomic => {
+                    frame.run.push(child);
+                    continue;
+                }
+                _ => (),
+            }
+
+            // a block-level box or a float ends the current line boxes
+            let pos = Pos2::new(origin.x, frame.y);
+            let lines = self.layout_inline(&frame.run, pos, width, frame.indent, floats, fonts);
+            if !frame.run.is_empty() || !matches!(kind, BoxKind::Float(_)) {
+                frame.indent = 0.0;
+            }
+            frame.run.clear();
+            frame.y += lines.y;
+            frame.used_width = frame.used_width.max(lines.x);
+
+            if let BoxKind::Float(side) = kind {
+                let pos = Pos2::new(origin.x, frame.y);
+                let size = self.layout_float(child, side, pos, width, floats, fonts);
+                frame.used_width = frame.used_width.max(size.x);
+                continue;
+            }
+
+            // move the box below the floats it has to clear
+            let clear = self.node_style(child).and_then(|s| s.clear);
+            if let Some(bottom) = clear.and_then(|clear| floats.clearance(clear)) {
+                frame.y = frame.y.max(bottom);
+            }
+            return Some((child, Pos2::new(origin.x, frame.y)));
+        }
+
+        let pos = Pos2::new(origin.x, frame.y);
+        let lines = self.layout_inline(&frame.run, pos, width, frame.indent, floats, fonts);
+        frame.run.clear();
+        frame.y += lines.y;
+        frame.used_width = frame.used_width.max(lines.x);
+        None
+    }
+
+    /// Lay out a float and add it to the float context. Returns the size of its margin box.
+    fn layout_float(
+        &mut self,
+        id: NodeId,
+        side: Float,
+        origin: Pos2,
+        width: f32,
+        floats: &mut FloatContext,
+        fonts: &mut FontManager,
+    ) -> Vec2 {
+        let size = self.layout_block(id, origin, width, &mut FloatContext::default(), true, fonts);
+        let pos = floats.place(side, size, origin.y, origin.x, origin.x + width);
+        self.translate(id, pos - origin);
+        log::debug!("placed {side} float at {pos:?}, size {size:?}");
+        size
+    }
+
+    /// Move a node and all of its descendants, with the static positions of the absolutely
+    /// positioned boxes in it.
+    fn translate(&mut self, id: NodeId, delta: Vec2) {
+        let ids: Vec<NodeId> = id.descendants(&self.arena).collect();
+        for id in ids {
+            let node = self.arena[id].get_mut();
+            node.border_rect = node.border_rect.translate(delta);
+            for fragment in &mut node.fragments {
+                fragment.pos += delta;
+            }
+        }
+        for (pending, pos) in &mut self.absolutes {
+            if pending
+                .ancestors(&self.arena)
+                .any(|ancestor| ancestor == id)
+            {
+                *pos += delta;
+            }
+        }
+    }
 
-        // return node id (will be used as a parent of children nodes)
-        node_id
+    /// Lay out an absolutely positioned box once the boxes around it are laid out, its
+    /// `static_pos` is where it would be in the normal flow.
+    fn defer_absolute(&mut self, id: NodeId, static_pos: Pos2) {
+        self.absolutes.retain(|&(pending, _)| pending != id);
+        self.absolutes.push((id, static_pos));
+    }
+
+    /// Resolved `top`, `right`, `bottom` and `left` of a positioned node in device pixels, in a
+    /// containing block `width` wide and `height` tall. `auto` offsets are `None`.
+    fn offsets(&self, id: NodeId, width: f32, height: Option<f32>) -> Sides<Option<f32>> {
+        let Some(style) = self.node_style(id) else {
+            return Sides::splat(None);
+        };
+        Sides::new(
+            self.length(id, style.inset.top, height),
+            self.length(id, style.inset.right, Some(width)),
+            self.length(id, style.inset.bottom, height),
+            self.length(id, style.inset.left, Some(width)),
+        )
+    }
+
+    /// Lay out an absolutely positioned box against its containing block: the padding box of
+    /// the closest positioned ancestor, or the viewport for fixed boxes and when there's none.
+    /// Along an axis where both offsets are `auto`, the box stays at its static position.
+    fn layout_absolute(&mut self, id: NodeId, static_pos: Pos2, fonts: &mut FontManager) {
+        let scale = self.viewport.scale_factor;
+        let viewport = Rect::from_pos_size(Pos2::new(0.0, 0.0), self.viewport.size * scale);
+        let position = |id| self.node_style(id).and_then(|s| s.position);
+        let block = match position(id) {
+            Some(Position::Fixed) => None,
+            _ => id.ancestors(&self.arena).skip(1).find(|&ancestor| {
+                !matches!(position(ancestor).unwrap_or_default(), Position::Static)
+            }),
+        };
+        let block = block.map_or(viewport, |block| {
+            let node = self.arena[block].get();
+            node.border_rect.inset(node.border)
+        });
+
+        let offsets = self.offsets(id, block.width(), Some(block.height()));
+        let x = match (offsets.left, offsets.right) {
+            (Some(left), _) => block.pos.x + left,
+            (None, Some(_)) => block.pos.x,
+            (None, None) => static_pos.x,
+        };
+        let y = offsets.top.map_or(static_pos.y, |top| block.pos.y + top);
+        let width = (block.max().x - x - offsets.right.unwrap_or(0.0)).max(0.0);
+        // with both horizontal offsets, a box without a width stretches between them
+        let stretch = offsets.left.is_some() && offsets.right.is_some();
+        let origin = Pos2::new(x, y);
+        let mut floats = FloatContext::default();
+        let size = self.layout_block(id, origin, width, &mut floats, !stretch, fonts);
+
+        let mut delta = Vec2::new(0.0, 0.0);
+        if let (None, Some(right)) = (offsets.left, offsets.right) {
+            delta.x = block.max().x - right - size.x - x;
+        }
+        if let (None, Some(bottom)) = (offsets.top, offsets.bottom) {
+            delta.y = block.max().y - bottom - size.y - y;
+        }
+        if delta != Vec2::new(0.0, 0.0) {
+            self.translate(id, delta);
+        }
+        log::debug!("placed absolute box at {:?}", origin + delta);
+    }
+
+    /// Move the relatively positioned boxes of a subtree by their offsets, `top` over `bottom`
+    /// and `left` over `right`. Absolutely positioned boxes in it are moved when they're laid
+    /// out.
+    fn shift_relative(&mut self, root: NodeId) {
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let position = self.node_style(id).and_then(|s| s.position);
+            if let Some(Position::Relative) = position {
+                let width = match self.arena[id].parent() {
+                    Some(parent) => {
+                        let node = self.arena[parent].get();
+                        node.border_rect
+                            .inset(node.border)
+                            .inset(node.padding)
+                            .width()
+                    }
+                    None => self.viewport.size.x * self.viewport.scale_factor,
+                };
+                let offsets = self.offsets(id, width, self.definite_height(id));
+                let offset = |start: Option<f32>, end: Option<f32>| {
+                    start.or(end.map(|end| -end)).unwrap_or(0.0)
+                };
+                let delta = Vec2::new(
+                    offset(offsets.left, offsets.right),
+                    offset(offsets.top, offsets.bottom),
+                );
+                if delta != Vec2::new(0.0, 0.0) {
+                    self.translate(id, delta);
+                }
+            }
+            stack.extend(
+                id.children(&self.arena)
+                    .filter(|&child| self.box_kind(child) != BoxKind::Absolute),
+            );
+        }
+    }
+
+    /// Lay out a run of inline-level nodes in line boxes starting at `origin`. The first line
+    /// is indented by `indent` from the start edge.
+    ///
+    /// Returns the width of the widest line and the total height of the lines.
+    fn layout_inline(
+        &mut self,
+        run: &[NodeId],
+        origin: Pos2,
+        width: f32,
+        indent: f32,
+        floats: &FloatContext,
+        fonts: &mut FontManager,
+    ) -> Vec2 {
+        if run.is_empty() {
+            return Vec2::new(0.0, 0.0);
+        }
+
+        // lines are aligned and indented by the block containing them
+        let (align, rtl) = match run[0].parent(&self.arena) {
+            Some(parent) => (
+                self.text_align(parent),
+                self.arena[parent].get().direction == Dir::Rtl,
+            ),
+            None => (TextAlign::Left, false),
+        };
+        let indent = match rtl {
+            true => (0.0, indent),
+            false => (indent, 0.0),
+        };
+        let pieces = self.collect_pieces(run, origin, width, fonts);
+        let size = self.layout_lines(&pieces, origin, width, align, indent, floats);
+
+        // compute the boxes of the text nodes and inline elements from their fragments
+        for &id in run {
+            self.inline_bounds(id);
+        }
+        size
+    }
+
+    /// Decorations drawn over the text of a node: the decorations of its ancestors up to the
+    /// nearest float or atomic inline box, outermost first.
+    fn decorations(&self, id: NodeId) -> Vec<Decoration> {
+        let mut decorations = vec![];
+        for ancestor in id.ancestors(&self.arena).skip(1) {
+            let node = self.arena[ancestor].get();
+            let decoration = node.style.as_ref().map(|style| style.text_decoration);
+            let decoration = decoration.unwrap_or_default();
+            let line = decoration.line.unwrap_or_default();
+            if !line.is_none() {
+                let color = decoration.color.unwrap_or_default();
+                decorations.push(Decoration {
+                    line,
+                    style: decoration.style.unwrap_or_default(),
+                    color: color.resolve(node.text_style().color),
+                });
+            }
+            if matches!(
+                self.box_kind(ancestor),
+                BoxKind::Float(_) | BoxKind::Atomic | BoxKind::Absolute
+            ) {
+                break;
+            }
+        }
+        decorations.reverse();
+        decorations
+    }
+
+    /// Alignment of the lines of a block: `left`, `right` or `center`, with `start` and `end`
+    /// resolved in the direction of the block.
+    fn text_align(&self, id: NodeId) -> TextAlign {
+        let align = self.node_style(id).and_then(|style| style.text_align);
+        match (align.unwrap_or_default(), self.arena[id].get().direction) {
+            // justified lines are aligned to the start
+            (TextAlign::Start | TextAlign::Justify, Dir::Rtl)
+            | (TextAlign::End, Dir::Ltr | Dir::Auto) => TextAlign::Right,
+            (TextAlign::Start | TextAlign::Justify | TextAlign::End, _) => TextAlign::Left,
+            (align, _) => align,
+        }
+    }
+
+    /// Split the text of an inline run into words and spaces, and lay out its atomic boxes.
+    fn collect_pieces(
+        &mut self,
+        run: &[NodeId],
+        origin: Pos2,
+        width: f32,
+        fonts: &mut FontManager,
+    ) -> Vec<InlinePiece> {
+        let mut pieces = vec![];
+        let mut stack: Vec<NodeId> = run.iter().rev().copied().collect();
+
+        while let Some(id) = stack.pop() {
+            match self.box_kind(id) {
+                BoxKind::Hidden => (),
+                BoxKind::Absolute => self.defer_absolute(id, origin),
+                BoxKind::Inline if self.arena[id].get().is_text() => {
+                    self.text_pieces(id, &mut pieces, fonts)
+                }
+                BoxKind::Inline => {
+                    // only block-level and atomic boxes honour their padding and borders
+                    // (see `DOMNode::padding`)
+                    let node = self.arena[id].get_mut();
+                    node.fragments.clear();
+                    node.padding = Sides::splat(0.0);
+                    node.border = Sides::splat(0.0);
+                    let children: Vec<NodeId> = id.children(&self.arena).collect();
+                    stack.extend(children.into_iter().rev());
+                }
+                // blocks and floats nested inside inline elements are laid out as inline blocks
+                _ => {
+                    let origin = Pos2::new(0.0, 0.0);
+                    let size = self.layout_block(
+                        id,
+                        origin,
+                        width,
+                        &mut FloatContext::default(),
+                        true,
+                        fonts,
+                    );
+                    let (align, shift) = self.vertical_align(id, size.y, 0.0, fonts);
+                    pieces.push(InlinePiece {
+                        node: id,
+                        range: 0..0,
+                        width: size.x,
+                        ascent: size.y,
+                        descent: 0.0,
+                        space: false,
+                        break_before: true,
+                        newline: false,
+                        nowrap: false,
+                        overflow_break: false,
+                        align,
+                        shift,
+                    });
+                }
+            }
+        }
+        pieces
+    }
+
+    /// Alignment of an inline-level box on its line, from the `vertical-align` of the node and
+    /// of its inline ancestors. Returns `Top` or `Bottom` for boxes aligned to an edge of the
+    /// line box, otherwise `Baseline` and the height of the baseline of the box above the
+    /// baseline of the line. `ascent` and `descent` are the extents of the box itself.
+    fn vertical_align(
+        &self,
+        id: NodeId,
+        ascent: f32,
+        descent: f32,
+        fonts: &mut FontManager,
+    ) -> (VerticalAlign, f32) {
+        let mut metrics = |style: &TextStyle| {
+            let family = style.font_family.clone();
+            fonts.line_metrics(style.font_size, family, style.font_weight, style.font_style)
+        };
+
+        let mut shift = 0.0;
+        for node in id.ancestors(&self.arena) {
+            if node != id && self.box_kind(node) != BoxKind::Inline {
+                break;
+            }
+            let Some(parent) = self.arena[node].parent() else {
+                break;
+            };
+            let align = self.node_style(node).and_then(|style| style.vertical_align);
+            let align = align.unwrap_or_default();
+            // inline elements are aligned with the content area of their font
+            let style = self.text_style(node);
+            let own = metrics(&style);
+            let (ascent, descent) = match node == id {
+                true => (ascent, descent),
+                false => (own.ascent, -own.descent),
+            };
+            let parent_style = self.text_style(parent);
+            let parent_metrics = metrics(&parent_style);
+            let parent_size = parent_style.font_size;
+            shift += match align {
+                VerticalAlign::Baseline => 0.0,
+                VerticalAlign::Sub => -parent_size / 5.0,
+                VerticalAlign::Super => parent_size / 3.0,
+                VerticalAlign::TextTop => parent_metrics.ascent - ascent,
+                VerticalAlign::TextBottom => descent + parent_metrics.descent,
+                // the x-height is approximated as half of the font size
+                VerticalAlign::Middle => parent_size / 4.0 - (ascent - descent) / 2.0,
+                VerticalAlign::Top | VerticalAlign::Bottom => return (align, 0.0),
+                VerticalAlign::Length(length) => {
+                    let line_height = style.line_height.unwrap_or(own.new_line_size);
+                    self.length(node, Some(length), Some(line_height))
+                        .unwrap_or(0.0)
+                }
+            };
+        }
+        (VerticalAlign::Baseline, shift)
+    }
+
+    /// Split the text of a text node into words, spaces and preserved newlines.
+    fn text_pieces(&mut self, id: NodeId, pieces: &mut Vec<InlinePiece>, fonts: &mut FontManager) {
+        let scale = self.viewport.scale_factor;
+        let style = self.text_style(id);
+        let node = self.arena[id].get_mut();
+        node.fragments.clear();
+
+        let metrics = fonts.line_metrics(
+            style.font_size,
+            style.font_family,
+            style.font_weight,
+            style.font_style,
+        );
+        // distribute the line gap evenly above and below the text
+        let line_height = style.line_height.unwrap_or(metrics.new_line_size);
+        let half_leading = (line_height - (metrics.ascent - metrics.descent)) / 2.0;
+        let ascent = metrics.ascent + half_leading;
+        let descent = -metrics.descent + half_leading;
+        let (align, shift) = self.vertical_align(id, ascent, descent, fonts);
+
+        let node = self.arena[id].get();
+        let text = node.text();
+        let wraps = style.white_space.wraps();
+        let (word_break, overflow_wrap) = (style.word_break, style.overflow_wrap);
+        for segment in segment_text_with(text, style.white_space) {
+            // spaces collapse with preceding spaces (also across nodes),
+            // and spaces at the start of the run are removed
+            if segment.space && pieces.last().is_none_or(|piece| piece.space) {
+                continue;
+            }
+            let mut piece = InlinePiece {
+                node: id,
+                range: segment.range.clone(),
+                width: 0.0,
+                ascent,
+                descent,
+                space: segment.space,
+                break_before: segment.break_before,
+                newline: segment.newline,
+                nowrap: !wraps,
+                overflow_break: false,
+                align,
+                shift,
+            };
+            if segment.newline {
+                pieces.push(piece);
+                continue;
+            }
+            // keep-all only leaves the break opportunities at spaces
+            let after_space = text[..segment.range.start].ends_with(char::is_whitespace);
+            if word_break == WordBreak::KeepAll && !after_space {
+                piece.break_before = false;
+            }
+
+            // words are split into characters where lines can break between them
+            let split = word_break == WordBreak::BreakAll || overflow_wrap != OverflowWrap::Normal;
+            if !wraps || segment.space || !split {
+                piece.width = node.measure_text(&text[segment.range], scale, fonts).x;
+                pieces.push(piece);
+                continue;
+            }
+            for (k, (i, c)) in text[segment.range.clone()].char_indices().enumerate() {
+                let start = segment.range.start + i;
+                let mut piece = piece.clone();
+                piece.range = start..start + c.len_utf8();
+                piece.width = node
+                    .measure_text(&text[piece.range.clone()], scale, fonts)
+                    .x;
+                if k > 0 {
+                    piece.break_before = word_break == WordBreak::BreakAll;
+                    piece.overflow_break = overflow_wrap != OverflowWrap::Normal;
+                }
+                pieces.push(piece);
+            }
+        }
+    }
+
+    /// Break inline pieces into lines and position them. Text is shortened next to floats, and
+    /// the first line is shortened by its (left, right) `indent`.
+    fn layout_lines(
+        &mut self,
+        pieces: &[InlinePiece],
+        origin: Pos2,
+        width: f32,
+        align: TextAlign,
+        indent: (f32, f32),
+        floats: &FloatContext,
+    ) -> Vec2 {
+        let mut y = origin.y;
+        let mut used_width = 0.0f32;
+        let mut i = 0;
+        let mut first = true;
+
+        while i < pieces.len() {
+            // spaces at the start of a line are removed
+            if pieces[i].space {
+                i += 1;
+                continue;
+            }
+
+            let height = pieces[i].ascent + pieces[i].descent;
+            let (left, right) = floats.available(y, height, origin.x, origin.x + width);
+            let narrowed = left > origin.x || right < origin.x + width;
+            // the first line is indented from its start edge
+            let (left, right) = match first {
+                true => (left + indent.0, right - indent.1),
+                false => (left, right),
+            };
+
+            // fit as many unbreakable units as possible on the line, the first unit is always placed.
+            // spaces at the end of the line are removed, and preserved newlines end the line
+            let (mut end, mut line_width) = Self::next_unit(pieces, i);
+            while !pieces[end - 1].newline {
+                let mut next = end;
+                let mut spaces = 0.0;
+                while next < pieces.len() && pieces[next].space {
+                    spaces += pieces[next].width;
+                    next += 1;
+                }
+                if next == pieces.len() {
+                    break;
+                }
+                if pieces[next].newline {
+                    end = next + 1;
+                    break;
+                }
+                let (unit_end, unit_width) = Self::next_unit(pieces, next);
+                if line_width + spaces + unit_width > right - left {
+                    break;
+                }
+                line_width += spaces + unit_width;
+                end = unit_end;
+            }
+
+            // if the first word doesn't fit next to the floats, move the line below them
+            if line_width > right - left && narrowed {
+                if let Some(bottom) = floats.next_bottom(y) {
+                    y = bottom;
+                    continue;
+                }
+            }
+            // otherwise it's broken where overflow-wrap allows it
+            if line_width > right - left {
+                if let Some((split, width)) = Self::overflow_split(pieces, i, end, right - left) {
+                    (end, line_width) = (split, width);
+                }
+            }
+
+            // a small line height can make the ascent or descent negative
+            let line = &pieces[i..end];
+            let (mut baseline, descent) = line
+                .iter()
+                .filter(|p| matches!(p.align, VerticalAlign::Baseline))
+                .map(|p| (p.ascent + p.shift, p.descent - p.shift))
+                .reduce(|a, b| (a.0.max(b.0), a.1.max(b.1)))
+                .unwrap_or((0.0, 0.0));
+            // pieces aligned to the top or the bottom of the line make it taller if needed,
+            // bottom aligned pieces push the baseline down
+            let edge_height = |top: bool| {
+                line.iter()
+                    .filter(|p| match p.align {
+                        VerticalAlign::Top => top,
+                        VerticalAlign::Bottom => !top,
+                        _ => false,
+                    })
+                    .map(|p| p.ascent + p.descent)
+                    .fold(0.0, f32::max)
+            };
+            baseline += (edge_height(false) - baseline - descent).max(0.0);
+            let height = (baseline + descent).max(edge_height(true));
+
+            // lines that overflow stay at the start
+            let free = (right - left - line_width).max(0.0);
+            let mut x = left
+                + match align {
+                    TextAlign::Start | TextAlign::Left | TextAlign::Justify => 0.0,
+                    TextAlign::End | TextAlign::Right => free,
+                    TextAlign::Center => free / 2.0,
+                };
+            for (k, piece) in line.iter().enumerate() {
+                let top = match piece.align {
+                    VerticalAlign::Top => y,
+                    VerticalAlign::Bottom => y + height - piece.ascent - piece.descent,
+                    _ => y + baseline - piece.shift - piece.ascent,
+                };
+                let pos = Pos2::new(x, top);
+                let continued = k > 0 && line[k - 1].node == piece.node;
+                self.place_piece(piece, pos, continued);
+                x += piece.width;
+            }
+
+            used_width = used_width.max(left + line_width - origin.x);
+            y += height;
+            i = end;
+            first = false;
+        }
+
+        Vec2::new(used_width, y - origin.y)
+    }
+
+    /// Find the end of the unbreakable unit of pieces starting at `start`,
+    /// returns the end index and the width of the unit. Newlines are units of their own.
+    fn next_unit(pieces: &[InlinePiece], start: usize) -> (usize, f32) {
+        let mut end = start + 1;
+        let mut width = pieces[start].width;
+        while end < pieces.len()
+            && !pieces[start].newline
+            && !pieces[end].newline
+            && (!pieces[end].space || pieces[end].nowrap)
+            && !pieces[end].break_before
+        {
+            width += pieces[end].width;
+            end += 1;
+        }
+        (end, width)
+    }
+
+    /// Find where to break a unit of pieces that overflows the `available` width, at the last
+    /// overflow break opportunity that fits. Returns the end index and the width of the part
+    /// kept on the line.
+    fn overflow_split(
+        pieces: &[InlinePiece],
+        start: usize,
+        end: usize,
+        available: f32,
+    ) -> Option<(usize, f32)> {
+        let mut width = pieces[start].width;
+        let mut split = None;
+        for (k, piece) in pieces.iter().enumerate().take(end).skip(start + 1) {
+            if piece.overflow_break {
+                split = Some((k, width));
+            }
+            width += piece.width;
+            if width > available {
+                break;
+            }
+        }
+        split
+    }
+
+    /// Position a piece on a line. Words of the same text node on the same line are merged into one fragment.
+    fn place_piece(&mut self, piece: &InlinePiece, pos: Pos2, continued: bool) {
+        let style = self.text_style(piece.node);
+        let node = self.arena[piece.node].get_mut();
+        let DOMNodeKind::Text(text) = &node.kind else {
+            // atomic boxes are laid out at the origin, move them to the line
+            self.translate(piece.node, pos - Pos2::new(0.0, 0.0));
+            return;
+        };
+        // newlines only end the line
+        if piece.newline {
+            return;
+        }
+
+        if continued {
+            if let Some(fragment) = node.fragments.last_mut() {
+                fragment.range.end = piece.range.end;
+                fragment.text = style.render(&text[fragment.range.clone()]).into_owned();
+                fragment.size.x += piece.width;
+                return;
+            }
+        }
+
+        let text = style.render(&text[piece.range.clone()]).into_owned();
+        let mut style = style;
+        style.decorations = self.decorations(piece.node);
+        self.arena[piece.node]
+            .get_mut()
+            .fragments
+            .push(TextFragment {
+                range: piece.range.clone(),
+                text,
+                pos,
+                size: Vec2::new(piece.width, piece.ascent + piece.descent),
+                baseline: piece.ascent,
+                style,
+            });
+    }
+
+    /// Compute the box of an inline node as the bounding box of its fragments and children.
+    fn inline_bounds(&mut self, id: NodeId) {
+        // collect the inline subtree in tree order, then compute the boxes bottom-up
+        let mut ids = vec![];
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            ids.push(id);
+            if self.box_kind(id) == BoxKind::Inline {
+                stack.extend(id.children(&self.arena));
+            }
+        }
+
+        // (min, max) corners of the boxes that have content
+        let mut bounds: HashMap<NodeId, (Pos2, Pos2)> = HashMap::new();
+        for &id in ids.iter().rev() {
+            let node = self.arena[id].get();
+            let node_bounds = match self.box_kind(id) {
+                BoxKind::Hidden | BoxKind::Absolute => None,
+                BoxKind::Inline => id
+                    .children(&self.arena)
+                    .filter_map(|child| bounds.get(&child).copied())
+                    .chain(node.fragments.iter().map(|f| (f.pos, f.pos + f.size)))
+                    .reduce(union),
+                _ => Some((node.border_rect.min(), node.border_rect.max())),
+            };
+
+            if let Some((min, max)) = node_bounds {
+                bounds.insert(id, (min, max));
+                let node = self.arena[id].get_mut();
+                node.border_rect = Rect::from_min_max(min, max);
+            }
+        }
+    }
+
+    /// Record the clip rectangles of the nodes with a non-visible overflow, and the ancestor
+    /// that clips each node. Ancestors come first in tree order, so their clips are known.
+    fn compute_clips(&mut self) {
+        let ids: Vec<NodeId> = self.root_id.descendants(&self.arena).collect();
+        for id in ids {
+            let clip = self.overflow_clip(id);
+            let node = self.arena[id].get();
+            let extent = node
+                .fragments
+                .iter()
+                .map(|fragment| Rect::from_pos_size(fragment.pos, fragment.size))
+                .fold(node.border_rect, |extent, rect| extent.union(&rect));
+            // boxes without an area (e.g. hidden ones) aren't clipped
+            let clipped_by = (!extent.is_empty())
+                .then(|| {
+                    id.ancestors(&self.arena).skip(1).find(|&ancestor| {
+                        self.arena[ancestor]
+                            .get()
+                            .overflow_clip
+                            .is_some_and(|clip| !clip.contains_rect(&extent))
+                    })
+                })
+                .flatten();
+            let node = self.arena[id].get_mut();
+            node.overflow_clip = clip;
+            node.clipped_by = clipped_by;
+        }
+    }
+
+    /// Round the edges of all boxes and text fragments to whole device pixels. The padding and
+    /// content edges of a box are rounded too, and its border widths and padding are taken
+    /// between the rounded edges, so the clip rectangles (computed after) stay on whole pixels.
+    fn snap_to_pixels(&mut self) {
+        let snap = |pos: &mut Pos2, size: &mut Vec2| {
+            let min = Pos2::new(pos.x.round(), pos.y.round());
+            let max = Pos2::new((pos.x + size.x).round(), (pos.y + size.y).round());
+            *pos = min;
+            *size = max - min;
+        };
+        // rounded edges of a rectangle, inset by the given sides: (top, right, bottom, left)
+        let edges = |rect: &Rect, inset: &Sides<f32>| {
+            let (min, max) = (rect.min(), rect.max());
+            Sides::new(
+                (min.y + inset.top).round(),
+                (max.x - inset.right).round(),
+                (max.y - inset.bottom).round(),
+                (min.x + inset.left).round(),
+            )
+        };
+        // widths between two sets of edges, the outer one first
+        let widths = |outer: &Sides<f32>, inner: &Sides<f32>| {
+            Sides::new(
+                (inner.top - outer.top).max(0.0),
+                (outer.right - inner.right).max(0.0),
+                (outer.bottom - inner.bottom).max(0.0),
+                (inner.left - outer.left).max(0.0),
+            )
+        };
+
+        for node in self.arena.iter_mut() {
+            let node = node.get_mut();
+            let outer = edges(&node.border_rect, &Sides::default());
+            let padding_edges = edges(&node.border_rect, &node.border);
+            let content_edges = edges(
+                &node.border_rect,
+                &Sides::new(
+                    node.border.top + node.padding.top,
+                    node.border.right + node.padding.right,
+                    node.border.bottom + node.padding.bottom,
+                    node.border.left + node.padding.left,
+                ),
+            );
+            node.border = widths(&outer, &padding_edges);
+            node.padding = widths(&padding_edges, &content_edges);
+            snap(&mut node.border_rect.pos, &mut node.border_rect.size);
+            for fragment in &mut node.fragments {
+                snap(&mut fragment.pos, &mut fragment.size);
+            }
+        }
     }
 }
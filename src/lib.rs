@@ -1,20 +1,81 @@
 #![forbid(unsafe_code)]
 
+mod accessibility;
+mod builder;
+mod cancel;
+mod clock;
 mod context;
+mod display_list;
 mod dom;
+mod engine;
 mod errors;
+mod favicon;
 mod fonts;
+mod forms;
+mod history;
+#[cfg(feature = "images")]
+mod images;
+mod interactive;
 mod layout;
+mod limits;
+mod lists;
+mod metadata;
+mod observer;
+mod plain_text;
 mod puller;
+#[cfg(feature = "raster")]
+mod raster;
+mod resources;
+mod scripts;
+mod scroll;
+mod selection;
+mod selector;
+mod serialize;
+#[cfg(feature = "serde")]
+mod snapshot;
 mod stylesheet;
+mod table;
+mod text;
 mod utils;
+mod visited;
+pub use accessibility::*;
+pub use builder::*;
+pub use cancel::*;
 pub use context::*;
+pub use display_list::*;
 pub use dom::*;
+pub use engine::*;
 pub use errors::*;
+pub use favicon::*;
 pub use fonts::*;
+pub use forms::*;
+pub use history::*;
+#[cfg(feature = "images")]
+pub use images::*;
+pub use interactive::*;
 pub use layout::*;
+pub use limits::*;
+pub use lists::*;
+pub use metadata::*;
+pub use observer::*;
+pub use plain_text::*;
 pub use puller::*;
+#[cfg(feature = "raster")]
+pub use raster::*;
+pub use resources::*;
+pub use scripts::*;
+pub use scroll::*;
+pub use selection::*;
+pub use selector::*;
+#[cfg(feature = "serde")]
+pub use snapshot::*;
 pub use stylesheet::*;
+pub use table::*;
+pub use text::*;
 pub use utils::*;
+pub use visited::*;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use clock::{set_clock, set_system_clock};
 
 pub extern crate url;
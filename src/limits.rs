@@ -0,0 +1,98 @@
+use crate::{DfError, LayoutError, LoadError, LoadPhase, WebContext};
+use std::fmt;
+use std::time::Duration;
+use url::Url;
+
+/// Limits on the resources a page load can use, to load untrusted pages safely.
+/// No limit is set by default.
+///
+/// Exceeding a limit doesn't fail the load: the page is loaded partially and the exceeded
+/// budget is reported by [`WebContext::exceeded_budgets`].
+///
+/// ```
+/// use dragonfly::{Budget, LoadLimits, WebContext};
+/// # include!("../tests/support/doctest.rs");
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let html = "<p>x</p>".repeat(1000);
+/// let limits = LoadLimits { max_nodes: Some(100), ..Default::default() };
+/// let mut ctx = WebContext::builder().html(&html).limits(limits).build()?;
+/// block_on(ctx.load())?;
+/// assert_eq!(ctx.layout.root_id().descendants(&ctx.layout.arena).count(), 100);
+/// assert_eq!(ctx.exceeded_budgets(), [Budget::Nodes(100)]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadLimits {
+    /// Maximum number of bytes pulled for the page and its subresources. The page is cut
+    /// at the limit, and subresources that don't fit are skipped.
+    pub max_bytes: Option<usize>,
+    /// Maximum number of nodes in the layout tree, further nodes of the document are dropped
+    pub max_nodes: Option<usize>,
+    /// Maximum number of author CSS rules, further rules are ignored
+    pub max_css_rules: Option<usize>,
+    /// Maximum duration of a layout. Boxes that were not laid out when it runs out
+    /// keep their previous geometry.
+    pub max_layout_time: Option<Duration>,
+}
+
+/// A limit of [`LoadLimits`] that was exceeded, with its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    Bytes(usize),
+    Nodes(usize),
+    CssRules(usize),
+    LayoutTime(Duration),
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bytes(max) => write!(f, "byte budget of {max} bytes"),
+            Self::Nodes(max) => write!(f, "node budget of {max} nodes"),
+            Self::CssRules(max) => write!(f, "CSS rule budget of {max} rules"),
+            Self::LayoutTime(max) => write!(f, "layout time budget of {max:?}"),
+        }
+    }
+}
+
+impl WebContext {
+    /// Budgets of the [`limits`](WebContext::limits) exceeded by the last load, or by
+    /// layouts computed since then (see [`WebContext::load_errors`]).
+    pub fn exceeded_budgets(&self) -> Vec<Budget> {
+        self.load_errors()
+            .iter()
+            .filter_map(|err| match err.error.as_deref()? {
+                DfError::BudgetExceeded(budget)
+                | DfError::Layout(LayoutError::BudgetExceeded(budget)) => Some(*budget),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Record an exceeded budget of the load.
+    pub(crate) fn exceed_budget(&mut self, phase: LoadPhase, url: Option<Url>, budget: Budget) {
+        log::warn!("{budget} exceeded");
+        self.add_load_error(LoadError::new(phase, url, DfError::BudgetExceeded(budget)));
+    }
+
+    /// Ignore the author CSS rules past the rule budget.
+    pub(crate) fn limit_css_rules(&mut self) {
+        let Some(max) = self.limits.max_css_rules else {
+            return;
+        };
+        let mut remaining = max;
+        let mut exceeded = false;
+        for sheet in &mut self.layout.stylesheets {
+            if sheet.rules.len() > remaining {
+                sheet.rules.truncate(remaining);
+                sheet.important.retain(|(i, _)| *i < remaining);
+                exceeded = true;
+            }
+            remaining -= sheet.rules.len();
+        }
+        if exceeded {
+            self.exceed_budget(LoadPhase::Style, None, Budget::CssRules(max));
+        }
+    }
+}
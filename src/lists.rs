@@ -0,0 +1,218 @@
+use crate::{
+    image_size, BackgroundImage, Dir, Display, FontManager, Layout, ListStylePosition,
+    ListStyleType, Pos2, Rect, TextFragment, Vec2,
+};
+use indextree::NodeId;
+
+/// Space between the start edge of a list item and its outside marker, in CSS pixels.
+const MARKER_GUTTER: f32 = 40.0;
+/// Size of a bullet marker, relative to the font size.
+const BULLET_SIZE: f32 = 0.35;
+
+/// Marker of a list item, computed by the layout (see [`DOMNode::marker`](crate::DOMNode::marker)).
+#[derive(Debug, Clone)]
+pub enum ListMarker {
+    /// Shape of a `disc`, `circle` or `square` marker
+    Bullet { kind: ListStyleType, rect: Rect },
+    /// Counter of the item and its suffix, such as `3. ` or `iv. `
+    Text(TextFragment),
+    /// Loaded `list-style-image`, whose bytes are in [`Layout::background_images`] by `url`
+    Image { rect: Rect, url: String },
+}
+
+impl Layout {
+    /// Number of a list item in its list: the `start` attribute of an `ol` (1 by default), plus
+    /// the number of list items before it. A `value` attribute sets the number of an item,
+    /// and the items after it count from there.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<ol start=3><li>a</li><li value=10>b</li><p>not an item</p><li>c</li></ol>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let numbers: Vec<i64> = layout.select("li").into_iter()
+    ///     .map(|li| layout.list_item_number(li))
+    ///     .collect();
+    /// assert_eq!(numbers, [3, 10, 11]);
+    /// ```
+    pub fn list_item_number(&self, id: NodeId) -> i64 {
+        let Some(list) = self.arena[id].parent() else {
+            return 1;
+        };
+        let list_node = self.arena[list].get();
+        let mut number = match list_node.name() {
+            "ol" => list_node
+                .attr("start")
+                .and_then(|start| start.trim().parse().ok()),
+            _ => None,
+        }
+        .unwrap_or(1);
+        for item in list.children(&self.arena) {
+            let item_node = self.arena[item].get();
+            if item_node.name() != "li" {
+                continue;
+            }
+            let value = item_node.attr("value");
+            if let Some(value) = value.and_then(|value| value.trim().parse().ok()) {
+                number = value;
+            }
+            if item == id {
+                break;
+            }
+            number += 1;
+        }
+        number
+    }
+
+    /// Whether a node is a list item (`display: list-item`).
+    fn is_list_item(&self, id: NodeId) -> bool {
+        let style = self.arena[id].get().style.as_ref();
+        matches!(style.and_then(|s| s.display), Some(Display::ListItem))
+    }
+
+    /// Marker type and position of a list item, `None` if it has no marker.
+    fn list_style(&self, id: NodeId) -> Option<(ListStyleType, ListStylePosition)> {
+        if !self.is_list_item(id) {
+            return None;
+        }
+        let style = self.arena[id].get().style.as_ref()?;
+        let kind = style.list_style_type.unwrap_or_default();
+        let position = style.list_style_position.unwrap_or_default();
+        match kind {
+            ListStyleType::None if self.marker_image(id).is_none() => None,
+            kind => Some((kind, position)),
+        }
+    }
+
+    /// Loaded `list-style-image` of a node, with its URL and its size in device pixels.
+    fn marker_image(&self, id: NodeId) -> Option<(&str, Vec2)> {
+        let style = self.arena[id].get().style.as_ref()?;
+        let BackgroundImage::Url(url) = style.list_style_image.as_ref()? else {
+            return None;
+        };
+        let (width, height) = image_size(self.background_images.get(url)?)?;
+        let size = Vec2::new(width as f32, height as f32) * self.viewport.scale_factor;
+        Some((url, size))
+    }
+
+    /// Space before the start edge of a list item taken by its outside marker, in device pixels.
+    pub(crate) fn marker_gutter(&self, id: NodeId) -> f32 {
+        match self.list_style(id) {
+            Some((_, ListStylePosition::Outside)) => MARKER_GUTTER * self.viewport.scale_factor,
+            _ => 0.0,
+        }
+    }
+
+    /// Width taken by the inside marker of a list item at the start of its first line.
+    pub(crate) fn inside_marker_width(&self, id: NodeId, fonts: &mut FontManager) -> f32 {
+        match self.list_style(id) {
+            Some((kind, ListStylePosition::Inside)) => self.marker_width(id, kind, fonts),
+            _ => 0.0,
+        }
+    }
+
+    /// Width of the marker of a list item, with the space after it.
+    fn marker_width(&self, id: NodeId, kind: ListStyleType, fonts: &mut FontManager) -> f32 {
+        let font_size = self.text_style(id).font_size;
+        if let Some((_, size)) = self.marker_image(id) {
+            return size.x + font_size / 2.0;
+        }
+        match self.marker_text(id, kind) {
+            Some(text) => {
+                let node = self.arena[id].get();
+                node.measure_text(&text, self.viewport.scale_factor, fonts)
+                    .x
+            }
+            None => font_size * (BULLET_SIZE + 0.5),
+        }
+    }
+
+    /// Text of the counter marker of a list item, `None` for bullets.
+    fn marker_text(&self, id: NodeId, kind: ListStyleType) -> Option<String> {
+        let counter = kind.counter(self.list_item_number(id))?;
+        Some(format!("{counter}. "))
+    }
+
+    /// Compute the markers of the list items once their boxes are placed. Outside markers are
+    /// before the start edge of the item, inside markers at the start of its first line, on the
+    /// baseline of that line.
+    pub(crate) fn compute_markers(&mut self, fonts: &mut FontManager) {
+        let ids: Vec<NodeId> = self.root_id().descendants(&self.arena).collect();
+        for id in ids {
+            let marker = self
+                .list_style(id)
+                .map(|(kind, position)| self.marker(id, kind, position, fonts));
+            self.arena[id].get_mut().marker = marker;
+        }
+    }
+
+    fn marker(
+        &self,
+        id: NodeId,
+        kind: ListStyleType,
+        position: ListStylePosition,
+        fonts: &mut FontManager,
+    ) -> ListMarker {
+        let node = self.arena[id].get();
+        let mut style = self.text_style(id);
+        let font_size = style.font_size;
+        let content = node.border_rect.inset(node.border).inset(node.padding);
+
+        // the first line of the item, or a line at the top of its content
+        let first_line = id
+            .descendants(&self.arena)
+            .find_map(|id| self.arena[id].get().fragments.first());
+        let (top, height, baseline) = match first_line {
+            Some(line) => (line.pos.y, line.size.y, line.baseline),
+            None => {
+                let family = style.font_family.clone();
+                let metrics =
+                    fonts.line_metrics(font_size, family, style.font_weight, style.font_style);
+                let height = style.line_height.unwrap_or(metrics.new_line_size);
+                let half_leading = (height - (metrics.ascent - metrics.descent)) / 2.0;
+                (content.pos.y, height, metrics.ascent + half_leading)
+            }
+        };
+
+        let width = self.marker_width(id, kind, fonts);
+        let rtl = node.direction == Dir::Rtl;
+        let x = match (position, rtl) {
+            (ListStylePosition::Outside, false) => node.border_rect.pos.x - width,
+            (ListStylePosition::Outside, true) => node.border_rect.max().x,
+            (ListStylePosition::Inside, false) => content.pos.x,
+            (ListStylePosition::Inside, true) => content.max().x - width,
+        };
+        // the space after the marker is on the side of the content
+        let start = match rtl {
+            true => x + font_size / 2.0,
+            false => x,
+        };
+
+        if let Some((url, size)) = self.marker_image(id) {
+            let pos = Pos2::new(start, top + baseline - size.y);
+            let rect = Rect::from_pos_size(pos, size);
+            let url = url.to_string();
+            return ListMarker::Image { rect, url };
+        }
+        match self.marker_text(id, kind) {
+            Some(text) => {
+                style.text_transform = Default::default();
+                ListMarker::Text(TextFragment {
+                    range: 0..text.len(),
+                    text,
+                    pos: Pos2::new(x, top),
+                    size: Vec2::new(width, height),
+                    baseline,
+                    style,
+                })
+            }
+            None => {
+                // bullets are centered around the middle of the lowercase letters
+                let size = font_size * BULLET_SIZE;
+                let center = top + baseline - font_size / 4.0;
+                let pos = Pos2::new(start, center - size / 2.0);
+                let rect = Rect::from_pos_size(pos, Vec2::new(size, size));
+                ListMarker::Bullet { kind, rect }
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{collapse_whitespace, resolve_fetchable_url, resolve_url, Layout};
+use indextree::NodeId;
+use std::time::Duration;
+use url::Url;
+
+/// Width requested by a `<meta name="viewport">` tag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportWidth {
+    /// `width=device-width`
+    DeviceWidth,
+    /// Width in CSS pixels
+    Px(f32),
+}
+
+/// Content of a `<meta name="viewport">` tag.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViewportMeta {
+    /// Raw `content` attribute
+    pub content: String,
+    pub width: Option<ViewportWidth>,
+    pub initial_scale: Option<f32>,
+}
+
+impl ViewportMeta {
+    /// Parse the `content` attribute of a viewport meta tag
+    /// (`width=device-width, initial-scale=1`).
+    pub fn parse(content: &str) -> Self {
+        let mut meta = Self {
+            content: content.to_string(),
+            ..Default::default()
+        };
+        for pair in content.split([',', ';']) {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "width" if value.eq_ignore_ascii_case("device-width") => {
+                    meta.width = Some(ViewportWidth::DeviceWidth)
+                }
+                "width" => meta.width = value.parse().ok().map(ViewportWidth::Px),
+                "initial-scale" => meta.initial_scale = value.parse().ok(),
+                _ => {}
+            }
+        }
+        meta
+    }
+}
+
+/// Refresh requested by a `<meta http-equiv="refresh">` tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaRefresh {
+    /// Time to wait before the refresh
+    pub delay: Duration,
+    /// Resolved URL to navigate to, `None` to reload the page
+    pub url: Option<Url>,
+}
+
+/// Parse the `content` attribute of a refresh meta tag (`5; url=/next`) into
+/// the delay and the raw URL, if there is one.
+///
+/// The URL can be quoted, and the `url=` prefix is optional:
+///
+/// ```
+/// use dragonfly::parse_refresh;
+/// use std::time::Duration;
+/// assert_eq!(parse_refresh("0;URL=\"next.html\""), Some((Duration::ZERO, Some("next.html"))));
+/// assert_eq!(parse_refresh(" 5 , /next "), Some((Duration::from_secs(5), Some("/next"))));
+/// assert_eq!(parse_refresh("3"), Some((Duration::from_secs(3), None)));
+/// assert_eq!(parse_refresh("url=/next"), None);
+/// ```
+pub fn parse_refresh(content: &str) -> Option<(Duration, Option<&str>)> {
+    let is_space = |c: char| c.is_ascii_whitespace();
+    let rest = content.trim_start_matches(is_space);
+
+    // delay in seconds, the fractional part is ignored
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 && !rest.starts_with('.') {
+        return None;
+    }
+    let delay = match digits {
+        0 => 0,
+        _ => rest[..digits].parse().ok()?,
+    };
+    let rest = rest[digits..].trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    // separator
+    let rest = rest.trim_start_matches(is_space);
+    let rest = rest
+        .strip_prefix([';', ','])
+        .unwrap_or(rest)
+        .trim_start_matches(is_space);
+    if rest.is_empty() {
+        return Some((Duration::from_secs(delay), None));
+    }
+
+    // optional `url=` prefix
+    let mut url = rest;
+    if rest.get(..3).is_some_and(|p| p.eq_ignore_ascii_case("url")) {
+        let after = rest[3..].trim_start_matches(is_space);
+        if let Some(after) = after.strip_prefix('=') {
+            url = after.trim_start_matches(is_space);
+        }
+    }
+
+    // optional quotes, the URL ends at the closing one
+    if let Some(quote) = url.chars().next().filter(|&c| c == '"' || c == '\'') {
+        url = &url[1..];
+        url = url.split(quote).next().unwrap_or(url);
+    }
+    let url = url.trim_matches(is_space);
+    Some((
+        Duration::from_secs(delay),
+        Some(url).filter(|url| !url.is_empty()),
+    ))
+}
+
+/// An icon declared with `<link rel="icon">` (or `apple-touch-icon`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconLink {
+    /// Resolved icon URL
+    pub href: Url,
+    /// Value of the `rel` attribute
+    pub rel: String,
+    /// Declared sizes (`sizes="16x16 32x32"`), empty if not declared or `any`
+    pub sizes: Vec<(u32, u32)>,
+    /// Declared MIME type
+    pub kind: Option<String>,
+}
+
+/// Metadata declared by the `meta`, `link` and `title` elements of a page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    /// `<meta name="description">`
+    pub description: Option<String>,
+    /// `<meta charset>` (or the charset of a `Content-Type` `http-equiv` meta)
+    pub charset: Option<String>,
+    /// `<meta name="viewport">`
+    pub viewport: Option<ViewportMeta>,
+    /// Open Graph `<meta property="og:...">` pairs, in document order
+    pub open_graph: Vec<(String, String)>,
+    /// Resolved `<link rel="canonical">` URL
+    pub canonical: Option<Url>,
+    /// Icon links, in document order
+    pub icons: Vec<IconLink>,
+    /// `<meta http-equiv="refresh">`
+    pub refresh: Option<MetaRefresh>,
+}
+
+/// Parse a `sizes` attribute (`16x16 32X32`).
+fn parse_sizes(sizes: &str) -> Vec<(u32, u32)> {
+    sizes
+        .split_ascii_whitespace()
+        .filter_map(|size| {
+            let (w, h) = size.split_once(['x', 'X'])?;
+            Some((w.parse().ok()?, h.parse().ok()?))
+        })
+        .collect()
+}
+
+impl Layout {
+    /// Title of the document: the whitespace-collapsed text of the first `title` element.
+    pub fn title(&self) -> Option<String> {
+        self.root_id()
+            .descendants(&self.arena)
+            .find(|&id| self.arena[id].get().name() == "title")
+            .map(|id| self.title_text(id))
+    }
+
+    fn title_text(&self, id: NodeId) -> String {
+        collapse_whitespace(self.text_content(id).trim()).into_owned()
+    }
+
+    /// Collect the page metadata from the `meta`, `link` and `title` elements of the document,
+    /// resolving URLs against `base`.
+    ///
+    /// The whole document is searched, so pages without a `head` work too. If a tag is
+    /// declared more than once, the first one wins (except Open Graph tags, which are all collected).
+    pub fn metadata(&self, base: &Url) -> PageMetadata {
+        let mut meta = PageMetadata::default();
+
+        for id in self.root_id().descendants(&self.arena) {
+            let node = self.arena[id].get();
+            match node.name() {
+                "title" if meta.title.is_none() => meta.title = Some(self.title_text(id)),
+                "meta" => {
+                    let content = node.attr("content").unwrap_or_default();
+                    if let Some(charset) = node.attr("charset") {
+                        meta.charset
+                            .get_or_insert_with(|| charset.trim().to_string());
+                    }
+                    if node
+                        .attr("http-equiv")
+                        .is_some_and(|h| h.eq_ignore_ascii_case("content-type"))
+                    {
+                        if let Some((_, charset)) = content.split_once("charset=") {
+                            meta.charset
+                                .get_or_insert_with(|| charset.trim().to_string());
+                        }
+                    }
+                    if node
+                        .attr("http-equiv")
+                        .is_some_and(|h| h.eq_ignore_ascii_case("refresh"))
+                        && meta.refresh.is_none()
+                    {
+                        meta.refresh = parse_refresh(content).and_then(|(delay, url)| {
+                            let url = match url.map(|url| resolve_fetchable_url(base, url)) {
+                                Some(Ok(url)) => Some(url),
+                                Some(Err(err)) => {
+                                    log::warn!("ignoring refresh to '{content}': {err}");
+                                    return None;
+                                }
+                                None => None,
+                            };
+                            Some(MetaRefresh { delay, url })
+                        });
+                    }
+                    if let Some(property) = node.attr("property") {
+                        if property.to_ascii_lowercase().starts_with("og:") {
+                            meta.open_graph
+                                .push((property.to_string(), content.to_string()));
+                        }
+                    }
+                    match node.attr("name").map(str::to_ascii_lowercase).as_deref() {
+                        Some("description") => {
+                            meta.description.get_or_insert_with(|| content.to_string());
+                        }
+                        Some("viewport") => {
+                            meta.viewport
+                                .get_or_insert_with(|| ViewportMeta::parse(content));
+                        }
+                        _ => {}
+                    }
+                }
+                "link" => {
+                    let (Some(rel), Some(href)) = (node.attr("rel"), node.attr("href")) else {
+                        continue;
+                    };
+                    let rels: Vec<String> = rel
+                        .split_ascii_whitespace()
+                        .map(str::to_ascii_lowercase)
+                        .collect();
+                    let Ok(href) = resolve_url(base, href) else {
+                        log::warn!("invalid link URL '{href}'");
+                        continue;
+                    };
+                    if rels.iter().any(|r| r == "canonical") && meta.canonical.is_none() {
+                        meta.canonical = Some(href.clone());
+                    }
+                    if rels.iter().any(|r| r == "icon" || r == "apple-touch-icon") {
+                        meta.icons.push(IconLink {
+                            href,
+                            rel: rel.to_string(),
+                            sizes: parse_sizes(node.attr("sizes").unwrap_or_default()),
+                            kind: node.attr("type").map(str::to_string),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        meta
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{ResourceTiming, Timers, WebContext};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// Receives the progress of the page loads of a [`WebContext`], for progress indicators
+/// or instrumentation. All callbacks do nothing by default.
+///
+/// For every page loaded, the callbacks are invoked in this order:
+/// 1. [`on_pull_start`](LoadObserver::on_pull_start)
+/// 2. [`on_pull_complete`](LoadObserver::on_pull_complete)
+/// 3. [`on_parse_complete`](LoadObserver::on_parse_complete)
+/// 4. [`on_subresource`](LoadObserver::on_subresource) for every stylesheet and image, in document order
+/// 5. [`on_layout_complete`](LoadObserver::on_layout_complete) for the layout with the subresources
+///
+/// then [`on_load_finished`](LoadObserver::on_load_finished) once the load succeeded (after the
+/// refreshes it followed). Layouts computed outside of loads also call
+/// [`on_layout_complete`](LoadObserver::on_layout_complete).
+///
+/// ```
+/// use dragonfly::{LoadObserver, ResourceTiming, Timers, WebContext};
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+/// use url::Url;
+/// # include!("../tests/support/doctest.rs");
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #[derive(Clone, Default)]
+/// struct Recorder(Arc<Mutex<Vec<String>>>);
+///
+/// impl Recorder {
+///     fn record(&self, event: String) {
+///         self.0.lock().unwrap().push(event);
+///     }
+/// }
+///
+/// impl LoadObserver for Recorder {
+///     fn on_pull_start(&self, url: &Url) {
+///         self.record(format!("pull start {}", url.path()));
+///     }
+///     fn on_pull_complete(&self, url: &Url, bytes: usize, _: Duration) {
+///         self.record(format!("pull complete {} {bytes}", url.path()));
+///     }
+///     fn on_parse_complete(&self, _: Duration, error_count: usize) {
+///         self.record(format!("parse complete {error_count}"));
+///     }
+///     fn on_subresource(&self, timing: &ResourceTiming) {
+///         let status = if timing.error.is_some() { "failed" } else { "ok" };
+///         self.record(format!("subresource {} {status}", timing.url.path()));
+///     }
+///     fn on_layout_complete(&self, _: Duration, _: usize) {
+///         self.record("layout complete".to_string());
+///     }
+///     fn on_load_finished(&self, _: &Timers) {
+///         self.record("load finished".to_string());
+///     }
+/// }
+///
+/// let page = "<!DOCTYPE html><link rel=stylesheet href=style.css><img src=missing.png alt=x>";
+/// let base = serve(move |path| match path {
+///     "/page" => Some(Response::ok("text/html", page)),
+///     "/style.css" => Some(Response::ok("text/css", "p { color: red }")),
+///     _ => None,
+/// });
+/// let recorder = Recorder::default();
+/// let mut ctx = WebContext::builder().url(&format!("{base}page")).build()?;
+/// ctx.set_load_observer(recorder.clone());
+/// block_on(ctx.load())?;
+/// assert_eq!(*recorder.0.lock().unwrap(), [
+///     "pull start /page".to_string(),
+///     format!("pull complete /page {}", page.len()),
+///     "parse complete 0".to_string(),
+///     "subresource /style.css ok".to_string(),
+///     "subresource /missing.png failed".to_string(),
+///     "layout complete".to_string(),
+///     "load finished".to_string(),
+/// ]);
+///
+/// // layouts outside of loads are reported too
+/// recorder.0.lock().unwrap().clear();
+/// ctx.set_viewport(400.0, 300.0);
+/// assert_eq!(*recorder.0.lock().unwrap(), ["layout complete"]);
+/// # Ok(())
+/// # }
+/// ```
+pub trait LoadObserver: Send + Sync {
+    /// The page is about to be pulled.
+    fn on_pull_start(&self, _url: &Url) {}
+
+    /// The page was pulled: its size in bytes and the time it took.
+    fn on_pull_complete(&self, _url: &Url, _bytes: usize, _duration: Duration) {}
+
+    /// The page was parsed: the time it took and the number of parser errors.
+    fn on_parse_complete(&self, _duration: Duration, _error_count: usize) {}
+
+    /// A subresource was pulled, or failed to be pulled (see [`ResourceTiming::error`]).
+    fn on_subresource(&self, _timing: &ResourceTiming) {}
+
+    /// A layout was computed: the time it took and the number of nodes in the layout tree.
+    fn on_layout_complete(&self, _duration: Duration, _node_count: usize) {}
+
+    /// The page was loaded.
+    fn on_load_finished(&self, _timers: &Timers) {}
+}
+
+/// Load observer shared by the clones of a context.
+#[derive(Clone)]
+pub(crate) struct SharedObserver(Arc<dyn LoadObserver>);
+
+impl SharedObserver {
+    pub(crate) fn new(observer: impl LoadObserver + 'static) -> Self {
+        Self(Arc::new(observer))
+    }
+}
+
+impl fmt::Debug for SharedObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LoadObserver")
+    }
+}
+
+impl WebContext {
+    /// Set the observer notified of the progress of page loads, replacing the previous one.
+    pub fn set_load_observer(&mut self, observer: impl LoadObserver + 'static) {
+        self.observer = Some(SharedObserver::new(observer));
+    }
+
+    /// Remove the load observer.
+    pub fn clear_load_observer(&mut self) {
+        self.observer = None;
+    }
+
+    /// Notify the load observer, if there is one.
+    pub(crate) fn observe(&self, f: impl FnOnce(&dyn LoadObserver)) {
+        if let Some(SharedObserver(observer)) = &self.observer {
+            f(observer.as_ref());
+        }
+    }
+}
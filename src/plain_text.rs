@@ -0,0 +1,211 @@
+use crate::{resolve_url, BoxKind, DOMNodeKind, Layout, ListStyleType, WebContext};
+use indextree::NodeId;
+use url::Url;
+
+/// Options of [`Layout::to_text`].
+#[derive(Debug, Clone)]
+pub struct TextOptions {
+    /// Prefix headings with `#` characters, one per level (`## Title` for `h2`)
+    pub heading_prefixes: bool,
+    /// Write the URL of links after their text: `text (url)`
+    pub link_urls: bool,
+    /// Marker of the items of unordered lists, ordered list items are numbered (`1. `)
+    pub bullet: String,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            heading_prefixes: true,
+            link_urls: false,
+            bullet: "- ".to_owned(),
+        }
+    }
+}
+
+/// Accumulates text, with line breaks only written before more text.
+struct TextWriter {
+    out: String,
+    /// Newlines to write before the next text
+    pending_lines: usize,
+    /// Inside a table cell, where blocks are separated by spaces
+    cell_depth: usize,
+}
+
+impl TextWriter {
+    /// Require a line break (1) or a blank line (2) before the next text.
+    fn line_break(&mut self, lines: usize) {
+        if self.cell_depth > 0 {
+            self.text(" ");
+        } else {
+            self.pending_lines = self.pending_lines.max(lines);
+        }
+    }
+
+    fn text(&mut self, mut text: &str) {
+        if self.pending_lines > 0 || self.out.is_empty() || self.out.ends_with([' ', '\t', '\n']) {
+            text = text.trim_start_matches(' ');
+        }
+        if !text.is_empty() {
+            self.flush_lines();
+            self.out.push_str(text);
+        }
+    }
+
+    /// Text written exactly, even at the start of a line (markers and tabs).
+    fn prefix(&mut self, prefix: &str) {
+        self.flush_lines();
+        self.out.push_str(prefix);
+    }
+
+    /// Write the pending line breaks.
+    fn flush_lines(&mut self) {
+        if self.pending_lines == 0 {
+            return;
+        }
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() {
+            let lines = self.out.len() - self.out.trim_end_matches('\n').len();
+            for _ in lines..self.pending_lines {
+                self.out.push('\n');
+            }
+        }
+        self.pending_lines = 0;
+    }
+
+    fn finish(self) -> String {
+        self.out.trim_end().to_owned()
+    }
+}
+
+impl Layout {
+    /// Readable plain text of the laid-out document, for terminals or text processing.
+    ///
+    /// Blocks are separated by blank lines, list items and table rows by line breaks, and
+    /// table cells by tabs. Hidden elements (`display: none`, `hidden`, scripts and styles)
+    /// are left out. Link URLs are resolved against `base`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use dragonfly::{parse_document, url::Url, FontManager, Layout, TextOptions};
+    /// let html = r#"<h1>Fruits</h1>
+    /// <p>Some <b>fresh</b>
+    ///    fruits, see <a href="/list">the list</a>.</p>
+    /// <ul><li>Apple</li><li>Pear</li></ul>
+    /// <ol start="3"><li>Third</li><li>Fourth</li></ol>
+    /// <table><tr><th>Name</th><th>Price</th></tr><tr><td>Apple</td><td><p>1</p></td></tr></table>
+    /// <div hidden>secret</div><script>let x = 1;</script>
+    /// <p>Bye<br>now</p>"#;
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let base = Url::parse("https://example.com/").unwrap();
+    /// let options = TextOptions { link_urls: true, ..Default::default() };
+    /// assert_eq!(
+    ///     layout.to_text(&base, &options),
+    ///     "# Fruits\n\n\
+    ///      Some fresh fruits, see the list (https://example.com/list).\n\n\
+    ///      - Apple\n- Pear\n\n\
+    ///      3. Third\n4. Fourth\n\n\
+    ///      Name\tPrice\nApple\t1\n\n\
+    ///      Bye\nnow"
+    /// );
+    /// ```
+    pub fn to_text(&self, base: &Url, options: &TextOptions) -> String {
+        let mut writer = TextWriter {
+            out: String::new(),
+            pending_lines: 0,
+            cell_depth: 0,
+        };
+        self.write_text(self.root_id(), base, options, &mut writer);
+        writer.finish()
+    }
+
+    fn write_text(&self, id: NodeId, base: &Url, options: &TextOptions, w: &mut TextWriter) {
+        let node = self.arena[id].get();
+        let kind = self.box_kind(id);
+        let element = match &node.kind {
+            DOMNodeKind::Text(text) => return w.text(&node.text_style().white_space.apply(text)),
+            DOMNodeKind::Comment(_) => return,
+            DOMNodeKind::Element(_) if kind == BoxKind::Hidden => return,
+            DOMNodeKind::Element(el) => el,
+        };
+
+        let name = element.name.as_str();
+        let is_block = matches!(kind, BoxKind::Block | BoxKind::Float(_) | BoxKind::Absolute);
+        match name {
+            "br" => return w.line_break(1),
+            "tr" => w.line_break(1),
+            "td" | "th" => {
+                if id.preceding_siblings(&self.arena).skip(1).any(|sibling| {
+                    matches!(self.arena[sibling].get().name(), "td" | "th")
+                        && self.box_kind(sibling) != BoxKind::Hidden
+                }) {
+                    w.prefix("\t");
+                }
+                w.cell_depth += 1;
+            }
+            "li" => {
+                w.line_break(1);
+                if let Some(marker) = self.list_marker(id, options) {
+                    w.prefix(&marker);
+                }
+            }
+            _ if is_block => w.line_break(2),
+            _ => (),
+        }
+        if options.heading_prefixes {
+            if let Some(level) = name
+                .strip_prefix('h')
+                .and_then(|level| level.parse::<usize>().ok())
+                .filter(|level| (1..=6).contains(level))
+            {
+                w.prefix(&format!("{} ", "#".repeat(level)));
+            }
+        }
+
+        for child in id.children(&self.arena) {
+            self.write_text(child, base, options, w);
+        }
+
+        match name {
+            "td" | "th" => w.cell_depth -= 1,
+            "tr" | "li" => w.line_break(1),
+            "a" if options.link_urls => {
+                if let Some(Ok(url)) = node.attr("href").map(|href| resolve_url(base, href)) {
+                    w.text(&format!(" ({url})"));
+                }
+            }
+            _ if is_block => w.line_break(2),
+            _ => (),
+        }
+    }
+
+    /// Marker of a list item: the bullet, or the counter of the item in its `list-style-type`.
+    fn list_marker(&self, id: NodeId, options: &TextOptions) -> Option<String> {
+        let list = self.arena[id].parent()?;
+        if !matches!(self.arena[list].get().name(), "ol" | "ul" | "menu") {
+            return None;
+        }
+        let style = self.arena[id].get().style.as_ref();
+        match style.and_then(|s| s.list_style_type).unwrap_or_default() {
+            ListStyleType::None => None,
+            kind => match kind.counter(self.list_item_number(id)) {
+                Some(counter) => Some(format!("{counter}. ")),
+                None => Some(options.bullet.clone()),
+            },
+        }
+    }
+}
+
+impl WebContext {
+    /// Readable plain text of the page, with the default [`TextOptions`] (see [`Layout::to_text`]).
+    pub fn to_text(&self) -> String {
+        self.to_text_with(&TextOptions::default())
+    }
+
+    /// Readable plain text of the page (see [`Layout::to_text`]).
+    pub fn to_text_with(&self, options: &TextOptions) -> String {
+        self.layout.to_text(self.base_url(), options)
+    }
+}
@@ -1,6 +1,9 @@
-use crate::DfResult;
-use bytes::Bytes;
+use crate::{percent_decode_bytes, prescan_charset, DfError, DfResult, DfResultExt, LoadPhase};
+use bytes::{Bytes, BytesMut};
+use encoding_rs::Encoding;
+use indexmap::IndexMap;
 use std::io::Read;
+use std::sync::{Arc, Mutex};
 use url::Url;
 
 #[derive(Debug, Clone)]
@@ -9,7 +12,119 @@ pub struct Puller {
     pub max_cache_size: usize,
     /// Whether to allow access to the OS filesystem through 'file://'
     pub allow_local_fs: bool,
-    // cache: HashMap<Url, Bytes>,
+    /// Maximum number of subresources pulled at the same time
+    pub max_concurrent_requests: usize,
+    /// Pulled resources, shared by the clones of the puller
+    cache: Arc<Mutex<Cache>>,
+}
+
+/// A resource pulled from a URL, with the response headers that describe it.
+#[derive(Debug, Clone, Default)]
+pub struct PulledResource {
+    pub data: Bytes,
+    /// `Content-Type` header
+    pub content_type: Option<String>,
+    /// First language of the `Content-Language` header
+    pub content_language: Option<String>,
+    /// Whether the resource was served from the cache
+    pub from_cache: bool,
+    /// URLs the request was redirected to, in order: the last one is the URL of the resource
+    pub redirects: Vec<Url>,
+}
+
+/// Body of a resource as it's received, kept up to a size.
+struct Body {
+    data: BytesMut,
+    /// Number of bytes received
+    received: usize,
+    /// Maximum size of the kept data
+    keep: usize,
+}
+
+impl Body {
+    fn new(keep: usize) -> Self {
+        Self {
+            data: BytesMut::new(),
+            received: 0,
+            keep,
+        }
+    }
+
+    /// Add a received chunk, dropping the data once it's bigger than the size it's kept up to.
+    fn push(&mut self, chunk: &[u8]) {
+        self.received += chunk.len();
+        match self.kept() {
+            true => self.data.extend_from_slice(chunk),
+            false => self.data = BytesMut::new(),
+        }
+    }
+
+    #[inline]
+    fn kept(&self) -> bool {
+        self.received <= self.keep
+    }
+}
+
+/// Page text pulled from a URL, with the response headers that describe the document.
+#[derive(Debug, Clone, Default)]
+pub struct PulledPage {
+    pub text: String,
+    /// First language of the `Content-Language` header
+    pub content_language: Option<String>,
+    /// Whether the page was served from the cache
+    pub from_cache: bool,
+}
+
+/// Progress of a pull, given with every chunk of data by [`Puller::pull_chunks`].
+#[derive(Debug, Clone, Copy)]
+pub struct PullProgress<'a> {
+    /// `Content-Type` header
+    pub content_type: Option<&'a str>,
+    /// Number of bytes received, including the current chunk
+    pub received: usize,
+    /// Size of the resource, if it's known
+    pub total: Option<usize>,
+}
+
+/// Statistics of the cache of a [`Puller`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    /// Number of cached resources
+    pub entries: usize,
+    /// Total size of the cached resources in bytes
+    pub size: usize,
+    /// Number of pulls served from the cache
+    pub hits: usize,
+    /// Number of pulls that were not in the cache
+    pub misses: usize,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    /// Oldest entries first
+    entries: IndexMap<Url, PulledResource>,
+    stats: CacheStats,
+}
+
+impl Cache {
+    /// Add a resource, evicting the oldest ones if the cache gets bigger than `max_size`.
+    fn insert(&mut self, url: Url, resource: PulledResource, max_size: usize) {
+        if resource.data.len() > max_size {
+            return;
+        }
+        if let Some(old) = self.entries.shift_remove(&url) {
+            self.stats.size -= old.data.len();
+        }
+        self.stats.size += resource.data.len();
+        self.entries.insert(url, resource);
+        while self.stats.size > max_size {
+            let Some((_, old)) = self.entries.shift_remove_index(0) else {
+                break;
+            };
+            self.stats.size -= old.data.len();
+        }
+        self.stats.entries = self.entries.len();
+    }
 }
 
 impl Default for Puller {
@@ -17,55 +132,443 @@ impl Default for Puller {
         Self {
             max_cache_size: Self::DEFAULT_MAX_CACHE_SIZE, // 1gb cache size
             allow_local_fs: true,
+            max_concurrent_requests: 6,
+            cache: Arc::default(),
+        }
+    }
+}
+
+/// Find the value of a `charset=` parameter, in a `Content-Type` header or the `content`
+/// attribute of a `meta` element (`text/html; charset="utf-8"`).
+pub(crate) fn extract_charset(s: &str) -> Option<&str> {
+    let lower = s.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find("charset") {
+        let rest = s[from + i + "charset".len()..].trim_start();
+        let Some(value) = rest.strip_prefix('=') else {
+            from += i + "charset".len();
+            continue;
+        };
+        let value = value.trim_start();
+        return match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next(),
+            _ => value
+                .split(|c: char| c.is_ascii_whitespace() || c == ';')
+                .next(),
+        }
+        .filter(|value| !value.is_empty());
+    }
+    None
+}
+
+/// Decode standard base64, ignoring ASCII whitespace. Returns `None` if the input is invalid.
+fn decode_base64(input: &[u8]) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let mut input: Vec<u8> = input
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    if input.len() % 4 == 0 {
+        for _ in 0..2 {
+            if input.last() == Some(&b'=') {
+                input.pop();
+            }
         }
     }
+    if input.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in input {
+        bits = bits << 6 | value(c)? as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Decode the content of a `data:` URL (`data:image/png;base64,...`), with its MIME type.
+/// Returns `None` if the URL is not a valid `data:` URL.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{decode_data_url, url::Url};
+/// let url = Url::parse("data:text/plain;base64,aGk/").unwrap();
+/// let resource = decode_data_url(&url).unwrap();
+/// assert_eq!(&resource.data[..], b"hi?");
+/// assert_eq!(resource.content_type.as_deref(), Some("text/plain"));
+/// let url = Url::parse("data:,a%20b").unwrap();
+/// assert_eq!(&decode_data_url(&url).unwrap().data[..], b"a b");
+/// ```
+pub fn decode_data_url(url: &Url) -> Option<PulledResource> {
+    if url.scheme() != "data" {
+        return None;
+    }
+    let (mime, data) = url[url::Position::BeforePath..].split_once(',')?;
+    let mime = mime.trim();
+    let (mime, base64) = match mime.len().checked_sub(";base64".len()) {
+        Some(i) if mime.is_char_boundary(i) && mime[i..].eq_ignore_ascii_case(";base64") => {
+            (mime[..i].trim_end(), true)
+        }
+        _ => (mime, false),
+    };
+    let data = percent_decode_bytes(data);
+    let data = match base64 {
+        true => decode_base64(&data)?,
+        false => data,
+    };
+    let content_type = match mime {
+        "" => "text/plain;charset=US-ASCII".to_string(),
+        _ if mime.starts_with(';') => format!("text/plain{mime}"),
+        _ => mime.to_string(),
+    };
+    Some(PulledResource {
+        data: data.into(),
+        content_type: Some(content_type),
+        ..Default::default()
+    })
+}
+
+/// Encoding of the charset of a `Content-Type` header, if it has a known one.
+pub fn content_type_charset(content_type: Option<&str>) -> Option<&'static Encoding> {
+    let charset = extract_charset(content_type?)?;
+    Encoding::for_label(charset.trim().as_bytes())
+}
+
+/// Encoding of the charset of a `Content-Type` header (UTF-8 if it's missing or unknown).
+pub fn content_type_encoding(content_type: Option<&str>) -> &'static Encoding {
+    content_type_charset(content_type).unwrap_or(encoding_rs::UTF_8)
+}
+
+/// Decode text with the charset of a `Content-Type` header (UTF-8 if it's missing or unknown).
+/// A byte order mark overrides the charset.
+pub fn decode_text(data: &[u8], content_type: Option<&str>) -> String {
+    content_type_encoding(content_type)
+        .decode(data)
+        .0
+        .into_owned()
 }
 
 impl Puller {
     /// Default cache size limit: 1GB
     const DEFAULT_MAX_CACHE_SIZE: usize = 1024 * 1024 * 1024;
+    /// Size of the chunks local files are read in
+    const CHUNK_SIZE: usize = 64 * 1024;
+    /// Size of the pages that are kept (and cached) when they're parsed as they're pulled.
+    /// Bigger pages are only given chunk by chunk.
+    pub(crate) const MAX_STREAMED_LEN: usize = 1024 * 1024;
+    /// Maximum number of redirects followed by a request
+    pub const MAX_REDIRECTS: usize = 10;
+
+    /// Make an http request, following redirects. Returns the response and the URLs the
+    /// request was redirected to. Responses that are not successful (and not redirects) are
+    /// [`DfError::HttpStatus`] errors.
+    async fn make_request(&self, mut url: Url) -> DfResult<(reqwest::Response, Vec<Url>)> {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        let mut redirects = vec![];
+        loop {
+            log::info!("pulling '{url}', scheme '{}'", url.scheme());
+            let response = client.get(url.clone()).send().await?;
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .filter(|_| response.status().is_redirection());
+            let Some(location) = location else {
+                // error pages are not the resource
+                if !response.status().is_success() {
+                    return Err(DfError::HttpStatus {
+                        url: url.to_string(),
+                        status: response.status().as_u16(),
+                    });
+                }
+                return Ok((response, redirects));
+            };
+            if redirects.len() == Self::MAX_REDIRECTS {
+                return Err(DfError::TooManyRedirects(url.to_string()));
+            }
 
-    /// Make an http request
-    async fn make_request(&self, url: Url) -> DfResult<reqwest::Response> {
-        log::info!("pulling '{url}', scheme '{}'", url.scheme());
+            let mut target = url.join(location)?;
+            if !matches!(target.scheme(), "http" | "https") {
+                return Err(DfError::UnfetchableUrl(target.to_string()));
+            }
+            // the fragment is carried to a target without one
+            if target.fragment().is_none() {
+                target.set_fragment(url.fragment());
+            }
+            log::info!("redirected to '{target}'");
+            redirects.push(target.clone());
+            url = target;
+        }
+    }
 
-        // make http request
-        Ok(reqwest::get(url.clone()).await?)
+    /// Pull a resource from a URL. Resources are served from the cache if they were pulled before,
+    /// unless `bypass_cache` is set.
+    pub async fn pull(&mut self, url: Url, bypass_cache: bool) -> DfResult<PulledResource> {
+        self.pull_chunks(url, bypass_cache, |_, _| {}).await
     }
 
-    /// Read a local file and return its contents as a [`Bytes`]
-    fn read_local_file(&self, path: &str) -> DfResult<Bytes> {
-        log::info!("reading local file '{}'", path);
-        let mut f = std::fs::File::open(path)?;
-        let mut buf: Vec<u8> = vec![];
-        f.read_to_end(&mut buf)?;
-        Ok(buf.into())
+    /// Pull a resource from a URL like [`Puller::pull`], calling `on_chunk` with every chunk of
+    /// data as it arrives.
+    pub async fn pull_chunks(
+        &mut self,
+        url: Url,
+        bypass_cache: bool,
+        on_chunk: impl FnMut(&[u8], &PullProgress),
+    ) -> DfResult<PulledResource> {
+        let resource = self
+            .pull_limited(url, bypass_cache, usize::MAX, usize::MAX, on_chunk)
+            .await?;
+        Ok(resource.unwrap_or_default())
     }
 
-    /// Read a local file to a [`String`]
-    fn read_local_file_str(&self, path: &str) -> DfResult<String> {
-        log::info!("reading local file '{}' to string", path);
-        let mut f = std::fs::File::open(path)?;
-        let mut buf = String::new();
-        f.read_to_string(&mut buf)?;
-        Ok(buf)
+    /// Pull a resource like [`Puller::pull_chunks`], stopping once more than `limit` bytes are
+    /// received. Returns `None` if the resource is bigger than `limit`: `on_chunk` was called
+    /// with its first `limit` bytes.
+    ///
+    /// The data of a resource bigger than `keep` bytes is only given to `on_chunk`: the
+    /// returned resource has no data and it's not cached.
+    ///
+    /// Errors are wrapped with the URL of the resource (see [`DfError::Resource`]).
+    pub(crate) async fn pull_limited(
+        &mut self,
+        url: Url,
+        bypass_cache: bool,
+        limit: usize,
+        keep: usize,
+        on_chunk: impl FnMut(&[u8], &PullProgress),
+    ) -> DfResult<Option<PulledResource>> {
+        let requested = url.clone();
+        let pull = self.pull_resource(url, bypass_cache, limit, keep, on_chunk);
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "pull",
+            url = %requested,
+            bytes = tracing::field::Empty,
+            from_cache = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let pull = tracing::Instrument::instrument(pull, span.clone());
+        let result = pull.await;
+        #[cfg(feature = "tracing")]
+        if let Ok(Some(resource)) = &result {
+            span.record("bytes", resource.data.len());
+            span.record("from_cache", resource.from_cache);
+        }
+        result.context(LoadPhase::Pull, &requested)
     }
 
-    /// Pull bytes from a URL as a [`Bytes`]
-    pub async fn pull_bytes(&mut self, url: Url) -> DfResult<Bytes> {
-        if url.scheme() == "file" && self.allow_local_fs {
-            self.read_local_file(url.path().trim_start_matches('/')) // trim starting slashes
+    async fn pull_resource(
+        &mut self,
+        url: Url,
+        bypass_cache: bool,
+        limit: usize,
+        keep: usize,
+        mut on_chunk: impl FnMut(&[u8], &PullProgress),
+    ) -> DfResult<Option<PulledResource>> {
+        // give the chunks up to the limit, returns whether the limit was exceeded
+        let mut receive = |chunk: &[u8], progress: &PullProgress| {
+            let before = progress.received - chunk.len();
+            let allowed = chunk.len().min(limit.saturating_sub(before));
+            on_chunk(&chunk[..allowed], progress);
+            progress.received > limit
+        };
+
+        // data: URLs carry their content, they are not cached
+        if url.scheme() == "data" {
+            let resource =
+                decode_data_url(&url).ok_or_else(|| DfError::InvalidDataUrl(url.to_string()))?;
+            let progress = PullProgress {
+                content_type: resource.content_type.as_deref(),
+                received: resource.data.len(),
+                total: Some(resource.data.len()),
+            };
+            return Ok((!receive(&resource.data, &progress)).then_some(resource));
+        }
+
+        if !bypass_cache {
+            let cached = {
+                let mut cache = self.cache();
+                let cached = cache.entries.get(&url).cloned();
+                match cached {
+                    Some(_) => cache.stats.hits += 1,
+                    None => cache.stats.misses += 1,
+                }
+                cached
+            };
+            if let Some(resource) = cached {
+                log::info!("pulled '{url}' from cache");
+                let progress = PullProgress {
+                    content_type: resource.content_type.as_deref(),
+                    received: resource.data.len(),
+                    total: Some(resource.data.len()),
+                };
+                if receive(&resource.data, &progress) {
+                    return Ok(None);
+                }
+                return Ok(Some(PulledResource {
+                    from_cache: true,
+                    ..resource
+                }));
+            }
+        }
+
+        let kept;
+        let resource = if url.scheme() == "file" && self.allow_local_fs {
+            let path = url.path().trim_start_matches('/'); // trim starting slashes
+            log::info!("reading local file '{}'", path);
+            let mut f = std::fs::File::open(path)?;
+            let total = f.metadata().ok().map(|m| m.len() as usize);
+            let mut body = Body::new(keep);
+            let mut buf = vec![0; Self::CHUNK_SIZE];
+            loop {
+                let len = f.read(&mut buf)?;
+                if len == 0 {
+                    break;
+                }
+                body.push(&buf[..len]);
+                let progress = PullProgress {
+                    content_type: None,
+                    received: body.received,
+                    total,
+                };
+                if receive(&buf[..len], &progress) {
+                    return Ok(None);
+                }
+            }
+            kept = body.kept();
+            PulledResource {
+                data: body.data.freeze(),
+                ..Default::default()
+            }
         } else {
-            Ok(self.make_request(url).await?.bytes().await?)
+            let (mut response, redirects) = self.make_request(url.clone()).await?;
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                    .map(str::to_string)
+            };
+            let content_type = header(reqwest::header::CONTENT_TYPE);
+            let content_language = header(reqwest::header::CONTENT_LANGUAGE)
+                .and_then(|value| value.split(',').next().map(|lang| lang.trim().to_string()))
+                .filter(|lang| !lang.is_empty());
+            let total = response.content_length().map(|len| len as usize);
+            let mut body = Body::new(keep);
+            while let Some(chunk) = response.chunk().await? {
+                body.push(&chunk);
+                let progress = PullProgress {
+                    content_type: content_type.as_deref(),
+                    received: body.received,
+                    total,
+                };
+                if receive(&chunk, &progress) {
+                    return Ok(None);
+                }
+            }
+            kept = body.kept();
+            PulledResource {
+                data: body.data.freeze(),
+                content_type,
+                content_language,
+                from_cache: false,
+                redirects,
+            }
+        };
+
+        if !kept {
+            log::info!("not caching '{url}': only its chunks were kept");
+            return Ok(Some(resource));
+        }
+        let mut cache = self.cache();
+        // a redirected resource is also pulled from the cache at its final URL
+        if let Some(final_url) = resource.redirects.last() {
+            let redirected = PulledResource {
+                redirects: vec![],
+                ..resource.clone()
+            };
+            cache.insert(final_url.clone(), redirected, self.max_cache_size);
         }
+        cache.insert(url, resource.clone(), self.max_cache_size);
+        Ok(Some(resource))
+    }
+
+    /// Pull bytes from a URL as a [`Bytes`]
+    pub async fn pull_bytes(&mut self, url: Url) -> DfResult<Bytes> {
+        Ok(self.pull(url, false).await?.data)
     }
 
     /// Pull a [`String`] from a URL
     pub async fn pull_str(&mut self, url: Url) -> DfResult<String> {
-        if url.scheme() == "file" && self.allow_local_fs {
-            self.read_local_file_str(url.path().trim_start_matches('/')) // trim starting slashes
-        } else {
-            Ok(self.make_request(url).await?.text().await?)
-        }
+        let resource = self.pull(url, false).await?;
+        Ok(decode_text(
+            &resource.data,
+            resource.content_type.as_deref(),
+        ))
+    }
+
+    /// Pull a page from a URL, keeping the headers that describe the document. The page is decoded
+    /// with the charset of the `Content-Type` header, or the one declared by a `<meta charset>`.
+    pub async fn pull_page(&mut self, url: Url, bypass_cache: bool) -> DfResult<PulledPage> {
+        let resource = self.pull(url, bypass_cache).await?;
+        let encoding = content_type_charset(resource.content_type.as_deref())
+            .or_else(|| prescan_charset(&resource.data))
+            .unwrap_or(encoding_rs::UTF_8);
+        Ok(PulledPage {
+            text: encoding.decode(&resource.data).0.into_owned(),
+            content_language: resource.content_language,
+            from_cache: resource.from_cache,
+        })
+    }
+
+    /// The cache, locked. A panic while it was locked doesn't make it unusable: it only
+    /// holds copies of resources, so the next loads keep using it.
+    fn cache(&self) -> std::sync::MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(|err| {
+            self.cache.clear_poison();
+            err.into_inner()
+        })
+    }
+
+    /// Statistics of the cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache().stats
+    }
+
+    /// Remove all resources from the cache.
+    pub fn clear_cache(&mut self) {
+        let mut cache = self.cache();
+        cache.entries.clear();
+        cache.stats.entries = 0;
+        cache.stats.size = 0;
+    }
+
+    /// Cached resource of a URL, without pulling it or counting a cache hit.
+    #[cfg(feature = "serde")]
+    pub(crate) fn cached(&self, url: &Url) -> Option<PulledResource> {
+        self.cache().entries.get(url).cloned()
+    }
+
+    /// Add a resource to the cache, as if it was pulled from `url`.
+    #[cfg(feature = "serde")]
+    pub(crate) fn insert_cached(&self, url: Url, resource: PulledResource) {
+        let mut cache = self.cache();
+        cache.insert(url, resource, self.max_cache_size);
     }
 }
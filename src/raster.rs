@@ -0,0 +1,531 @@
+use crate::{
+    inner_radii, Decoration, DfResult, DisplayItem, FontManager, Pos2, Rect, Sides, Vec2,
+    WebContext,
+};
+use css_color::Srgb;
+
+/// Options of [`WebContext::render_to_rgba_with`].
+#[derive(Debug, Clone)]
+pub struct RasterOptions {
+    /// Render the whole page instead of the viewport, when the page is taller
+    pub full_page: bool,
+    /// Color of the canvas below the page, opaque white by default
+    pub background: Srgb,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self {
+            full_page: false,
+            background: Srgb::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// An image with 8-bit RGBA pixels, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    fn new(width: u32, height: u32, background: Srgb) -> Self {
+        let pixel = [
+            channel(background.red),
+            channel(background.green),
+            channel(background.blue),
+            channel(background.alpha),
+        ];
+        Self {
+            width,
+            height,
+            data: pixel.repeat(width as usize * height as usize),
+        }
+    }
+
+    /// RGBA value of a pixel, `None` outside of the image.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        Some(self.data[i..i + 4].try_into().unwrap())
+    }
+
+    /// Blend a color over a pixel, with a coverage from 0 to 1. Pixels outside of the
+    /// image are clipped.
+    fn blend(&mut self, x: i64, y: i64, color: Srgb, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let alpha = (color.alpha * coverage).clamp(0.0, 1.0);
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let pixel = &mut self.data[i..i + 4];
+        for (dst, src) in pixel.iter_mut().zip([color.red, color.green, color.blue]) {
+            let dst_value = *dst as f32 / 255.0;
+            *dst = channel(src * alpha + dst_value * (1.0 - alpha));
+        }
+        let dst_alpha = pixel[3] as f32 / 255.0;
+        pixel[3] = channel(alpha + dst_alpha * (1.0 - alpha));
+    }
+
+    /// Fill the pixels of a rectangle that are inside a shape (given the pixel center).
+    fn fill_shape(&mut self, pos: Pos2, size: Vec2, color: Srgb, inside: impl Fn(Pos2) -> bool) {
+        self.fill_coverage(pos, size, color, |p| if inside(p) { 1.0 } else { 0.0 });
+    }
+
+    /// Fill the pixels of a rectangle with a coverage from 0 to 1 (given the pixel center).
+    fn fill_coverage(
+        &mut self,
+        pos: Pos2,
+        size: Vec2,
+        color: Srgb,
+        coverage: impl Fn(Pos2) -> f32,
+    ) {
+        let (x0, y0) = (pos.x.round() as i64, pos.y.round() as i64);
+        let (x1, y1) = (
+            (pos.x + size.x).round() as i64,
+            (pos.y + size.y).round() as i64,
+        );
+        let clip = |v: i64, max: u32| v.clamp(0, max as i64);
+        for y in clip(y0, self.height)..clip(y1, self.height) {
+            for x in clip(x0, self.width)..clip(x1, self.width) {
+                let coverage = coverage(Pos2::new(x as f32 + 0.5, y as f32 + 0.5));
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Composite a layer of the same size over the image with an `opacity` from 0 to 1, only
+    /// inside of the `clip` rectangle if there's one. Layers start transparent, so the colors
+    /// they're painted with are premultiplied by their alpha.
+    fn composite(&mut self, layer: &RgbaImage, opacity: f32, clip: Option<Rect>) {
+        let width = self.width as usize;
+        for (i, (dst, src)) in self
+            .data
+            .chunks_exact_mut(4)
+            .zip(layer.data.chunks_exact(4))
+            .enumerate()
+        {
+            let center = Pos2::new((i % width) as f32 + 0.5, (i / width) as f32 + 0.5);
+            if clip.is_some_and(|clip| !clip.contains(center)) {
+                continue;
+            }
+            let value = |v: u8| v as f32 / 255.0;
+            let alpha = value(src[3]) * opacity;
+            for i in 0..3 {
+                dst[i] = channel(value(src[i]) * opacity + value(dst[i]) * (1.0 - alpha));
+            }
+            dst[3] = channel(alpha + value(dst[3]) * (1.0 - alpha));
+        }
+    }
+
+    /// Fill a rectangle, rounded to whole pixels.
+    fn fill_rect(&mut self, pos: Pos2, size: Vec2, color: Srgb) {
+        let (x0, y0) = (pos.x.round() as i64, pos.y.round() as i64);
+        let (x1, y1) = (
+            (pos.x + size.x).round() as i64,
+            (pos.y + size.y).round() as i64,
+        );
+        let clip = |v: i64, max: u32| v.clamp(0, max as i64);
+        for y in clip(y0, self.height)..clip(y1, self.height) {
+            for x in clip(x0, self.width)..clip(x1, self.width) {
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    /// Encode the image as a PNG file (uncompressed).
+    ///
+    /// ```
+    /// use dragonfly::RgbaImage;
+    /// let image = RgbaImage { width: 1, height: 1, data: vec![255, 0, 0, 255] };
+    /// let png = image.to_png();
+    /// assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    /// assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    /// ```
+    pub fn to_png(&self) -> Vec<u8> {
+        // rows start with their filter type (none)
+        let row_len = self.width as usize * 4;
+        let mut raw = Vec::with_capacity((row_len + 1) * self.height as usize);
+        for row in self.data.chunks(row_len.max(1)).take(self.height as usize) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        // zlib stream of stored deflate blocks
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+        if blocks.peek().is_none() {
+            zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+        }
+        while let Some(block) = blocks.next() {
+            let len = block.len() as u16;
+            zlib.push(blocks.peek().is_none() as u8);
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut ihdr = vec![];
+        ihdr.extend_from_slice(&self.width.to_be_bytes());
+        ihdr.extend_from_slice(&self.height.to_be_bytes());
+        // 8-bit RGBA, deflate, no filtering, no interlacing
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in [(b"IHDR", ihdr), (b"IDAT", zlib), (b"IEND", vec![])] {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend_from_slice(kind);
+            png.extend_from_slice(&data);
+            let crc = crc32(&png[start..]);
+            png.extend_from_slice(&crc.to_be_bytes());
+        }
+        png
+    }
+}
+
+/// Convert a color channel from 0-1 to 0-255.
+fn channel(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Rectangles of the sides of a border `widths` wide inside a rectangle, in the order top,
+/// right, bottom, left.
+fn side_rects(pos: Pos2, size: Vec2, widths: Sides<f32>) -> impl Iterator<Item = (Pos2, Vec2)> {
+    let max = pos + size;
+    [
+        (pos, Vec2::new(size.x, widths.top)),
+        (
+            Pos2::new(max.x - widths.right, pos.y),
+            Vec2::new(widths.right, size.y),
+        ),
+        (
+            Pos2::new(pos.x, max.y - widths.bottom),
+            Vec2::new(size.x, widths.bottom),
+        ),
+        (pos, Vec2::new(widths.left, size.y)),
+    ]
+    .into_iter()
+}
+
+/// Whether a point is inside a rectangle with elliptical corners (top-left, top-right,
+/// bottom-right, bottom-left).
+fn in_rounded_rect(p: Pos2, pos: Pos2, size: Vec2, radii: &[Vec2; 4]) -> bool {
+    let max = pos + size;
+    if p.x < pos.x || p.y < pos.y || p.x >= max.x || p.y >= max.y {
+        return false;
+    }
+    // centers of the corner ellipses
+    let centers = [
+        Pos2::new(pos.x + radii[0].x, pos.y + radii[0].y),
+        Pos2::new(max.x - radii[1].x, pos.y + radii[1].y),
+        Pos2::new(max.x - radii[2].x, max.y - radii[2].y),
+        Pos2::new(pos.x + radii[3].x, max.y - radii[3].y),
+    ];
+    let in_corner = [
+        p.x < centers[0].x && p.y < centers[0].y,
+        p.x > centers[1].x && p.y < centers[1].y,
+        p.x > centers[2].x && p.y > centers[2].y,
+        p.x < centers[3].x && p.y > centers[3].y,
+    ];
+    (0..4).all(|i| {
+        let radius = radii[i];
+        if !in_corner[i] || radius.x <= 0.0 || radius.y <= 0.0 {
+            return true;
+        }
+        let d = p - centers[i];
+        (d.x / radius.x).powi(2) + (d.y / radius.y).powi(2) <= 1.0
+    })
+}
+
+/// Add `d` to both components of a size, without making them negative.
+fn grow(size: Vec2, d: f32) -> Vec2 {
+    Vec2::new((size.x + d).max(0.0), (size.y + d).max(0.0))
+}
+
+/// Coverage of a point by a shadow shape: a rounded rectangle with edges blurred over `blur`
+/// pixels. The blur is approximated by a linear ramp across each edge, and ignores the corners.
+fn shadow_coverage(p: Pos2, pos: Pos2, size: Vec2, radii: &[Vec2; 4], blur: f32) -> f32 {
+    if blur <= 0.0 {
+        return in_rounded_rect(p, pos, size, radii) as u8 as f32;
+    }
+    let ramp = |v: f32, min: f32, max: f32| {
+        let edge = |d: f32| (d / (2.0 * blur) + 0.5).clamp(0.0, 1.0);
+        edge(v - min) * edge(max - v)
+    };
+    let max = pos + size;
+    ramp(p.x, pos.x, max.x) * ramp(p.y, pos.y, max.y)
+}
+
+/// Paint a display list on an image.
+fn paint(image: &mut RgbaImage, items: &[DisplayItem], fonts: &mut FontManager) {
+    // images painted under the open opacity groups and clips, which are painted on layers of
+    // their own
+    let mut groups = vec![];
+    let transparent = Srgb::new(0.0, 0.0, 0.0, 0.0);
+    for item in items {
+        match item {
+            DisplayItem::PushOpacity { opacity, .. } => {
+                let layer = RgbaImage::new(image.width, image.height, transparent);
+                groups.push((std::mem::replace(image, layer), *opacity, None));
+            }
+            DisplayItem::PushClip { rect, .. } => {
+                let layer = RgbaImage::new(image.width, image.height, transparent);
+                groups.push((std::mem::replace(image, layer), 1.0, Some(*rect)));
+            }
+            DisplayItem::PopOpacity { .. } | DisplayItem::PopClip { .. } => {
+                if let Some((mut below, opacity, clip)) = groups.pop() {
+                    below.composite(image, opacity, clip);
+                    *image = below;
+                }
+            }
+            DisplayItem::Rect {
+                pos,
+                size,
+                radii,
+                color,
+                ..
+            } if radii.iter().any(|r| r.x > 0.0 && r.y > 0.0) => {
+                image.fill_shape(*pos, *size, *color, |p| {
+                    in_rounded_rect(p, *pos, *size, radii)
+                })
+            }
+            DisplayItem::Rect {
+                pos, size, color, ..
+            } => image.fill_rect(*pos, *size, *color),
+            // every style is drawn solid, each pixel of rounded borders has the color of the
+            // side it's next to
+            DisplayItem::Border {
+                pos,
+                size,
+                radii,
+                widths,
+                colors,
+                ..
+            } if radii.iter().any(|r| r.x > 0.0 && r.y > 0.0) => {
+                let inner_pos = Pos2::new(pos.x + widths.left, pos.y + widths.top);
+                let inner_size = Vec2::new(
+                    (size.x - widths.horizontal()).max(0.0),
+                    (size.y - widths.vertical()).max(0.0),
+                );
+                let inner_radii = inner_radii(*radii, *widths);
+                let in_border = |p: Pos2| {
+                    in_rounded_rect(p, *pos, *size, radii)
+                        && !in_rounded_rect(p, inner_pos, inner_size, &inner_radii)
+                };
+                let max = *pos + *size;
+                let sides = [
+                    (colors.top, 0),
+                    (colors.right, 1),
+                    (colors.bottom, 2),
+                    (colors.left, 3),
+                ];
+                for (color, side) in sides {
+                    image.fill_shape(*pos, *size, color, |p| {
+                        let side_of = match p {
+                            p if p.y < inner_pos.y => 0,
+                            p if p.y >= max.y - widths.bottom => 2,
+                            p if p.x < inner_pos.x => 3,
+                            _ => 1,
+                        };
+                        side_of == side && in_border(p)
+                    });
+                }
+            }
+            // every style is drawn solid, the corners are not mitered
+            DisplayItem::Border {
+                pos,
+                size,
+                widths,
+                colors,
+                ..
+            } => {
+                for ((pos, size), color) in side_rects(*pos, *size, *widths).zip(*colors) {
+                    image.fill_rect(pos, size, color);
+                }
+            }
+            DisplayItem::BoxShadow {
+                pos,
+                size,
+                radii,
+                offset,
+                blur,
+                spread,
+                color,
+                inset: false,
+                ..
+            } => {
+                let shadow_pos = *pos + *offset - Vec2::new(*spread, *spread);
+                let shadow_size = grow(*size, 2.0 * spread);
+                let shadow_radii = radii.map(|r| match r.x > 0.0 && r.y > 0.0 {
+                    true => grow(r, *spread),
+                    false => r,
+                });
+                let area_pos = shadow_pos - Vec2::new(*blur, *blur);
+                let area_size = grow(shadow_size, 2.0 * blur);
+                image.fill_coverage(area_pos, area_size, *color, |p| {
+                    match in_rounded_rect(p, *pos, *size, radii) {
+                        true => 0.0,
+                        false => shadow_coverage(p, shadow_pos, shadow_size, &shadow_radii, *blur),
+                    }
+                });
+            }
+            DisplayItem::BoxShadow {
+                pos,
+                size,
+                radii,
+                offset,
+                blur,
+                spread,
+                color,
+                inset: true,
+                ..
+            } => {
+                let hole_pos = *pos + *offset + Vec2::new(*spread, *spread);
+                let hole_size = grow(*size, -2.0 * spread);
+                let hole_radii = radii.map(|r| grow(r, -spread));
+                image.fill_coverage(*pos, *size, *color, |p| {
+                    match in_rounded_rect(p, *pos, *size, radii) {
+                        true => 1.0 - shadow_coverage(p, hole_pos, hole_size, &hole_radii, *blur),
+                        false => 0.0,
+                    }
+                });
+            }
+            DisplayItem::Outline {
+                pos,
+                size,
+                width,
+                color,
+                ..
+            } => {
+                for (pos, size) in side_rects(*pos, *size, Sides::splat(*width)) {
+                    image.fill_rect(pos, size, *color);
+                }
+            }
+            // drawn by the embedder
+            DisplayItem::BackgroundImage { .. } | DisplayItem::Replaced { .. } => (),
+            #[cfg(feature = "images")]
+            DisplayItem::Image { .. } => (),
+            DisplayItem::Text {
+                pos,
+                size,
+                baseline,
+                text,
+                style,
+                ..
+            } => {
+                let family = style.font_family.clone();
+                let (weight, font_style) = (style.font_weight, style.font_style);
+                let metrics = fonts.line_metrics(style.font_size, family, weight, font_style);
+                let bitmap = fonts.rasterize_text(text, style);
+                let baseline = pos.y + baseline;
+                let left = pos.x.round() as i64;
+                let top = baseline.round() as i64 - bitmap.ascent as i64;
+                for (i, &coverage) in bitmap.coverage.iter().enumerate() {
+                    if coverage > 0 {
+                        let (x, y) = ((i % bitmap.width) as i64, (i / bitmap.width) as i64);
+                        image.blend(left + x, top + y, style.color, coverage as f32 / 255.0);
+                    }
+                }
+
+                // dotted, dashed and wavy lines are drawn solid
+                let thickness = Decoration::thickness(style.font_size);
+                for decoration in &style.decorations {
+                    for offset in decoration.offsets(style.font_size, metrics.ascent) {
+                        let pos = Pos2::new(pos.x, (baseline + offset).round());
+                        let size = Vec2::new(size.x, thickness);
+                        image.fill_rect(pos, size, decoration.color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl WebContext {
+    /// Render the viewport of the page with the default [`RasterOptions`]
+    /// (see [`WebContext::render_to_rgba_with`]).
+    pub fn render_to_rgba(&mut self) -> DfResult<RgbaImage> {
+        self.render_to_rgba_with(&RasterOptions::default())
+    }
+
+    /// Render the page with a software rasterizer: background colors, borders and text are painted,
+    /// replaced elements (images) are not. The image has the size of the viewport in
+    /// device pixels, or the height of the page if `full_page` is set and it's taller.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// let html = "<style>div { background-color: red; height: 40px; }</style>\
+    ///             <div></div><p>Some text</p>";
+    /// let mut ctx = WebContext::builder()
+    ///     .html(html)
+    ///     .viewport(200.0, 100.0)
+    ///     .scale_factor(2.0)
+    ///     .build()?;
+    /// ctx.set_html(html, None)?;
+    /// let image = ctx.render_to_rgba()?;
+    /// assert_eq!((image.width, image.height), (400, 200));
+    /// // body margin, then the div
+    /// assert_eq!(image.pixel(10, 4), Some([255, 255, 255, 255]));
+    /// assert_eq!(image.pixel(200, 50), Some([255, 0, 0, 255]));
+    /// // the text of the paragraph, below the div
+    /// let painted = (0..200)
+    ///     .flat_map(|x| (100..200).map(move |y| (x, y)))
+    ///     .filter(|&(x, y)| image.pixel(x, y) != Some([255, 255, 255, 255]))
+    ///     .count();
+    /// assert!(painted > 100);
+    /// # Ok::<(), dragonfly::DfError>(())
+    /// ```
+    pub fn render_to_rgba_with(&mut self, options: &RasterOptions) -> DfResult<RgbaImage> {
+        self.try_document()?;
+        let viewport = self.viewport();
+        let mut size = viewport.size * viewport.scale_factor;
+        if options.full_page {
+            let root = self.layout.arena[self.layout.root_id()].get();
+            size.y = size.y.max(root.border_rect.max().y);
+        }
+
+        let items = self.display_list();
+        let (width, height) = (size.x.ceil() as u32, size.y.ceil() as u32);
+        let mut image = RgbaImage::new(width, height, options.background);
+        paint(&mut image, &items, &mut self.font_manager);
+        Ok(image)
+    }
+
+    /// Render the viewport of the page (see [`WebContext::render_to_rgba`]) and encode
+    /// it as a PNG file.
+    pub fn render_to_png_bytes(&mut self) -> DfResult<Vec<u8>> {
+        Ok(self.render_to_rgba()?.to_png())
+    }
+}
@@ -0,0 +1,471 @@
+use crate::clock::Instant;
+use crate::{
+    decode_text, resolve_fetchable_url, BackgroundImage, Budget, CancellationToken, DfError,
+    DfResult, GlobalStyle, Layout, LoadError, LoadPhase, PulledResource, Vec2, WebContext,
+};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use indextree::NodeId;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::time::Duration;
+use strum_macros::Display;
+use url::Url;
+
+/// Kind of a resource referenced by a page.
+#[derive(Debug, Clone, Copy, PartialEq, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[strum(serialize_all = "lowercase")]
+pub enum ResourceKind {
+    Stylesheet,
+    Image,
+    /// Image of a `background` or `background-image` property
+    #[strum(serialize = "background image")]
+    #[cfg_attr(feature = "serde", serde(rename = "background-image"))]
+    BackgroundImage,
+    Script,
+}
+
+/// A resource referenced by an element of the page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subresource {
+    /// Element that references the resource (`link`, `img` or `script`), or the first element
+    /// styled with a background image
+    pub node: NodeId,
+    pub kind: ResourceKind,
+    /// Resolved URL
+    pub url: Url,
+}
+
+/// Timing of a subresource fetch.
+///
+/// ```
+/// use dragonfly::{LoadObserver, ResourceKind, WebContext};
+/// use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+/// use std::time::Duration;
+/// # include!("../tests/support/doctest.rs");
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// struct Layouts(Arc<AtomicUsize>);
+/// impl LoadObserver for Layouts {
+///     fn on_layout_complete(&self, _: Duration, _: usize) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let html = "<link rel=stylesheet href=test.css><h1>title</h1><img src=pixel.png>";
+/// let mut ctx = WebContext::builder().html(html).url("file:///tests/page.html").build()?;
+/// let layouts = Arc::new(AtomicUsize::new(0));
+/// ctx.set_load_observer(Layouts(layouts.clone()));
+/// block_on(ctx.load())?;
+///
+/// let resources = &ctx.timers.resources;
+/// let kinds: Vec<_> = resources.iter().map(|r| (r.kind, r.url.path())).collect();
+/// assert_eq!(kinds, [(ResourceKind::Stylesheet, "/tests/test.css"), (ResourceKind::Image, "/tests/pixel.png")]);
+/// assert!(resources.iter().all(|r| r.size > 0 && !r.from_cache && r.error.is_none()));
+/// // the layout is computed once, with the subresources
+/// assert_eq!(layouts.load(Ordering::Relaxed), 1);
+/// // h1 { color: rgb(255, 128, 16) }
+/// let h1 = ctx.layout.select("h1")[0];
+/// let color = ctx.layout.arena[h1].get().style.as_ref().unwrap().color.unwrap();
+/// assert_eq!((color.red, color.green, color.blue), (1.0, 128.0 / 255.0, 16.0 / 255.0));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResourceTiming {
+    pub url: Url,
+    pub kind: ResourceKind,
+    /// Time it took to pull the resource
+    pub duration: Duration,
+    /// Size of the resource in bytes
+    pub size: usize,
+    /// Whether the resource was served from the cache
+    pub from_cache: bool,
+    /// Why the resource couldn't be pulled
+    pub error: Option<String>,
+}
+
+/// Size of a PNG, GIF or JPEG image in pixels, read from its header.
+pub fn image_size(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| Some(u16::from_be_bytes(data.get(i..i + 2)?.try_into().ok()?) as u32);
+    let be32 = |i: usize| Some(u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?));
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // IHDR is always the first chunk
+        return Some((be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        let le16 = |i: usize| Some(u16::from_le_bytes(data.get(i..i + 2)?.try_into().ok()?) as u32);
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        // walk the segments until a start of frame marker
+        let mut i = 2;
+        while i + 4 <= data.len() {
+            if data[i] != 0xFF {
+                return None;
+            }
+            let marker = data[i + 1];
+            let len = be16(i + 2)? as usize;
+            let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+            if is_sof {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + len;
+        }
+    }
+    None
+}
+
+impl Layout {
+    /// Find the stylesheets and images referenced by the page, resolved against `base`.
+    /// Resources that can't be fetched (invalid or unsupported URLs) are skipped.
+    pub fn subresources(&self, base: &Url) -> Vec<Subresource> {
+        let mut resources = vec![];
+        for id in self.root_id().descendants(&self.arena) {
+            let node = self.arena[id].get();
+            let (kind, raw) = match node.name() {
+                "link" => {
+                    let is_stylesheet = node.attr("rel").is_some_and(|rel| {
+                        rel.split_ascii_whitespace()
+                            .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+                    });
+                    match node.attr("href") {
+                        Some(href) if is_stylesheet => (ResourceKind::Stylesheet, href),
+                        _ => continue,
+                    }
+                }
+                "img" => match node.attr("src") {
+                    Some(src) => (ResourceKind::Image, src),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            match resolve_fetchable_url(base, raw) {
+                Ok(url) => resources.push(Subresource {
+                    node: id,
+                    kind,
+                    url,
+                }),
+                Err(err) => log::warn!("skipping {kind} '{raw}': {err}"),
+            }
+        }
+        resources
+    }
+
+    /// Find the background images and the list marker images of the computed styles, resolved
+    /// against `base`. Each URL is found once, with the first element that uses it.
+    pub fn background_subresources(&self, base: &Url) -> Vec<Subresource> {
+        let mut seen = HashSet::new();
+        let mut resources = vec![];
+        for id in self.root_id().descendants(&self.arena) {
+            let Some(style) = self.arena[id].get().style.as_ref() else {
+                continue;
+            };
+            let images = [&style.background_image, &style.list_style_image];
+            for image in images.into_iter().flatten() {
+                let BackgroundImage::Url(raw) = image else {
+                    continue;
+                };
+                if !seen.insert(raw) {
+                    continue;
+                }
+                match resolve_fetchable_url(base, raw) {
+                    Ok(url) => resources.push(Subresource {
+                        node: id,
+                        kind: ResourceKind::BackgroundImage,
+                        url,
+                    }),
+                    Err(err) => log::warn!("skipping background image '{raw}': {err}"),
+                }
+            }
+        }
+        resources
+    }
+
+    /// Pulled bytes of the background image of a node, if it has one and it was loaded.
+    ///
+    /// ```
+    /// use dragonfly::{LoadPhase, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<style>div { background: url('pixel.png') no-repeat }</style>
+    ///     <div>image</div><p style='background-image: url(missing.png)'>missing</p>
+    ///     <div style='background-image: none'>none</div>";
+    /// let mut ctx = WebContext::builder().html(html).url("file:///tests/page.html").build()?;
+    /// block_on(ctx.load())?;
+    /// let layout = &ctx.layout;
+    /// let [div, p, none] = ["div", "p", "div:last-child"].map(|s| layout.select(s)[0]);
+    /// assert!(layout.background_image(div).unwrap().starts_with(b"\x89PNG"));
+    /// // failures are not fatal
+    /// assert!(layout.background_image(p).is_none());
+    /// let errors = ctx.load_errors().iter().filter(|err| err.phase == LoadPhase::Pull);
+    /// assert!(errors.map(|err| err.url.as_ref().unwrap().path()).eq(["/tests/missing.png"]));
+    /// assert!(layout.background_image(none).is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn background_image(&self, id: NodeId) -> Option<&Bytes> {
+        let style = self.arena[id].get().style.as_ref()?;
+        match style.background_image.as_ref()? {
+            BackgroundImage::Url(raw) => self.background_images.get(raw),
+            BackgroundImage::None => None,
+        }
+    }
+}
+
+impl WebContext {
+    /// Pull the stylesheets and images referenced by the page, then feed them to the layout:
+    /// stylesheets are added to the author styles (with the `<style>` elements, in document order)
+    /// and images give their intrinsic size to the `img` elements. Background images are pulled
+    /// once the stylesheets apply, into [`Layout::background_images`]. Failed resources are
+    /// skipped, and so are the kinds of resources that are turned off
+    /// ([`WebContext::load_images`], [`WebContext::load_stylesheets`]).
+    ///
+    /// If `bypass_cache` is set, the resources are not served from the cache. Nothing is changed
+    /// if `cancel` is cancelled before all resources are pulled.
+    pub(crate) async fn load_subresources(
+        &mut self,
+        bypass_cache: bool,
+        cancel: &CancellationToken,
+    ) -> DfResult<()> {
+        let start = Instant::now();
+        let mut resources = self.layout.subresources(self.base_url());
+        resources.retain(|resource| match resource.kind {
+            ResourceKind::Stylesheet => self.load_stylesheets,
+            ResourceKind::Image | ResourceKind::BackgroundImage => self.load_images,
+            ResourceKind::Script => true,
+        });
+        if self.pull_scripts {
+            resources.extend(self.scripts().iter().filter_map(|script| {
+                Some(Subresource {
+                    node: script.node,
+                    kind: ResourceKind::Script,
+                    url: script.src.clone()?,
+                })
+            }));
+        }
+        // bytes pulled for the page and the subresources accepted so far
+        let used_bytes = Cell::new(self.page_bytes);
+        let mut stylesheets = vec![];
+        for (resource, pulled) in self
+            .pull_subresources(resources, &used_bytes, bypass_cache, cancel)
+            .await?
+        {
+            match resource.kind {
+                ResourceKind::Stylesheet => {
+                    stylesheets.push((resource.node, resource.url.clone(), pulled))
+                }
+                ResourceKind::Image => self.add_image(resource.node, &resource.url, &pulled.data),
+                // found once the stylesheets apply
+                ResourceKind::BackgroundImage => (),
+                ResourceKind::Script => self.add_script(resource.node, &pulled),
+            }
+        }
+        self.set_author_stylesheets(stylesheets);
+
+        // background images are referenced by the rules that match, including the ones of the
+        // linked stylesheets
+        if self.load_images {
+            self.layout.compute_styles();
+            let resources = self.layout.background_subresources(self.base_url());
+            for (resource, pulled) in self
+                .pull_subresources(resources, &used_bytes, bypass_cache, cancel)
+                .await?
+            {
+                self.add_background_image(resource.node, &resource.url, pulled.data);
+            }
+        }
+
+        self.timers.subresources = start.elapsed();
+        log::info!(
+            "pulled {} subresources in {:?}",
+            self.timers.resources.len(),
+            self.timers.subresources
+        );
+        Ok(())
+    }
+
+    /// Pull resources in parallel, within the byte budget of the page (`used_bytes` is the
+    /// number of bytes pulled so far). The pulled resources are returned in order, the
+    /// failures are recorded as load errors and every pull is timed.
+    async fn pull_subresources(
+        &mut self,
+        resources: Vec<Subresource>,
+        used_bytes: &Cell<usize>,
+        bypass_cache: bool,
+        cancel: &CancellationToken,
+    ) -> DfResult<Vec<(Subresource, PulledResource)>> {
+        let limit = self.puller.max_concurrent_requests.max(1);
+        let max_bytes = self.limits.max_bytes.unwrap_or(usize::MAX);
+        // pulled in parallel, results are kept in document order
+        let puller = &self.puller;
+        let pulls = futures_util::stream::iter(resources)
+            .map(|resource| {
+                let mut puller = puller.clone();
+                async move {
+                    let start = Instant::now();
+                    let remaining = max_bytes.saturating_sub(used_bytes.get());
+                    let result = match remaining {
+                        0 => Ok(None),
+                        _ => {
+                            let url = resource.url.clone();
+                            puller
+                                .pull_limited(url, bypass_cache, remaining, usize::MAX, |_, _| {})
+                                .await
+                        }
+                    };
+                    // resources pulled at the same time share the remaining budget
+                    let result = result.and_then(|pulled| match pulled {
+                        Some(pulled) if used_bytes.get() + pulled.data.len() <= max_bytes => {
+                            used_bytes.set(used_bytes.get() + pulled.data.len());
+                            Ok(pulled)
+                        }
+                        _ => Err(DfError::BudgetExceeded(Budget::Bytes(max_bytes))),
+                    });
+                    (resource, result, start.elapsed())
+                }
+            })
+            .buffered(limit)
+            .collect();
+        let results: Vec<(Subresource, DfResult<PulledResource>, Duration)> =
+            cancel.run(pulls).await?;
+
+        let mut pulled_resources = vec![];
+        for (resource, result, duration) in results {
+            let mut timing = ResourceTiming {
+                url: resource.url.clone(),
+                kind: resource.kind,
+                duration,
+                size: 0,
+                from_cache: false,
+                error: None,
+            };
+            match result {
+                Ok(pulled) => {
+                    timing.size = pulled.data.len();
+                    timing.from_cache = pulled.from_cache;
+                    pulled_resources.push((resource, pulled));
+                }
+                Err(DfError::BudgetExceeded(budget)) => {
+                    log::warn!(
+                        "skipping {} '{}': {budget} exceeded",
+                        resource.kind,
+                        resource.url
+                    );
+                    timing.error = Some(DfError::BudgetExceeded(budget).to_string());
+                    self.exceed_budget(LoadPhase::Pull, Some(resource.url.clone()), budget);
+                }
+                Err(err) => {
+                    let report = err.report();
+                    log::warn!(
+                        "failed to pull {} '{}': {report}",
+                        resource.kind,
+                        resource.url
+                    );
+                    timing.error = Some(report);
+                    self.add_load_error(LoadError::new(
+                        LoadPhase::Pull,
+                        Some(resource.url.clone()),
+                        err,
+                    ));
+                }
+            }
+            self.observe(|o| o.on_subresource(&timing));
+            self.timers.resources.push(timing);
+        }
+
+        Ok(pulled_resources)
+    }
+
+    /// Keep the bytes of a pulled background or list marker image of `node` from `url`, by URL
+    /// as written in its style.
+    pub(crate) fn add_background_image(&mut self, node: NodeId, url: &Url, data: Bytes) {
+        let Some(style) = self.layout.arena[node].get().style.as_ref() else {
+            return;
+        };
+        let images = [&style.background_image, &style.list_style_image];
+        let raw = images.into_iter().flatten().find_map(|image| match image {
+            BackgroundImage::Url(raw) => {
+                let resolved = resolve_fetchable_url(self.base_url(), raw).ok()?;
+                (&resolved == url).then(|| raw.clone())
+            }
+            BackgroundImage::None => None,
+        });
+        if let Some(raw) = raw {
+            self.layout.background_images.insert(raw, data);
+        }
+    }
+
+    /// Keep the content of a pulled external script.
+    fn add_script(&mut self, node: NodeId, pulled: &PulledResource) {
+        let content = decode_text(&pulled.data, pulled.content_type.as_deref());
+        if let Some(script) = self.scripts.iter_mut().find(|script| script.node == node) {
+            script.content = Some(content);
+        }
+    }
+
+    /// Record the intrinsic size of a loaded image, and decode its pixels with the `images`
+    /// feature. Images that fail to decode are sized like images that weren't loaded.
+    pub(crate) fn add_image(&mut self, node: NodeId, url: &Url, data: &[u8]) {
+        let Some(src) = self.layout.arena[node]
+            .get()
+            .attr("src")
+            .map(str::to_string)
+        else {
+            return;
+        };
+        #[cfg(feature = "images")]
+        if let Err(err) = self.decode_image(&src, url, data) {
+            log::warn!("failed to decode image '{url}': {err}");
+            self.add_load_error(LoadError::new(LoadPhase::Decode, Some(url.clone()), err));
+            return;
+        }
+        match image_size(data) {
+            Some((width, height)) => {
+                let size = Vec2::new(width as f32, height as f32);
+                self.layout.image_sizes.insert(src, size);
+            }
+            None => log::warn!("unsupported image format: '{url}'"),
+        }
+    }
+
+    /// Parse the `<style>` elements and the pulled stylesheets of `link` elements (with their
+    /// URL), and make them the author stylesheets of the layout, in document order.
+    pub(crate) fn set_author_stylesheets(
+        &mut self,
+        mut linked: Vec<(NodeId, Url, PulledResource)>,
+    ) {
+        let layout = &self.layout;
+        let sheets: Vec<(Option<Url>, GlobalStyle)> = layout
+            .root_id()
+            .descendants(&layout.arena)
+            .filter_map(|id| match layout.arena[id].get().name() {
+                "style" => Some((None, layout.text_content(id))),
+                "link" => {
+                    let index = linked.iter().position(|(node, _, _)| *node == id)?;
+                    let (_, url, pulled) = linked.remove(index);
+                    let css = decode_text(&pulled.data, pulled.content_type.as_deref());
+                    Some((Some(url), css))
+                }
+                _ => None,
+            })
+            .map(|(url, css)| (url, GlobalStyle::from_css(&css, layout.author_mode())))
+            .collect();
+
+        self.layout.stylesheets = Vec::with_capacity(sheets.len());
+        for (url, sheet) in sheets {
+            for diagnostic in &sheet.diagnostics {
+                self.add_load_error(LoadError::message(
+                    LoadPhase::Style,
+                    url.clone(),
+                    diagnostic,
+                ));
+            }
+            self.layout.stylesheets.push(sheet);
+        }
+        self.limit_css_rules();
+    }
+}
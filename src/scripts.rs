@@ -0,0 +1,159 @@
+use crate::{resolve_fetchable_url, Layout, WebContext};
+use indextree::NodeId;
+use url::Url;
+
+/// MIME types of classic scripts.
+const JAVASCRIPT_MIME_TYPES: &[&str] = &[
+    "application/ecmascript",
+    "application/javascript",
+    "application/x-ecmascript",
+    "application/x-javascript",
+    "text/ecmascript",
+    "text/javascript",
+    "text/javascript1.0",
+    "text/javascript1.1",
+    "text/javascript1.2",
+    "text/javascript1.3",
+    "text/javascript1.4",
+    "text/javascript1.5",
+    "text/jscript",
+    "text/livescript",
+    "text/x-ecmascript",
+    "text/x-javascript",
+];
+
+/// Type of a `<script>` element, from its `type` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptType {
+    /// JavaScript (no `type`, or a JavaScript MIME type)
+    Classic,
+    /// `type="module"`
+    Module,
+    /// Data block with its lowercase MIME type (`application/ld+json` for example)
+    Data(String),
+}
+
+impl ScriptType {
+    /// Type of a script with a `type` attribute.
+    pub fn parse(type_attr: Option<&str>) -> Self {
+        let ty = type_attr.unwrap_or_default().trim().to_ascii_lowercase();
+        let essence = ty.split(';').next().unwrap_or_default().trim();
+        match essence {
+            "" => Self::Classic,
+            "module" => Self::Module,
+            _ if JAVASCRIPT_MIME_TYPES.contains(&essence) => Self::Classic,
+            _ => Self::Data(ty),
+        }
+    }
+}
+
+/// A `<script>` element of a page. Scripts are never executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptInfo {
+    pub node: NodeId,
+    pub kind: ScriptType,
+    /// Resolved `src` URL of an external script
+    pub src: Option<Url>,
+    /// Inline text of the element, verbatim
+    pub text: String,
+    /// `async` attribute
+    pub is_async: bool,
+    /// `defer` attribute
+    pub defer: bool,
+    /// Pulled content of an external script (see [`WebContext::pull_scripts`])
+    pub content: Option<String>,
+}
+
+impl ScriptInfo {
+    #[inline]
+    pub fn is_module(&self) -> bool {
+        self.kind == ScriptType::Module
+    }
+
+    /// Whether the script is a JSON data block (`application/json`, `application/ld+json`...).
+    pub fn is_json(&self) -> bool {
+        match &self.kind {
+            ScriptType::Data(ty) => {
+                let essence = ty.split(';').next().unwrap_or_default().trim();
+                essence == "application/json" || essence.ends_with("+json")
+            }
+            _ => false,
+        }
+    }
+
+    /// Raw JSON text of a JSON data block, to parse with a JSON library.
+    pub fn json(&self) -> Option<&str> {
+        self.is_json().then_some(self.text.as_str())
+    }
+}
+
+impl Layout {
+    /// Find the `<script>` elements of the page, with `src` URLs resolved against `base`.
+    /// An invalid `src` is ignored.
+    pub fn scripts(&self, base: &Url) -> Vec<ScriptInfo> {
+        self.root_id()
+            .descendants(&self.arena)
+            .filter(|&id| self.arena[id].get().name() == "script")
+            .map(|id| {
+                let node = self.arena[id].get();
+                let src = node
+                    .attr("src")
+                    .and_then(|src| match resolve_fetchable_url(base, src) {
+                        Ok(url) => Some(url),
+                        Err(err) => {
+                            log::warn!("ignoring script '{src}': {err}");
+                            None
+                        }
+                    });
+                ScriptInfo {
+                    node: id,
+                    kind: ScriptType::parse(node.attr("type")),
+                    src,
+                    text: self.text_content(id),
+                    is_async: node.has_bool_attr("async"),
+                    defer: node.has_bool_attr("defer"),
+                    content: None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl WebContext {
+    /// Scripts of the page, in document order.
+    ///
+    /// ```
+    /// use dragonfly::{ScriptType, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let base = serve(|path| match path {
+    ///     "/scripts.html" => Some(Response::ok("text/html", include_str!("../tests/scripts.html"))),
+    ///     "/async.js" => Some(Response::ok("text/javascript", "console.log('async')")),
+    ///     "/defer.js" => Some(Response::ok("text/javascript", "console.log('defer')")),
+    ///     _ => None,
+    /// });
+    /// let mut ctx = WebContext::builder().url(&format!("{base}scripts.html")).build()?;
+    /// ctx.pull_scripts = true;
+    /// block_on(ctx.load())?;
+    /// // the title is not changed, scripts are never executed
+    /// assert_eq!(ctx.title().as_deref(), Some("Scripts"));
+    ///
+    /// // async and deferred scripts keep their place in the document
+    /// let scripts = ctx.scripts();
+    /// let srcs: Vec<_> = scripts.iter().map(|s| s.src.as_ref().map(|src| src.path())).collect();
+    /// assert_eq!(srcs, [Some("/async.js"), Some("/defer.js"), None]);
+    /// let flags: Vec<_> = scripts.iter().map(|s| (s.is_async, s.defer)).collect();
+    /// assert_eq!(flags, [(true, false), (false, true), (false, false)]);
+    /// assert!(scripts.iter().all(|s| s.kind == ScriptType::Classic));
+    ///
+    /// let contents: Vec<_> = scripts.iter().map(|s| s.content.as_deref()).collect();
+    /// assert_eq!(contents, [Some("console.log('async')"), Some("console.log('defer')"), None]);
+    /// assert_eq!(scripts[2].text, "\n  document.title = \"<inline>\";\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn scripts(&self) -> &[ScriptInfo] {
+        &self.scripts
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{percent_decode, BoxKind, Layout, Rect, WebContext};
+use indextree::NodeId;
+use url::Url;
+
+/// Where to scroll to show the fragment of a URL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollTarget {
+    /// Element indicated by the fragment, `None` for the top of the document
+    pub node: Option<NodeId>,
+    /// Box of the element in the document
+    pub rect: Rect,
+}
+
+impl Layout {
+    /// Find where to scroll to for a URL fragment (without the `#`): the element with that id,
+    /// or else an `a` element with that name. The fragment is matched as is, then percent-decoded.
+    /// An empty fragment or `top` is the top of the document.
+    ///
+    /// Returns `None` if no rendered element is indicated by the fragment.
+    pub fn scroll_target(&self, fragment: &str) -> Option<ScrollTarget> {
+        let decoded = percent_decode(fragment);
+        let target = self
+            .indicated_element(fragment)
+            .or_else(|| self.indicated_element(&decoded));
+        match target {
+            Some(id) => {
+                let rendered = id
+                    .ancestors(&self.arena)
+                    .all(|ancestor| self.box_kind(ancestor) != BoxKind::Hidden);
+                if !rendered {
+                    return None;
+                }
+                Some(ScrollTarget {
+                    node: Some(id),
+                    rect: self.region_rects(id)[0],
+                })
+            }
+            None if decoded.is_empty() || decoded.eq_ignore_ascii_case("top") => {
+                Some(ScrollTarget {
+                    node: None,
+                    rect: Rect::ZERO,
+                })
+            }
+            None => None,
+        }
+    }
+
+    /// Element with an id, or else the first `a` element with that name.
+    fn indicated_element(&self, name: &str) -> Option<NodeId> {
+        if name.is_empty() {
+            return None;
+        }
+        let elements = || {
+            self.root_id()
+                .descendants(&self.arena)
+                .filter(|&id| self.arena[id].get().is_element())
+        };
+        elements()
+            .find(|&id| self.arena[id].get().id() == name)
+            .or_else(|| {
+                elements().find(|&id| {
+                    let node = self.arena[id].get();
+                    node.name() == "a" && node.attr("name") == Some(name)
+                })
+            })
+    }
+}
+
+impl WebContext {
+    /// Where to scroll to for the fragment of the page URL, `None` if it has no fragment
+    /// or no element is indicated by it.
+    pub fn fragment_target(&self) -> Option<ScrollTarget> {
+        self.layout.scroll_target(self.url().fragment()?)
+    }
+
+    /// Where to scroll to for a link to the loaded document (a same-document link),
+    /// so it can be followed without loading the page again. Returns `None` if the URL
+    /// points to another document or has no fragment.
+    pub fn scroll_target_for(&self, url: &Url) -> Option<ScrollTarget> {
+        if url[..url::Position::AfterQuery] != self.url()[..url::Position::AfterQuery] {
+            return None;
+        }
+        self.layout.scroll_target(url.fragment()?)
+    }
+}
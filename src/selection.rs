@@ -0,0 +1,297 @@
+use crate::{collapse_whitespace, BoxKind, FontManager, Layout, Pos2, Rect, Vec2};
+use indextree::NodeId;
+
+/// A position between two characters of a text node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Caret {
+    /// Text node
+    pub node: NodeId,
+    /// Byte offset in the text of the node, always on a character boundary
+    pub offset: usize,
+}
+
+/// Text between two carets.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Selected text, with a newline between the text of different blocks
+    pub text: String,
+    /// Selected part of every covered line fragment
+    pub rects: Vec<Rect>,
+}
+
+/// Options of [`Layout::find_text`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FindOptions {
+    /// Compare characters case-insensitively
+    pub ignore_case: bool,
+    /// Only match whole words (the match is not preceded or followed by a letter or digit)
+    pub whole_word: bool,
+}
+
+/// An occurrence of the searched text.
+#[derive(Debug, Clone)]
+pub struct TextMatch {
+    /// Start of the match
+    pub from: Caret,
+    /// End of the match (can be in another text node than the start)
+    pub to: Caret,
+    /// Matched text, as laid out
+    pub text: String,
+    /// Highlight rectangles, computed like the ones of a [`Selection`]
+    pub rects: Vec<Rect>,
+}
+
+impl Layout {
+    /// Find the caret closest to a point, if the point hits a line fragment of text.
+    pub fn caret_at(&self, pos: Pos2, fonts: &mut FontManager) -> Option<Caret> {
+        let scale = self.viewport.scale_factor;
+
+        for id in self.text_nodes() {
+            let node = self.arena[id].get();
+            let Some(fragment) = node.fragments.iter().find(|f| {
+                (f.pos.x..=f.pos.x + f.size.x).contains(&pos.x)
+                    && (f.pos.y..=f.pos.y + f.size.y).contains(&pos.y)
+            }) else {
+                continue;
+            };
+
+            // walk the characters until the point is closer to the next boundary than to the current one.
+            // the text is measured as laid out, with its whitespace handled and transformed
+            let text = &node.text()[fragment.range.clone()];
+            let mut x = fragment.pos.x;
+            let mut offset = fragment.range.start;
+            for (i, c) in text.char_indices() {
+                let end = i + c.len_utf8();
+                let next_x = fragment.pos.x + node.measure_text(&text[..end], scale, fonts).x;
+                if pos.x < (x + next_x) / 2.0 {
+                    break;
+                }
+                x = next_x;
+                offset = fragment.range.start + end;
+            }
+            return Some(Caret { node: id, offset });
+        }
+        None
+    }
+
+    /// Compute the text and the highlight rectangles of the range between two carets.
+    ///
+    /// The carets can be given in any order.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, Caret, FontManager, Layout, Pos2};
+    /// let html = "<!DOCTYPE html><body style='margin: 0'>
+    ///     <p style='margin: 0; width: 90px'>hello world again</p></body>";
+    /// let mut fonts = FontManager::default();
+    /// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let node = layout.select("p")[0].children(&layout.arena).next().unwrap();
+    /// let lines = layout.arena[node].get().fragments.clone();
+    /// assert_eq!((lines[0].text.as_str(), lines[1].text.as_str()), ("hello world", "again"));
+    ///
+    /// // points on the text hit the closest caret, points next to it miss
+    /// let middle = |line: usize| lines[line].pos.y + lines[line].size.y / 2.0;
+    /// let caret = |x, y| layout.caret_at(Pos2::new(x, y), &mut FontManager::default());
+    /// assert_eq!(caret(1.0, middle(0)), Some(Caret { node, offset: 0 }));
+    /// assert_eq!(caret(lines[1].size.x - 1.0, middle(1)), Some(Caret { node, offset: 17 }));
+    /// assert_eq!(caret(lines[0].size.x + 1.0, middle(0)), None);
+    /// assert_eq!(caret(1.0, lines[1].rect().max().y + 1.0), None);
+    ///
+    /// // "world ag" spans both lines: one rect per line, joined by the space at the line break
+    /// let (from, to) = (Caret { node, offset: 6 }, Caret { node, offset: 14 });
+    /// let selection = layout.selection(from, to, &mut fonts);
+    /// assert_eq!(selection.text, "world ag");
+    /// assert_eq!(selection.rects.len(), 2);
+    /// let (first, second) = (selection.rects[0], selection.rects[1]);
+    /// assert_eq!((first.pos.y, second.pos.y), (lines[0].pos.y, lines[1].pos.y));
+    /// assert!(first.pos.x > 0.0 && first.max().x == lines[0].rect().max().x);
+    /// assert!(second.pos.x == 0.0 && second.width() < lines[1].size.x);
+    /// assert_eq!(layout.selection(to, from, &mut fonts).rects, selection.rects);
+    /// ```
+    pub fn selection(&self, from: Caret, to: Caret, fonts: &mut FontManager) -> Selection {
+        let scale = self.viewport.scale_factor;
+        let nodes = self.text_nodes();
+        let index = |caret: &Caret| nodes.iter().position(|&id| id == caret.node);
+        let (Some(from_index), Some(to_index)) = (index(&from), index(&to)) else {
+            return Selection::default();
+        };
+        let ((first, start), (last, end)) = if (from_index, from.offset) <= (to_index, to.offset) {
+            ((from_index, from.offset), (to_index, to.offset))
+        } else {
+            ((to_index, to.offset), (from_index, from.offset))
+        };
+
+        let mut selection = Selection::default();
+        let mut prev_block = None;
+        for (i, &id) in nodes.iter().enumerate().take(last + 1).skip(first) {
+            let node = self.arena[id].get();
+            let start = if i == first { start } else { 0 };
+            let end = if i == last { end } else { node.text().len() };
+
+            let block = self.containing_block(id);
+            if prev_block.is_some_and(|prev| prev != block) {
+                selection.text.push('\n');
+            }
+            prev_block = Some(block);
+            let white_space = node.text_style().white_space;
+            selection
+                .text
+                .push_str(&white_space.apply(&node.text()[start..end]));
+
+            for fragment in &node.fragments {
+                let (from, to) = (start.max(fragment.range.start), end.min(fragment.range.end));
+                if from >= to {
+                    continue;
+                }
+                let mut offset = |byte: usize| {
+                    let text = &node.text()[fragment.range.start..byte];
+                    node.measure_text(text, scale, fonts).x
+                };
+                let x = fragment.pos.x + offset(from);
+                let width = fragment.pos.x + offset(to) - x;
+                selection.rects.push(Rect::from_pos_size(
+                    Pos2::new(x, fragment.pos.y),
+                    Vec2::new(width, fragment.size.y),
+                ));
+            }
+        }
+        selection
+    }
+
+    /// Find all occurrences of a text in the laid-out text of the document, in document order.
+    ///
+    /// The text of each block is searched as it's laid out: the text of its nodes is
+    /// concatenated, then whitespace is collapsed over it. Whitespace in the query is collapsed
+    /// too. Matches can span several text nodes of the same block (`bold <b>te</b>xt` contains
+    /// `text`), but not blocks. Hidden text is never matched.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FindOptions, FontManager, Layout};
+    /// let html = "<p>Bold <b>te</b>xt,  and\n <i> more </i> text</p><p>text</p><p hidden>text</p>";
+    /// let mut fonts = FontManager::default();
+    /// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let find = |query, ignore_case, fonts: &mut FontManager| {
+    ///     let options = FindOptions { ignore_case, ..Default::default() };
+    ///     layout.find_text(query, options, fonts)
+    /// };
+    ///
+    /// // a match across the boundary of `<b>` has the rects of both fragments
+    /// let matches = find("text", false, &mut fonts);
+    /// assert_eq!(matches.len(), 3);
+    /// assert_ne!(matches[0].from.node, matches[0].to.node);
+    /// assert_eq!(matches[0].text, "text");
+    /// assert_eq!(matches[0].rects.len(), 2);
+    ///
+    /// // whitespace collapses across the text nodes
+    /// let matches = find("and more text", false, &mut fonts);
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].text, "and more text");
+    ///
+    /// assert_eq!(find("bold TEXT", true, &mut fonts).len(), 1);
+    /// assert_eq!(find("bold TEXT", false, &mut fonts).len(), 0);
+    /// assert!(find("missing", true, &mut fonts).is_empty());
+    /// ```
+    pub fn find_text(
+        &self,
+        query: &str,
+        options: FindOptions,
+        fonts: &mut FontManager,
+    ) -> Vec<TextMatch> {
+        let query: Vec<char> = collapse_whitespace(query).chars().collect();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        // characters of the laid-out text with their position. Blocks are separated by a
+        // newline, which never matches: whitespace is collapsed to spaces in the query and the text
+        let mut chars: Vec<(char, Caret)> = vec![];
+        let mut prev_block = None;
+        for id in self.text_nodes() {
+            let block = self.containing_block(id);
+            if prev_block.is_some_and(|prev| prev != block) {
+                chars.push((
+                    '\n',
+                    Caret {
+                        node: id,
+                        offset: 0,
+                    },
+                ));
+            }
+            prev_block = Some(block);
+            let node = self.arena[id].get();
+            let white_space = node.text_style().white_space;
+            for (offset, c) in node.text().char_indices() {
+                let caret = Caret { node: id, offset };
+                if !c.is_whitespace() || !white_space.collapses_spaces() {
+                    chars.push((c, caret));
+                } else if c == '\n' && white_space.preserves_newlines() {
+                    chars.push(('\n', caret));
+                } else if chars
+                    .last()
+                    .is_some_and(|&(prev, _)| prev != ' ' && prev != '\n')
+                {
+                    // a run of collapsible whitespace is a single space, even across text
+                    // nodes, and there's none at the start of a block
+                    chars.push((' ', caret));
+                }
+            }
+        }
+
+        let same = |a: char, b: char| match options.ignore_case {
+            true => a == b || a.to_lowercase().eq(b.to_lowercase()),
+            false => a == b,
+        };
+        let is_word = |i: usize| chars.get(i).is_some_and(|(c, _)| c.is_alphanumeric());
+
+        let mut matches = vec![];
+        let mut start = 0;
+        while start + query.len() <= chars.len() {
+            let end = start + query.len();
+            let found = chars[start..end]
+                .iter()
+                .zip(&query)
+                .all(|(&(c, _), &q)| same(c, q))
+                && !(options.whole_word
+                    && (start.checked_sub(1).is_some_and(is_word) || is_word(end)));
+            if !found {
+                start += 1;
+                continue;
+            }
+
+            let from = chars[start].1;
+            let last = chars[end - 1].1;
+            let last_len = self.arena[last.node].get().text()[last.offset..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+            let to = Caret {
+                node: last.node,
+                offset: last.offset + last_len,
+            };
+            let selection = self.selection(from, to, fonts);
+            matches.push(TextMatch {
+                from,
+                to,
+                text: chars[start..end].iter().map(|&(c, _)| c).collect(),
+                rects: selection.rects,
+            });
+            start = end;
+        }
+        matches
+    }
+
+    /// Text nodes that were laid out on lines, in document order.
+    fn text_nodes(&self) -> Vec<NodeId> {
+        self.root_id()
+            .descendants(&self.arena)
+            .filter(|&id| !self.arena[id].get().fragments.is_empty())
+            .collect()
+    }
+
+    /// Closest ancestor of a node that is not laid out inline.
+    fn containing_block(&self, id: NodeId) -> NodeId {
+        id.ancestors(&self.arena)
+            .skip(1)
+            .find(|&id| self.box_kind(id) != BoxKind::Inline)
+            .unwrap_or(self.root_id())
+    }
+}
@@ -0,0 +1,732 @@
+use crate::{DfError, DfResult, ElementData, Layout};
+use indextree::NodeId;
+use std::collections::HashMap;
+use url::Url;
+
+/// How an attribute selector compares the attribute value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrOperator {
+    /// `[attr]`
+    Exists,
+    /// `[attr=value]`
+    Equals(String),
+    /// `[attr~=value]`, one of the whitespace-separated words is `value`
+    Includes(String),
+    /// `[attr|=value]`, `value` or starts with `value-`
+    DashMatch(String),
+    /// `[attr^=value]`
+    Prefix(String),
+    /// `[attr$=value]`
+    Suffix(String),
+    /// `[attr*=value]`
+    Substring(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttrSelector {
+    pub name: String,
+    pub operator: AttrOperator,
+    /// Compare the value ASCII case-insensitively (`[attr=value i]`)
+    pub ignore_case: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PseudoClass {
+    Root,
+    Empty,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    /// `:nth-child(n)`, the element is the n-th (1-based) element among its siblings.
+    /// Only integer arguments are supported.
+    NthChild(usize),
+    /// `:nth-of-type(n)`, the element is the n-th (1-based) element with its tag name among its siblings.
+    /// Only integer arguments are supported.
+    NthOfType(usize),
+    /// Checked checkboxes and radio buttons, selected options
+    Checked,
+    /// Disabled form controls
+    Disabled,
+    /// Form controls that are not disabled
+    Enabled,
+    /// The element under the pointer and its ancestors (see [`Layout::set_hover`])
+    Hover,
+    /// The focused element (see [`Layout::set_focus`])
+    Focus,
+    /// Links (`a` and `area` elements with an `href`) to pages that were not visited
+    Link,
+    /// Links to visited pages (see [`Layout::visited`])
+    Visited,
+    Not(Selector),
+}
+
+/// Dynamic state of an element that selectors can depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementState {
+    /// `:hover`
+    Hover,
+    /// `:focus`
+    Focus,
+    /// `:visited` and `:link`
+    Visited,
+}
+
+/// A sequence of simple selectors that all have to match the same element, e.g. `a.link[href]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompoundSelector {
+    /// Tag name, `None` for `*` or no type selector
+    pub tag: Option<String>,
+    pub ids: Vec<String>,
+    pub classes: Vec<String>,
+    pub attrs: Vec<AttrSelector>,
+    pub pseudo_classes: Vec<PseudoClass>,
+}
+
+/// Relation between two compound selectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combinator {
+    /// `a b`
+    Descendant,
+    /// `a > b`
+    Child,
+    /// `a + b`
+    NextSibling,
+    /// `a ~ b`
+    SubsequentSibling,
+}
+
+/// Compound selectors joined by combinators, e.g. `nav > ul a`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComplexSelector {
+    /// The first compound selector, followed by the combinator before every other compound
+    pub first: CompoundSelector,
+    pub rest: Vec<(Combinator, CompoundSelector)>,
+}
+
+/// A parsed comma-separated selector list.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::Selector;
+/// let selector = Selector::parse("nav > a[href^='https'], p.note").unwrap();
+/// assert_eq!(selector.list.len(), 2);
+/// assert!(Selector::parse("p::before").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    /// Selector source text
+    pub source: String,
+    pub list: Vec<ComplexSelector>,
+    /// Element states the selector depends on, recorded when it's parsed
+    pub states: Vec<ElementState>,
+}
+
+/// Specificity of a selector: (ids, classes/attributes/pseudo-classes, tags).
+pub type Specificity = (u32, u32, u32);
+
+impl Selector {
+    /// Parse a selector list.
+    pub fn parse(source: &str) -> DfResult<Self> {
+        let error = || DfError::InvalidSelector(source.to_string());
+        let list = split_top_level(source, ',')
+            .into_iter()
+            .map(|complex| parse_complex(complex).ok_or_else(error))
+            .collect::<DfResult<Vec<_>>>()?;
+        let mut states = vec![];
+        for state in list.iter().flat_map(ComplexSelector::states) {
+            if !states.contains(&state) {
+                states.push(state);
+            }
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            list,
+            states,
+        })
+    }
+
+    /// Whether matching the selector depends on a state of elements.
+    #[inline]
+    pub fn depends_on(&self, state: ElementState) -> bool {
+        self.states.contains(&state)
+    }
+
+    /// Highest specificity of the selectors in the list.
+    pub fn specificity(&self) -> Specificity {
+        self.list
+            .iter()
+            .map(ComplexSelector::specificity)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl ComplexSelector {
+    /// The compound selectors, from the first one.
+    fn compounds(&self) -> impl Iterator<Item = &CompoundSelector> {
+        std::iter::once(&self.first).chain(self.rest.iter().map(|(_, compound)| compound))
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        self.compounds()
+            .map(CompoundSelector::specificity)
+            .fold((0, 0, 0), |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2))
+    }
+
+    /// Element states the selector depends on (may contain duplicates).
+    fn states(&self) -> impl Iterator<Item = ElementState> + '_ {
+        self.compounds()
+            .flat_map(|compound| &compound.pseudo_classes)
+            .flat_map(|pseudo| match pseudo {
+                PseudoClass::Hover => &[ElementState::Hover][..],
+                PseudoClass::Focus => &[ElementState::Focus],
+                PseudoClass::Link | PseudoClass::Visited => &[ElementState::Visited],
+                PseudoClass::Not(selector) => &selector.states,
+                _ => &[],
+            })
+            .copied()
+    }
+
+    /// Whether matching the selector depends on a state of elements.
+    pub fn depends_on(&self, state: ElementState) -> bool {
+        self.states().any(|s| s == state)
+    }
+}
+
+impl CompoundSelector {
+    pub fn specificity(&self) -> Specificity {
+        let mut specificity = (
+            self.ids.len() as u32,
+            (self.classes.len() + self.attrs.len()) as u32,
+            self.tag.is_some() as u32,
+        );
+        for pseudo in &self.pseudo_classes {
+            let (a, b, c) = match pseudo {
+                PseudoClass::Not(selector) => selector.specificity(),
+                _ => (0, 1, 0),
+            };
+            specificity = (specificity.0 + a, specificity.1 + b, specificity.2 + c);
+        }
+        specificity
+    }
+
+    /// Whether the element matches the parts of the selector that don't depend on the
+    /// position of the element in the tree (everything except pseudo-classes).
+    pub fn matches_element(&self, el: &ElementData) -> bool {
+        self.tag
+            .as_ref()
+            .is_none_or(|tag| tag.eq_ignore_ascii_case(&el.name))
+            && self.ids.iter().all(|id| *id == el.id)
+            && self.classes.iter().all(|class| el.has_class(class))
+            && self.attrs.iter().all(|attr| attr.matches(el))
+    }
+}
+
+impl AttrSelector {
+    pub fn matches(&self, el: &ElementData) -> bool {
+        let Some(value) = el.attrs.get(&self.name) else {
+            return false;
+        };
+        let expected = match &self.operator {
+            AttrOperator::Exists => return true,
+            AttrOperator::Equals(v)
+            | AttrOperator::Includes(v)
+            | AttrOperator::DashMatch(v)
+            | AttrOperator::Prefix(v)
+            | AttrOperator::Suffix(v)
+            | AttrOperator::Substring(v) => v,
+        };
+        let (value, expected) = if self.ignore_case {
+            (value.to_ascii_lowercase(), expected.to_ascii_lowercase())
+        } else {
+            (value.clone(), expected.clone())
+        };
+
+        match self.operator {
+            AttrOperator::Exists => true,
+            AttrOperator::Equals(_) => value == expected,
+            AttrOperator::Includes(_) => value.split_whitespace().any(|word| word == expected),
+            AttrOperator::DashMatch(_) => {
+                value == expected || value.starts_with(&format!("{expected}-"))
+            }
+            // an empty value never matches the substring operators
+            AttrOperator::Prefix(_) => !expected.is_empty() && value.starts_with(&expected),
+            AttrOperator::Suffix(_) => !expected.is_empty() && value.ends_with(&expected),
+            AttrOperator::Substring(_) => !expected.is_empty() && value.contains(&expected),
+        }
+    }
+}
+
+/// Split at a separator that is not nested in brackets, parentheses or quotes.
+pub(crate) fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' | '\'' if quote == Some(c) => quote = None,
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            _ if quote.is_some() => (),
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            _ if c == separator && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+/// Consume an identifier from the start of `s`, returns the identifier and the rest.
+fn ident(s: &str) -> Option<(&str, &str)> {
+    let end = s.find(|c| !is_ident_char(c)).unwrap_or(s.len());
+    (end > 0).then(|| s.split_at(end))
+}
+
+fn parse_complex(s: &str) -> Option<ComplexSelector> {
+    let mut s = s.trim();
+    let mut compounds = vec![];
+    let mut combinators = vec![];
+
+    loop {
+        let (compound, rest) = parse_compound(s)?;
+        compounds.push(compound);
+
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            break;
+        }
+        let (combinator, rest) = match trimmed.chars().next()? {
+            '>' => (Combinator::Child, &trimmed[1..]),
+            '+' => (Combinator::NextSibling, &trimmed[1..]),
+            '~' => (Combinator::SubsequentSibling, &trimmed[1..]),
+            // whitespace between two compound selectors
+            _ if trimmed.len() < rest.len() => (Combinator::Descendant, trimmed),
+            _ => return None,
+        };
+        combinators.push(combinator);
+        s = rest.trim_start();
+    }
+
+    let mut compounds = compounds.into_iter();
+    Some(ComplexSelector {
+        first: compounds.next()?,
+        rest: combinators.into_iter().zip(compounds).collect(),
+    })
+}
+
+fn parse_compound(mut s: &str) -> Option<(CompoundSelector, &str)> {
+    let mut compound = CompoundSelector::default();
+    let start_len = s.len();
+
+    if let Some(rest) = s.strip_prefix('*') {
+        s = rest;
+    } else if let Some((tag, rest)) = ident(s) {
+        compound.tag = Some(tag.to_ascii_lowercase());
+        s = rest;
+    }
+
+    loop {
+        match s.chars().next() {
+            Some('#') => {
+                let (id, rest) = ident(&s[1..])?;
+                compound.ids.push(id.to_string());
+                s = rest;
+            }
+            Some('.') => {
+                let (class, rest) = ident(&s[1..])?;
+                compound.classes.push(class.to_string());
+                s = rest;
+            }
+            Some('[') => {
+                let end = s.find(']')?;
+                compound.attrs.push(parse_attr(&s[1..end])?);
+                s = &s[end + 1..];
+            }
+            Some(':') => {
+                // pseudo-elements never match an element
+                let (name, rest) = ident(&s[1..])?;
+                let pseudo = match name.to_ascii_lowercase().as_str() {
+                    "root" => PseudoClass::Root,
+                    "empty" => PseudoClass::Empty,
+                    "first-child" => PseudoClass::FirstChild,
+                    "last-child" => PseudoClass::LastChild,
+                    "only-child" => PseudoClass::OnlyChild,
+                    "checked" => PseudoClass::Checked,
+                    "disabled" => PseudoClass::Disabled,
+                    "enabled" => PseudoClass::Enabled,
+                    "hover" => PseudoClass::Hover,
+                    "focus" => PseudoClass::Focus,
+                    "link" => PseudoClass::Link,
+                    "visited" => PseudoClass::Visited,
+                    "nth-child" | "nth-of-type" => {
+                        let inner = rest.strip_prefix('(')?;
+                        let end = inner.find(')')?;
+                        let n: usize = inner[..end].trim().parse().ok().filter(|&n| n > 0)?;
+                        s = &inner[end + 1..];
+                        compound.pseudo_classes.push(
+                            match name.eq_ignore_ascii_case("nth-child") {
+                                true => PseudoClass::NthChild(n),
+                                false => PseudoClass::NthOfType(n),
+                            },
+                        );
+                        continue;
+                    }
+                    "not" => {
+                        let inner = rest.strip_prefix('(')?;
+                        let end = find_closing_paren(inner)?;
+                        let selector = Selector::parse(&inner[..end]).ok()?;
+                        s = &inner[end + 1..];
+                        compound.pseudo_classes.push(PseudoClass::Not(selector));
+                        continue;
+                    }
+                    _ => return None,
+                };
+                compound.pseudo_classes.push(pseudo);
+                s = rest;
+            }
+            _ => break,
+        }
+    }
+
+    (s.len() < start_len).then_some((compound, s))
+}
+
+fn find_closing_paren(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+fn parse_attr(s: &str) -> Option<AttrSelector> {
+    let s = s.trim();
+    let (name, rest) = ident(s)?;
+    let rest = rest.trim_start();
+    let name = name.to_ascii_lowercase();
+    if rest.is_empty() {
+        return Some(AttrSelector {
+            name,
+            operator: AttrOperator::Exists,
+            ignore_case: false,
+        });
+    }
+
+    let (operator, rest): (fn(String) -> AttrOperator, &str) = match rest.split_at(1) {
+        ("=", rest) => (AttrOperator::Equals, rest),
+        (_, _) if rest.len() >= 2 && &rest[1..2] == "=" => {
+            let operator: fn(String) -> AttrOperator = match &rest[..1] {
+                "~" => AttrOperator::Includes,
+                "|" => AttrOperator::DashMatch,
+                "^" => AttrOperator::Prefix,
+                "$" => AttrOperator::Suffix,
+                "*" => AttrOperator::Substring,
+                _ => return None,
+            };
+            (operator, &rest[2..])
+        }
+        _ => return None,
+    };
+
+    let rest = rest.trim();
+    let (value, flags) = match rest.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = rest[1..].find(quote)? + 1;
+            (&rest[1..end], rest[end + 1..].trim())
+        }
+        _ => match rest.split_once(char::is_whitespace) {
+            Some((value, flags)) => (value, flags.trim()),
+            None => (rest, ""),
+        },
+    };
+    let ignore_case = match flags {
+        "" | "s" | "S" => false,
+        "i" | "I" => true,
+        _ => return None,
+    };
+
+    Some(AttrSelector {
+        name,
+        operator: operator(value.to_string()),
+        ignore_case,
+    })
+}
+
+impl Layout {
+    /// Whether a node matches a selector. Invalid selectors never match.
+    pub fn matches(&self, id: NodeId, selector: &str) -> bool {
+        Selector::parse(selector).is_ok_and(|selector| self.matches_selector(id, &selector))
+    }
+
+    /// Whether a node matches a parsed selector.
+    pub fn matches_selector(&self, id: NodeId, selector: &Selector) -> bool {
+        selector
+            .list
+            .iter()
+            .any(|complex| self.matches_complex_selector(id, complex))
+    }
+
+    /// Whether a node matches a complex selector (one selector of a list).
+    pub fn matches_complex_selector(&self, id: NodeId, complex: &ComplexSelector) -> bool {
+        self.matches_complex(id, complex, complex.rest.len())
+    }
+
+    /// Closest element that matches a selector, starting from the node itself and walking up its ancestors.
+    pub fn closest(&self, id: NodeId, selector: &str) -> Option<NodeId> {
+        let selector = Selector::parse(selector).ok()?;
+        id.ancestors(&self.arena)
+            .find(|&id| self.matches_selector(id, &selector))
+    }
+
+    /// Find all elements that match a selector, in document order.
+    pub fn select(&self, selector: &str) -> Vec<NodeId> {
+        let Ok(selector) = Selector::parse(selector) else {
+            return vec![];
+        };
+        self.root_id()
+            .descendants(&self.arena)
+            .filter(|&id| self.matches_selector(id, &selector))
+            .collect()
+    }
+
+    /// Whether a node matches the compound selectors of a complex selector up to the one at `index`
+    /// (0 is the first compound), with `index` matching the node itself.
+    fn matches_complex(&self, id: NodeId, complex: &ComplexSelector, index: usize) -> bool {
+        let (compound, combinator) = match index {
+            0 => (&complex.first, None),
+            _ => {
+                let (combinator, compound) = &complex.rest[index - 1];
+                (compound, Some(*combinator))
+            }
+        };
+        if !self.matches_compound(id, compound) {
+            return false;
+        }
+
+        let previous = |id: NodeId| self.previous_element_sibling(id);
+        match combinator {
+            None => true,
+            Some(Combinator::Child) => self.arena[id]
+                .parent()
+                .is_some_and(|parent| self.matches_complex(parent, complex, index - 1)),
+            Some(Combinator::Descendant) => id
+                .ancestors(&self.arena)
+                .skip(1)
+                .any(|ancestor| self.matches_complex(ancestor, complex, index - 1)),
+            Some(Combinator::NextSibling) => previous(id)
+                .is_some_and(|sibling| self.matches_complex(sibling, complex, index - 1)),
+            Some(Combinator::SubsequentSibling) => {
+                std::iter::successors(previous(id), |&sibling| previous(sibling))
+                    .any(|sibling| self.matches_complex(sibling, complex, index - 1))
+            }
+        }
+    }
+
+    fn matches_compound(&self, id: NodeId, compound: &CompoundSelector) -> bool {
+        let Some(el) = self.arena[id].get().as_element() else {
+            return false;
+        };
+        compound.matches_element(el)
+            && compound.pseudo_classes.iter().all(|pseudo| match pseudo {
+                PseudoClass::Root => id == self.root_id(),
+                PseudoClass::Empty => id.children(&self.arena).all(|child| {
+                    let child = self.arena[child].get();
+                    child.is_comment() || (child.is_text() && child.text().is_empty())
+                }),
+                PseudoClass::FirstChild => self.previous_element_sibling(id).is_none(),
+                PseudoClass::LastChild => self.next_element_sibling(id).is_none(),
+                PseudoClass::OnlyChild => {
+                    self.previous_element_sibling(id).is_none()
+                        && self.next_element_sibling(id).is_none()
+                }
+                PseudoClass::NthChild(n) => self.element_index(id, false) == *n,
+                PseudoClass::NthOfType(n) => self.element_index(id, true) == *n,
+                PseudoClass::Checked => match el.name.as_str() {
+                    "input" => {
+                        let node = self.arena[id].get();
+                        matches!(node.input_type(), "checkbox" | "radio")
+                            && node.has_bool_attr("checked")
+                    }
+                    "option" => self.arena[id].get().has_bool_attr("selected"),
+                    _ => false,
+                },
+                PseudoClass::Disabled => self.is_disabled(id) == Some(true),
+                PseudoClass::Enabled => self.is_disabled(id) == Some(false),
+                PseudoClass::Hover => self.arena[id].get().hover,
+                PseudoClass::Focus => self.arena[id].get().focus,
+                PseudoClass::Link => self.is_visited_link(id) == Some(false),
+                PseudoClass::Visited => self.is_visited_link(id) == Some(true),
+                PseudoClass::Not(selector) => !self.matches_selector(id, selector),
+            })
+    }
+
+    /// Whether a form control is disabled (directly or by a disabled `fieldset`),
+    /// `None` for elements that can't be disabled.
+    pub(crate) fn is_disabled(&self, id: NodeId) -> Option<bool> {
+        let node = self.arena[id].get();
+        if !matches!(
+            node.name(),
+            "button" | "input" | "select" | "textarea" | "option" | "optgroup" | "fieldset"
+        ) {
+            return None;
+        }
+        let in_disabled_fieldset = id.ancestors(&self.arena).skip(1).any(|ancestor| {
+            let ancestor = self.arena[ancestor].get();
+            ancestor.name() == "fieldset" && ancestor.has_bool_attr("disabled")
+        });
+        Some(node.has_bool_attr("disabled") || in_disabled_fieldset)
+    }
+
+    /// Whether a link points to a visited page, `None` for elements that are not links.
+    fn is_visited_link(&self, id: NodeId) -> Option<bool> {
+        let node = self.arena[id].get();
+        if !matches!(node.name(), "a" | "area") {
+            return None;
+        }
+        let href = node.attr("href")?;
+        let url = match &self.base_url {
+            Some(base) => base.join(href.trim()),
+            None => Url::parse(href.trim()),
+        };
+        Some(url.is_ok_and(|url| self.visited.contains(&url)))
+    }
+
+    /// 1-based index of an element among its element siblings (only the ones with the same tag name if `of_type`).
+    fn element_index(&self, id: NodeId, of_type: bool) -> usize {
+        let name = self.arena[id].get().name();
+        id.preceding_siblings(&self.arena)
+            .filter(|&sibling| {
+                let sibling = self.arena[sibling].get();
+                sibling.is_element() && (!of_type || sibling.name() == name)
+            })
+            .count()
+    }
+
+    /// Build a selector that matches the node, from the node up to the root: `#id` if the node
+    /// (or an ancestor) has an id that is unique in the document, otherwise `tag.class:nth-of-type(n)`
+    /// segments joined with ` > `.
+    ///
+    /// Text and comment nodes get the path of their parent, with a comment that gives
+    /// the position of the node among the children of the parent.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = r#"
+    ///     <div id="main">
+    ///         <p class="a b">one</p><p class="a">two</p>
+    ///         <div id="dup"><span>x</span></div><div id="dup"><span>y</span></div>
+    ///         <ul><li>1</li><li class="9bad">2</li></ul>
+    ///     </div>
+    ///     <section><p>three</p></section>"#;
+    /// let mut ctx = WebContext::builder().html(html).build()?;
+    /// block_on(ctx.load())?;
+    /// let layout = &ctx.layout;
+    /// let elements: Vec<_> = layout
+    ///     .root_id()
+    ///     .descendants(&layout.arena)
+    ///     .filter(|&id| layout.arena[id].get().is_element())
+    ///     .collect();
+    /// assert!(elements.len() > 10);
+    /// for id in elements {
+    ///     let path = layout.css_path(id);
+    ///     assert_eq!(layout.select(&path), [id], "{path}");
+    /// }
+    /// assert_eq!(layout.css_path(layout.select("#main > p")[1]), "#main > p.a:nth-of-type(2)");
+    /// // duplicate ids are skipped, the path continues up to a unique one
+    /// let span = layout.select("#dup span")[1];
+    /// assert_eq!(layout.css_path(span), "#main > div:nth-of-type(2) > span:nth-of-type(1)");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn css_path(&self, id: NodeId) -> String {
+        let node = self.arena[id].get();
+        if !node.is_element() {
+            let kind = if node.is_text() { "text" } else { "comment" };
+            let index = id.preceding_siblings(&self.arena).count();
+            return match self.arena[id].parent() {
+                Some(parent) => format!("{} /* {kind} node {index} */", self.css_path(parent)),
+                None => format!("/* {kind} node */"),
+            };
+        }
+
+        let is_ident = |s: &str| !s.is_empty() && s.chars().all(is_ident_char);
+        // number of elements with each id, counted once on the first ancestor that has one
+        let mut id_counts: Option<HashMap<&str, usize>> = None;
+        let mut segments = vec![];
+        for id in id.ancestors(&self.arena) {
+            let node = self.arena[id].get();
+            let name = node.name();
+            let unique_id = is_ident(node.id())
+                && id_counts.get_or_insert_with(|| self.id_counts())[node.id()] == 1;
+            if unique_id {
+                segments.push(format!("#{}", node.id()));
+                break;
+            }
+            if id == self.root_id() {
+                segments.push(":root".to_string());
+                break;
+            }
+
+            let mut segment = String::new();
+            if is_ident(name) {
+                segment.push_str(name);
+            } else {
+                segment.push('*');
+            }
+            for class in node.classes().iter().filter(|class| is_ident(class)) {
+                segment.push('.');
+                segment.push_str(class);
+            }
+            let index = match is_ident(name) {
+                true => format!(":nth-of-type({})", self.element_index(id, true)),
+                false => format!(":nth-child({})", self.element_index(id, false)),
+            };
+            segment.push_str(&index);
+            segments.push(segment);
+        }
+
+        segments.reverse();
+        segments.join(" > ")
+    }
+
+    /// Number of elements in the document with each id.
+    fn id_counts(&self) -> HashMap<&str, usize> {
+        let mut counts = HashMap::new();
+        for id in self.root_id().descendants(&self.arena) {
+            let node = self.arena[id].get();
+            if !node.id().is_empty() {
+                *counts.entry(node.id()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    fn previous_element_sibling(&self, id: NodeId) -> Option<NodeId> {
+        id.preceding_siblings(&self.arena)
+            .skip(1)
+            .find(|&sibling| self.arena[sibling].get().is_element())
+    }
+
+    fn next_element_sibling(&self, id: NodeId) -> Option<NodeId> {
+        id.following_siblings(&self.arena)
+            .skip(1)
+            .find(|&sibling| self.arena[sibling].get().is_element())
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{DOMNodeKind, Layout};
+use indextree::NodeId;
+
+/// Elements that never have children or an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose text is not escaped.
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "script",
+    "style",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+/// Elements that drop a newline right after their start tag when they're parsed.
+const LEADING_NEWLINE_ELEMENTS: &[&str] = &["pre", "textarea", "listing"];
+
+/// Escape text content (`&`, `<` and `>`).
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Escape a double-quoted attribute value (`&` and `"`).
+fn escape_attr(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+impl Layout {
+    /// Serialize a node and its subtree to HTML.
+    ///
+    /// Attributes are written in source order, so the output is stable across parses: parsing
+    /// the serialized markup gives the same tree. The text of raw text elements (`script`,
+    /// `style`...) is written unescaped, and `template`, `svg` and `canvas` elements, whose
+    /// content isn't laid out, are written with the content they were parsed with.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = r#"<a title="t" href="/" class="c" href="/dup" data-x="1">link</a>"#;
+    /// let mut fonts = FontManager::default();
+    /// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let link = layout.select("a")[0];
+    /// assert_eq!(
+    ///     layout.outer_html(link),
+    ///     r#"<a title="t" href="/" class="c" data-x="1">link</a>"#
+    /// );
+    /// let again = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// assert_eq!(layout.outer_html(layout.root_id()), again.outer_html(again.root_id()));
+    ///
+    /// // parse -> serialize -> parse keeps the tree
+    /// let html = r#"<!DOCTYPE html><style>p > b { color: red }</style>
+    /// <script>if (a < b && c) {}</script><title>a &amp; b</title>
+    /// <textarea>x &lt; y</textarea><pre>
+    ///
+    /// line</pre><p>one  two <b>x</b></p><template><p>inert</p></template>
+    /// <svg width="10"><rect x="1"></rect><text>t</text></svg><canvas><p>fallback</p></canvas>"#;
+    /// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let serialized = layout.outer_html(layout.root_id());
+    /// assert!(serialized.contains("<script>if (a < b && c) {}</script>"));
+    /// assert!(serialized.contains("<pre>\n\nline</pre>"));
+    /// assert!(serialized.contains("<template><p>inert</p></template>"));
+    /// assert!(serialized.contains(r#"<svg width="10"><rect x="1"></rect><text>t</text></svg>"#));
+    /// let again = Layout::compute(&mut parse_document(&serialized), &mut fonts);
+    /// assert_eq!(again.outer_html(again.root_id()), serialized);
+    /// let svg = again.select("svg")[0];
+    /// assert_eq!(again.svg_source(svg), layout.svg_source(layout.select("svg")[0]));
+    /// ```
+    pub fn outer_html(&self, id: NodeId) -> String {
+        self.serialize(vec![id])
+    }
+
+    /// Serialize the children of a node to HTML.
+    pub fn inner_html(&self, id: NodeId) -> String {
+        self.serialize(id.children(&self.arena).collect())
+    }
+
+    /// Concatenated text of all text nodes in the subtree of a node, in document order.
+    pub fn text_content(&self, id: NodeId) -> String {
+        id.descendants(&self.arena)
+            .filter_map(|id| match &self.arena[id].get().kind {
+                DOMNodeKind::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Serialize a list of sibling nodes and their subtrees.
+    fn serialize(&self, nodes: Vec<NodeId>) -> String {
+        let mut out = String::new();
+        // (node, whether its start tag was already written)
+        let mut stack: Vec<(NodeId, bool)> =
+            nodes.into_iter().rev().map(|id| (id, false)).collect();
+
+        while let Some((id, opened)) = stack.pop() {
+            let node = self.arena[id].get();
+            match &node.kind {
+                // all children are written, close the element
+                DOMNodeKind::Element(el) if opened => {
+                    out.push_str("</");
+                    out.push_str(&el.name);
+                    out.push('>');
+                }
+                DOMNodeKind::Element(el) => {
+                    out.push('<');
+                    out.push_str(&el.name);
+                    for (name, value) in &el.attrs {
+                        out.push(' ');
+                        out.push_str(name);
+                        out.push_str("=\"");
+                        escape_attr(value, &mut out);
+                        out.push('"');
+                    }
+                    out.push('>');
+
+                    if VOID_ELEMENTS.contains(&el.name.as_str()) {
+                        continue;
+                    }
+                    stack.push((id, true));
+                    // the content of inert elements isn't in the tree
+                    if let Some(markup) = self.inert_markup(id) {
+                        out.push_str(markup);
+                        continue;
+                    }
+                    // the parser drops the first newline of these, so one is added back
+                    let first = id
+                        .first_child(&self.arena)
+                        .map(|child| self.arena[child].get());
+                    if LEADING_NEWLINE_ELEMENTS.contains(&el.name.as_str())
+                        && first
+                            .is_some_and(|first| first.is_text() && first.text().starts_with('\n'))
+                    {
+                        out.push('\n');
+                    }
+                    let children: Vec<NodeId> = id.children(&self.arena).collect();
+                    stack.extend(children.into_iter().rev().map(|child| (child, false)));
+                }
+                DOMNodeKind::Text(text) => {
+                    let parent = self.arena[id]
+                        .parent()
+                        .map(|parent| self.arena[parent].get());
+                    if parent.is_some_and(|parent| RAW_TEXT_ELEMENTS.contains(&parent.name())) {
+                        out.push_str(text);
+                    } else {
+                        escape_text(text, &mut out);
+                    }
+                }
+                DOMNodeKind::Comment(text) => {
+                    out.push_str("<!--");
+                    out.push_str(text);
+                    out.push_str("-->");
+                }
+            }
+        }
+        out
+    }
+}
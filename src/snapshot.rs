@@ -0,0 +1,436 @@
+use crate::{
+    content_type_charset, decode_data_url, parse_document, DfError, DfResult, FontManager,
+    HtmlStreamParser, LoadState, PulledResource, Puller, ResourceKind, ResourceTiming, Subresource,
+    Timers, Vec2, Viewport, WebContext,
+};
+use bytes::Bytes;
+use std::time::Duration;
+use url::Url;
+
+/// Magic bytes at the start of every snapshot.
+const SNAPSHOT_MAGIC: &[u8; 6] = b"DFSNAP";
+
+/// Version of the snapshot format written by [`WebContext::snapshot`]. Snapshots of other
+/// versions are rejected by [`WebContext::restore`].
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Markup of a snapshotted page.
+enum Markup {
+    /// Markup set as a string (see [`WebContext::set_html`])
+    Text(String),
+    /// Pulled bytes, decoded with the charset of the content type or sniffed
+    Bytes {
+        data: Bytes,
+        content_type: Option<String>,
+    },
+}
+
+/// Writes the fields of a snapshot, little-endian and length-prefixed.
+struct SnapshotWriter(Vec<u8>);
+
+impl SnapshotWriter {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) -> DfResult<()> {
+        let len = u32::try_from(len)
+            .map_err(|_| DfError::InvalidSnapshot(format!("{len} is too large to snapshot")))?;
+        self.u32(len);
+        Ok(())
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> DfResult<()> {
+        self.len(bytes.len())?;
+        self.0.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn str(&mut self, s: &str) -> DfResult<()> {
+        self.bytes(s.as_bytes())
+    }
+
+    fn opt_str(&mut self, s: Option<&str>) -> DfResult<()> {
+        match s {
+            Some(s) => {
+                self.u8(1);
+                self.str(s)
+            }
+            None => {
+                self.u8(0);
+                Ok(())
+            }
+        }
+    }
+
+    fn duration(&mut self, duration: Duration) {
+        self.u64(duration.as_nanos().min(u64::MAX as u128) as u64);
+    }
+}
+
+/// Reads the fields written by [`SnapshotWriter`].
+struct SnapshotReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, len: usize) -> DfResult<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| DfError::InvalidSnapshot("unexpected end of snapshot".to_string()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> DfResult<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> DfResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> DfResult<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> DfResult<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> DfResult<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn bool(&mut self) -> DfResult<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn bytes(&mut self) -> DfResult<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> DfResult<String> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| DfError::InvalidSnapshot("invalid UTF-8 string".to_string()))
+    }
+
+    fn opt_string(&mut self) -> DfResult<Option<String>> {
+        match self.bool()? {
+            true => Ok(Some(self.string()?)),
+            false => Ok(None),
+        }
+    }
+
+    fn url(&mut self) -> DfResult<Url> {
+        Ok(Url::parse(&self.string()?)?)
+    }
+
+    fn duration(&mut self) -> DfResult<Duration> {
+        Ok(Duration::from_nanos(self.u64()?))
+    }
+}
+
+const RESOURCE_KINDS: [ResourceKind; 4] = [
+    ResourceKind::Stylesheet,
+    ResourceKind::Image,
+    ResourceKind::Script,
+    ResourceKind::BackgroundImage,
+];
+
+impl WebContext {
+    /// Serialize the loaded page with the subresources held by the cache of the puller,
+    /// so it can be restored without network (see [`WebContext::restore`]).
+    pub fn snapshot(&self) -> DfResult<Vec<u8>> {
+        self.snapshot_with(true)
+    }
+
+    /// Serialize the loaded page: its URL, markup (the markup string, or the pulled bytes if
+    /// they're still cached), base URL, timers, viewport and page language. If `resources`
+    /// is set, the subresources held by the cache of the puller are included.
+    ///
+    /// The styles and metadata are not stored, they are computed again from the markup and
+    /// the stylesheets when the snapshot is restored.
+    pub fn snapshot_with(&self, resources: bool) -> DfResult<Vec<u8>> {
+        let document = self.try_document()?;
+        let mut w = SnapshotWriter(SNAPSHOT_MAGIC.to_vec());
+        w.u32(SNAPSHOT_VERSION);
+        w.str(self.url.as_str())?;
+        w.str(self.base_url.as_str())?;
+
+        let markup = match &self.html_str {
+            Some(html) => Markup::Text(html.clone()),
+            None => match self.puller.cached(&self.url) {
+                Some(pulled) => Markup::Bytes {
+                    data: pulled.data,
+                    content_type: pulled.content_type,
+                },
+                // evicted from the cache, serialize the parsed document instead
+                None => Markup::Text(document.html()),
+            },
+        };
+        match &markup {
+            Markup::Text(html) => {
+                w.u8(0);
+                w.str(html)?;
+            }
+            Markup::Bytes { data, content_type } => {
+                w.u8(1);
+                w.bytes(data)?;
+                w.opt_str(content_type.as_deref())?;
+            }
+        }
+        w.str(&self.layout.default_lang)?;
+
+        let viewport = self.viewport();
+        w.f32(viewport.size.x);
+        w.f32(viewport.size.y);
+        w.f32(viewport.scale_factor);
+        w.u8(viewport.pixel_snapping as u8);
+
+        let timers = &self.timers;
+        for duration in [
+            timers.pull,
+            timers.parse,
+            timers.subresources,
+            timers.style,
+            timers.layout,
+            timers.paint,
+            timers.total,
+        ] {
+            w.duration(duration);
+        }
+        w.len(timers.resources.len())?;
+        for timing in &timers.resources {
+            w.str(timing.url.as_str())?;
+            w.u8(RESOURCE_KINDS
+                .iter()
+                .position(|&kind| kind == timing.kind)
+                .unwrap() as u8);
+            w.duration(timing.duration);
+            w.u64(timing.size as u64);
+            w.u8(timing.from_cache as u8);
+            w.opt_str(timing.error.as_deref())?;
+        }
+
+        let cached: Vec<(&Url, PulledResource)> = match resources {
+            true => timers
+                .resources
+                .iter()
+                .filter(|timing| timing.error.is_none())
+                .filter_map(|timing| Some((&timing.url, self.puller.cached(&timing.url)?)))
+                .collect(),
+            false => vec![],
+        };
+        w.len(cached.len())?;
+        for (url, pulled) in cached {
+            w.str(url.as_str())?;
+            w.bytes(&pulled.data)?;
+            w.opt_str(pulled.content_type.as_deref())?;
+            w.opt_str(pulled.content_language.as_deref())?;
+        }
+        Ok(w.0)
+    }
+
+    /// Restore a page serialized by [`WebContext::snapshot`], and lay it out again without
+    /// network: the subresources of the snapshot are added to the cache of `puller`, and
+    /// subresources missing from both are skipped. The timers are the ones of the original load.
+    ///
+    /// Snapshots of another [`SNAPSHOT_VERSION`] fail with [`DfError::InvalidSnapshot`].
+    ///
+    /// ```
+    /// use dragonfly::{FontManager, Puller, WebContext};
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = r#"<title>Snap</title><style>p { height: 30px; }</style>
+    /// <link rel="stylesheet" href="data:text/css,h1%20%7B%20height%3A%2050px%3B%20%7D">
+    /// <h1>Hello</h1><p>Some text</p>"#;
+    /// let mut ctx = WebContext::builder().html(html).url("https://example.com/").build()?;
+    /// block_on(ctx.load())?;
+    ///
+    /// let snapshot = ctx.snapshot()?;
+    /// let restored = WebContext::restore(&snapshot, FontManager::default(), Puller::default())?;
+    /// assert_eq!(restored.url(), ctx.url());
+    /// assert_eq!(restored.title().as_deref(), Some("Snap"));
+    /// assert_eq!(restored.to_text(), ctx.to_text());
+    /// // the restored page is laid out the same
+    /// let layout_json = |ctx: &WebContext| {
+    ///     let layout = &ctx.layout;
+    ///     let nodes: Vec<_> = layout
+    ///         .root_id()
+    ///         .descendants(&layout.arena)
+    ///         .map(|id| layout.arena[id].get())
+    ///         .map(|node| (node.name(), node.border_rect, node.padding, node.border))
+    ///         .collect();
+    ///     serde_json::to_string(&nodes).unwrap()
+    /// };
+    /// assert!(layout_json(&ctx).contains(r#"["h1",{"x":8.0,"#));
+    /// assert_eq!(layout_json(&restored), layout_json(&ctx));
+    ///
+    /// let mut corrupted = snapshot.clone();
+    /// corrupted[6] = 99;
+    /// assert!(WebContext::restore(&corrupted, FontManager::default(), Puller::default()).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn restore(bytes: &[u8], font_manager: FontManager, puller: Puller) -> DfResult<Self> {
+        let mut r = SnapshotReader {
+            data: bytes,
+            pos: 0,
+        };
+        if r.take(SNAPSHOT_MAGIC.len()).ok() != Some(SNAPSHOT_MAGIC.as_slice()) {
+            return Err(DfError::InvalidSnapshot("not a snapshot".to_string()));
+        }
+        let version = r.u32()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(DfError::InvalidSnapshot(format!(
+                "unsupported snapshot version {version} (expected {SNAPSHOT_VERSION})"
+            )));
+        }
+        let url = r.url()?;
+        let base_url = r.url()?;
+        let markup = match r.u8()? {
+            0 => Markup::Text(r.string()?),
+            1 => Markup::Bytes {
+                data: Bytes::copy_from_slice(r.bytes()?),
+                content_type: r.opt_string()?,
+            },
+            tag => {
+                return Err(DfError::InvalidSnapshot(format!(
+                    "unknown markup kind {tag}"
+                )))
+            }
+        };
+        let default_lang = r.string()?;
+        let viewport = Viewport {
+            size: Vec2::new(r.f32()?, r.f32()?),
+            scale_factor: r.f32()?,
+            pixel_snapping: r.bool()?,
+        };
+
+        let mut timers = Timers {
+            pull: r.duration()?,
+            parse: r.duration()?,
+            subresources: r.duration()?,
+            style: r.duration()?,
+            layout: r.duration()?,
+            paint: r.duration()?,
+            total: r.duration()?,
+            resources: vec![],
+        };
+        for _ in 0..r.u32()? {
+            let url = r.url()?;
+            let kind = *RESOURCE_KINDS
+                .get(r.u8()? as usize)
+                .ok_or_else(|| DfError::InvalidSnapshot("unknown resource kind".to_string()))?;
+            timers.resources.push(ResourceTiming {
+                url,
+                kind,
+                duration: r.duration()?,
+                size: r.u64()? as usize,
+                from_cache: r.bool()?,
+                error: r.opt_string()?,
+            });
+        }
+
+        for _ in 0..r.u32()? {
+            let url = r.url()?;
+            let resource = PulledResource {
+                data: Bytes::copy_from_slice(r.bytes()?),
+                content_type: r.opt_string()?,
+                content_language: r.opt_string()?,
+                ..Default::default()
+            };
+            puller.insert_cached(url, resource);
+        }
+        if r.pos != bytes.len() {
+            return Err(DfError::InvalidSnapshot(
+                "unexpected data after the snapshot".to_string(),
+            ));
+        }
+
+        let mut ctx = WebContext::builder()
+            .url(url.as_str())
+            .font_manager(font_manager)
+            .puller(puller)
+            .build()?;
+        ctx.layout.viewport = viewport;
+        ctx.layout.default_lang = default_lang;
+        let (document, encoding) = match markup {
+            Markup::Text(html) => {
+                let document = parse_document(&html);
+                ctx.html_str = Some(html);
+                (document, encoding_rs::UTF_8)
+            }
+            Markup::Bytes { data, content_type } => {
+                let mut parser =
+                    HtmlStreamParser::sniff(content_type_charset(content_type.as_deref()));
+                parser.feed(&data);
+                let resource = PulledResource {
+                    data,
+                    content_type,
+                    ..Default::default()
+                };
+                ctx.puller.insert_cached(url, resource);
+                parser.finish_with_encoding()
+            }
+        };
+        ctx.set_document(document, encoding);
+        ctx.base_url = base_url;
+
+        // subresources from the cache, data: URLs carry their content
+        let cached = |ctx: &WebContext, resource: &Subresource| {
+            let pulled = ctx
+                .puller
+                .cached(&resource.url)
+                .or_else(|| decode_data_url(&resource.url));
+            if pulled.is_none() {
+                log::warn!(
+                    "{} '{}' is not in the snapshot",
+                    resource.kind,
+                    resource.url
+                );
+            }
+            pulled
+        };
+        let mut stylesheets = vec![];
+        for resource in ctx.layout.subresources(&ctx.base_url) {
+            let Some(pulled) = cached(&ctx, &resource) else {
+                continue;
+            };
+            match resource.kind {
+                ResourceKind::Stylesheet => stylesheets.push((resource.node, resource.url, pulled)),
+                ResourceKind::Image => ctx.add_image(resource.node, &resource.url, &pulled.data),
+                ResourceKind::BackgroundImage | ResourceKind::Script => (),
+            }
+        }
+        ctx.set_author_stylesheets(stylesheets);
+        ctx.layout.compute_styles();
+        for resource in ctx.layout.background_subresources(&ctx.base_url) {
+            if let Some(pulled) = cached(&ctx, &resource) {
+                ctx.add_background_image(resource.node, &resource.url, pulled.data);
+            }
+        }
+        ctx.relayout();
+        ctx.update_metadata();
+        ctx.timers = timers;
+        ctx.load_state = LoadState::Loaded;
+        Ok(ctx)
+    }
+}
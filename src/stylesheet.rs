@@ -1,8 +1,13 @@
+use crate::{
+    collapse_whitespace, ComplexSelector, DOMNode, DfError, Dir, ElementState, Selector, Side,
+    Sides, DEFAULT_FONT_SIZE, DEFAULT_FONT_WEIGHT,
+};
 use css_color::Srgb;
+use std::borrow::Cow;
 use std::str::FromStr;
 use strum_macros::{Display, EnumString};
 
-#[derive(Debug, Clone, Copy, Display, Default, EnumString)]
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
 pub enum Position {
     /// Default. The element renders in the order as they appear in the document flow
     #[strum(serialize = "static")]
@@ -75,40 +80,2435 @@ pub enum FontFamily {
     Custom(String),
 }
 
-#[derive(Debug, Clone, Copy, Display, Default, EnumString)]
-pub enum Display {
-    #[strum(serialize = "block")]
-    #[default]
-    Block,
-    #[strum(serialize = "inline")]
-    Inline,
-    #[strum(serialize = "inline-block")]
-    InlineBlock,
-    #[strum(serialize = "flex")]
-    Flex,
-    #[strum(serialize = "inline-flex")]
-    InlineFlex,
-    #[strum(serialize = "grid")]
-    Grid,
-    #[strum(serialize = "inline-grid")]
-    InlineGrid,
-    #[strum(serialize = "flow-root")]
-    FlowRoot,
+/// Value of the `font-style` property.
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, FontStyle, Layout};
+/// let html = "<p>a <em>b <span>c</span></em></p>";
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let span = layout.select("span")[0];
+/// let text = span.children(&layout.arena).next().unwrap();
+/// assert_eq!(layout.arena[text].get().text_style().font_style, FontStyle::Italic);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Display, Default, EnumString)]
+pub enum FontStyle {
+    #[strum(serialize = "normal")]
+    #[default]
+    Normal,
+    #[strum(serialize = "italic")]
+    Italic,
+    /// Slanted version of the regular face, optionally with the angle (in degrees)
+    #[strum(serialize = "oblique")]
+    Oblique(Option<f32>),
+}
+
+impl FontStyle {
+    /// Parse a `font-style` value, such as `italic` or `oblique 10deg`.
+    ///
+    /// ```
+    /// use dragonfly::FontStyle;
+    /// assert_eq!(FontStyle::parse("italic"), Some(FontStyle::Italic));
+    /// assert_eq!(FontStyle::parse("oblique"), Some(FontStyle::Oblique(None)));
+    /// assert_eq!(FontStyle::parse("oblique 10deg"), Some(FontStyle::Oblique(Some(10.0))));
+    /// assert_eq!(FontStyle::parse("italic 10deg"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut tokens = value.split_whitespace();
+        let style = Self::from_str(tokens.next()?).ok()?;
+        match (style, tokens.next(), tokens.next()) {
+            (_, None, _) => Some(style),
+            (Self::Oblique(_), Some(angle), None) => {
+                let angle: f32 = angle.strip_suffix("deg")?.parse().ok()?;
+                (-90.0..=90.0)
+                    .contains(&angle)
+                    .then_some(Self::Oblique(Some(angle)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Value of the `font-weight` property.
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = r#"<p style="font-weight: 300">a <b>b <b>c</b></b></p>"#;
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let weights: Vec<u16> = layout
+///     .select("b")
+///     .iter()
+///     .map(|b| {
+///         let text = b.children(&layout.arena).next().unwrap();
+///         layout.arena[text].get().text_style().font_weight
+///     })
+///     .collect();
+/// assert_eq!(weights, [400, 700]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontWeight {
+    /// Numeric weight from 1 to 1000 (`normal` is 400, `bold` is 700)
+    Absolute(u16),
+    /// Bolder than the weight of the parent
+    Bolder,
+    /// Lighter than the weight of the parent
+    Lighter,
+}
+
+impl FontWeight {
+    pub const NORMAL: Self = Self::Absolute(400);
+    pub const BOLD: Self = Self::Absolute(700);
+
+    /// Resolve the weight against the weight of the parent element.
+    ///
+    /// ```
+    /// use dragonfly::FontWeight;
+    /// assert_eq!(FontWeight::Bolder.resolve(400), 700);
+    /// assert_eq!(FontWeight::Bolder.resolve(700), 900);
+    /// assert_eq!(FontWeight::Lighter.resolve(700), 400);
+    /// assert_eq!(FontWeight::Lighter.resolve(400), 100);
+    /// assert_eq!(FontWeight::BOLD.resolve(100), 700);
+    /// ```
+    pub fn resolve(self, parent: u16) -> u16 {
+        match self {
+            Self::Absolute(weight) => weight,
+            Self::Bolder => match parent {
+                0..350 => 400,
+                350..550 => 700,
+                _ => parent.max(900),
+            },
+            Self::Lighter => match parent {
+                0..550 => parent.min(100),
+                550..750 => 400,
+                _ => 700,
+            },
+        }
+    }
+}
+
+impl FromStr for FontWeight {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::NORMAL),
+            "bold" => Ok(Self::BOLD),
+            "bolder" => Ok(Self::Bolder),
+            "lighter" => Ok(Self::Lighter),
+            _ => s
+                .parse()
+                .ok()
+                .filter(|weight| (1..=1000).contains(weight))
+                .map(Self::Absolute)
+                .ok_or(strum::ParseError::VariantNotFound),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Display, Default, EnumString)]
+pub enum Display {
+    #[strum(serialize = "block")]
+    #[default]
+    Block,
+    #[strum(serialize = "inline")]
+    Inline,
+    #[strum(serialize = "inline-block")]
+    InlineBlock,
+    #[strum(serialize = "flex")]
+    Flex,
+    #[strum(serialize = "inline-flex")]
+    InlineFlex,
+    #[strum(serialize = "grid")]
+    Grid,
+    #[strum(serialize = "inline-grid")]
+    InlineGrid,
+    #[strum(serialize = "flow-root")]
+    FlowRoot,
+    #[strum(serialize = "none")]
+    None,
+    #[strum(serialize = "contents")]
+    Contents,
+    /// A block with a list marker
+    #[strum(serialize = "list-item")]
+    ListItem,
+}
+
+/// Direction of the main axis of a flex container (the `flex-direction` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum FlexDirection {
+    /// Items are laid out in the inline direction
+    #[strum(serialize = "row")]
+    #[default]
+    Row,
+    #[strum(serialize = "row-reverse")]
+    RowReverse,
+    /// Items are laid out in the block direction
+    #[strum(serialize = "column")]
+    Column,
+    #[strum(serialize = "column-reverse")]
+    ColumnReverse,
+}
+
+/// Whether the items of a flex container wrap onto multiple lines (the `flex-wrap` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum FlexWrap {
+    /// All items are on a single line
+    #[strum(serialize = "nowrap")]
+    #[default]
+    NoWrap,
+    #[strum(serialize = "wrap")]
+    Wrap,
+    /// Lines are stacked in the opposite direction of the cross axis
+    #[strum(serialize = "wrap-reverse")]
+    WrapReverse,
+}
+
+/// Distribution of the free space between and around the items of a flex line along the main
+/// axis (the `justify-content` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum JustifyContent {
+    /// Like `flex-start` in flex containers
+    #[strum(serialize = "normal")]
+    #[default]
+    Normal,
+    #[strum(serialize = "flex-start")]
+    FlexStart,
+    #[strum(serialize = "flex-end")]
+    FlexEnd,
+    #[strum(serialize = "start")]
+    Start,
+    #[strum(serialize = "end")]
+    End,
+    #[strum(serialize = "left")]
+    Left,
+    #[strum(serialize = "right")]
+    Right,
+    #[strum(serialize = "center")]
+    Center,
+    #[strum(serialize = "space-between")]
+    SpaceBetween,
+    #[strum(serialize = "space-around")]
+    SpaceAround,
+    #[strum(serialize = "space-evenly")]
+    SpaceEvenly,
+}
+
+/// Alignment of the items of a flex line along the cross axis (the `align-items` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum AlignItems {
+    /// Like `stretch` in flex containers
+    #[strum(serialize = "normal")]
+    #[default]
+    Normal,
+    /// Items without a cross size fill the line
+    #[strum(serialize = "stretch")]
+    Stretch,
+    #[strum(serialize = "flex-start")]
+    FlexStart,
+    #[strum(serialize = "flex-end")]
+    FlexEnd,
+    #[strum(serialize = "start")]
+    Start,
+    #[strum(serialize = "end")]
+    End,
+    #[strum(serialize = "center")]
+    Center,
+    /// Items are aligned by the baselines of their first lines
+    #[strum(serialize = "baseline")]
+    Baseline,
+}
+
+/// Distribution of the free space between and around the lines of a multi-line flex container
+/// along the cross axis (the `align-content` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum AlignContent {
+    /// Like `stretch` in flex containers
+    #[strum(serialize = "normal")]
+    #[default]
+    Normal,
+    /// Lines grow to fill the container
+    #[strum(serialize = "stretch")]
+    Stretch,
+    #[strum(serialize = "flex-start")]
+    FlexStart,
+    #[strum(serialize = "flex-end")]
+    FlexEnd,
+    #[strum(serialize = "start")]
+    Start,
+    #[strum(serialize = "end")]
+    End,
+    #[strum(serialize = "center")]
+    Center,
+    #[strum(serialize = "space-between")]
+    SpaceBetween,
+    #[strum(serialize = "space-around")]
+    SpaceAround,
+    #[strum(serialize = "space-evenly")]
+    SpaceEvenly,
+}
+
+/// Alignment of a flex item along the cross axis, overriding the `align-items` of its
+/// container (the `align-self` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum AlignSelf {
+    /// The `align-items` of the container
+    #[strum(serialize = "auto")]
+    #[default]
+    Auto,
+    #[strum(serialize = "normal")]
+    Normal,
+    #[strum(serialize = "stretch")]
+    Stretch,
+    #[strum(serialize = "flex-start")]
+    FlexStart,
+    #[strum(serialize = "flex-end")]
+    FlexEnd,
+    #[strum(serialize = "start")]
+    Start,
+    #[strum(serialize = "end")]
+    End,
+    #[strum(serialize = "center")]
+    Center,
+    #[strum(serialize = "baseline")]
+    Baseline,
+}
+
+/// Size of a grid track (a column or a row) in `grid-template-columns` and
+/// `grid-template-rows`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackSize {
+    /// A length or a percentage of the grid container
+    Length(Dimension),
+    /// A share of the free space (`fr`)
+    Fraction(f32),
+    /// Sized to the content, and grows into the free space
+    Auto,
+    MinContent,
+    MaxContent,
+}
+
+impl TrackSize {
+    /// Maximum number of tracks in a list. Longer lists, such as a huge `repeat()` count, are
+    /// invalid.
+    pub const MAX_TRACKS: usize = 10000;
+
+    /// Parse a list of track sizes, with `repeat(n, ...)` expanded. `none` is an empty list.
+    ///
+    /// ```
+    /// use dragonfly::{Dimension, TrackSize};
+    /// let tracks = TrackSize::parse_list("100px repeat(2, 1fr auto) 20%").unwrap();
+    /// assert_eq!(tracks.len(), 6);
+    /// assert_eq!(tracks[0], TrackSize::Length(Dimension::px(100.0)));
+    /// assert_eq!(tracks[1..5], [TrackSize::Fraction(1.0), TrackSize::Auto].repeat(2));
+    /// assert_eq!(TrackSize::parse_list("repeat(3, 1fr)").unwrap(), [TrackSize::Fraction(1.0); 3]);
+    /// assert_eq!(TrackSize::parse_list("none").unwrap(), []);
+    /// assert!(TrackSize::parse_list("repeat(auto-fill, 10px)").is_none());
+    /// assert!(TrackSize::parse_list("-1fr").is_none());
+    /// assert_eq!(TrackSize::parse_list("repeat(5000, 1fr 1fr)").unwrap().len(), 10000);
+    /// assert!(TrackSize::parse_list("1fr repeat(5000, 1fr 1fr)").is_none());
+    /// assert!(TrackSize::parse_list("repeat(1000000000, 1fr)").is_none());
+    /// ```
+    pub fn parse_list(value: &str) -> Option<Vec<Self>> {
+        if value.trim() == "none" {
+            return Some(vec![]);
+        }
+        let mut tracks = vec![];
+        let tokens = crate::selector::split_top_level(value.trim(), ' ');
+        for token in tokens.into_iter().filter(|token| !token.is_empty()) {
+            match token
+                .strip_prefix("repeat(")
+                .and_then(|args| args.strip_suffix(')'))
+            {
+                Some(args) => {
+                    let (count, repeated) = args.split_once(',')?;
+                    let count: usize = count.trim().parse().ok().filter(|&n| n > 0)?;
+                    let repeated = Self::parse_list(repeated)?;
+                    if repeated.is_empty() {
+                        return None;
+                    }
+                    // checked before expanding, the count can be huge
+                    repeated
+                        .len()
+                        .checked_mul(count)
+                        .and_then(|len| len.checked_add(tracks.len()))
+                        .filter(|&len| len <= Self::MAX_TRACKS)?;
+                    tracks.extend(repeated.repeat(count));
+                }
+                None => tracks.push(Self::parse(token)?),
+            }
+            if tracks.len() > Self::MAX_TRACKS {
+                return None;
+            }
+        }
+        (!tracks.is_empty()).then_some(tracks)
+    }
+
+    /// Parse a single track size, such as `1fr` or `20%`.
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "min-content" => Some(Self::MinContent),
+            "max-content" => Some(Self::MaxContent),
+            _ => match value.strip_suffix("fr") {
+                Some(fraction) => fraction
+                    .parse()
+                    .ok()
+                    .filter(|fraction: &f32| *fraction >= 0.0)
+                    .map(Self::Fraction),
+                None => parse_length_percentage(value)
+                    .filter(|length| length.number >= 0.0)
+                    .map(Self::Length),
+            },
+        }
+    }
+}
+
+/// A line that a grid item starts or ends at.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum GridLine {
+    /// Placed automatically
+    #[default]
+    Auto,
+    /// A line number, from 1. Negative numbers count from the last line
+    Line(i32),
+    /// A number of tracks from the other edge of the item
+    Span(u32),
+}
+
+impl GridLine {
+    fn parse(value: &str) -> Option<Self> {
+        let tokens: Vec<&str> = value.split_whitespace().collect();
+        match tokens[..] {
+            ["auto"] => Some(Self::Auto),
+            ["span", span] | [span, "span"] => span.parse().ok().filter(|&n| n > 0).map(Self::Span),
+            [line] => line.parse().ok().filter(|&n| n != 0).map(Self::Line),
+            _ => None,
+        }
+    }
+}
+
+/// Placement of a grid item along an axis (the `grid-column` and `grid-row` properties).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GridPlacement {
+    pub start: GridLine,
+    pub end: GridLine,
+}
+
+impl GridPlacement {
+    /// Parse a placement, the start and end lines separated by a `/`. Without an end line,
+    /// the item spans one track.
+    ///
+    /// ```
+    /// use dragonfly::{GridLine, GridPlacement};
+    /// let placement = GridPlacement::parse("2 / span 3").unwrap();
+    /// assert_eq!((placement.start, placement.end), (GridLine::Line(2), GridLine::Span(3)));
+    /// let placement = GridPlacement::parse("span 2").unwrap();
+    /// assert_eq!((placement.start, placement.end), (GridLine::Span(2), GridLine::Auto));
+    /// assert_eq!(GridPlacement::parse("-1").unwrap().start, GridLine::Line(-1));
+    /// assert!(GridPlacement::parse("0 / 2").is_none());
+    /// assert!(GridPlacement::parse("span 0").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.split_once('/') {
+            Some((start, end)) => Some(Self {
+                start: GridLine::parse(start)?,
+                end: GridLine::parse(end)?,
+            }),
+            None => Some(Self {
+                start: GridLine::parse(value)?,
+                end: GridLine::Auto,
+            }),
+        }
+    }
+}
+
+/// Base direction of text (the `direction` property). The `dir` attribute of elements sets it
+/// as a presentational hint, which style sheets can override.
+///
+/// The lines of right-to-left blocks start at their right edge. The characters are not
+/// reordered (there's no bidirectional algorithm).
+///
+/// ```
+/// use dragonfly::{parse_document, Dir, FontManager, Layout};
+/// let html = r#"<div style="direction: rtl"><p>right</p><p style="direction: ltr">left</p></div>
+///     <p dir="rtl" style="direction: ltr">left</p>"#;
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let p = layout.select("p");
+/// let direction: Vec<Dir> = p.iter().map(|&p| layout.arena[p].get().direction).collect();
+/// assert_eq!(direction, [Dir::Rtl, Dir::Ltr, Dir::Ltr]);
+/// let x = |p| layout.arena[p].get().border_rect.min().x;
+/// let text_x = |p: indextree::NodeId| {
+///     layout.arena[p.children(&layout.arena).next().unwrap()].get().fragments[0].pos.x
+/// };
+/// assert!(text_x(p[0]) > x(p[0]) + 100.0);
+/// assert_eq!(text_x(p[1]), x(p[1]));
+///
+/// // `dir="auto"` takes the direction of the first strong character of the text
+/// let html = r#"<html lang="en"><p dir="auto">123 שלום, <b>world</b></p><p dir="auto">hi שלום</p>"#;
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let p = layout.select("p");
+/// assert_eq!(layout.arena[p[0]].get().direction, Dir::Rtl);
+/// assert_eq!(layout.arena[layout.select("b")[0]].get().direction, Dir::Rtl);
+/// assert_eq!(layout.arena[p[1]].get().direction, Dir::Ltr);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum Direction {
+    #[strum(serialize = "ltr")]
+    #[default]
+    Ltr,
+    #[strum(serialize = "rtl")]
+    Rtl,
+}
+
+impl From<Direction> for Dir {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Ltr => Dir::Ltr,
+            Direction::Rtl => Dir::Rtl,
+        }
+    }
+}
+
+/// Horizontal alignment of the lines of a block (the `text-align` property).
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = r#"<p>text</p><p style="text-align: center">text</p>
+///     <p style="text-align: right">text</p>"#;
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let x: Vec<f32> = layout
+///     .select("p")
+///     .iter()
+///     .map(|p| {
+///         let text = p.children(&layout.arena).next().unwrap();
+///         layout.arena[text].get().border_rect.pos.x
+///     })
+///     .collect();
+/// assert!(x[0] < x[1] && x[1] < x[2]);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum TextAlign {
+    /// Default. Lines are aligned to the start edge (the right edge in right-to-left text)
+    #[strum(serialize = "start")]
+    #[default]
+    Start,
+    /// Lines are aligned to the end edge (the left edge in right-to-left text)
+    #[strum(serialize = "end")]
+    End,
+    #[strum(serialize = "left")]
+    Left,
+    #[strum(serialize = "right")]
+    Right,
+    #[strum(serialize = "center")]
+    Center,
+    /// Lines are stretched to the width of the block (laid out like `start` for now)
+    #[strum(serialize = "justify")]
+    Justify,
+}
+
+/// What the `width` and `height` of a box (and their minimums and maximums) measure (the
+/// `box-sizing` property). With `border-box` the content is shrunk to make room for the
+/// padding and the border, but never below 0:
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = "<div style='box-sizing: border-box; width: 100px; height: 50px;
+///     padding: 10px; border: 2px solid'></div>
+///     <div style='box-sizing: border-box; width: 20px; padding: 15px; border: 5px solid'></div>
+///     <div style='width: 100px; padding: 10px'></div>";
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let size = |selector| layout.arena[layout.select(selector)[0]].get().border_rect.size;
+/// assert_eq!((size("div").x, size("div").y), (100.0, 50.0));
+/// assert_eq!(size("div:nth-child(2)").x, 15.0 * 2.0 + 5.0 * 2.0);
+/// assert_eq!(size("div:last-child").x, 120.0);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum BoxSizing {
+    /// Sizes are of the content box
+    #[strum(serialize = "content-box")]
+    #[default]
+    ContentBox,
+    /// Sizes include the padding and the border
+    #[strum(serialize = "border-box")]
+    BorderBox,
+}
+
+/// Whether the boxes of an element are painted (the `visibility` property). Hidden elements
+/// still take space, and their descendants can be made visible again:
+///
+/// ```
+/// use dragonfly::{parse_document, DisplayItem, FontManager, Layout};
+/// let html = "<div style='visibility: hidden'>hidden <span style='visibility: visible'>shown</span></div>
+///     <p style='visibility: collapse'>collapsed</p><p>after</p>";
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let texts: Vec<String> = layout
+///     .display_list()
+///     .into_iter()
+///     .filter_map(|item| match item {
+///         DisplayItem::Text { text, .. } => Some(text.trim().to_string()),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(texts, ["shown", "after"]);
+/// let y = |selector| layout.arena[layout.select(selector)[0]].get().border_rect.pos.y;
+/// assert!(y("p:last-child") > y("p") + 16.0);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum Visibility {
+    #[strum(serialize = "visible")]
+    #[default]
+    Visible,
+    /// Not painted, but laid out
+    #[strum(serialize = "hidden")]
+    Hidden,
+    /// Like `hidden`, there are no table rows and columns it would remove
+    #[strum(serialize = "collapse")]
+    Collapse,
+}
+
+/// How the content that overflows a box is handled (the `overflow` properties).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum Overflow {
+    /// Overflowing content is painted
+    #[strum(serialize = "visible")]
+    #[default]
+    Visible,
+    /// Clipped to the padding box, it can still be scrolled by scripts
+    #[strum(serialize = "hidden")]
+    Hidden,
+    /// Clipped to the padding box, it can't be scrolled
+    #[strum(serialize = "clip")]
+    Clip,
+    /// Clipped, with scrollbars
+    #[strum(serialize = "scroll")]
+    Scroll,
+    /// Clipped, with scrollbars if the content overflows
+    #[strum(serialize = "auto")]
+    Auto,
+}
+
+impl Overflow {
+    /// Whether the overflowing content is clipped.
+    pub fn clips(self) -> bool {
+        self != Self::Visible
+    }
+
+    /// Whether the box can be scrolled, which makes it clip on both axes.
+    pub fn scrolls(self) -> bool {
+        matches!(self, Self::Hidden | Self::Scroll | Self::Auto)
+    }
+
+    /// Parse the `overflow` shorthand: a value for both axes, or the horizontal and vertical
+    /// values.
+    pub fn parse_shorthand(value: &str) -> Option<(Self, Self)> {
+        let values: Vec<&str> = value.split_whitespace().collect();
+        match values[..] {
+            [both] => Some((Self::from_str(both).ok()?, Self::from_str(both).ok()?)),
+            [x, y] => Some((Self::from_str(x).ok()?, Self::from_str(y).ok()?)),
+            _ => None,
+        }
+    }
+}
+
+/// Handling of the whitespace in text (the `white-space` property).
+///
+/// The text of the DOM keeps its whitespace, it's collapsed or preserved when laying it out.
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = "<pre>a  b\nc</pre><p>a  b\nc</p><p style='white-space: pre-line'>a  b\nc</p>";
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let lines = |selector| -> Vec<String> {
+///     let element = layout.select(selector)[0];
+///     let text = layout.arena[element.children(&layout.arena).next().unwrap()].get();
+///     text.fragments.iter().map(|f| f.text.clone()).collect()
+/// };
+/// assert_eq!(lines("pre"), ["a  b", "c"]);
+/// assert_eq!(lines("p"), ["a b c"]);
+/// assert_eq!(lines("p:last-child"), ["a b", "c"]);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum WhiteSpace {
+    /// Whitespace is collapsed, lines wrap
+    #[strum(serialize = "normal")]
+    #[default]
+    Normal,
+    /// Whitespace is collapsed, lines don't wrap
+    #[strum(serialize = "nowrap")]
+    Nowrap,
+    /// Whitespace is preserved, lines only break at newlines
+    #[strum(serialize = "pre")]
+    Pre,
+    /// Whitespace is preserved, lines wrap
+    #[strum(serialize = "pre-wrap")]
+    PreWrap,
+    /// Spaces are collapsed but newlines are preserved, lines wrap
+    #[strum(serialize = "pre-line")]
+    PreLine,
+}
+
+impl WhiteSpace {
+    /// Whether runs of spaces are collapsed to a single space.
+    pub fn collapses_spaces(&self) -> bool {
+        matches!(self, Self::Normal | Self::Nowrap | Self::PreLine)
+    }
+
+    /// Whether newlines are preserved as line breaks.
+    pub fn preserves_newlines(&self) -> bool {
+        matches!(self, Self::Pre | Self::PreWrap | Self::PreLine)
+    }
+
+    /// Whether lines can wrap at spaces (and between CJK characters).
+    pub fn wraps(&self) -> bool {
+        matches!(self, Self::Normal | Self::PreWrap | Self::PreLine)
+    }
+
+    /// Text with its whitespace handled: collapsible whitespace is collapsed (keeping
+    /// preserved newlines), and preserved tabs are replaced by 8 spaces.
+    ///
+    /// ```
+    /// use dragonfly::WhiteSpace;
+    /// assert_eq!(WhiteSpace::Normal.apply("a \n\t b"), "a b");
+    /// assert_eq!(WhiteSpace::PreLine.apply("a  \n\t b"), "a \n b");
+    /// assert_eq!(WhiteSpace::Pre.apply("a  \tb"), "a          b");
+    /// ```
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.collapses_spaces() {
+            true if *self == Self::PreLine && text.contains('\n') => {
+                let lines: Vec<_> = text.split('\n').map(collapse_whitespace).collect();
+                Cow::Owned(lines.join("\n"))
+            }
+            true => collapse_whitespace(text),
+            false if text.contains('\t') => Cow::Owned(text.replace('\t', "        ")),
+            false => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Whether a word that doesn't fit on a line can be broken at any character (the
+/// `overflow-wrap` property, also known as `word-wrap`).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum OverflowWrap {
+    /// Words are only broken at regular break opportunities
+    #[strum(serialize = "normal")]
+    #[default]
+    Normal,
+    /// Words that overflow an empty line are broken at any character
+    #[strum(serialize = "break-word")]
+    BreakWord,
+    /// Like `break-word` (the min-content width is not computed)
+    #[strum(serialize = "anywhere")]
+    Anywhere,
+}
+
+/// Break opportunities between the letters of words (the `word-break` property).
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = "<p style='width: 30px; word-break: break-all'>abcdefghij</p>
+///     <p style='width: 30px; overflow-wrap: anywhere'>abc abcdefghij</p>
+///     <p style='width: 30px'>abcdefghij</p>";
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let lines = |i: usize| {
+///     let p = layout.select("p")[i];
+///     layout.arena[p.children(&layout.arena).next().unwrap()].get().fragments.len()
+/// };
+/// assert!(lines(0) > 1);
+/// assert!(lines(1) > 2);
+/// assert_eq!(lines(2), 1);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum WordBreak {
+    /// Lines break at spaces and between CJK characters
+    #[strum(serialize = "normal")]
+    #[default]
+    Normal,
+    /// Lines can also break between any two letters
+    #[strum(serialize = "break-all")]
+    BreakAll,
+    /// Lines don't break between CJK characters either, only at spaces
+    #[strum(serialize = "keep-all")]
+    KeepAll,
+}
+
+/// Case transformation of text (the `text-transform` property).
+///
+/// The text of the DOM is left as is, the transformation is applied when laying it out.
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = r#"<p style="text-transform: uppercase">straße</p>"#;
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let p = layout.select("p")[0];
+/// let text = layout.arena[p.children(&layout.arena).next().unwrap()].get();
+/// assert_eq!(text.text(), "straße");
+/// assert_eq!(text.fragments[0].text, "STRASSE");
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum TextTransform {
+    #[strum(serialize = "none")]
+    #[default]
+    None,
+    #[strum(serialize = "uppercase")]
+    Uppercase,
+    #[strum(serialize = "lowercase")]
+    Lowercase,
+    /// The first letter of each word is uppercased
+    #[strum(serialize = "capitalize")]
+    Capitalize,
+}
+
+impl TextTransform {
+    /// Transform a string. The result can be longer than the original text.
+    ///
+    /// ```
+    /// use dragonfly::TextTransform;
+    /// assert_eq!(TextTransform::Uppercase.apply("straße"), "STRASSE");
+    /// assert_eq!(TextTransform::Lowercase.apply("ÉLAN"), "élan");
+    /// assert_eq!(TextTransform::Capitalize.apply("ßen (élan) 3d"), "SSen (Élan) 3d");
+    /// ```
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::None => Cow::Borrowed(text),
+            Self::Uppercase => Cow::Owned(text.to_uppercase()),
+            Self::Lowercase => Cow::Owned(text.to_lowercase()),
+            Self::Capitalize => {
+                let mut result = String::with_capacity(text.len());
+                // punctuation before the first letter or digit of a word is skipped
+                let mut word_start = true;
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        word_start = true;
+                        result.push(c);
+                    } else if c.is_alphanumeric() && std::mem::take(&mut word_start) {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.push(c);
+                    }
+                }
+                Cow::Owned(result)
+            }
+        }
+    }
+}
+
+/// A color value, or the `currentcolor` keyword.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Color {
+    /// The value of the `color` property of the element
+    #[default]
+    CurrentColor,
+    Srgb(Srgb),
+}
+
+impl Color {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.eq_ignore_ascii_case("currentcolor") {
+            true => Some(Self::CurrentColor),
+            false => Srgb::from_str(value).ok().map(Self::Srgb),
+        }
+    }
+
+    /// Resolve the color, given the value of the `color` property.
+    pub fn resolve(self, current_color: Srgb) -> Srgb {
+        match self {
+            Self::CurrentColor => current_color,
+            Self::Srgb(color) => color,
+        }
+    }
+}
+
+/// Style of a side of a border (the `border-style` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum BorderStyle {
+    /// No border, its width is 0
+    #[strum(serialize = "none")]
+    #[default]
+    None,
+    /// Like `none`
+    #[strum(serialize = "hidden")]
+    Hidden,
+    #[strum(serialize = "solid")]
+    Solid,
+    #[strum(serialize = "dashed")]
+    Dashed,
+    #[strum(serialize = "dotted")]
+    Dotted,
+    #[strum(serialize = "double")]
+    Double,
+    #[strum(serialize = "groove")]
+    Groove,
+    #[strum(serialize = "ridge")]
+    Ridge,
+    #[strum(serialize = "inset")]
+    Inset,
+    #[strum(serialize = "outset")]
+    Outset,
+}
+
+impl BorderStyle {
+    /// Whether the border is drawn and takes space (it's not `none` or `hidden`).
+    pub fn is_visible(&self) -> bool {
+        !matches!(self, Self::None | Self::Hidden)
+    }
+}
+
+/// Width, style and color of a side of a border.
+///
+/// The parts are set separately by the longhands (`border-top-width`...) and together by the
+/// shorthands (`border`, `border-top`...).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BorderSide {
+    /// Width of the border, `thin`, `medium` and `thick` are 1px, 3px and 5px
+    pub width: Option<Dimension>,
+    pub style: Option<BorderStyle>,
+    pub color: Option<Color>,
+}
+
+impl BorderSide {
+    /// Width of the `medium` keyword, the initial width.
+    pub const MEDIUM: f32 = 3.0;
+
+    /// Parse a border shorthand value, such as `1px solid red`. Parts that are not specified
+    /// are set to their initial values (`medium none currentcolor`).
+    ///
+    /// ```
+    /// use dragonfly::{BorderSide, BorderStyle, Color};
+    /// let side = BorderSide::parse("thin dashed").unwrap();
+    /// assert_eq!(side.width.unwrap().to_px(16.0), 1.0);
+    /// assert_eq!(side.style, Some(BorderStyle::Dashed));
+    /// assert_eq!(side.color, Some(Color::CurrentColor));
+    /// assert!(BorderSide::parse("solid solid").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let (width, style, color) = parse_line(value, |token| BorderStyle::from_str(token).ok())?;
+        Some(Self {
+            width: Some(width),
+            style: Some(style),
+            color: Some(color),
+        })
+    }
+
+    /// Overwrite the parts that are set in `other`.
+    fn apply(&mut self, other: &BorderSide) {
+        overlay(&mut self.width, &other.width);
+        overlay(&mut self.style, &other.style);
+        overlay(&mut self.color, &other.color);
+    }
+}
+
+/// Parse the width, style and color of a border or outline shorthand, in any order. Parts that
+/// are not specified are set to their initial values (`medium none currentcolor`).
+fn parse_line(
+    value: &str,
+    parse_style: impl Fn(&str) -> Option<BorderStyle>,
+) -> Option<(Dimension, BorderStyle, Color)> {
+    let (mut width, mut style, mut color) = (None, None, None);
+    let tokens = crate::selector::split_top_level(value, ' ');
+    for token in tokens.into_iter().filter(|token| !token.is_empty()) {
+        if let (None, Some(value)) = (width, parse_border_width(token)) {
+            width = Some(value);
+        } else if let (None, Some(value)) = (style, parse_style(token)) {
+            style = Some(value);
+        } else if let (None, Some(value)) = (color, Color::parse(token)) {
+            color = Some(value);
+        } else {
+            return None;
+        }
+    }
+    if width.is_none() && style.is_none() && color.is_none() {
+        return None;
+    }
+    Some((
+        width.unwrap_or(Dimension::px(BorderSide::MEDIUM)),
+        style.unwrap_or_default(),
+        color.unwrap_or_default(),
+    ))
+}
+
+/// Outline drawn around the border box of an element (the `outline` properties). Unlike
+/// borders, outlines don't take space: they're drawn around the box, over what's next to it.
+///
+/// ```
+/// use dragonfly::{BorderStyle, Declaration};
+/// let style = Declaration::from_inline("outline: 2px dotted red; outline-offset: -1px");
+/// assert_eq!(style.outline.style, Some(BorderStyle::Dotted));
+/// assert_eq!(style.outline.offset.unwrap().to_px(16.0), -1.0);
+/// let style = Declaration::from_inline("outline: auto; outline: none");
+/// assert_eq!(style.outline.style, Some(BorderStyle::None));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Outline {
+    pub width: Option<Dimension>,
+    /// Style of the outline, `auto` is drawn `solid`
+    pub style: Option<BorderStyle>,
+    pub color: Option<Color>,
+    /// Distance between the border box and the outline, can be negative
+    pub offset: Option<Dimension>,
+}
+
+impl Outline {
+    /// Parse the `outline` shorthand, such as `2px solid red`. Parts that are not specified are
+    /// set to their initial values, the offset is left as is.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (width, style, color) = parse_line(value, parse_outline_style)?;
+        Some(Self {
+            width: Some(width),
+            style: Some(style),
+            color: Some(color),
+            offset: None,
+        })
+    }
+
+    /// Overwrite the parts that are set in `other`.
+    fn apply(&mut self, other: &Outline) {
+        overlay(&mut self.width, &other.width);
+        overlay(&mut self.style, &other.style);
+        overlay(&mut self.color, &other.color);
+        overlay(&mut self.offset, &other.offset);
+    }
+}
+
+/// Parse an outline style: the border styles without `hidden`, and `auto`.
+fn parse_outline_style(value: &str) -> Option<BorderStyle> {
+    match value {
+        "auto" => Some(BorderStyle::Solid),
+        "hidden" => None,
+        _ => BorderStyle::from_str(value).ok(),
+    }
+}
+
+/// Parse a length that can be negative, such as an outline offset. Percentages are invalid.
+fn parse_length(value: &str) -> Option<Dimension> {
+    let length = Dimension::from_str(value);
+    let valid = value.starts_with(|c: char| c.is_ascii_digit() || ".-+".contains(c))
+        && !length.is_auto()
+        && !matches!(length.unit, Unit::Percent(_));
+    valid.then_some(length)
+}
+
+/// Parse a border width: a length or `thin`, `medium` or `thick`. Negative widths and
+/// percentages are invalid.
+fn parse_border_width(value: &str) -> Option<Dimension> {
+    let px = match value {
+        "thin" => 1.0,
+        "medium" => BorderSide::MEDIUM,
+        "thick" => 5.0,
+        _ => {
+            let length = Dimension::from_str(value);
+            let valid = value.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+                && !length.is_auto()
+                && !matches!(length.unit, Unit::Percent(_));
+            return valid.then_some(length);
+        }
+    };
+    Some(Dimension::px(px))
+}
+
+/// Horizontal and vertical radii of a rounded corner of a border box. Percentages are relative
+/// to the width (`x`) and the height (`y`) of the border box.
+#[derive(Debug, Clone, Copy)]
+pub struct CornerRadius {
+    pub x: Dimension,
+    pub y: Dimension,
+}
+
+impl CornerRadius {
+    /// Parse the value of a corner longhand (`border-top-left-radius`...): one radius for
+    /// both axes, or the horizontal and vertical radii.
+    ///
+    /// ```
+    /// use dragonfly::CornerRadius;
+    /// let radius = CornerRadius::parse("10px 20%").unwrap();
+    /// assert_eq!((radius.x.to_px(16.0), radius.y.number), (10.0, 20.0));
+    /// assert!(CornerRadius::parse("-1px").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let values: Vec<Dimension> = value
+            .split_whitespace()
+            .map(parse_radius)
+            .collect::<Option<_>>()?;
+        match values[..] {
+            [radius] => Some(Self {
+                x: radius,
+                y: radius,
+            }),
+            [x, y] => Some(Self { x, y }),
+            _ => None,
+        }
+    }
+
+    /// Parse the `border-radius` shorthand: one to four horizontal radii, then optionally a
+    /// `/` and one to four vertical radii. The values are expanded like the ones of `margin`,
+    /// the corners are in the order top-left, top-right, bottom-right, bottom-left.
+    ///
+    /// ```
+    /// use dragonfly::CornerRadius;
+    /// let [top_left, top_right, bottom_right, bottom_left] =
+    ///     CornerRadius::parse_shorthand("1px 2px / 3px").unwrap();
+    /// let px = |radius: CornerRadius| (radius.x.to_px(16.0), radius.y.to_px(16.0));
+    /// assert_eq!(px(top_left), (1.0, 3.0));
+    /// assert_eq!(px(top_right), (2.0, 3.0));
+    /// assert_eq!(px(bottom_right), (1.0, 3.0));
+    /// assert_eq!(px(bottom_left), (2.0, 3.0));
+    /// assert!(CornerRadius::parse_shorthand("1px / 2px / 3px").is_none());
+    /// ```
+    pub fn parse_shorthand(value: &str) -> Option<[Self; 4]> {
+        let corners = |values: &str| -> Option<[Dimension; 4]> {
+            let values: Vec<Dimension> = values
+                .split_whitespace()
+                .map(parse_radius)
+                .collect::<Option<_>>()?;
+            let sides = Sides::from_shorthand(&values)?;
+            Some([sides.top, sides.right, sides.bottom, sides.left])
+        };
+        let (x, y) = match value.split_once('/') {
+            Some((x, y)) => (corners(x)?, corners(y)?),
+            None => (corners(value)?, corners(value)?),
+        };
+        Some(std::array::from_fn(|i| Self { x: x[i], y: y[i] }))
+    }
+}
+
+/// Parse a corner radius: a non-negative length or percentage.
+fn parse_radius(value: &str) -> Option<Dimension> {
+    let radius = Dimension::from_str(value);
+    let valid = value.starts_with(|c: char| c.is_ascii_digit() || c == '.') && !radius.is_auto();
+    valid.then_some(radius)
+}
+
+/// Shadow of a box (the `box-shadow` property). Shadows don't take space.
+#[derive(Debug, Clone, Copy)]
+pub struct Shadow {
+    pub offset_x: Dimension,
+    pub offset_y: Dimension,
+    /// Blur radius, `0` for a sharp shadow
+    pub blur: Dimension,
+    /// Distance the shadow is expanded by, or shrunk by if negative
+    pub spread: Dimension,
+    pub color: Color,
+    /// Whether the shadow is drawn inside the padding box instead of outside of the border box
+    pub inset: bool,
+}
+
+impl Shadow {
+    /// Parse the `box-shadow` property: `none` (no shadows) or a comma-separated list of
+    /// shadows, the first one drawn on top. A shadow has 2 to 4 lengths (the offsets, the blur
+    /// radius and the spread), and optionally a color and `inset` before or after them.
+    ///
+    /// ```
+    /// use dragonfly::{Color, Shadow};
+    /// let shadows = Shadow::parse_list("rgb(0, 0, 0) 1px 2px, inset 0 0 3px -1px red").unwrap();
+    /// let px = |shadow: &Shadow| {
+    ///     [shadow.offset_x, shadow.offset_y, shadow.blur, shadow.spread].map(|d| d.to_px(16.0))
+    /// };
+    /// assert_eq!(px(&shadows[0]), [1.0, 2.0, 0.0, 0.0]);
+    /// assert_eq!((shadows[0].color, shadows[0].inset), (Color::parse("black").unwrap(), false));
+    /// assert_eq!(px(&shadows[1]), [0.0, 0.0, 3.0, -1.0]);
+    /// assert_eq!((shadows[1].color, shadows[1].inset), (Color::parse("red").unwrap(), true));
+    /// assert!(Shadow::parse_list("none").unwrap().is_empty());
+    /// assert!(Shadow::parse_list("1px 1px -1px").is_none());
+    /// assert!(Shadow::parse_list("1px red 1px").is_none());
+    /// ```
+    pub fn parse_list(value: &str) -> Option<Vec<Self>> {
+        if value == "none" {
+            return Some(vec![]);
+        }
+        crate::selector::split_top_level(value, ',')
+            .into_iter()
+            .map(|shadow| Self::parse(shadow.trim()))
+            .collect()
+    }
+
+    /// Parse a single shadow, such as `inset 1px 1px 2px red`.
+    fn parse(value: &str) -> Option<Self> {
+        let (mut lengths, mut color, mut inset) = (vec![], None, false);
+        let tokens = crate::selector::split_top_level(value, ' ');
+        let mut tokens = tokens
+            .into_iter()
+            .filter(|token| !token.is_empty())
+            .peekable();
+        while let Some(token) = tokens.next() {
+            if token == "inset" && !inset {
+                inset = true;
+            } else if let (None, Some(value)) = (&color, Color::parse(token)) {
+                color = Some(value);
+            } else if lengths.is_empty() {
+                // the lengths can't be split by the color or `inset`
+                lengths.push(parse_length(token)?);
+                while let Some(length) = tokens.peek().and_then(|token| parse_length(token)) {
+                    lengths.push(length);
+                    tokens.next();
+                }
+            } else {
+                return None;
+            }
+        }
+        let zero = Dimension::px(0.0);
+        let (offset_x, offset_y, blur, spread) = match lengths[..] {
+            [x, y] => (x, y, zero, zero),
+            [x, y, blur] => (x, y, blur, zero),
+            [x, y, blur, spread] => (x, y, blur, spread),
+            _ => return None,
+        };
+        if blur.number < 0.0 {
+            return None;
+        }
+        Some(Self {
+            offset_x,
+            offset_y,
+            blur,
+            spread,
+            color: color.unwrap_or_default(),
+            inset,
+        })
+    }
+}
+
+/// Image drawn in the background of a box (the `background-image` property).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum BackgroundImage {
+    #[default]
+    None,
+    /// URL of the image, as written in the stylesheet
+    Url(String),
+}
+
+impl BackgroundImage {
+    /// Parse `none` or a `url()`, with or without quotes.
+    ///
+    /// ```
+    /// use dragonfly::BackgroundImage;
+    /// let image = BackgroundImage::parse("url('a b.png')").unwrap();
+    /// assert_eq!(image, BackgroundImage::Url("a b.png".to_string()));
+    /// assert!(BackgroundImage::parse("bg.png").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        if value == "none" {
+            return Some(Self::None);
+        }
+        let url = value.strip_prefix("url(")?.strip_suffix(')')?.trim();
+        let unquoted = ['"', '\'']
+            .into_iter()
+            .find_map(|quote| url.strip_prefix(quote)?.strip_suffix(quote));
+        Some(Self::Url(unquoted.unwrap_or(url).to_string()))
+    }
+}
+
+/// Marker of a list item (the `list-style-type` property): a bullet shape, or a counter
+/// written in a numbering system.
+///
+/// ```
+/// use dragonfly::ListStyleType;
+/// assert_eq!(ListStyleType::UpperRoman.counter(1994).unwrap(), "MCMXCIV");
+/// assert_eq!(ListStyleType::LowerAlpha.counter(28).unwrap(), "ab");
+/// // counters that can't be written in a system are decimal
+/// assert_eq!(ListStyleType::LowerAlpha.counter(0).unwrap(), "0");
+/// assert_eq!(ListStyleType::Disc.counter(1), None);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum ListStyleType {
+    /// Filled circle
+    #[strum(serialize = "disc")]
+    #[default]
+    Disc,
+    /// Hollow circle
+    #[strum(serialize = "circle")]
+    Circle,
+    /// Filled square
+    #[strum(serialize = "square")]
+    Square,
+    #[strum(serialize = "decimal")]
+    Decimal,
+    #[strum(serialize = "lower-alpha", serialize = "lower-latin")]
+    LowerAlpha,
+    #[strum(serialize = "upper-alpha", serialize = "upper-latin")]
+    UpperAlpha,
+    #[strum(serialize = "lower-roman")]
+    LowerRoman,
+    #[strum(serialize = "upper-roman")]
+    UpperRoman,
+    /// No marker
+    #[strum(serialize = "none")]
+    None,
+}
+
+impl ListStyleType {
+    /// Whether the marker is a shape rather than text.
+    pub fn is_bullet(self) -> bool {
+        matches!(self, Self::Disc | Self::Circle | Self::Square)
+    }
+
+    /// Counter of the item `number` (without the `.` suffix), `None` for bullets and `none`.
+    pub fn counter(self, number: i64) -> Option<String> {
+        let alpha = |first: u8| {
+            let mut n = number;
+            let mut letters = vec![];
+            while n > 0 {
+                n -= 1;
+                letters.push((first + (n % 26) as u8) as char);
+                n /= 26;
+            }
+            letters.iter().rev().collect::<String>()
+        };
+        let roman = || {
+            const NUMERALS: [(i64, &str); 13] = [
+                (1000, "m"),
+                (900, "cm"),
+                (500, "d"),
+                (400, "cd"),
+                (100, "c"),
+                (90, "xc"),
+                (50, "l"),
+                (40, "xl"),
+                (10, "x"),
+                (9, "ix"),
+                (5, "v"),
+                (4, "iv"),
+                (1, "i"),
+            ];
+            let mut n = number;
+            let mut numerals = String::new();
+            for (value, numeral) in NUMERALS {
+                while n >= value {
+                    numerals.push_str(numeral);
+                    n -= value;
+                }
+            }
+            numerals
+        };
+        Some(match self {
+            Self::Disc | Self::Circle | Self::Square | Self::None => return None,
+            Self::LowerAlpha if number > 0 => alpha(b'a'),
+            Self::UpperAlpha if number > 0 => alpha(b'A'),
+            Self::LowerRoman if (1..4000).contains(&number) => roman(),
+            Self::UpperRoman if (1..4000).contains(&number) => roman().to_uppercase(),
+            _ => number.to_string(),
+        })
+    }
+}
+
+/// Where the marker of a list item is (the `list-style-position` property).
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum ListStylePosition {
+    /// Before the start edge of the item
+    #[strum(serialize = "outside")]
+    #[default]
+    Outside,
+    /// At the start of the first line, like text
+    #[strum(serialize = "inside")]
+    Inside,
+}
+
+/// The parts of the `list-style` shorthand, such as `square inside`.
+struct ListStyle {
+    kind: ListStyleType,
+    position: ListStylePosition,
+    image: BackgroundImage,
+}
+
+impl ListStyle {
+    /// Parse the shorthand, the parts that aren't specified are reset. `none` is the type or
+    /// the image, whichever isn't specified (or both).
+    fn parse(value: &str) -> Option<Self> {
+        let (mut kind, mut position, mut image, mut nones) = (None, None, None, 0);
+        let tokens = crate::selector::split_top_level(value, ' ');
+        for token in tokens.into_iter().filter(|token| !token.is_empty()) {
+            if token == "none" {
+                nones += 1;
+            } else if let (None, Ok(value)) = (kind, ListStyleType::from_str(token)) {
+                kind = Some(value);
+            } else if let (None, Ok(value)) = (position, ListStylePosition::from_str(token)) {
+                position = Some(value);
+            } else if let (None, Some(value)) = (&image, BackgroundImage::parse(token)) {
+                image = Some(value);
+            } else {
+                return None;
+            }
+        }
+        // an unset image is already `none`
+        let unset = kind.is_none() as usize + image.is_none() as usize;
+        if nones > unset {
+            return None;
+        }
+        if nones > 0 && kind.is_none() {
+            kind = Some(ListStyleType::None);
+        }
+        Some(Self {
+            kind: kind.unwrap_or_default(),
+            position: position.unwrap_or_default(),
+            image: image.unwrap_or_default(),
+        })
+    }
+}
+
+/// How a background image is repeated along an axis.
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum Repeat {
+    #[strum(serialize = "repeat")]
+    #[default]
+    Repeat,
+    /// Repeated without clipping, the space left is distributed between the images
+    #[strum(serialize = "space")]
+    Space,
+    /// Repeated without clipping, the images are scaled to fill the space
+    #[strum(serialize = "round")]
+    Round,
+    #[strum(serialize = "no-repeat")]
+    NoRepeat,
+}
+
+/// How a background image is repeated horizontally and vertically (the `background-repeat`
+/// property).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BackgroundRepeat {
+    pub x: Repeat,
+    pub y: Repeat,
+}
+
+impl BackgroundRepeat {
+    /// Parse `repeat-x`, `repeat-y`, or a value for both axes, or a value for each axis.
+    pub fn parse(value: &str) -> Option<Self> {
+        let values: Vec<&str> = value.split_whitespace().collect();
+        let (x, y) = match values[..] {
+            ["repeat-x"] => (Repeat::Repeat, Repeat::NoRepeat),
+            ["repeat-y"] => (Repeat::NoRepeat, Repeat::Repeat),
+            [both] => (Repeat::from_str(both).ok()?, Repeat::from_str(both).ok()?),
+            [x, y] => (Repeat::from_str(x).ok()?, Repeat::from_str(y).ok()?),
+            _ => return None,
+        };
+        Some(Self { x, y })
+    }
+}
+
+/// Position of a background image in its box (the `background-position` property).
+/// A percentage aligns the same point of the image and of the box, so `100%` aligns their
+/// right or bottom edges.
+#[derive(Debug, Clone, Copy)]
+pub struct BackgroundPosition {
+    pub x: Dimension,
+    pub y: Dimension,
+}
+
+impl Default for BackgroundPosition {
+    fn default() -> Self {
+        Self {
+            x: percent(0.0),
+            y: percent(0.0),
+        }
+    }
+}
+
+impl BackgroundPosition {
+    /// Parse one or two keywords (`left`, `center`, `top`...) or lengths. With one value, the
+    /// other axis is centered. Keywords can be in any order (`top left`), lengths are
+    /// horizontal first.
+    ///
+    /// ```
+    /// use dragonfly::BackgroundPosition;
+    /// let position = BackgroundPosition::parse("bottom 10px").unwrap();
+    /// assert_eq!((position.x.to_px(16.0), position.y.number), (10.0, 100.0));
+    /// let position = BackgroundPosition::parse("top").unwrap();
+    /// assert_eq!((position.x.number, position.y.number), (50.0, 0.0));
+    /// assert!(BackgroundPosition::parse("left right").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let values: Vec<&str> = value.split_whitespace().collect();
+        Self::parse_values(&values)
+    }
+
+    fn parse_values(values: &[&str]) -> Option<Self> {
+        let vertical = |value: &str| matches!(value, "top" | "bottom");
+        let horizontal = |value: &str| matches!(value, "left" | "right");
+        let (x, y) = match *values {
+            [y] if vertical(y) => ("center", y),
+            [x] => (x, "center"),
+            [y, x] if vertical(y) || horizontal(x) => (x, y),
+            [x, y] => (x, y),
+            _ => return None,
+        };
+        if vertical(x) || horizontal(y) {
+            return None;
+        }
+        let offset = |value: &str| match value {
+            "left" | "top" => Some(percent(0.0)),
+            "center" => Some(percent(50.0)),
+            "right" | "bottom" => Some(percent(100.0)),
+            _ => parse_length_percentage(value),
+        };
+        Some(Self {
+            x: offset(x)?,
+            y: offset(y)?,
+        })
+    }
+}
+
+/// Size of a background image (the `background-size` property).
+#[derive(Debug, Clone, Copy)]
+pub enum BackgroundSize {
+    /// Scaled to cover the whole box, keeping its aspect ratio
+    Cover,
+    /// Scaled to fit in the box, keeping its aspect ratio
+    Contain,
+    /// Width and height, [`Unit::Auto`] for the intrinsic size or to keep the aspect ratio
+    Size(Dimension, Dimension),
+}
+
+impl Default for BackgroundSize {
+    fn default() -> Self {
+        let auto = Dimension::from_str("auto");
+        Self::Size(auto, auto)
+    }
+}
+
+impl BackgroundSize {
+    /// Parse `cover`, `contain`, or a width and optionally a height (`auto` if not specified).
+    pub fn parse(value: &str) -> Option<Self> {
+        let values: Vec<&str> = value.split_whitespace().collect();
+        Self::parse_values(&values)
+    }
+
+    fn parse_values(values: &[&str]) -> Option<Self> {
+        let length = |value: &str| match value {
+            "auto" => Some(Dimension::from_str(value)),
+            _ => parse_length_percentage(value).filter(|length| length.number >= 0.0),
+        };
+        match *values {
+            ["cover"] => Some(Self::Cover),
+            ["contain"] => Some(Self::Contain),
+            [width] => Some(Self::Size(length(width)?, Dimension::from_str("auto"))),
+            [width, height] => Some(Self::Size(length(width)?, length(height)?)),
+            _ => None,
+        }
+    }
+}
+
+/// A percentage.
+fn percent(n: f32) -> Dimension {
+    Dimension {
+        number: n,
+        unit: Unit::Percent(n),
+    }
+}
+
+/// Parse a length or a percentage, which can be negative.
+fn parse_length_percentage(value: &str) -> Option<Dimension> {
+    let length = Dimension::from_str(value);
+    let valid = value.starts_with(|c: char| c.is_ascii_digit() || ".-+".contains(c))
+        && !length.is_auto()
+        && !length.is_quirky();
+    valid.then_some(length)
+}
+
+/// The parts of the `background` shorthand, such as `#fff url(bg.png) no-repeat center`.
+struct BackgroundShorthand {
+    color: Srgb,
+    image: BackgroundImage,
+    repeat: BackgroundRepeat,
+    position: BackgroundPosition,
+    size: BackgroundSize,
+}
+
+impl BackgroundShorthand {
+    /// Parse the shorthand, `None` if any part of it is invalid. Its parts are in any order,
+    /// the size follows the position after a `/`. `background-attachment`, `-origin` and
+    /// `-clip` keywords are accepted but ignored.
+    fn parse(value: &str) -> Option<Self> {
+        let is_position = |token: &str| {
+            matches!(token, "left" | "right" | "top" | "bottom" | "center")
+                || parse_length_percentage(token).is_some()
+        };
+        let is_repeat = |token: &str| BackgroundRepeat::parse(token).is_some();
+        let is_size = |token: &str| BackgroundSize::parse(token).is_some();
+        // `url()` and colors are single tokens, `/` is a token of its own
+        let tokens: Vec<&str> = crate::selector::split_top_level(value, ' ')
+            .into_iter()
+            .flat_map(|token| match token.starts_with("url(") {
+                true => vec![token],
+                false => split_keep(token, '/'),
+            })
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        let (mut color, mut image, mut repeat, mut position, mut size) =
+            (None, None, None, None, None);
+        let mut i = 0;
+        // take the next tokens that match, at most `max`
+        let take = |i: &mut usize, max: usize, matches: &dyn Fn(&str) -> bool| {
+            let start = *i;
+            while *i < tokens.len() && *i - start < max && matches(tokens[*i]) {
+                *i += 1;
+            }
+            &tokens[start..*i]
+        };
+        while i < tokens.len() {
+            let token = tokens[i];
+            if image.is_none() && BackgroundImage::parse(token).is_some() {
+                image = BackgroundImage::parse(token);
+                i += 1;
+            } else if repeat.is_none() && is_repeat(token) {
+                repeat = BackgroundRepeat::parse(&take(&mut i, 2, &is_repeat).join(" "));
+            } else if position.is_none() && is_position(token) {
+                position = Some(BackgroundPosition::parse_values(take(
+                    &mut i,
+                    2,
+                    &is_position,
+                ))?);
+                if tokens.get(i) == Some(&"/") {
+                    i += 1;
+                    size = Some(BackgroundSize::parse_values(take(&mut i, 2, &is_size))?);
+                }
+            } else if color.is_none() && Srgb::from_str(token).is_ok() {
+                color = Srgb::from_str(token).ok();
+                i += 1;
+            } else if matches!(
+                token,
+                "scroll" | "fixed" | "local" | "border-box" | "padding-box" | "content-box"
+            ) {
+                i += 1;
+            } else {
+                return None;
+            }
+        }
+        Some(Self {
+            color: color.unwrap_or(Srgb::new(0.0, 0.0, 0.0, 0.0)),
+            image: image.unwrap_or_default(),
+            repeat: repeat.unwrap_or_default(),
+            position: position.unwrap_or_default(),
+            size: size.unwrap_or_default(),
+        })
+    }
+}
+
+/// Split a string at a separator, keeping the separators as parts of their own.
+fn split_keep(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    for (i, _) in s.match_indices(separator) {
+        parts.extend([&s[start..i], &s[i..i + separator.len_utf8()]]);
+        start = i + separator.len_utf8();
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Part of the sides of a border set by a border property.
+#[derive(Debug, Clone, Copy)]
+enum BorderPart {
+    /// Every part, from a shorthand like `1px solid red`
+    All,
+    Width,
+    Style,
+    Color,
+}
+
+const ALL_SIDES: &[Side] = &[Side::Top, Side::Right, Side::Bottom, Side::Left];
+
+/// Border properties, with the sides and the part of them they set. Properties that set
+/// the same part of every side (`border-width`...) take one to four values like `margin`.
+const BORDER_PROPERTIES: &[(&str, &[Side], BorderPart)] = &[
+    ("border", ALL_SIDES, BorderPart::All),
+    ("border-width", ALL_SIDES, BorderPart::Width),
+    ("border-style", ALL_SIDES, BorderPart::Style),
+    ("border-color", ALL_SIDES, BorderPart::Color),
+    ("border-top", &[Side::Top], BorderPart::All),
+    ("border-right", &[Side::Right], BorderPart::All),
+    ("border-bottom", &[Side::Bottom], BorderPart::All),
+    ("border-left", &[Side::Left], BorderPart::All),
+    ("border-top-width", &[Side::Top], BorderPart::Width),
+    ("border-right-width", &[Side::Right], BorderPart::Width),
+    ("border-bottom-width", &[Side::Bottom], BorderPart::Width),
+    ("border-left-width", &[Side::Left], BorderPart::Width),
+    ("border-top-style", &[Side::Top], BorderPart::Style),
+    ("border-right-style", &[Side::Right], BorderPart::Style),
+    ("border-bottom-style", &[Side::Bottom], BorderPart::Style),
+    ("border-left-style", &[Side::Left], BorderPart::Style),
+    ("border-top-color", &[Side::Top], BorderPart::Color),
+    ("border-right-color", &[Side::Right], BorderPart::Color),
+    ("border-bottom-color", &[Side::Bottom], BorderPart::Color),
+    ("border-left-color", &[Side::Left], BorderPart::Color),
+];
+
+/// Parse the value of a border property for each side it sets (see [`BORDER_PROPERTIES`]).
+/// Only the parts named by the property are set.
+fn parse_border_property(name: &str, value: &str) -> Option<Vec<(Side, BorderSide)>> {
+    let &(_, sides, part) = BORDER_PROPERTIES.iter().find(|(n, ..)| *n == name)?;
+    let parse = |token: &str| -> Option<BorderSide> {
+        let mut side = BorderSide::default();
+        match part {
+            BorderPart::All => return BorderSide::parse(token),
+            BorderPart::Width => side.width = Some(parse_border_width(token)?),
+            BorderPart::Style => side.style = Some(BorderStyle::from_str(token).ok()?),
+            BorderPart::Color => side.color = Some(Color::parse(token)?),
+        }
+        Some(side)
+    };
+
+    let values: Vec<BorderSide> = match (part, sides.len()) {
+        (BorderPart::All, _) | (_, 1) => vec![parse(value)?],
+        _ => {
+            let tokens = crate::selector::split_top_level(value, ' ');
+            let tokens = tokens.into_iter().filter(|token| !token.is_empty());
+            tokens.map(parse).collect::<Option<_>>()?
+        }
+    };
+    let values = match sides.len() {
+        1 => Sides::splat(values[0]),
+        _ => Sides::from_shorthand(&values)?,
+    };
+    Some(sides.iter().map(|&side| (side, values[side])).collect())
+}
+
+/// Lines of the `text-decoration-line` property, no lines is `none`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextDecorationLine {
+    pub underline: bool,
+    pub overline: bool,
+    pub line_through: bool,
+}
+
+impl TextDecorationLine {
+    /// Whether there are no lines (`none`).
+    pub fn is_none(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Parse `none`, or a list of `underline`, `overline` and `line-through`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut line = Self::default();
+        if value == "none" {
+            return Some(line);
+        }
+        for token in value.split_whitespace() {
+            let flag = match token {
+                "underline" => &mut line.underline,
+                "overline" => &mut line.overline,
+                "line-through" => &mut line.line_through,
+                _ => return None,
+            };
+            if std::mem::replace(flag, true) {
+                return None;
+            }
+        }
+        (!line.is_none()).then_some(line)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum TextDecorationStyle {
+    #[strum(serialize = "solid")]
+    #[default]
+    Solid,
+    #[strum(serialize = "double")]
+    Double,
+    #[strum(serialize = "dotted")]
+    Dotted,
+    #[strum(serialize = "dashed")]
+    Dashed,
+    #[strum(serialize = "wavy")]
+    Wavy,
+}
+
+/// Values of the `text-decoration-line`, `text-decoration-style` and `text-decoration-color`
+/// properties, set by the `text-decoration` shorthand.
+///
+/// Decorations are not inherited, but they're drawn over the text of the descendants of the
+/// element (see [`TextStyle::decorations`]).
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = r#"<p style="text-decoration: line-through">
+///     <a href="/a">a</a> <a href="/b" style="text-decoration: none">b</a></p>"#;
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let decorations: Vec<_> = layout
+///     .select("a")
+///     .iter()
+///     .map(|a| {
+///         let text = a.children(&layout.arena).next().unwrap();
+///         let fragment = &layout.arena[text].get().fragments[0];
+///         let lines = fragment.style.decorations.iter().map(|d| d.line);
+///         lines.map(|l| (l.line_through, l.underline)).collect::<Vec<_>>()
+///     })
+///     .collect();
+/// // links are underlined by the default stylesheet
+/// assert_eq!(decorations[0], [(true, false), (false, true)]);
+/// assert_eq!(decorations[1], [(true, false)]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextDecoration {
+    pub line: Option<TextDecorationLine>,
+    pub style: Option<TextDecorationStyle>,
+    pub color: Option<Color>,
+}
+
+impl TextDecoration {
+    /// Parse the `text-decoration` shorthand, such as `underline dotted red`.
+    /// Parts that are not specified are set to their initial values.
+    ///
+    /// ```
+    /// use dragonfly::{Color, TextDecoration, TextDecorationStyle};
+    /// let decoration = TextDecoration::parse("underline overline wavy rgb(255, 0, 0)").unwrap();
+    /// let line = decoration.line.unwrap();
+    /// assert!(line.underline && line.overline && !line.line_through);
+    /// assert_eq!(decoration.style, Some(TextDecorationStyle::Wavy));
+    /// assert!(matches!(decoration.color, Some(Color::Srgb(_))));
+    /// assert!(TextDecoration::parse("none").unwrap().line.unwrap().is_none());
+    /// assert!(TextDecoration::parse("underline none").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let (mut lines, mut style, mut color) = (vec![], None, None);
+        let tokens = crate::selector::split_top_level(value, ' ');
+        for token in tokens.into_iter().filter(|token| !token.is_empty()) {
+            if matches!(token, "none" | "underline" | "overline" | "line-through") {
+                lines.push(token);
+            } else if let (None, Ok(value)) = (style, TextDecorationStyle::from_str(token)) {
+                style = Some(value);
+            } else if let (None, Some(value)) = (color, Color::parse(token)) {
+                color = Some(value);
+            } else {
+                return None;
+            }
+        }
+        let line = match lines.is_empty() {
+            true if style.is_none() && color.is_none() => return None,
+            true => TextDecorationLine::default(),
+            false => TextDecorationLine::parse(&lines.join(" "))?,
+        };
+        Some(Self {
+            line: Some(line),
+            style: Some(style.unwrap_or_default()),
+            color: Some(color.unwrap_or_default()),
+        })
+    }
+}
+
+/// A text decoration drawn over a run of text, set on its element or on an ancestor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decoration {
+    pub line: TextDecorationLine,
+    pub style: TextDecorationStyle,
+    pub color: Srgb,
+}
+
+impl Decoration {
+    /// Thickness of the lines for a font size, in pixels.
+    pub fn thickness(font_size: f32) -> f32 {
+        (font_size / 16.0).round().max(1.0)
+    }
+
+    /// Vertical offsets of the top of the lines from the baseline (positive below it), given
+    /// the font size and the ascent of the font. A `double` line is drawn twice.
+    ///
+    /// ```
+    /// use dragonfly::{Decoration, TextDecorationLine, TextDecorationStyle};
+    /// let decoration = Decoration {
+    ///     line: TextDecorationLine { underline: true, overline: true, line_through: false },
+    ///     style: TextDecorationStyle::Solid,
+    ///     color: css_color::Srgb::new(0.0, 0.0, 0.0, 1.0),
+    /// };
+    /// assert_eq!(decoration.offsets(16.0, 12.0), [2.0, -12.0]);
+    /// ```
+    pub fn offsets(&self, font_size: f32, ascent: f32) -> Vec<f32> {
+        let thickness = Self::thickness(font_size);
+        let mut offsets = vec![];
+        // doubled lines are drawn away from the text
+        let mut push = |offset: f32, away: f32| {
+            offsets.push(offset);
+            if self.style == TextDecorationStyle::Double {
+                offsets.push(offset + away * thickness * 2.0);
+            }
+        };
+        if self.line.underline {
+            push((font_size / 8.0).round().max(1.0), 1.0);
+        }
+        if self.line.overline {
+            push(-ascent, -1.0);
+        }
+        if self.line.line_through {
+            push((-ascent * 0.35).round(), 1.0);
+        }
+        offsets
+    }
+}
+
+/// Value of the `line-height` property.
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = r#"<p style="font-size: 20px; line-height: 1">a</p>
+///     <p style="font-size: 20px; line-height: 2">a</p>"#;
+/// let mut fonts = FontManager::default();
+/// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+/// let heights: Vec<f32> = layout
+///     .select("p")
+///     .iter()
+///     .map(|p| layout.arena[*p].get().border_rect.size.y)
+///     .collect();
+/// assert_eq!(heights, [20.0, 40.0]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub enum LineHeight {
+    /// Default line height of the font
+    #[default]
+    Normal,
+    /// Multiple of the font size of the element, inherited as is
+    Number(f32),
+    /// Length, or percentage of the font size of the element
+    Length(Dimension),
+}
+
+impl LineHeight {
+    /// Parse a `line-height` value. `1.5` is a multiplier, while `1.5em` is a length.
+    ///
+    /// ```
+    /// use dragonfly::LineHeight;
+    /// assert!(matches!(LineHeight::parse("1.5"), Some(LineHeight::Number(1.5))));
+    /// let length = LineHeight::parse("1.5em").unwrap();
+    /// assert!(matches!(length, LineHeight::Length(d) if d.to_px(10.0) == 15.0));
+    /// assert!(LineHeight::parse("-1").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        if value == "normal" {
+            return Some(Self::Normal);
+        }
+        if let Ok(number) = value.parse::<f32>() {
+            return (number >= 0.0).then_some(Self::Number(number));
+        }
+        let length = Dimension::from_str(value);
+        let valid =
+            value.starts_with(|c: char| c.is_ascii_digit() || c == '.') && !length.is_auto();
+        valid.then_some(Self::Length(length))
+    }
+
+    /// Resolve the line height in pixels for a font size, `None` for `normal`.
+    /// `lh` lengths are relative to `parent_line_height`.
+    pub fn resolve(&self, font_size: f32, parent_line_height: f32) -> Option<f32> {
+        match self {
+            Self::Normal => None,
+            Self::Number(n) => Some(n * font_size),
+            Self::Length(Dimension {
+                unit: Unit::RelativeToLineHeight(n),
+                ..
+            }) => Some(n * parent_line_height),
+            Self::Length(length) => length.resolve(font_size, Some(font_size)),
+        }
+    }
+}
+
+/// Value of the `vertical-align` property: the position of an inline-level box on its line.
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = "<p>x<sup>2</sup>y<sub>i</sub></p>";
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let rect = |selector| layout.arena[layout.select(selector)[0]].get().border_rect;
+/// let text = layout.arena[layout.select("p")[0].children(&layout.arena).next().unwrap()].get();
+/// let x = text.fragments[0].pos.y;
+/// assert!(rect("sup").min().y < x - 1.0);
+/// assert!(rect("sub").max().y > x + text.fragments[0].size.y + 1.0);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub enum VerticalAlign {
+    /// The baseline of the box is on the baseline of its parent
+    #[default]
+    Baseline,
+    /// Lowered to the position of subscripts of the parent
+    Sub,
+    /// Raised to the position of superscripts of the parent
+    Super,
+    /// The top of the box is aligned with the top of the text of the parent
+    TextTop,
+    /// The bottom of the box is aligned with the bottom of the text of the parent
+    TextBottom,
+    /// The middle of the box is aligned with the middle of the lowercase letters of the parent
+    Middle,
+    /// The top of the box is aligned with the top of the line
+    Top,
+    /// The bottom of the box is aligned with the bottom of the line
+    Bottom,
+    /// Raised by a length, or by a percentage of the line height of the element
+    Length(Dimension),
+}
+
+impl VerticalAlign {
+    /// Parse a `vertical-align` value.
+    ///
+    /// ```
+    /// use dragonfly::VerticalAlign;
+    /// assert!(matches!(VerticalAlign::parse("text-top"), Some(VerticalAlign::TextTop)));
+    /// let length = VerticalAlign::parse("-2px").unwrap();
+    /// assert!(matches!(length, VerticalAlign::Length(d) if d.to_px(10.0) == -2.0));
+    /// assert!(VerticalAlign::parse("center").is_none());
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let keyword = match value {
+            "baseline" => Self::Baseline,
+            "sub" => Self::Sub,
+            "super" => Self::Super,
+            "text-top" => Self::TextTop,
+            "text-bottom" => Self::TextBottom,
+            "middle" => Self::Middle,
+            "top" => Self::Top,
+            "bottom" => Self::Bottom,
+            _ => {
+                let length = Dimension::from_str(value);
+                let valid = value.starts_with(|c: char| c.is_ascii_digit() || ".-+".contains(c))
+                    && !length.is_auto();
+                return valid.then_some(Self::Length(length));
+            }
+        };
+        Some(keyword)
+    }
+}
+
+/// Side a box is floated to. Floats are placed at the edge of their block formatting context,
+/// the line boxes next to them are shortened, and boxes with [`Clear`] move below them.
+///
+/// ```
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let html = "<!DOCTYPE html><body style='margin: 0; width: 200px'>
+///     <div id=left style='float: left; width: 50px; height: 30px'></div>
+///     <div id=right style='float: right; width: 40px; height: 80px'></div>
+///     <p style='margin: 0'>aaa bbb ccc ddd eee fff ggg hhh iii jjj</p>
+///     <div id=clear-left style='clear: left; height: 10px'></div>
+///     <div id=clear-right style='clear: right; height: 10px'></div></body>";
+/// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+/// let rect = |selector| layout.arena[layout.select(selector)[0]].get().border_rect;
+/// assert_eq!(rect("#left").to_string(), "50x30 at (0, 0)");
+/// assert_eq!(rect("#right").to_string(), "40x80 at (160, 0)");
+///
+/// // the text wraps between the floats, on lines shortened by the floats next to them
+/// let text = layout.select("p")[0].children(&layout.arena).next().unwrap();
+/// let lines = &layout.arena[text].get().fragments;
+/// assert!(lines.len() > 2);
+/// for line in lines {
+///     let left = if line.pos.y < 30.0 { 50.0 } else { 0.0 };
+///     assert_eq!(line.pos.x, left, "{:?}", line.text);
+///     assert!(line.pos.x + line.size.x <= 160.0, "{:?}", line.text);
+/// }
+/// assert_eq!(lines[0].text, "aaa bbb ccc");
+/// assert_eq!(lines.last().unwrap().pos.x, 0.0);
+///
+/// // the paragraph ends below the left float, so only clearing the right one moves a box
+/// let paragraph = rect("p");
+/// assert!(paragraph.max().y > 30.0 && paragraph.max().y < 80.0);
+/// assert_eq!(rect("#clear-left").pos.y, paragraph.max().y);
+/// assert_eq!(rect("#clear-right").pos.y, 80.0);
+/// ```
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum Float {
+    /// Default. The element is not floated and is laid out in the normal flow
+    #[strum(serialize = "none")]
+    #[default]
+    None,
+    /// The element floats to the left edge of its containing block
+    #[strum(serialize = "left")]
+    Left,
+    /// The element floats to the right edge of its containing block
+    #[strum(serialize = "right")]
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Display, Default, PartialEq, EnumString)]
+pub enum Clear {
+    /// Default. The element is not moved down to clear past floats
     #[strum(serialize = "none")]
+    #[default]
     None,
-    #[strum(serialize = "contents")]
-    Contents,
+    /// The element is moved down to clear past left floats
+    #[strum(serialize = "left")]
+    Left,
+    /// The element is moved down to clear past right floats
+    #[strum(serialize = "right")]
+    Right,
+    /// The element is moved down to clear past both left and right floats
+    #[strum(serialize = "both")]
+    Both,
 }
 
 /// CSS rule declaration for one or multiple selectors.
+///
+/// Every property is optional: `None` means that the property was not specified,
+/// so it can be overridden by other rules or inherited from the parent.
 #[derive(Debug, Clone, Default)]
 pub struct Declaration {
-    pub display: Display,
-    pub position: Position,
+    pub display: Option<Display>,
+    /// Direction of the items of a flex container. Flex containers are laid out as blocks for
+    /// now, their properties are only computed for embedders:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FlexDirection, FlexWrap, FontManager, JustifyContent, Layout};
+    /// let html = "<div style='display: flex; flex-flow: wrap column; justify-content: center;
+    ///     align-items: sideways'></div>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let style = layout.arena[layout.select("div")[0]].get().style.clone().unwrap();
+    /// assert_eq!(style.flex_direction, Some(FlexDirection::Column));
+    /// assert_eq!(style.flex_wrap, Some(FlexWrap::Wrap));
+    /// assert_eq!(style.justify_content, Some(JustifyContent::Center));
+    /// // unknown keywords are the initial value
+    /// assert_eq!(style.align_items, Some(Default::default()));
+    /// ```
+    pub flex_direction: Option<FlexDirection>,
+    pub flex_wrap: Option<FlexWrap>,
+    pub justify_content: Option<JustifyContent>,
+    pub align_items: Option<AlignItems>,
+    pub align_content: Option<AlignContent>,
+    /// How much a flex item grows to fill the free space of its line, relative to the other
+    /// items. The `flex` shorthand sets it with `flex-shrink` and `flex-basis`:
+    ///
+    /// ```
+    /// use dragonfly::{Declaration, Dimension, Unit};
+    /// let flex = |value| {
+    ///     let style = Declaration::from_inline(&format!("flex: {value}"));
+    ///     (style.flex_grow.unwrap(), style.flex_shrink.unwrap(), style.flex_basis.unwrap().unit)
+    /// };
+    /// // a single number is a grow factor with a basis of 0%
+    /// assert_eq!(flex("2"), (2.0, 1.0, Unit::Percent(0.0)));
+    /// assert_eq!(flex("2 3"), (2.0, 3.0, Unit::Percent(0.0)));
+    /// assert_eq!(flex("10px"), (1.0, 1.0, Unit::Absolute(10.0)));
+    /// assert_eq!(flex("0 0 50%"), (0.0, 0.0, Unit::Percent(50.0)));
+    /// assert_eq!(flex("auto"), (1.0, 1.0, Unit::Auto));
+    /// assert_eq!(flex("none"), (0.0, 0.0, Unit::Auto));
+    /// assert_eq!(flex("initial"), (0.0, 1.0, Unit::Auto));
+    /// assert_eq!(Declaration::from_inline("flex: 1 2 3").flex_grow, None);
+    /// ```
+    pub flex_grow: Option<f32>,
+    /// How much a flex item shrinks when its line overflows, relative to the other items
+    pub flex_shrink: Option<f32>,
+    /// Initial main size of a flex item, `auto` (its `width` or `height`) is [`Unit::Auto`]
+    pub flex_basis: Option<Dimension>,
+    /// Position of a flex item among the items of its container, see
+    /// [`Layout::ordered_children`](crate::Layout::ordered_children)
+    pub order: Option<i32>,
+    pub align_self: Option<AlignSelf>,
+    /// Sizes of the columns of a grid container. Grid containers are laid out as blocks for
+    /// now, their tracks are only computed for embedders:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, GridLine, Layout, TrackSize};
+    /// let html = "<div style='display: grid; grid-template-columns: repeat(3, 1fr);
+    ///     grid-template-rows: 50px auto'><p style='grid-column: 1 / 3; grid-row: 2'></p></div>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let style = |selector| layout.arena[layout.select(selector)[0]].get().style.clone().unwrap();
+    /// let columns = style("div").grid_template_columns.unwrap();
+    /// assert_eq!(columns, [TrackSize::Fraction(1.0); 3]);
+    /// assert_eq!(style("div").grid_template_rows.unwrap()[1], TrackSize::Auto);
+    /// assert_eq!(style("p").grid_column.unwrap().end, GridLine::Line(3));
+    /// assert_eq!(style("p").grid_row.unwrap().start, GridLine::Line(2));
+    /// ```
+    pub grid_template_columns: Option<Vec<TrackSize>>,
+    /// Sizes of the rows of a grid container
+    pub grid_template_rows: Option<Vec<TrackSize>>,
+    /// Lines a grid item starts and ends at horizontally
+    pub grid_column: Option<GridPlacement>,
+    /// Lines a grid item starts and ends at vertically
+    pub grid_row: Option<GridPlacement>,
+    pub position: Option<Position>,
+    /// Offsets of a positioned element (`top`, `right`, `bottom` and `left`), `auto` is
+    /// [`Unit::Auto`]. Relative elements are moved from their place in the normal flow, absolute
+    /// and fixed ones are placed against the edges of their containing block, and stretched
+    /// between `left` and `right` without a `width`:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<div style='position: relative; top: 5px; left: 10px; bottom: 100px; height: 10px'>
+    ///     <p style='position: absolute; left: 20px; right: 25%; top: 0; margin: 0'>text</p></div>
+    ///     <p style='position: absolute; right: 0; bottom: auto; width: 50px; margin: 0'></p>";
+    /// let mut layout = Layout::default();
+    /// layout.viewport.size.x = 400.0;
+    /// layout.compute_document(&parse_document(html), &mut FontManager::default());
+    /// let rect = |selector| layout.arena[layout.select(selector)[0]].get().border_rect;
+    /// // `top` wins over `bottom`
+    /// assert_eq!((rect("div").pos.x, rect("div").pos.y), (8.0 + 10.0, 8.0 + 5.0));
+    /// let p = rect("div p");
+    /// assert_eq!((p.pos.x, p.pos.y), (18.0 + 20.0, 13.0));
+    /// assert_eq!(p.width(), rect("div").width() * 0.75 - 20.0);
+    /// // `auto` isn't 0: the paragraph stays where it would be in the normal flow vertically
+    /// let last = rect("body > p");
+    /// assert_eq!((last.pos.x, last.pos.y), (400.0 - 50.0, 8.0 + 10.0));
+    /// ```
+    pub inset: Sides<Option<Dimension>>,
     pub color: Option<Srgb>,
     pub background_color: Option<Srgb>,
+    /// Background image. The `background` shorthand resets the parts it doesn't specify:
+    ///
+    /// ```
+    /// use dragonfly::{BackgroundImage, Declaration, Repeat};
+    /// let style = Declaration::from_inline("background:red");
+    /// assert_eq!(style.background_color, "red".parse().ok());
+    /// assert_eq!(style.background_image, Some(BackgroundImage::None));
+    /// let style = Declaration::from_inline(
+    ///     "background-color: red; background: url(data:image/png;base64,AA==) no-repeat center",
+    /// );
+    /// assert_eq!(style.background_color.unwrap().alpha, 0.0);
+    /// let url = "data:image/png;base64,AA==".to_string();
+    /// assert_eq!(style.background_image, Some(BackgroundImage::Url(url)));
+    /// assert_eq!(style.background_repeat.unwrap().x, Repeat::NoRepeat);
+    /// assert_eq!(style.background_position.unwrap().y.number, 50.0);
+    /// ```
+    pub background_image: Option<BackgroundImage>,
+    pub background_repeat: Option<BackgroundRepeat>,
+    pub background_position: Option<BackgroundPosition>,
+    pub background_size: Option<BackgroundSize>,
     pub font_family: Option<FontFamily>,
-    pub margin: [Option<Dimension>; 4],
+    /// Font size. Computed styles have an absolute size, relative sizes are resolved
+    /// against the parent when the style is inherited (see [`Declaration::inherit`]).
+    pub font_size: Option<Dimension>,
+    pub margin: Sides<Option<Dimension>>,
+    pub padding: Sides<Option<Dimension>>,
+    /// Borders. The longhands only set the side and the part they name:
+    ///
+    /// ```
+    /// use dragonfly::{BorderStyle, Color, Declaration};
+    /// let style = Declaration::from_inline(
+    ///     "border: 1px solid black; border-bottom-color: red; border-width: 2px 3px; \
+    ///      border-left: dotted; border-top-style: none",
+    /// );
+    /// let [top, right, bottom, left] = [0, 1, 2, 3].map(|i| style.border.iter().nth(i).unwrap());
+    /// let widths = style.border.map(|side| side.width.unwrap().to_px(16.0));
+    /// assert_eq!(widths.iter().copied().collect::<Vec<_>>(), [2.0, 3.0, 2.0, 3.0]);
+    /// assert_eq!(top.style, Some(BorderStyle::None));
+    /// assert_eq!(right.style, Some(BorderStyle::Solid));
+    /// assert_eq!(left.style, Some(BorderStyle::Dotted));
+    /// let black = Color::parse("black");
+    /// assert_eq!((top.color, right.color), (black, black));
+    /// assert_eq!(bottom.color, Color::parse("red"));
+    /// assert_eq!(left.color, Some(Color::CurrentColor));
+    /// ```
+    pub border: Sides<BorderSide>,
+    /// Radii of the corners of the border box: top-left, top-right, bottom-right, bottom-left
+    pub border_radius: [Option<CornerRadius>; 4],
+    pub outline: Outline,
+    /// Opacity of the element and its content as a group, from 0 to 1. It's not inherited,
+    /// but the opacities of nested elements compound:
+    ///
+    /// ```
+    /// use dragonfly::Declaration;
+    /// let opacity = |value| Declaration::from_inline(&format!("opacity: {value}")).opacity;
+    /// assert_eq!(opacity("0.5"), Some(0.5));
+    /// assert_eq!(opacity("25%"), Some(0.25));
+    /// assert_eq!((opacity("-1"), opacity("150%")), (Some(0.0), Some(1.0)));
+    /// assert_eq!(opacity("half"), None);
+    /// // transparent elements still take space
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<div style='opacity: 0; height: 20px'></div><p style='margin: 0'>text</p>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// assert_eq!(layout.arena[layout.select("p")[0]].get().border_rect.pos.y, 8.0 + 20.0);
+    /// ```
+    pub opacity: Option<f32>,
+    pub visibility: Option<Visibility>,
+    /// Marker of list items, inherited like the other list style properties. `list-style:
+    /// none` removes the marker and the space it takes:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, Declaration, FontManager, Layout, ListStyleType};
+    /// let style = Declaration::from_inline("list-style: inside url(dot.png) upper-roman");
+    /// assert_eq!(style.list_style_type, Some(ListStyleType::UpperRoman));
+    /// assert_eq!(Declaration::from_inline("list-style: none").list_style_type, Some(ListStyleType::None));
+    /// let html = "<ul><li>item</li></ul><ul style='list-style: none'><li>item</li></ul>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let x = |li| layout.arena[li].get().border_rect.pos.x;
+    /// let items = layout.select("li");
+    /// assert_eq!((x(items[0]), x(items[1])), (8.0 + 40.0, 8.0));
+    /// assert!(layout.arena[items[0]].get().marker.is_some());
+    /// assert!(layout.arena[items[1]].get().marker.is_none());
+    /// ```
+    pub list_style_type: Option<ListStyleType>,
+    pub list_style_position: Option<ListStylePosition>,
+    /// Image used as the marker of list items, instead of their `list-style-type` when it's
+    /// loaded
+    pub list_style_image: Option<BackgroundImage>,
+    pub overflow_x: Option<Overflow>,
+    pub overflow_y: Option<Overflow>,
+    /// Stacking order of a positioned element, `auto` is `None`. Positioned elements are
+    /// painted over the normal flow, from the lowest z-index to the highest, except those with
+    /// a negative z-index that are painted under it:
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, DisplayItem, FontManager, Layout};
+    /// let div = |id, style| format!("<div id={id} style='{style}; height: 20px; background: red'>");
+    /// let html = [
+    ///     div("a", "position: absolute; z-index: 2"),
+    ///     div("b", "position: absolute; z-index: -1; margin-top: -10px"),
+    ///     div("c", "position: absolute; z-index: auto; margin-top: -10px"),
+    ///     div("d", "z-index: -5; margin-top: -10px"),
+    /// ]
+    /// .join("</div>");
+    /// let layout = Layout::compute(&mut parse_document(&html), &mut FontManager::default());
+    /// let painted: Vec<&str> = layout
+    ///     .display_list()
+    ///     .iter()
+    ///     .filter_map(|item| match item {
+    ///         DisplayItem::Rect { node, .. } => layout.arena[*node].get().attr("id"),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// // z-index doesn't apply to `d`, which isn't positioned
+    /// assert_eq!(painted, ["b", "d", "c", "a"]);
+    /// ```
+    pub z_index: Option<i32>,
+    /// Shadows of the box, the first one on top. `none` is an empty list
+    pub box_shadow: Option<Vec<Shadow>>,
+    pub float: Option<Float>,
+    pub clear: Option<Clear>,
+    /// Font weight, from 1 to 1000 (`normal` is 400, `bold` is 700)
+    pub font_weight: Option<FontWeight>,
+    pub font_style: Option<FontStyle>,
+    pub line_height: Option<LineHeight>,
+    pub vertical_align: Option<VerticalAlign>,
+    pub text_align: Option<TextAlign>,
+    pub direction: Option<Direction>,
+    /// Indentation of the first line of a block. Computed styles have it in pixels or as a
+    /// percentage of the width of the block
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = "<p style='text-indent: 2em'>indented</p><p>not indented</p>";
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let x = |selector| {
+    ///     let p = layout.select(selector)[0];
+    ///     layout.arena[p.children(&layout.arena).next().unwrap()].get().fragments[0].pos.x
+    /// };
+    /// assert_eq!(x("p:first-child") - x("p:last-child"), 28.0);
+    /// ```
+    pub text_indent: Option<Dimension>,
+    pub text_decoration: TextDecoration,
+    pub text_transform: Option<TextTransform>,
+    pub white_space: Option<WhiteSpace>,
+    pub overflow_wrap: Option<OverflowWrap>,
+    pub word_break: Option<WordBreak>,
+    pub letter_spacing: Option<Dimension>,
+    pub word_spacing: Option<Dimension>,
+    pub width: Option<Dimension>,
+    pub height: Option<Dimension>,
+    pub min_width: Option<Dimension>,
+    /// `none` is [`Unit::Auto`]
+    pub max_width: Option<Dimension>,
+    pub min_height: Option<Dimension>,
+    /// `none` is [`Unit::Auto`]
+    pub max_height: Option<Dimension>,
+    pub box_sizing: Option<BoxSizing>,
+}
+
+/// Resolved style that is used to measure and paint a run of text.
+#[derive(Debug, Clone)]
+pub struct TextStyle {
+    pub font_family: FontFamily,
+    /// Font size in pixels
+    pub font_size: f32,
+    pub font_weight: u16,
+    pub font_style: FontStyle,
+    /// Line height in pixels, `None` for the default line height of the font
+    pub line_height: Option<f32>,
+    pub color: Srgb,
+    pub text_transform: TextTransform,
+    pub white_space: WhiteSpace,
+    pub overflow_wrap: OverflowWrap,
+    pub word_break: WordBreak,
+    /// Extra space after each character, in pixels (can be negative)
+    pub letter_spacing: f32,
+    /// Extra space after each space character, in pixels (can be negative)
+    pub word_spacing: f32,
+    /// Decorations of the element of the text and of its ancestors, outermost first.
+    /// Only set on the styles of laid-out text (see [`TextFragment`](crate::TextFragment))
+    pub decorations: Vec<Decoration>,
+    /// Language of the text, empty if unknown. The fallback fonts of the characters the font
+    /// doesn't have depend on it (see [`FontManager::font_for_char`](crate::FontManager::font_for_char))
+    pub lang: String,
+}
+
+impl TextStyle {
+    /// Scale the lengths of the style, e.g. from CSS pixels to device pixels.
+    pub fn scaled(mut self, scale_factor: f32) -> Self {
+        self.font_size *= scale_factor;
+        self.line_height = self.line_height.map(|px| px * scale_factor);
+        self.letter_spacing *= scale_factor;
+        self.word_spacing *= scale_factor;
+        self
+    }
+
+    /// Text as it's rendered on a single line in this style: with its whitespace handled
+    /// (see [`WhiteSpace::apply`]) and transformed.
+    pub fn render<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = self.white_space.apply(text);
+        match self.text_transform {
+            TextTransform::None => text,
+            transform => Cow::Owned(transform.apply(&text).into_owned()),
+        }
+    }
+
+    /// Letter and word spacing added after a character.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = r#"<span>a b</span><span style="letter-spacing: 2px; word-spacing: -1px">a b</span>
+    ///     <span style="letter-spacing: -100px">a b</span>"#;
+    /// let mut fonts = FontManager::default();
+    /// let layout = Layout::compute(&mut parse_document(html), &mut fonts);
+    /// let widths: Vec<f32> = layout
+    ///     .select("span")
+    ///     .iter()
+    ///     .map(|span| {
+    ///         let text = layout.arena[span.children(&layout.arena).next().unwrap()].get();
+    ///         text.measure_text("a b", 1.0, &mut fonts).x
+    ///     })
+    ///     .collect();
+    /// // 3 letters and 1 space
+    /// assert_eq!(widths[1] - widths[0], 3.0 * 2.0 - 1.0);
+    /// assert_eq!(widths[2], 0.0);
+    /// ```
+    pub fn spacing_after(&self, c: char) -> f32 {
+        match c {
+            ' ' | '\u{a0}' => self.letter_spacing + self.word_spacing,
+            _ => self.letter_spacing,
+        }
+    }
+
+    /// Resolve a length in the font of this style: `em` is relative to the font size, and
+    /// `lh` to the line height (approximated from the font size for `normal`).
+    /// See [`Dimension::resolve`].
+    pub fn resolve(&self, length: Dimension, percent_base: Option<f32>) -> Option<f32> {
+        match length.unit {
+            Unit::RelativeToLineHeight(n) => {
+                Some(n * self.line_height.unwrap_or(self.font_size * 1.2))
+            }
+            _ => length.resolve(self.font_size, percent_base),
+        }
+    }
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            font_family: FontFamily::default(),
+            font_size: DEFAULT_FONT_SIZE,
+            font_weight: DEFAULT_FONT_WEIGHT,
+            font_style: FontStyle::Normal,
+            line_height: None,
+            color: Srgb::new(0.0, 0.0, 0.0, 1.0),
+            text_transform: TextTransform::None,
+            white_space: WhiteSpace::Normal,
+            overflow_wrap: OverflowWrap::Normal,
+            word_break: WordBreak::Normal,
+            letter_spacing: 0.0,
+            word_spacing: 0.0,
+            decorations: vec![],
+            lang: String::new(),
+        }
+    }
+}
+
+/// Overwrite `dst` with `src` if `src` is set.
+fn overlay<T: Clone>(dst: &mut Option<T>, src: &Option<T>) {
+    if src.is_some() {
+        *dst = src.clone();
+    }
+}
+
+/// Set `dst` to `src` if `dst` is not set.
+fn inherit<T: Clone>(dst: &mut Option<T>, src: &Option<T>) {
+    if dst.is_none() {
+        *dst = src.clone();
+    }
 }
 
 impl Declaration {
@@ -117,28 +2517,272 @@ impl Declaration {
     /// # Example
     ///
     /// ```rust
-    /// use dragonfly::Style;
-    /// let style = Style::from_inline("position: absolute; color: red;");
-    /// let style = Style::from_inline("color: yellow");
+    /// use dragonfly::{Declaration, FontStyle, FontWeight, Sides};
+    /// let style = Declaration::from_inline("position: absolute; color: red;");
+    /// let style = Declaration::from_inline("color: yellow");
+    /// // top, horizontal, bottom
+    /// let style = Declaration::from_inline("margin: 1px 2px 3px");
+    /// let margin = style.margin.map(|m| m.map(|m| m.number));
+    /// assert_eq!(margin, Sides::new(Some(1.0), Some(2.0), Some(3.0), Some(2.0)));
+    /// // vertical, horizontal
+    /// let style = Declaration::from_inline("padding: 1em 2em");
+    /// let padding = style.padding.map(|p| p.map(|p| p.to_px(16.0)));
+    /// assert_eq!(padding, Sides::new(Some(16.0), Some(32.0), Some(16.0), Some(32.0)));
+    /// // style, weight, size/line height, family
+    /// let style = Declaration::from_inline("font: italic bold 14px/1.4 Georgia, serif");
+    /// assert_eq!(style.font_style, Some(FontStyle::Italic));
+    /// assert_eq!(style.font_weight, Some(FontWeight::BOLD));
+    /// assert_eq!(style.text_style().font_size, 14.0);
+    /// // unspecified parts are reset, invalid shorthands are ignored
+    /// let style = Declaration::from_inline("font-weight: 300; font: 2em serif; font: bold");
+    /// assert_eq!(style.font_weight, Some(FontWeight::NORMAL));
+    /// assert_eq!(style.text_style().font_size, 28.0);
     /// ```
     #[inline]
     pub fn from_inline(inline: &str) -> Self {
         CssParser::parse_inline(inline)
     }
+
+    /// Apply every property that is set in `other` on top of this declaration.
+    pub fn apply(&mut self, other: &Declaration) {
+        overlay(&mut self.display, &other.display);
+        overlay(&mut self.position, &other.position);
+        overlay(&mut self.flex_direction, &other.flex_direction);
+        overlay(&mut self.flex_wrap, &other.flex_wrap);
+        overlay(&mut self.justify_content, &other.justify_content);
+        overlay(&mut self.align_items, &other.align_items);
+        overlay(&mut self.align_content, &other.align_content);
+        overlay(&mut self.flex_grow, &other.flex_grow);
+        overlay(&mut self.flex_shrink, &other.flex_shrink);
+        overlay(&mut self.flex_basis, &other.flex_basis);
+        overlay(&mut self.order, &other.order);
+        overlay(&mut self.align_self, &other.align_self);
+        overlay(
+            &mut self.grid_template_columns,
+            &other.grid_template_columns,
+        );
+        overlay(&mut self.grid_template_rows, &other.grid_template_rows);
+        overlay(&mut self.grid_column, &other.grid_column);
+        overlay(&mut self.grid_row, &other.grid_row);
+        overlay(&mut self.color, &other.color);
+        overlay(&mut self.background_color, &other.background_color);
+        overlay(&mut self.background_image, &other.background_image);
+        overlay(&mut self.background_repeat, &other.background_repeat);
+        overlay(&mut self.background_position, &other.background_position);
+        overlay(&mut self.background_size, &other.background_size);
+        overlay(&mut self.font_family, &other.font_family);
+        overlay(&mut self.font_size, &other.font_size);
+        for (dst, src) in self.inset.iter_mut().zip(other.inset.iter()) {
+            overlay(dst, src);
+        }
+        for (dst, src) in self.margin.iter_mut().zip(other.margin.iter()) {
+            overlay(dst, src);
+        }
+        for (dst, src) in self.padding.iter_mut().zip(other.padding.iter()) {
+            overlay(dst, src);
+        }
+        for (dst, src) in self.border.iter_mut().zip(other.border.iter()) {
+            dst.apply(src);
+        }
+        for (dst, src) in self.border_radius.iter_mut().zip(&other.border_radius) {
+            overlay(dst, src);
+        }
+        self.outline.apply(&other.outline);
+        overlay(&mut self.opacity, &other.opacity);
+        overlay(&mut self.visibility, &other.visibility);
+        overlay(&mut self.list_style_type, &other.list_style_type);
+        overlay(&mut self.list_style_position, &other.list_style_position);
+        overlay(&mut self.list_style_image, &other.list_style_image);
+        overlay(&mut self.overflow_x, &other.overflow_x);
+        overlay(&mut self.overflow_y, &other.overflow_y);
+        overlay(&mut self.z_index, &other.z_index);
+        overlay(&mut self.box_shadow, &other.box_shadow);
+        overlay(&mut self.float, &other.float);
+        overlay(&mut self.clear, &other.clear);
+        overlay(&mut self.font_weight, &other.font_weight);
+        overlay(&mut self.font_style, &other.font_style);
+        overlay(&mut self.line_height, &other.line_height);
+        overlay(&mut self.text_align, &other.text_align);
+        overlay(&mut self.text_transform, &other.text_transform);
+        overlay(&mut self.white_space, &other.white_space);
+        overlay(&mut self.overflow_wrap, &other.overflow_wrap);
+        overlay(&mut self.word_break, &other.word_break);
+        overlay(&mut self.text_indent, &other.text_indent);
+        overlay(&mut self.direction, &other.direction);
+        overlay(&mut self.vertical_align, &other.vertical_align);
+        overlay(&mut self.letter_spacing, &other.letter_spacing);
+        overlay(&mut self.word_spacing, &other.word_spacing);
+        overlay(&mut self.text_decoration.line, &other.text_decoration.line);
+        overlay(
+            &mut self.text_decoration.style,
+            &other.text_decoration.style,
+        );
+        overlay(
+            &mut self.text_decoration.color,
+            &other.text_decoration.color,
+        );
+        overlay(&mut self.width, &other.width);
+        overlay(&mut self.box_sizing, &other.box_sizing);
+        overlay(&mut self.height, &other.height);
+        overlay(&mut self.min_width, &other.min_width);
+        overlay(&mut self.max_width, &other.max_width);
+        overlay(&mut self.min_height, &other.min_height);
+        overlay(&mut self.max_height, &other.max_height);
+    }
+
+    /// Only the color properties of this declaration, the ones `:visited` rules can set.
+    pub fn colors(&self) -> Declaration {
+        Declaration {
+            color: self.color,
+            background_color: self.background_color,
+            border: self.border.map(|side| BorderSide {
+                color: side.color,
+                ..Default::default()
+            }),
+            outline: Outline {
+                color: self.outline.color,
+                ..Default::default()
+            },
+            text_decoration: TextDecoration {
+                color: self.text_decoration.color,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Fill in the inherited properties that are not set on this declaration
+    /// from the computed style of the parent. Relative font sizes (`em`, `%`, `larger`...)
+    /// are resolved against the font size of the parent.
+    ///
+    /// ```
+    /// use dragonfly::Declaration;
+    /// let parent = Declaration::from_inline("font-size: 20px");
+    /// let mut style = Declaration::from_inline("font-size: 2em");
+    /// style.inherit(&parent);
+    /// assert_eq!(style.text_style().font_size, 40.0);
+    /// let mut child = Declaration::default();
+    /// child.inherit(&style);
+    /// assert_eq!(child.text_style().font_size, 40.0);
+    /// ```
+    pub fn inherit(&mut self, parent: &Declaration) {
+        inherit(&mut self.color, &parent.color);
+        inherit(&mut self.font_family, &parent.font_family);
+        inherit(&mut self.font_style, &parent.font_style);
+        inherit(&mut self.text_align, &parent.text_align);
+        inherit(&mut self.text_transform, &parent.text_transform);
+        inherit(&mut self.white_space, &parent.white_space);
+        inherit(&mut self.overflow_wrap, &parent.overflow_wrap);
+        inherit(&mut self.word_break, &parent.word_break);
+        let parent_style = parent.text_style();
+        let font_weight = match self.font_weight {
+            Some(weight) => weight.resolve(parent_style.font_weight),
+            None => parent_style.font_weight,
+        };
+        self.font_weight = Some(FontWeight::Absolute(font_weight));
+        let parent_size = parent_style.font_size;
+        let font_size = match self.font_size {
+            Some(size) => size.resolve(parent_size, Some(parent_size)),
+            None => None,
+        };
+        let font_size = font_size.unwrap_or(parent_size);
+        self.font_size = Some(Dimension::px(font_size));
+        // lengths are inherited as pixels, numbers are inherited as is
+        let parent_line_height = parent_style.line_height.unwrap_or(parent_size * 1.2);
+        if let Some(line_height @ LineHeight::Length(_)) = self.line_height {
+            let line_height = line_height.resolve(font_size, parent_line_height);
+            self.line_height = line_height.map(|px| LineHeight::Length(Dimension::px(px)));
+        }
+        inherit(&mut self.line_height, &parent.line_height);
+        if let Some(indent) = self
+            .text_indent
+            .filter(|d| !matches!(d.unit, Unit::Percent(_)))
+        {
+            self.text_indent = Some(Dimension::px(indent.to_px(font_size)));
+        }
+        inherit(&mut self.text_indent, &parent.text_indent);
+        inherit(&mut self.direction, &parent.direction);
+        inherit(&mut self.visibility, &parent.visibility);
+        inherit(&mut self.list_style_type, &parent.list_style_type);
+        inherit(&mut self.list_style_position, &parent.list_style_position);
+        inherit(&mut self.list_style_image, &parent.list_style_image);
+        for (spacing, parent) in [
+            (&mut self.letter_spacing, &parent.letter_spacing),
+            (&mut self.word_spacing, &parent.word_spacing),
+        ] {
+            if let Some(length) = spacing {
+                let px = length.resolve(font_size, Some(font_size)).unwrap_or(0.0);
+                *spacing = Some(Dimension::px(px));
+            }
+            inherit(spacing, parent);
+        }
+    }
+
+    /// Resolve the style of the text inside an element with this computed style.
+    pub fn text_style(&self) -> TextStyle {
+        let default = TextStyle::default();
+        let font_size = self
+            .font_size
+            .and_then(|size| size.resolve(default.font_size, Some(default.font_size)))
+            .unwrap_or(default.font_size);
+        let spacing =
+            |length: Option<Dimension>| length.map_or(0.0, |length| length.to_px(font_size));
+        TextStyle {
+            font_family: self.font_family.clone().unwrap_or(default.font_family),
+            font_size,
+            font_weight: self.font_weight.map_or(default.font_weight, |weight| {
+                weight.resolve(default.font_weight)
+            }),
+            font_style: self.font_style.unwrap_or(default.font_style),
+            line_height: self
+                .line_height
+                .and_then(|line_height| line_height.resolve(font_size, default.font_size * 1.2)),
+            color: self.color.unwrap_or(default.color),
+            text_transform: self.text_transform.unwrap_or_default(),
+            white_space: self.white_space.unwrap_or_default(),
+            overflow_wrap: self.overflow_wrap.unwrap_or_default(),
+            word_break: self.word_break.unwrap_or_default(),
+            letter_spacing: spacing(self.letter_spacing),
+            word_spacing: spacing(self.word_spacing),
+            decorations: vec![],
+            lang: String::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct GlobalStyle {
     /// Selector, declarations
-    pub rules: Vec<(String, Declaration)>,
+    pub rules: Vec<(Selector, Declaration)>,
+    /// `!important` declarations of the rules, by index in `rules`
+    pub important: Vec<(usize, Declaration)>,
+    /// Problems found while parsing the stylesheet (invalid selectors, unsupported properties)
+    pub diagnostics: Vec<String>,
 }
 
 impl GlobalStyle {
+    /// Add a rule. Rules with an invalid selector are ignored, like browsers do.
     pub fn add_rule(&mut self, selector: &str, decl: Declaration) {
         log::debug!("adding rule '{decl:?} to GlobalStyle (selector: {selector})'");
-        self.rules.push((selector.to_string(), decl));
+        match Selector::parse(selector) {
+            Ok(selector) => self.rules.push((selector, decl)),
+            Err(err) => {
+                log::warn!("ignoring rule: {err}");
+                self.diagnostics.push(err.to_string());
+            }
+        }
     }
 
+    /// Parse a stylesheet. Declarations are parsed like inline styles.
+    ///
+    /// ```
+    /// use dragonfly::{Declaration, GlobalStyle, ParserMode};
+    /// let css = "div { min-width: 50%; max-width: none; max-height: 3em }";
+    /// let style = GlobalStyle::from_css(css, ParserMode::Normal);
+    /// let inline = Declaration::from_inline("min-width: 50%; max-width: none; max-height: 3em");
+    /// assert_eq!(format!("{:?}", style.rules[0].1), format!("{inline:?}"));
+    /// assert!(inline.max_width.unwrap().is_auto());
+    /// ```
     pub fn from_css(css: &str, mode: ParserMode) -> Self {
         CssParser::new(css, mode).parse()
     }
@@ -146,6 +2790,165 @@ impl GlobalStyle {
     pub fn default_css() -> Self {
         Self::from_css(include_str!("internal/default.css"), ParserMode::DefaultCss)
     }
+
+    /// Compute the specified style of an element: the rules of this (user agent) stylesheet that
+    /// match it, the rules of the `user` stylesheet, its presentational attributes, the rules of the
+    /// `author` stylesheets and its inline `style` attribute, in increasing order of precedence.
+    /// `!important` declarations come last, with the precedence of the origins inverted (author,
+    /// then user, then user agent). Matched rules of each origin are ordered by specificity,
+    /// then source order. A rule with a selector list has the specificity of the most specific
+    /// selector of the list that matches.
+    ///
+    /// `matches` tells whether the element matches a selector of a list, and the inline style
+    /// is parsed in `mode`.
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = "<style>
+    ///     #nope, p { font-size: 40px } .c { font-size: 10px }
+    ///     a:visited, .link { font-size: 30px; color: red } a:visited { font-size: 50px }
+    /// </style><p class=c>text</p><a class=link href='https://example.com/'>link</a>";
+    /// let mut ctx = WebContext::builder().html(html).url("https://example.com/page").build()?;
+    /// ctx.visited().mark(&"https://example.com/".parse()?);
+    /// block_on(ctx.load())?;
+    /// let layout = &ctx.layout;
+    /// let style = |selector| layout.arena[layout.select(selector)[0]].get().style.clone().unwrap();
+    /// // `p` matched, not the more specific `#nope`
+    /// assert_eq!(style("p").text_style().font_size, 10.0);
+    /// // `.link` matched, so the whole rule applies; `a:visited` alone only sets colors
+    /// assert_eq!(style("a").text_style().font_size, 30.0);
+    /// assert_eq!(style("a").color, "red".parse().ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Presentational attributes, such as the `width` (in pixels or a percentage), `size` and
+    /// `color` of `hr` elements:
+    ///
+    /// ```
+    /// use dragonfly::WebContext;
+    /// # include!("../tests/support/doctest.rs");
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let html = r#"<!DOCTYPE html><body style="margin: 0">
+    ///     <hr width="50%" size="4" color="blue"><hr width="200px"><hr width="wide">"#;
+    /// let mut ctx = WebContext::builder().html(html).viewport(800.0, 600.0).build()?;
+    /// block_on(ctx.load())?;
+    /// let rules = ctx.layout.select("hr");
+    /// let rect = |i: usize| ctx.layout.arena[rules[i]].get().border_rect;
+    /// assert_eq!((rect(0).width(), rect(0).height()), (400.0, 4.0));
+    /// assert_eq!((rect(1).width(), rect(1).height()), (200.0, 2.0));
+    /// // invalid values are ignored
+    /// assert_eq!(rect(2).width(), 800.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cascade(
+        &self,
+        user: Option<&GlobalStyle>,
+        author: &[GlobalStyle],
+        node: &DOMNode,
+        mode: ParserMode,
+        matches: impl Fn(&ComplexSelector) -> bool,
+    ) -> Declaration {
+        let mut decl = Declaration::default();
+        if !node.is_element() {
+            return decl;
+        }
+
+        let apply_matching = |decl: &mut Declaration, sheets: &[&GlobalStyle], important: bool| {
+            let rules: Vec<(&Selector, &Declaration)> = match important {
+                false => sheets
+                    .iter()
+                    .flat_map(|sheet| &sheet.rules)
+                    .map(|(selector, rule)| (selector, rule))
+                    .collect(),
+                true => sheets
+                    .iter()
+                    .flat_map(|sheet| {
+                        sheet
+                            .important
+                            .iter()
+                            .map(|(i, rule)| (&sheet.rules[*i].0, rule))
+                    })
+                    .collect(),
+            };
+            // every selector of a list that matches applies the rule with its own specificity
+            let mut matched: Vec<(&ComplexSelector, &Declaration)> = rules
+                .into_iter()
+                .flat_map(|(selector, rule)| selector.list.iter().map(move |c| (c, rule)))
+                .filter(|(complex, _)| matches(complex))
+                .collect();
+            // stable, so rules with the same specificity stay in source order
+            matched.sort_by_key(|(complex, _)| complex.specificity());
+            for (complex, rule) in matched {
+                // like browsers, `:visited` can't reveal the history through the layout
+                match complex.depends_on(ElementState::Visited) {
+                    true => decl.apply(&rule.colors()),
+                    false => decl.apply(rule),
+                }
+            }
+        };
+
+        let user: Vec<&GlobalStyle> = user.into_iter().collect();
+        let author: Vec<&GlobalStyle> = author.iter().collect();
+        let (inline, inline_important) = node
+            .attr("style")
+            .map(|inline| CssParser::parse_inline_with_mode(inline, mode))
+            .unwrap_or_default();
+
+        apply_matching(&mut decl, &[self], false);
+        apply_matching(&mut decl, &user, false);
+        decl.apply(&presentational_hints(node));
+        apply_matching(&mut decl, &author, false);
+        decl.apply(&inline);
+        apply_matching(&mut decl, &author, true);
+        decl.apply(&inline_important);
+        apply_matching(&mut decl, &user, true);
+        apply_matching(&mut decl, &[self], true);
+        decl
+    }
+}
+
+/// Style of the legacy presentational attributes of an element (e.g. `<hr size="4">`).
+fn presentational_hints(node: &DOMNode) -> Declaration {
+    let mut decl = Declaration::default();
+    let attr = |name: &str| node.attr(name).map(str::trim);
+    // dimension values: a number of pixels or a percentage, anything after them is ignored
+    let dimension = |name: &str| {
+        let value = attr(name).filter(|v| v.starts_with(|c: char| c.is_ascii_digit()))?;
+        let dimension = Dimension::from_str(value);
+        match dimension.unit {
+            Unit::Percent(_) => Some(dimension),
+            _ => Some(Dimension::px(dimension.number)),
+        }
+    };
+
+    // `hidden` (and `hidden="until-found"`) hides the element unless a rule shows it again
+    if node.has_bool_attr("hidden") {
+        decl.display = Some(Display::None);
+    }
+
+    if node.name() == "hr" {
+        // `noshade` is the default look: the rule is always painted as a solid bar
+        decl.width = dimension("width");
+        decl.height = dimension("size");
+        decl.background_color = attr("color").and_then(|v| Srgb::from_str(v).ok());
+    }
+
+    if matches!(node.name(), "ol" | "li") {
+        // the `type` attribute is case-sensitive, unlike the property
+        decl.list_style_type = match attr("type") {
+            Some("1") => Some(ListStyleType::Decimal),
+            Some("a") => Some(ListStyleType::LowerAlpha),
+            Some("A") => Some(ListStyleType::UpperAlpha),
+            Some("i") => Some(ListStyleType::LowerRoman),
+            Some("I") => Some(ListStyleType::UpperRoman),
+            _ => None,
+        };
+    }
+    decl
 }
 
 /// Remove all block comments & extra whitespace (multiple consecutive whitespace characters) from a string.
@@ -155,8 +2958,8 @@ impl GlobalStyle {
 /// # Example
 ///
 /// ```rust
-/// use dragonfly::remove_comments;
-/// assert!(remove_comments("body{/* comment */color:/**/red/* hi */;}") == "body{color:red;}");
+/// use dragonfly::remove_comments_and_extra_whitespace;
+/// assert!(remove_comments_and_extra_whitespace("body{/* comment */color:/**/red/* hi */;}") == "body{color:red;}");
 /// ```
 pub fn remove_comments_and_extra_whitespace(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
@@ -170,7 +2973,7 @@ pub fn remove_comments_and_extra_whitespace(s: &str) -> String {
             in_comment = false;
         }
         // if previous and current chars are whitespace or if we're in a comment, skip the current character
-        if !in_comment && !(i > 0 && c.is_whitespace() && get_char(i - 1).is_whitespace()) {
+        if !(in_comment || i > 0 && c.is_whitespace() && get_char(i - 1).is_whitespace()) {
             result.push(if c.is_whitespace() { ' ' } else { c });
         }
     }
@@ -184,6 +2987,65 @@ pub enum ParserMode {
     Normal,
     /// Parse the browsers `default.css` file.
     DefaultCss,
+    /// Parse the author styles of a document in quirks mode: numbers without a unit are
+    /// lengths in pixels for the properties of the unitless length quirk (see
+    /// [`QuirksMode`](crate::QuirksMode)). Other modes ignore these declarations.
+    Quirks,
+}
+
+/// Properties that take numbers without a unit as pixels in quirks mode.
+const UNITLESS_LENGTH_PROPERTIES: &[&str] = &[
+    "background-position",
+    "border-spacing",
+    "border-top-width",
+    "border-right-width",
+    "border-bottom-width",
+    "border-left-width",
+    "border-width",
+    "bottom",
+    "clip",
+    "font-size",
+    "height",
+    "left",
+    "letter-spacing",
+    "margin",
+    "margin-top",
+    "margin-right",
+    "margin-bottom",
+    "margin-left",
+    "max-height",
+    "max-width",
+    "min-height",
+    "min-width",
+    "padding",
+    "padding-top",
+    "padding-right",
+    "padding-bottom",
+    "padding-left",
+    "right",
+    "text-indent",
+    "top",
+    "vertical-align",
+    "width",
+    "word-spacing",
+];
+
+/// Whether a token of a value is a number without a unit, other than 0.
+fn is_unitless_number(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_digit() || ".-+".contains(c))
+        && token.parse::<f32>().is_ok_and(|n| n != 0.0)
+}
+
+/// Add `px` to the numbers without a unit of a value (`10 auto` is `10px auto`).
+fn unitless_to_px(value: &str) -> String {
+    value
+        .split(' ')
+        .map(|token| match is_unitless_number(token) {
+            true => format!("{token}px"),
+            false => token.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 /// Dragonfly's CSS parser. Does not use any external libraries except for [`css_color`] for color parsing.
@@ -196,6 +3058,8 @@ pub struct CssParser {
     selector: Option<String>,
     attr_name: Option<String>,
     decl: Declaration,
+    /// `!important` declarations of the current rule
+    important: Option<Declaration>,
     mode: ParserMode,
     style: GlobalStyle,
 }
@@ -212,6 +3076,7 @@ impl CssParser {
             selector: None,
             attr_name: None,
             decl: Declaration::default(),
+            important: None,
             mode,
             style: GlobalStyle::default(),
         }
@@ -232,7 +3097,7 @@ impl CssParser {
         let (_, cur_char) = iter.next().unwrap();
         let (next_pos, _) = iter.next().unwrap_or((1, ' '));
         self.pos += next_pos;
-        return cur_char;
+        cur_char
     }
 
     fn consume_while<F: Fn(char) -> bool>(&mut self, test: F) -> String {
@@ -243,11 +3108,32 @@ impl CssParser {
         s
     }
 
-    fn consume_name(&mut self) -> String {
-        self.consume_while(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => true,
-            _ => false,
-        })
+    /// Consume the value of a declaration, up to a `;` or `}` that is not in parentheses or
+    /// quotes.
+    fn consume_value(&mut self) -> String {
+        let mut value = String::new();
+        let (mut depth, mut quote) = (0usize, None);
+        while !self.eof() {
+            let c = self.peek();
+            match (quote, c) {
+                (Some(q), _) if c == q => quote = None,
+                (Some(_), _) => (),
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') => depth = depth.saturating_sub(1),
+                (None, ';' | '}') if depth == 0 => break,
+                _ => (),
+            }
+            value.push(self.consume());
+        }
+        value
+    }
+
+    /// Consume a selector list (everything up to the declaration block).
+    fn consume_selector(&mut self) -> String {
+        self.consume_while(|c| c != '{' && c != '}')
+            .trim()
+            .to_string()
     }
 
     fn replace_browser_keyword(value: &str) -> &str {
@@ -269,6 +3155,17 @@ impl CssParser {
     }
 
     fn parse_attr_value(&mut self, value: &str) {
+        // `!important` declarations are parsed on their own
+        if let Some((value, flag)) = value.rsplit_once('!') {
+            if flag.trim().eq_ignore_ascii_case("important") {
+                let important = self.important.take().unwrap_or_default();
+                let normal = std::mem::replace(&mut self.decl, important);
+                self.parse_attr_value(value.trim());
+                self.important = Some(std::mem::replace(&mut self.decl, normal));
+                return;
+            }
+        }
+
         let attr_name = self.attr_name.clone().unwrap();
         log::debug!("parsing attr '{attr_name}: {value}'");
         let value = match self.mode {
@@ -277,30 +3174,226 @@ impl CssParser {
         };
         log::debug!("new value (mode: {:?}) => '{value}'", self.mode);
 
+        // the unitless length quirk
+        let quirky_value;
+        let value = match UNITLESS_LENGTH_PROPERTIES.contains(&attr_name.as_str())
+            && value.split_ascii_whitespace().any(is_unitless_number)
+        {
+            true if matches!(self.mode, ParserMode::Quirks) => {
+                quirky_value = unitless_to_px(value);
+                quirky_value.as_str()
+            }
+            true => {
+                log::warn!("ignoring '{attr_name}: {value}': lengths need a unit");
+                return;
+            }
+            false => value,
+        };
+
         match attr_name.as_str() {
-            "display" => self.decl.display = Display::from_str(value).unwrap_or(Display::default()),
-            "position" => {
-                self.decl.position = Position::from_str(&value).unwrap_or(Position::default())
+            "display" => self.decl.display = Some(Display::from_str(value).unwrap_or_default()),
+            "position" => self.decl.position = Some(Position::from_str(value).unwrap_or_default()),
+            "flex-direction" => self.decl.flex_direction = Some(keyword(&attr_name, value)),
+            "flex-wrap" => self.decl.flex_wrap = Some(keyword(&attr_name, value)),
+            "flex-flow" => {
+                let (direction, wrap) = parse_flex_flow(value).unwrap_or_else(|| {
+                    log::warn!("invalid flex-flow '{value}'");
+                    Default::default()
+                });
+                self.decl.flex_direction = Some(direction);
+                self.decl.flex_wrap = Some(wrap);
             }
-            "color" => self.decl.color = Srgb::from_str(&value).ok(),
-            "background-color" => self.decl.background_color = Srgb::from_str(&value).ok(),
-            "font-family" => {
-                self.decl.font_family = Some(
-                    FontFamily::from_str(value).unwrap_or(FontFamily::Custom(value.to_string())),
-                )
+            "justify-content" => self.decl.justify_content = Some(keyword(&attr_name, value)),
+            "align-items" => self.decl.align_items = Some(keyword(&attr_name, value)),
+            "align-content" => self.decl.align_content = Some(keyword(&attr_name, value)),
+            "flex" => match parse_flex(value) {
+                Some((grow, shrink, basis)) => {
+                    self.decl.flex_grow = Some(grow);
+                    self.decl.flex_shrink = Some(shrink);
+                    self.decl.flex_basis = Some(basis);
+                }
+                None => log::warn!("invalid flex '{value}'"),
+            },
+            "flex-grow" => self.decl.flex_grow = parse_flex_factor(value),
+            "flex-shrink" => self.decl.flex_shrink = parse_flex_factor(value),
+            "flex-basis" => self.decl.flex_basis = parse_flex_basis(value),
+            "order" => self.decl.order = value.parse().ok(),
+            "align-self" => self.decl.align_self = Some(keyword(&attr_name, value)),
+            "grid-template-columns" => match TrackSize::parse_list(value) {
+                Some(tracks) => self.decl.grid_template_columns = Some(tracks),
+                None => log::warn!("invalid grid-template-columns '{value}'"),
+            },
+            "grid-template-rows" => match TrackSize::parse_list(value) {
+                Some(tracks) => self.decl.grid_template_rows = Some(tracks),
+                None => log::warn!("invalid grid-template-rows '{value}'"),
+            },
+            "grid-column" => match GridPlacement::parse(value) {
+                Some(placement) => self.decl.grid_column = Some(placement),
+                None => log::warn!("invalid grid-column '{value}'"),
+            },
+            "grid-row" => match GridPlacement::parse(value) {
+                Some(placement) => self.decl.grid_row = Some(placement),
+                None => log::warn!("invalid grid-row '{value}'"),
+            },
+            "color" => self.decl.color = Srgb::from_str(value).ok(),
+            "background-color" => self.decl.background_color = Srgb::from_str(value).ok(),
+            "background-image" => self.decl.background_image = BackgroundImage::parse(value),
+            "background-repeat" => self.decl.background_repeat = BackgroundRepeat::parse(value),
+            "background-position" => {
+                self.decl.background_position = BackgroundPosition::parse(value)
             }
+            "background-size" => self.decl.background_size = BackgroundSize::parse(value),
+            "background" => match BackgroundShorthand::parse(value) {
+                // properties that are not specified are reset to their initial values
+                Some(background) => {
+                    self.decl.background_color = Some(background.color);
+                    self.decl.background_image = Some(background.image);
+                    self.decl.background_repeat = Some(background.repeat);
+                    self.decl.background_position = Some(background.position);
+                    self.decl.background_size = Some(background.size);
+                }
+                None => log::warn!("invalid background shorthand '{value}'"),
+            },
+            "font-family" => self.decl.font_family = Some(parse_font_family(value)),
+            "font-size" => self.decl.font_size = Some(parse_font_size(value)),
             "margin" => {
-                // top, right, bottom, left
-                for (i, s) in value.split_whitespace().enumerate() {
-                    self.decl.margin[i] = Some(Dimension::from_str(s));
+                let values: Vec<_> = value.split_whitespace().map(Dimension::from_str).collect();
+                if let Some(margin) = Sides::from_shorthand(&values) {
+                    self.decl.margin = margin.map(Some);
+                }
+            }
+            "inset" => {
+                let values: Vec<_> = value.split_whitespace().map(Dimension::from_str).collect();
+                if let Some(inset) = Sides::from_shorthand(&values) {
+                    self.decl.inset = inset.map(Some);
+                }
+            }
+            "top" => self.decl.inset.top = Some(Dimension::from_str(value)),
+            "right" => self.decl.inset.right = Some(Dimension::from_str(value)),
+            "bottom" => self.decl.inset.bottom = Some(Dimension::from_str(value)),
+            "left" => self.decl.inset.left = Some(Dimension::from_str(value)),
+            "margin-top" => self.decl.margin.top = Some(Dimension::from_str(value)),
+            "margin-right" => self.decl.margin.right = Some(Dimension::from_str(value)),
+            "margin-bottom" => self.decl.margin.bottom = Some(Dimension::from_str(value)),
+            "margin-left" => self.decl.margin.left = Some(Dimension::from_str(value)),
+            "padding" => {
+                let values: Vec<_> = value.split_whitespace().map(Dimension::from_str).collect();
+                if let Some(padding) = Sides::from_shorthand(&values) {
+                    self.decl.padding = padding.map(Some);
+                }
+            }
+            "padding-top" => self.decl.padding.top = Some(Dimension::from_str(value)),
+            "padding-right" => self.decl.padding.right = Some(Dimension::from_str(value)),
+            "padding-bottom" => self.decl.padding.bottom = Some(Dimension::from_str(value)),
+            "padding-left" => self.decl.padding.left = Some(Dimension::from_str(value)),
+            name if BORDER_PROPERTIES.iter().any(|(n, ..)| *n == name) => {
+                match parse_border_property(name, value) {
+                    Some(sides) => {
+                        for (side, value) in sides {
+                            self.decl.border[side].apply(&value);
+                        }
+                    }
+                    None => log::warn!("invalid value '{value}' for '{name}'"),
+                }
+            }
+            "border-radius" => match CornerRadius::parse_shorthand(value) {
+                Some(radii) => self.decl.border_radius = radii.map(Some),
+                None => log::warn!("invalid border radius '{value}'"),
+            },
+            "border-top-left-radius" => self.decl.border_radius[0] = CornerRadius::parse(value),
+            "border-top-right-radius" => self.decl.border_radius[1] = CornerRadius::parse(value),
+            "border-bottom-right-radius" => self.decl.border_radius[2] = CornerRadius::parse(value),
+            "border-bottom-left-radius" => self.decl.border_radius[3] = CornerRadius::parse(value),
+            "outline" => match Outline::parse(value) {
+                Some(outline) => {
+                    let offset = self.decl.outline.offset;
+                    self.decl.outline = Outline { offset, ..outline };
+                }
+                None => log::warn!("invalid outline '{value}'"),
+            },
+            "outline-width" => self.decl.outline.width = parse_border_width(value),
+            "outline-style" => self.decl.outline.style = parse_outline_style(value),
+            "outline-color" => self.decl.outline.color = Color::parse(value),
+            "outline-offset" => self.decl.outline.offset = parse_length(value),
+            "opacity" => self.decl.opacity = parse_opacity(value),
+            "visibility" => self.decl.visibility = Visibility::from_str(value).ok(),
+            "list-style" => match ListStyle::parse(value) {
+                Some(style) => {
+                    self.decl.list_style_type = Some(style.kind);
+                    self.decl.list_style_position = Some(style.position);
+                    self.decl.list_style_image = Some(style.image);
+                }
+                None => log::warn!("invalid list-style '{value}'"),
+            },
+            "list-style-type" => self.decl.list_style_type = ListStyleType::from_str(value).ok(),
+            "list-style-position" => {
+                self.decl.list_style_position = ListStylePosition::from_str(value).ok()
+            }
+            "list-style-image" => self.decl.list_style_image = BackgroundImage::parse(value),
+            "overflow" => match Overflow::parse_shorthand(value) {
+                Some((x, y)) => (self.decl.overflow_x, self.decl.overflow_y) = (Some(x), Some(y)),
+                None => log::warn!("invalid overflow '{value}'"),
+            },
+            "overflow-x" => self.decl.overflow_x = Overflow::from_str(value).ok(),
+            "overflow-y" => self.decl.overflow_y = Overflow::from_str(value).ok(),
+            "z-index" => self.decl.z_index = value.parse().ok(),
+            "box-shadow" => match Shadow::parse_list(value) {
+                Some(shadows) => self.decl.box_shadow = Some(shadows),
+                None => log::warn!("invalid box-shadow '{value}'"),
+            },
+            "float" => self.decl.float = Some(Float::from_str(value).unwrap_or_default()),
+            "clear" => self.decl.clear = Some(Clear::from_str(value).unwrap_or_default()),
+            "width" => self.decl.width = Some(Dimension::from_str(value)),
+            "box-sizing" => self.decl.box_sizing = BoxSizing::from_str(value).ok(),
+            "height" => self.decl.height = Some(Dimension::from_str(value)),
+            "min-width" => self.decl.min_width = Some(Dimension::from_str(value)),
+            "max-width" => self.decl.max_width = Some(Dimension::from_str(value)),
+            "min-height" => self.decl.min_height = Some(Dimension::from_str(value)),
+            "max-height" => self.decl.max_height = Some(Dimension::from_str(value)),
+            "font-weight" => self.decl.font_weight = FontWeight::from_str(value).ok(),
+            "font-style" => self.decl.font_style = FontStyle::parse(value),
+            "line-height" => self.decl.line_height = LineHeight::parse(value),
+            "vertical-align" => self.decl.vertical_align = VerticalAlign::parse(value),
+            "text-align" => self.decl.text_align = TextAlign::from_str(value).ok(),
+            "direction" => self.decl.direction = Direction::from_str(value).ok(),
+            "text-indent" => self.decl.text_indent = Some(Dimension::from_str(value)),
+            "letter-spacing" => self.decl.letter_spacing = parse_spacing(value),
+            "word-spacing" => self.decl.word_spacing = parse_spacing(value),
+            "white-space" => self.decl.white_space = WhiteSpace::from_str(value).ok(),
+            "overflow-wrap" | "word-wrap" => {
+                self.decl.overflow_wrap = OverflowWrap::from_str(value).ok()
+            }
+            "word-break" => self.decl.word_break = WordBreak::from_str(value).ok(),
+            "text-transform" => self.decl.text_transform = TextTransform::from_str(value).ok(),
+            "text-decoration" => {
+                if let Some(decoration) = TextDecoration::parse(value) {
+                    self.decl.text_decoration = decoration;
                 }
             }
-            "margin-top" => self.decl.margin[0] = Some(Dimension::from_str(value)),
-            "margin-right" => self.decl.margin[1] = Some(Dimension::from_str(value)),
-            "margin-bottom" => self.decl.margin[2] = Some(Dimension::from_str(value)),
-            "margin-left" => self.decl.margin[3] = Some(Dimension::from_str(value)),
+            "text-decoration-line" => {
+                self.decl.text_decoration.line = TextDecorationLine::parse(value)
+            }
+            "text-decoration-style" => {
+                self.decl.text_decoration.style = TextDecorationStyle::from_str(value).ok()
+            }
+            "text-decoration-color" => self.decl.text_decoration.color = Color::parse(value),
+            "font" => match FontShorthand::parse(value) {
+                // properties that are not specified are reset to their initial values
+                Some(font) => {
+                    self.decl.font_style = Some(font.style);
+                    self.decl.font_weight = Some(font.weight);
+                    self.decl.font_size = Some(font.size);
+                    self.decl.font_family = Some(font.family);
+                    self.decl.line_height = Some(font.line_height);
+                }
+                None => log::warn!("invalid font shorthand '{value}'"),
+            },
             _ => {
-                log::warn!("unhandled attr '{attr_name}'")
+                log::warn!("unhandled attr '{attr_name}'");
+                let diagnostic = DfError::UnknownStyleProperty(attr_name.to_string()).to_string();
+                if !self.style.diagnostics.contains(&diagnostic) {
+                    self.style.diagnostics.push(diagnostic);
+                }
             }
         }
 
@@ -321,10 +3414,18 @@ impl CssParser {
                 // check if current selector rule list has been closed
                 if let Some(decl_brace_level) = self.decl_brace_level {
                     if decl_brace_level == self.brace_level {
-                        self.style
-                            .add_rule(&self.selector.clone().unwrap(), self.decl.clone());
+                        let decl = std::mem::take(&mut self.decl);
+                        let index = self.style.rules.len();
+                        self.style.add_rule(&self.selector.clone().unwrap(), decl);
+                        // only kept if the selector is valid
+                        if let Some(important) = self.important.take() {
+                            if self.style.rules.len() > index {
+                                self.style.important.push((index, important));
+                            }
+                        }
                         self.decl_brace_level = None;
                         self.selector = None;
+                        self.attr_name = None;
                     }
                 }
             }
@@ -334,7 +3435,7 @@ impl CssParser {
             _ => {
                 // if brace level is 0, we just want to consume a selector
                 if self.brace_level == 0 {
-                    let name = self.consume_name();
+                    let name = self.consume_selector();
                     if name.is_empty() {
                         self.consume(); // always consume something
                         return;
@@ -346,8 +3447,14 @@ impl CssParser {
                 }
 
                 // if we're inside braces, we might need to parse attributes, so regular selector parsing wont work
-                // consume everything until the next ';' or ':' (so stuff like `rgb(255, 255, 255)` is parsed correctly)
-                let name = self.consume_while(|c| c != ';' && c != ':');
+                // consume everything until the next ';' or ':' (so stuff like `rgb(255, 255, 255)` is parsed correctly),
+                // or the end of the block (the last declaration doesn't need a ';'). Values can contain
+                // colons, and semicolons in parentheses or quotes (`url(data:...)`)
+                let name = match self.attr_name {
+                    Some(_) if self.peek() == ':' => String::new(),
+                    Some(_) => self.consume_value(),
+                    None => self.consume_while(|c| c != ';' && c != ':' && c != '}'),
+                };
                 if name.is_empty() {
                     self.consume(); // always consume something
                     return;
@@ -358,7 +3465,7 @@ impl CssParser {
                     self.attr_name = Some(name); // attr name
                 } else if self.brace_level == 1 {
                     log::debug!("raw attr value: '{name}'");
-                    self.parse_attr_value(&name); // attr value
+                    self.parse_attr_value(name.trim()); // attr value
                     self.attr_name = None; // parsed attr, get ready for parsing the next one
                 }
             }
@@ -374,12 +3481,22 @@ impl CssParser {
     }
 
     pub fn parse_inline(inline: &str) -> Declaration {
-        let mut parser = CssParser::new("", ParserMode::Normal);
-        for attr in inline.split(';') {
-            let mut parts = attr.split(':');
+        let (mut decl, important) = Self::parse_inline_with_important(inline);
+        decl.apply(&important);
+        decl
+    }
+
+    /// Parse an inline style into its normal and its `!important` declarations.
+    pub fn parse_inline_with_important(inline: &str) -> (Declaration, Declaration) {
+        Self::parse_inline_with_mode(inline, ParserMode::Normal)
+    }
 
-            let key = parts.next().unwrap_or("").trim();
-            let value = parts.next().unwrap_or("").trim();
+    /// Parse an inline style like [`CssParser::parse_inline_with_important`], in a parsing mode.
+    pub fn parse_inline_with_mode(inline: &str, mode: ParserMode) -> (Declaration, Declaration) {
+        let mut parser = CssParser::new("", mode);
+        for attr in crate::selector::split_top_level(inline, ';') {
+            let (key, value) = attr.split_once(':').unwrap_or((attr, ""));
+            let (key, value) = (key.trim(), value.trim());
 
             // don't attempt to parse failed values
             if key.is_empty() && value.is_empty() {
@@ -389,11 +3506,11 @@ impl CssParser {
             parser.attr_name = Some(key.to_string());
             parser.parse_attr_value(value);
         }
-        parser.decl
+        (parser.decl, parser.important.unwrap_or_default())
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Unit {
     /// Units that are not relative to anything else, and are generally considered to always be the same size.
     /// Value is in pixels.
@@ -409,6 +3526,15 @@ pub enum Unit {
     RelativeToRootFontSize(f32),
     /// Relative to the line height of the element.
     RelativeToLineHeight(f32),
+    /// Number without a unit, only a length if it's 0. In quirks mode, the numbers of some
+    /// properties are parsed as pixels instead (see [`ParserMode::Quirks`]).
+    Unitless(f32),
+    /// Percentage of a length of the containing block (its width for `width`, `margin` and `padding`,
+    /// its height for `height`).
+    Percent(f32),
+    /// The `auto` keyword: the length is computed by the layout. `none` (no maximum size)
+    /// is parsed as `auto`.
+    Auto,
 }
 
 impl Default for Unit {
@@ -420,6 +3546,12 @@ impl Default for Unit {
 impl Unit {
     /// Parses a unit from a string.
     pub fn from_str(s: &str, num: f32) -> Self {
+        if s.trim().is_empty() {
+            return Self::Unitless(num);
+        }
+        if s.trim() == "%" {
+            return Self::Percent(num);
+        }
         // only leave lowercase alphabetic characters and whitespace
         // without unnecessary whitespace on the left and right
         let mut s = s.trim().to_lowercase();
@@ -431,7 +3563,10 @@ impl Unit {
             "cm" => Self::Absolute(num * 96.0 / 2.54),
             "mm" => Self::Absolute((num * 96.0 / 2.54) / 10.0),
             "em" => Self::RelativeToParentFontSize(num),
+            "rem" => Self::RelativeToRootFontSize(num),
             "ex" => Self::RelativeToParentFontHeight(num),
+            "lh" => Self::RelativeToLineHeight(num),
+            "auto" | "none" => Self::Auto,
             _ => {
                 // TODO: what should we do here?
                 log::warn!("unhandled unit '{s}'");
@@ -441,8 +3576,196 @@ impl Unit {
     }
 }
 
+/// Parse a keyword, falling back to the initial value of the property when it's unknown.
+fn keyword<T: FromStr + Default>(name: &str, value: &str) -> T {
+    T::from_str(value).unwrap_or_else(|_| {
+        log::warn!("invalid value '{value}' for '{name}'");
+        T::default()
+    })
+}
+
+/// Parse the `flex-flow` shorthand: a direction and a wrap, in any order.
+fn parse_flex_flow(value: &str) -> Option<(FlexDirection, FlexWrap)> {
+    let (mut direction, mut wrap) = (None, None);
+    for token in value.split_whitespace() {
+        match (FlexDirection::from_str(token), FlexWrap::from_str(token)) {
+            (Ok(value), _) if direction.is_none() => direction = Some(value),
+            (_, Ok(value)) if wrap.is_none() => wrap = Some(value),
+            _ => return None,
+        }
+    }
+    (direction.is_some() || wrap.is_some())
+        .then(|| (direction.unwrap_or_default(), wrap.unwrap_or_default()))
+}
+
+/// Parse a `flex-grow` or `flex-shrink` factor, which can't be negative.
+fn parse_flex_factor(value: &str) -> Option<f32> {
+    value.parse().ok().filter(|factor: &f32| *factor >= 0.0)
+}
+
+/// Parse a `flex-basis`: `auto`, `content` (sized like `auto`) or a non-negative length.
+fn parse_flex_basis(value: &str) -> Option<Dimension> {
+    match value {
+        "auto" | "content" => Some(Dimension::from_str("auto")),
+        _ => parse_length_percentage(value).filter(|basis| basis.number >= 0.0),
+    }
+}
+
+/// Parse the `flex` shorthand into the grow and shrink factors and the basis. A grow factor
+/// without a basis has a basis of 0%, not `auto`.
+fn parse_flex(value: &str) -> Option<(f32, f32, Dimension)> {
+    let auto = Dimension::from_str("auto");
+    match value {
+        "none" => return Some((0.0, 0.0, auto)),
+        "auto" => return Some((1.0, 1.0, auto)),
+        "initial" => return Some((0.0, 1.0, auto)),
+        _ => (),
+    }
+    let (mut factors, mut basis) = (vec![], None);
+    // the factors are next to each other, before or after the basis
+    let mut factors_done = false;
+    for token in value.split_whitespace() {
+        // a unitless 0 is a factor, unless both factors are already there
+        match parse_flex_factor(token) {
+            Some(factor) if factors.len() < 2 && !factors_done => factors.push(factor),
+            _ if basis.is_none() => {
+                basis = Some(parse_flex_basis(token)?);
+                factors_done = !factors.is_empty();
+            }
+            _ => return None,
+        }
+    }
+    let basis = match (basis, factors.is_empty()) {
+        (None, true) => return None,
+        (None, false) => percent(0.0),
+        (Some(basis), _) => basis,
+    };
+    Some((
+        factors.first().copied().unwrap_or(1.0),
+        factors.get(1).copied().unwrap_or(1.0),
+        basis,
+    ))
+}
+
+/// Parse an opacity: a number or a percentage, clamped between 0 and 1.
+fn parse_opacity(value: &str) -> Option<f32> {
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+        None => value.parse::<f32>().ok()?,
+    };
+    opacity.is_finite().then_some(opacity.clamp(0.0, 1.0))
+}
+
+/// Parse a `letter-spacing` or `word-spacing` value, `normal` is `None`.
+fn parse_spacing(value: &str) -> Option<Dimension> {
+    (value != "normal").then(|| Dimension::from_str(value))
+}
+
+fn parse_font_family(value: &str) -> FontFamily {
+    FontFamily::from_str(value).unwrap_or(FontFamily::Custom(value.to_string()))
+}
+
+/// The parts of the `font` shorthand, such as `italic bold 14px/1.4 Georgia, serif`.
+struct FontShorthand {
+    style: FontStyle,
+    weight: FontWeight,
+    size: Dimension,
+    line_height: LineHeight,
+    family: FontFamily,
+}
+
+impl FontShorthand {
+    /// Parse the shorthand, `None` if any part of it is invalid.
+    fn parse(value: &str) -> Option<Self> {
+        let is_length = |token: &str| token.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+        let value = value.replace('/', " / ");
+        let mut tokens = value.split_whitespace().peekable();
+        let (mut style, mut weight, mut variant, mut stretch) = (None, None, false, false);
+
+        // style, variant, weight and stretch in any order, then the size
+        let size = loop {
+            let token = tokens.next()?;
+            match token {
+                // `normal` resets any of them
+                "normal" => (),
+                "small-caps" if !variant => variant = true,
+                "ultra-condensed" | "extra-condensed" | "condensed" | "semi-condensed"
+                | "semi-expanded" | "expanded" | "extra-expanded" | "ultra-expanded"
+                    if !stretch =>
+                {
+                    stretch = true
+                }
+                "oblique" if style.is_none() => {
+                    let angle = tokens.next_if(|token| token.ends_with("deg"));
+                    let value = format!("oblique {}", angle.unwrap_or_default());
+                    style = Some(FontStyle::parse(&value)?);
+                }
+                _ if style.is_none() && FontStyle::from_str(token).is_ok() => {
+                    style = FontStyle::parse(token)
+                }
+                _ if weight.is_none() && FontWeight::from_str(token).is_ok() => {
+                    weight = FontWeight::from_str(token).ok()
+                }
+                _ if is_length(token) => {
+                    let size = Dimension::from_str(token);
+                    if size.is_quirky() || size.is_auto() {
+                        return None;
+                    }
+                    break size;
+                }
+                _ => break font_size_keyword(token)?,
+            }
+        };
+
+        let line_height = match tokens.next_if_eq(&"/") {
+            Some(_) => LineHeight::parse(tokens.next()?)?,
+            None => LineHeight::Normal,
+        };
+
+        let family = tokens.collect::<Vec<_>>().join(" ");
+        if family.is_empty() {
+            return None;
+        }
+        Some(Self {
+            style: style.unwrap_or_default(),
+            weight: weight.unwrap_or(FontWeight::NORMAL),
+            size,
+            line_height,
+            family: parse_font_family(&family),
+        })
+    }
+}
+
+/// Parse a `font-size` value: a length, a percentage of the parent font size, or a keyword.
+fn parse_font_size(value: &str) -> Dimension {
+    font_size_keyword(value).unwrap_or_else(|| Dimension::from_str(value))
+}
+
+/// Resolve a `font-size` keyword. Absolute keywords are scaled from `medium`, the default
+/// font size.
+fn font_size_keyword(value: &str) -> Option<Dimension> {
+    let relative = |em: f32| Dimension {
+        number: em,
+        unit: Unit::RelativeToParentFontSize(em),
+    };
+    let scale = match value {
+        "xx-small" => 3.0 / 5.0,
+        "x-small" => 3.0 / 4.0,
+        "small" => 8.0 / 9.0,
+        "medium" => 1.0,
+        "large" => 6.0 / 5.0,
+        "x-large" => 3.0 / 2.0,
+        "xx-large" => 2.0,
+        "xxx-large" => 3.0,
+        "larger" => return Some(relative(1.2)),
+        "smaller" => return Some(relative(1.0 / 1.2)),
+        _ => return None,
+    };
+    Some(Dimension::px(DEFAULT_FONT_SIZE * scale))
+}
+
 /// Represents and parses CSS dimensions (number + unit) (e.g. `4px`, `.7em`, `1.2rem`).
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Dimension {
     /// The number part of the dimension.
     pub number: f32,
@@ -451,6 +3774,15 @@ pub struct Dimension {
 }
 
 impl Dimension {
+    /// A length in pixels.
+    pub fn px(px: f32) -> Self {
+        Self {
+            number: px,
+            unit: Unit::Absolute(px),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         log::debug!("parsing dimension '{s}'");
         let (number, number_len) = Self::parse_number(s);
@@ -460,12 +3792,13 @@ impl Dimension {
     }
 
     fn parse_number(s: &str) -> (f32, usize) {
-        let mut number_str = String::new();
-        for c in s.chars() {
-            if c.is_numeric() || c == '.' {
-                number_str.push(c)
-            }
-        }
+        // an optional sign, then digits and the decimal point
+        let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+        let sign_len = s.len() - unsigned.len();
+        let digits = unsigned
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(unsigned.len());
+        let number_str = &s[..sign_len + digits];
         let parsed = number_str.parse::<f32>();
         if let Ok(num) = parsed {
             log::debug!("dimension number str: {number_str}");
@@ -478,4 +3811,51 @@ impl Dimension {
             (0.0, 0)
         }
     }
+
+    /// Whether the dimension is a number without a unit, other than 0.
+    pub fn is_quirky(&self) -> bool {
+        matches!(self.unit, Unit::Unitless(n) if n != 0.0)
+    }
+
+    /// Whether the dimension is the `auto` keyword.
+    pub fn is_auto(&self) -> bool {
+        matches!(self.unit, Unit::Auto)
+    }
+
+    /// Resolve the dimension to pixels, given the font size of the element. Percentages and
+    /// `auto` are 0, see [`Dimension::resolve`].
+    pub fn to_px(&self, font_size: f32) -> f32 {
+        self.resolve(font_size, Some(0.0)).unwrap_or(0.0)
+    }
+
+    /// Resolve the dimension to pixels, given the font size of the element and the length that
+    /// percentages refer to. Returns `None` for `auto`, for percentages without a
+    /// `percent_base`, and for numbers without a unit other than 0 (they are only lengths
+    /// in quirks mode, where they are parsed as pixels).
+    ///
+    /// ```
+    /// use dragonfly::Dimension;
+    /// assert_eq!(Dimension::from_str("300px").resolve(16.0, None), Some(300.0));
+    /// assert_eq!(Dimension::from_str("2em").resolve(16.0, None), Some(32.0));
+    /// assert_eq!(Dimension::from_str("50%").resolve(16.0, Some(600.0)), Some(300.0));
+    /// assert_eq!(Dimension::from_str("50%").resolve(16.0, None), None);
+    /// assert_eq!(Dimension::from_str("auto").resolve(16.0, Some(600.0)), None);
+    /// assert_eq!(Dimension::from_str("0").resolve(16.0, None), Some(0.0));
+    /// assert_eq!(Dimension::from_str("40").resolve(16.0, None), None);
+    /// ```
+    pub fn resolve(&self, font_size: f32, percent_base: Option<f32>) -> Option<f32> {
+        Some(match self.unit {
+            Unit::Percent(n) => n / 100.0 * percent_base?,
+            Unit::Auto => return None,
+            Unit::Absolute(px) => px,
+            Unit::Unitless(0.0) => 0.0,
+            Unit::Unitless(_) => return None,
+            Unit::RelativeToParentFontSize(n) => n * font_size,
+            Unit::RelativeToParentFontHeight(n) | Unit::RelativeToGlyph0Width(n) => {
+                n * font_size * 0.5
+            }
+            Unit::RelativeToRootFontSize(n) => n * DEFAULT_FONT_SIZE,
+            Unit::RelativeToLineHeight(n) => n * font_size * 1.2,
+        })
+    }
 }
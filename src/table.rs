@@ -0,0 +1,155 @@
+use crate::Layout;
+use indextree::NodeId;
+
+/// A `th` or `td` cell of a table.
+#[derive(Debug, Clone)]
+pub struct TableCell {
+    pub node: NodeId,
+    /// Trimmed text content of the cell
+    pub text: String,
+    /// Whether this is a `th` cell
+    pub header: bool,
+    pub row_span: usize,
+    pub col_span: usize,
+}
+
+/// Cells of a table laid out in a grid.
+#[derive(Debug, Clone, Default)]
+pub struct TableData {
+    /// All cells, in document order
+    pub cells: Vec<TableCell>,
+    /// Rows of the grid: an index into `cells` for every slot. Slots covered by a spanning cell
+    /// repeat the index of that cell, slots of rows shorter than the widest row are `None`.
+    pub grid: Vec<Vec<Option<usize>>>,
+}
+
+impl TableData {
+    /// Cell covering a grid slot.
+    pub fn cell(&self, row: usize, col: usize) -> Option<&TableCell> {
+        let index = (*self.grid.get(row)?.get(col)?)?;
+        self.cells.get(index)
+    }
+
+    /// Text of every grid slot (empty for padding slots).
+    pub fn texts(&self) -> Vec<Vec<&str>> {
+        self.grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|slot| slot.map_or("", |index| self.cells[index].text.as_str()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Layout {
+    /// Extract the cells of a table into a grid, expanding `colspan` and `rowspan`.
+    /// Rows and cells of nested tables are not included.
+    ///
+    /// ```
+    /// use dragonfly::{parse_document, FontManager, Layout};
+    /// let html = r#"<table>
+    ///     <thead><tr><th colspan=2>Name</th><th rowspan=2>Age</th></tr></thead>
+    ///     <tbody>
+    ///         <tr><td>First</td><td>Last</td></tr>
+    ///         <tr><td rowspan=0>all</td><td>a</td><td>1</td></tr>
+    ///         <tr><td colspan=2> b </td></tr>
+    ///         <tr><td>c<table><tr><td>nested</td></tr></table></td></tr>
+    ///     </tbody>
+    /// </table>"#;
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let data = layout.table_data(layout.select("table")[0]);
+    /// assert_eq!(data.texts(), [
+    ///     ["Name", "Name", "Age"],
+    ///     ["First", "Last", "Age"],
+    ///     ["all", "a", "1"],
+    ///     ["all", "b", "b"],
+    ///     ["all", "cnested", ""],
+    /// ]);
+    /// // `rowspan=0` spans to the last row, spans are clamped to the table
+    /// let all = data.cell(2, 0).unwrap();
+    /// assert_eq!((all.row_span, all.col_span), (3, 1));
+    /// let age = data.cell(0, 2).unwrap();
+    /// assert_eq!((age.header, age.row_span, age.col_span), (true, 2, 1));
+    /// assert_eq!(data.cell(3, 1).unwrap().col_span, 2);
+    /// assert!(data.cell(4, 2).is_none() && data.cell(5, 0).is_none());
+    /// assert_eq!(data.cells.len(), 9);
+    ///
+    /// // a cell overlapping a row span from above covers its slots
+    /// let html = r#"<table>
+    ///     <tr><td>a</td><td rowspan=2>b</td></tr>
+    ///     <tr><td colspan=3>c</td></tr>
+    /// </table>"#;
+    /// let layout = Layout::compute(&mut parse_document(html), &mut FontManager::default());
+    /// let data = layout.table_data(layout.select("table")[0]);
+    /// assert_eq!(data.texts(), [["a", "b", ""], ["c", "c", "c"]]);
+    /// assert_eq!(data.cell(0, 1).unwrap().row_span, 2);
+    /// ```
+    pub fn table_data(&self, table: NodeId) -> TableData {
+        let rows: Vec<NodeId> = table
+            .descendants(&self.arena)
+            .filter(|&id| self.arena[id].get().name() == "tr")
+            .filter(|&id| self.closest(id, "table") == Some(table))
+            .collect();
+
+        let mut data = TableData {
+            grid: vec![vec![]; rows.len()],
+            ..Default::default()
+        };
+        let span = |id: NodeId, name: &str, max: usize| {
+            self.arena[id]
+                .get()
+                .attr(name)
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or(1)
+                .min(max)
+        };
+
+        for (r, &row) in rows.iter().enumerate() {
+            let mut c = 0;
+            for id in row.children(&self.arena) {
+                let node = self.arena[id].get();
+                if !matches!(node.name(), "td" | "th") {
+                    continue;
+                }
+                // skip the slots taken by cells spanning from the rows above
+                while data.grid[r].get(c).is_some_and(Option::is_some) {
+                    c += 1;
+                }
+
+                // `rowspan="0"` spans to the last row
+                let row_span = match span(id, "rowspan", 65534) {
+                    0 => rows.len() - r,
+                    n => n.min(rows.len() - r),
+                };
+                let col_span = span(id, "colspan", 1000).max(1);
+                let index = data.cells.len();
+                data.cells.push(TableCell {
+                    node: id,
+                    text: self.text_content(id).trim().to_string(),
+                    header: node.name() == "th",
+                    row_span,
+                    col_span,
+                });
+
+                for grid_row in &mut data.grid[r..r + row_span] {
+                    if grid_row.len() < c + col_span {
+                        grid_row.resize(c + col_span, None);
+                    }
+                    for slot in &mut grid_row[c..c + col_span] {
+                        *slot = Some(index);
+                    }
+                }
+                c += col_span;
+            }
+        }
+
+        // pad ragged rows
+        let width = data.grid.iter().map(Vec::len).max().unwrap_or(0);
+        for row in &mut data.grid {
+            row.resize(width, None);
+        }
+        data
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{Dir, WhiteSpace};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Line breaking class of a character.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreakClass {
+    /// Collapsible space, lines can break after it.
+    Space,
+    /// CJK ideographs, kana and hangul: lines can break between them.
+    Ideographic,
+    /// Closing punctuation and small kana that can't start a line (kinsoku).
+    Closing,
+    /// Opening punctuation that can't end a line (kinsoku).
+    Opening,
+    /// Everything else (latin letters, digits...), lines only break at spaces.
+    Other,
+}
+
+/// Characters that must not start a line.
+const CLOSING: &str = "、。，．・：；？！ーｰ」』）】〕〉》〙〗〟｝］”’ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶㇰㇱㇲㇳㇴㇵㇶㇷㇸㇹㇺㇻㇼㇽㇾㇿ々〻‐゠–〜";
+
+/// Characters that must not end a line.
+const OPENING: &str = "「『（【〔〈《〘〖〝｛［“‘";
+
+impl BreakClass {
+    /// Classify a character for line breaking.
+    pub fn of(c: char) -> Self {
+        if c == ' ' {
+            Self::Space
+        } else if CLOSING.contains(c) {
+            Self::Closing
+        } else if OPENING.contains(c) {
+            Self::Opening
+        } else if is_cjk(c) {
+            Self::Ideographic
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Whether the character is a CJK ideograph, kana or hangul character (or CJK punctuation).
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x11FF // hangul jamo
+        | 0x2E80..=0x2FDF // CJK radicals
+        | 0x3000..=0x303F // CJK symbols and punctuation
+        | 0x3040..=0x309F // hiragana
+        | 0x30A0..=0x30FF // katakana
+        | 0x3130..=0x318F // hangul compatibility jamo
+        | 0x31F0..=0x31FF // katakana phonetic extensions
+        | 0x3400..=0x4DBF // CJK unified ideographs extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xAC00..=0xD7AF // hangul syllables
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFF00..=0xFFEF // halfwidth and fullwidth forms
+        | 0x20000..=0x2FA1F // supplementary ideographic plane
+    )
+}
+
+/// Whether the character is a strong right-to-left character (Hebrew, Arabic, Syriac, Thaana...).
+pub fn is_strong_rtl(c: char) -> bool {
+    matches!(c as u32,
+        0x0590..=0x08FF // hebrew, arabic, syriac, thaana, nko, samaritan...
+        | 0xFB1D..=0xFDFF // hebrew and arabic presentation forms
+        | 0xFE70..=0xFEFF // arabic presentation forms B
+        | 0x10800..=0x10FFF // old semitic scripts
+        | 0x1E800..=0x1EFFF // adlam, arabic mathematical symbols
+    ) && c.is_alphabetic()
+}
+
+/// Direction of the first strong character of the text, used to resolve `dir="auto"`.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{first_strong_direction, Dir};
+/// assert_eq!(first_strong_direction("123 שלום world"), Some(Dir::Rtl));
+/// assert_eq!(first_strong_direction("123"), None);
+/// ```
+pub fn first_strong_direction(text: &str) -> Option<Dir> {
+    text.chars()
+        .find(|c| c.is_alphabetic())
+        .map(|c| if is_strong_rtl(c) { Dir::Rtl } else { Dir::Ltr })
+}
+
+/// A piece of text that is never broken across lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSegment {
+    /// Byte range of the segment in the text
+    pub range: Range<usize>,
+    /// Whether this is a collapsible space
+    pub space: bool,
+    /// Whether a line may break right before this segment (without a space between them)
+    pub break_before: bool,
+    /// Whether this is a preserved newline: the line ends after it
+    pub newline: bool,
+}
+
+/// Split text into segments at line break opportunities.
+///
+/// Lines can break at spaces and between CJK characters, but never before closing
+/// punctuation or after opening punctuation.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::segment_text;
+/// let text = "hello 世界。";
+/// let segments: Vec<&str> = segment_text(text).iter().map(|s| &text[s.range.clone()]).collect();
+/// assert_eq!(segments, ["hello", " ", "世", "界。"]);
+/// ```
+///
+/// A paragraph of Chinese text wraps in a narrow container, and no line starts with
+/// closing punctuation:
+///
+/// ```rust
+/// use dragonfly::{parse_document, FontManager, Layout};
+/// let text = "我们今天去公园散步，天气很好、阳光明媚。孩子们在草地上玩耍，老人们在树下下棋。我们买了冰淇淋、喝茶。";
+/// assert_eq!(text.chars().count(), 50);
+/// let html = format!(r#"<!DOCTYPE html><div style="width: 100px">{text}</div>"#);
+/// let layout = Layout::compute(&mut parse_document(&html), &mut FontManager::default());
+/// let div = layout.select("div")[0];
+/// let node = layout.arena[layout.arena[div].first_child().unwrap()].get();
+///
+/// // first fragment of every line
+/// let mut lines: Vec<(f32, &str)> = vec![];
+/// for fragment in &node.fragments {
+///     if lines.last().is_none_or(|&(y, _)| fragment.pos.y > y) {
+///         lines.push((fragment.pos.y, &fragment.text));
+///     }
+/// }
+/// assert!(lines.len() > 1);
+/// for (_, line) in &lines {
+///     assert!(!line.starts_with(['。', '、', '，']), "{line}");
+/// }
+/// let all: String = node.fragments.iter().map(|f| f.text.as_str()).collect();
+/// assert_eq!(all, text);
+/// ```
+pub fn segment_text(text: &str) -> Vec<TextSegment> {
+    let mut segments = vec![];
+    let mut current: Option<TextSegment> = None;
+    // class of the last character in the current segment
+    let mut last = BreakClass::Space;
+
+    for (i, c) in text.char_indices() {
+        let class = BreakClass::of(c);
+        let end = i + c.len_utf8();
+
+        let starts_segment = match class {
+            BreakClass::Space => {
+                segments.extend(current.take());
+                segments.push(TextSegment {
+                    range: i..end,
+                    space: true,
+                    break_before: false,
+                    newline: false,
+                });
+                last = class;
+                continue;
+            }
+            BreakClass::Closing => false,
+            _ if last == BreakClass::Opening => false,
+            BreakClass::Ideographic | BreakClass::Opening => true,
+            BreakClass::Other => matches!(last, BreakClass::Ideographic | BreakClass::Closing),
+        };
+
+        match current.as_mut() {
+            Some(segment) if !starts_segment => segment.range.end = end,
+            _ => {
+                segments.extend(current.take());
+                current = Some(TextSegment {
+                    range: i..end,
+                    space: false,
+                    break_before: starts_segment,
+                    newline: false,
+                });
+            }
+        }
+        last = class;
+    }
+
+    segments.extend(current);
+    segments
+}
+
+/// Whether the character is document whitespace (space, tab, newline, carriage return or form
+/// feed), the whitespace handled by the `white-space` property.
+fn is_document_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+/// Split text into segments at line break opportunities like [`segment_text`], with its
+/// whitespace handled as specified by a `white-space` value.
+///
+/// Collapsible whitespace runs are a single space segment, preserved spaces are part of the
+/// content and preserved newlines are segments of their own. Text that doesn't wrap has no
+/// break opportunities.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{segment_text_with, WhiteSpace};
+/// let text = "a  b\n c";
+/// let segments = |white_space| -> Vec<&str> {
+///     let segments = segment_text_with(text, white_space);
+///     segments.iter().map(|s| &text[s.range.clone()]).collect()
+/// };
+/// assert_eq!(segments(WhiteSpace::Normal), ["a", "  ", "b", "\n ", "c"]);
+/// assert_eq!(segments(WhiteSpace::PreLine), ["a", "  ", "b", "\n", " ", "c"]);
+/// assert_eq!(segments(WhiteSpace::Pre), ["a", "  ", "b", "\n", " ", "c"]);
+/// assert!(segment_text_with(text, WhiteSpace::Pre).iter().all(|s| !s.space));
+/// ```
+pub fn segment_text_with(text: &str, white_space: WhiteSpace) -> Vec<TextSegment> {
+    let mut segments: Vec<TextSegment> = vec![];
+    let push = |segments: &mut Vec<TextSegment>, range, space, newline| {
+        segments.push(TextSegment {
+            range,
+            space,
+            break_before: false,
+            newline,
+        })
+    };
+
+    let mut start = 0;
+    while start < text.len() {
+        let rest = &text[start..];
+        let word_len = rest.find(is_document_whitespace).unwrap_or(rest.len());
+        if word_len > 0 {
+            let words = segment_text(&rest[..word_len]);
+            // lines can break after preserved spaces that wrap
+            let after_space = start > 0 && white_space == WhiteSpace::PreWrap;
+            for (k, mut segment) in words.into_iter().enumerate() {
+                segment.range = segment.range.start + start..segment.range.end + start;
+                segment.break_before = match white_space.wraps() {
+                    true => segment.break_before || (k == 0 && after_space),
+                    false => false,
+                };
+                segments.push(segment);
+            }
+            start += word_len;
+            continue;
+        }
+
+        let run_len = rest
+            .find(|c| !is_document_whitespace(c))
+            .unwrap_or(rest.len());
+        let run = start..start + run_len;
+        start = run.end;
+        if !white_space.preserves_newlines() {
+            push(&mut segments, run, true, false);
+            continue;
+        }
+        // newlines are segments of their own, the spaces between them are collapsible or not
+        let mut from = run.start;
+        for (i, c) in text[run.clone()].char_indices() {
+            let i = run.start + i;
+            if c == '\n' {
+                if from < i {
+                    push(
+                        &mut segments,
+                        from..i,
+                        white_space.collapses_spaces(),
+                        false,
+                    );
+                }
+                push(&mut segments, i..i + 1, false, true);
+                from = i + 1;
+            }
+        }
+        if from < run.end {
+            push(
+                &mut segments,
+                from..run.end,
+                white_space.collapses_spaces(),
+                false,
+            );
+        }
+    }
+    segments
+}
+
+/// Replace every run of whitespace characters with a single space.
+///
+/// Text that doesn't need collapsing (very common) is returned as is, without allocating.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::collapse_whitespace;
+/// assert_eq!(collapse_whitespace("a \n\t b"), "a b");
+/// assert!(matches!(collapse_whitespace("a b"), std::borrow::Cow::Borrowed(_)));
+///
+/// // multi-byte characters are kept whole
+/// assert_eq!(collapse_whitespace("Привет,\n   мир"), "Привет, мир");
+/// assert_eq!(collapse_whitespace("👍\t\t🎉 \u{3000}é"), "👍 🎉 é");
+///
+/// // all-whitespace text collapses to a single space, which is borrowed
+/// assert_eq!(collapse_whitespace(" \n\t\r "), " ");
+/// assert!(matches!(collapse_whitespace(" "), std::borrow::Cow::Borrowed(" ")));
+/// assert_eq!(collapse_whitespace(""), "");
+///
+/// // about 1 MB of text: borrowed as is, or collapsed in one pass
+/// let text = "lorem ipsum ".repeat(90_000);
+/// let collapsed = collapse_whitespace(&text);
+/// assert!(matches!(collapsed, std::borrow::Cow::Borrowed(s) if std::ptr::eq(s, text.as_str())));
+/// let text = "lorem \n ipsum ".repeat(75_000);
+/// assert!(text.len() > 1_000_000);
+/// assert_eq!(collapse_whitespace(&text), "lorem ipsum ".repeat(75_000));
+/// ```
+pub fn collapse_whitespace(text: &str) -> Cow<'_, str> {
+    let mut prev_whitespace = false;
+    let needs_collapsing = text.chars().any(|c| {
+        let collapse = c.is_whitespace() && (prev_whitespace || c != ' ');
+        prev_whitespace = c.is_whitespace();
+        collapse
+    });
+    if !needs_collapsing {
+        return Cow::Borrowed(text);
+    }
+
+    let mut collapsed = String::with_capacity(text.len());
+    prev_whitespace = false;
+    for c in text.chars() {
+        if c.is_whitespace() && prev_whitespace {
+            continue;
+        }
+        prev_whitespace = c.is_whitespace();
+        collapsed.push(if prev_whitespace { ' ' } else { c });
+    }
+    Cow::Owned(collapsed)
+}
@@ -1,5 +1,747 @@
-/// Shorthand for `cgmath::Vector2<f32>`
-pub type Vec2 = cgmath::Vector2<f32>;
+use crate::UrlResolveError;
+use url::Url;
 
-/// Shorthand for `cgmath::Point2<f32>`
-pub type Pos2 = cgmath::Point2<f32>;
+/// A 2D displacement or size, in CSS pixels.
+///
+/// Convert from and to `[f32; 2]`, and to the `mint` and `glam` types with the features
+/// of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A 2D position, in CSS pixels.
+///
+/// Convert from and to `[f32; 2]`, and to the `mint` and `glam` types with the features
+/// of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Pos2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2 {
+    pub const ZERO: Self = Self::new(0.0, 0.0);
+
+    #[inline]
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Pos2 {
+    pub const ZERO: Self = Self::new(0.0, 0.0);
+
+    #[inline]
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    /// Offset of the position from the origin.
+    #[inline]
+    pub const fn to_vec2(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+macro_rules! impl_op {
+    ($lhs:ty, $trait:ident, $method:ident, $rhs:ty, $out:ty, $op:tt) => {
+        impl std::ops::$trait<$rhs> for $lhs {
+            type Output = $out;
+            #[inline]
+            fn $method(self, rhs: $rhs) -> $out {
+                <$out>::new(self.x $op rhs.x, self.y $op rhs.y)
+            }
+        }
+    };
+}
+
+macro_rules! impl_op_assign {
+    ($lhs:ty, $trait:ident, $method:ident, $rhs:ty, $op:tt) => {
+        impl std::ops::$trait<$rhs> for $lhs {
+            #[inline]
+            fn $method(&mut self, rhs: $rhs) {
+                self.x $op rhs.x;
+                self.y $op rhs.y;
+            }
+        }
+    };
+}
+
+impl_op!(Pos2, Add, add, Vec2, Pos2, +);
+impl_op!(Pos2, Sub, sub, Vec2, Pos2, -);
+impl_op!(Pos2, Sub, sub, Pos2, Vec2, -);
+impl_op!(Vec2, Add, add, Vec2, Vec2, +);
+impl_op!(Vec2, Sub, sub, Vec2, Vec2, -);
+impl_op_assign!(Pos2, AddAssign, add_assign, Vec2, +=);
+impl_op_assign!(Pos2, SubAssign, sub_assign, Vec2, -=);
+impl_op_assign!(Vec2, AddAssign, add_assign, Vec2, +=);
+impl_op_assign!(Vec2, SubAssign, sub_assign, Vec2, -=);
+
+impl std::ops::Mul<f32> for Vec2 {
+    type Output = Vec2;
+    #[inline]
+    fn mul(self, rhs: f32) -> Vec2 {
+        Vec2::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl std::ops::Div<f32> for Vec2 {
+    type Output = Vec2;
+    #[inline]
+    fn div(self, rhs: f32) -> Vec2 {
+        Vec2::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl std::ops::Neg for Vec2 {
+    type Output = Vec2;
+    #[inline]
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+macro_rules! impl_array_conversions {
+    ($ty:ty) => {
+        impl From<[f32; 2]> for $ty {
+            #[inline]
+            fn from([x, y]: [f32; 2]) -> Self {
+                Self::new(x, y)
+            }
+        }
+
+        impl From<$ty> for [f32; 2] {
+            #[inline]
+            fn from(v: $ty) -> Self {
+                [v.x, v.y]
+            }
+        }
+
+        impl From<(f32, f32)> for $ty {
+            #[inline]
+            fn from((x, y): (f32, f32)) -> Self {
+                Self::new(x, y)
+            }
+        }
+
+        impl From<$ty> for (f32, f32) {
+            #[inline]
+            fn from(v: $ty) -> Self {
+                (v.x, v.y)
+            }
+        }
+    };
+}
+
+impl_array_conversions!(Pos2);
+impl_array_conversions!(Vec2);
+
+/// An axis-aligned rectangle, from its top-left corner and its size.
+///
+/// Rectangles built with [`Rect::from_pos_size`] and [`Rect::from_min_max`] are normalized:
+/// their size is never negative. A rectangle without an area is empty, it contains no point
+/// and intersects nothing.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{Pos2, Rect, Sides, Vec2};
+/// let a = Rect::from_pos_size(Pos2::new(0.0, 0.0), Vec2::new(10.0, 10.0));
+/// let b = Rect::from_min_max(Pos2::new(15.0, 5.0), Pos2::new(5.0, 20.0));
+/// assert_eq!(b, Rect::from_pos_size(Pos2::new(5.0, 5.0), Vec2::new(10.0, 15.0)));
+///
+/// // negative sizes are normalized
+/// let c = Rect::from_pos_size(Pos2::new(10.0, 10.0), Vec2::new(-4.0, 5.0));
+/// assert_eq!((c.pos, c.size), (Pos2::new(6.0, 10.0), Vec2::new(4.0, 5.0)));
+///
+/// // points on the right and bottom edges are outside
+/// assert!(a.contains(Pos2::new(0.0, 0.0)) && a.contains(Pos2::new(9.5, 9.5)));
+/// assert!(!a.contains(Pos2::new(10.0, 5.0)) && !a.contains(Pos2::new(5.0, -0.1)));
+///
+/// assert!(a.intersects(&b));
+/// assert_eq!(a.intersection(&b), Some(Rect::from_min_max(Pos2::new(5.0, 5.0), Pos2::new(10.0, 10.0))));
+/// assert_eq!(a.union(&b), Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(15.0, 20.0)));
+/// assert_eq!((a.area(), b.area()), (100.0, 150.0));
+///
+/// // rects that only touch don't intersect
+/// let right = a.translate(Vec2::new(10.0, 0.0));
+/// assert_eq!(right.pos, Pos2::new(10.0, 0.0));
+/// assert!(!a.intersects(&right) && a.intersection(&right).is_none());
+///
+/// assert_eq!(a.expand(Sides::splat(2.0)), Rect::from_min_max(Pos2::new(-2.0, -2.0), Pos2::new(12.0, 12.0)));
+/// assert_eq!(a.inset(Sides::new(1.0, 2.0, 3.0, 4.0)), Rect::from_min_max(Pos2::new(4.0, 1.0), Pos2::new(8.0, 7.0)));
+/// // insetting past the center collapses the rect at its center
+/// assert_eq!(a.inset(Sides::splat(6.0)), Rect::from_pos_size(Pos2::new(5.0, 5.0), Vec2::new(0.0, 0.0)));
+///
+/// // empty rects
+/// let line = Rect::from_pos_size(Pos2::new(2.0, 2.0), Vec2::new(5.0, 0.0));
+/// assert!(line.is_empty() && Rect::ZERO.is_empty() && !a.is_empty());
+/// assert!(!line.contains(Pos2::new(3.0, 2.0)));
+/// assert!(!line.intersects(&a) && line.intersection(&a).is_none());
+/// assert_eq!(a.union(&line), a);
+/// assert_eq!(line.union(&a), a);
+/// assert_eq!(line.area(), 0.0);
+///
+/// assert_eq!(b.to_string(), "10x15 at (5, 5)");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    /// Top-left corner
+    pub pos: Pos2,
+    pub size: Vec2,
+}
+
+impl Default for Rect {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl Rect {
+    /// Empty rectangle at the origin.
+    pub const ZERO: Self = Self {
+        pos: Pos2::new(0.0, 0.0),
+        size: Vec2::new(0.0, 0.0),
+    };
+
+    /// Rectangle from its corner and its size. A negative size extends the rectangle
+    /// to the left (or top) of `pos`.
+    pub fn from_pos_size(pos: Pos2, size: Vec2) -> Self {
+        Self::from_min_max(pos, pos + size)
+    }
+
+    /// Rectangle between two opposite corners, in any order.
+    pub fn from_min_max(a: Pos2, b: Pos2) -> Self {
+        let min = Pos2::new(a.x.min(b.x), a.y.min(b.y));
+        let max = Pos2::new(a.x.max(b.x), a.y.max(b.y));
+        Self {
+            pos: min,
+            size: max - min,
+        }
+    }
+
+    /// Top-left corner.
+    #[inline]
+    pub fn min(&self) -> Pos2 {
+        self.pos
+    }
+
+    /// Bottom-right corner.
+    #[inline]
+    pub fn max(&self) -> Pos2 {
+        self.pos + self.size
+    }
+
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.size.x
+    }
+
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.size.y
+    }
+
+    #[inline]
+    pub fn area(&self) -> f32 {
+        self.size.x.max(0.0) * self.size.y.max(0.0)
+    }
+
+    /// Whether the rectangle has no area.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.size.x <= 0.0 || self.size.y <= 0.0
+    }
+
+    /// Whether a point is inside the rectangle. The left and top edges are inside,
+    /// the right and bottom edges are not.
+    pub fn contains(&self, point: Pos2) -> bool {
+        let max = self.max();
+        point.x >= self.pos.x && point.y >= self.pos.y && point.x < max.x && point.y < max.y
+    }
+
+    /// Whether another rectangle is entirely inside this one (edges included).
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        let (max, other_max) = (self.max(), other.max());
+        other.pos.x >= self.pos.x
+            && other.pos.y >= self.pos.y
+            && other_max.x <= max.x
+            && other_max.y <= max.y
+    }
+
+    /// Whether the rectangles overlap (rectangles that only touch don't).
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Overlapping part of the rectangles, `None` if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let (max, other_max) = (self.max(), other.max());
+        let min = Pos2::new(self.pos.x.max(other.pos.x), self.pos.y.max(other.pos.y));
+        let max = Pos2::new(max.x.min(other_max.x), max.y.min(other_max.y));
+        let rect = Self {
+            pos: min,
+            size: max - min,
+        };
+        (!rect.is_empty()).then_some(rect)
+    }
+
+    /// Smallest rectangle that contains both rectangles. Empty rectangles are ignored.
+    pub fn union(&self, other: &Rect) -> Rect {
+        match (self.is_empty(), other.is_empty()) {
+            (_, true) => *self,
+            (true, false) => *other,
+            (false, false) => {
+                let (max, other_max) = (self.max(), other.max());
+                Self::from_min_max(
+                    Pos2::new(self.pos.x.min(other.pos.x), self.pos.y.min(other.pos.y)),
+                    Pos2::new(max.x.max(other_max.x), max.y.max(other_max.y)),
+                )
+            }
+        }
+    }
+
+    /// The rectangle moved by an offset.
+    pub fn translate(&self, offset: Vec2) -> Rect {
+        Self {
+            pos: self.pos + offset,
+            size: self.size,
+        }
+    }
+
+    /// The rectangle grown by an amount on each side.
+    pub fn expand(&self, sides: Sides<f32>) -> Rect {
+        self.inset(sides.map(|v| -v))
+    }
+
+    /// The rectangle shrunk by an amount on each side. A rectangle shrunk past its center
+    /// collapses between its new edges.
+    pub fn inset(&self, sides: Sides<f32>) -> Rect {
+        let (min, max) = (self.min(), self.max());
+        let (left, right) = (min.x + sides.left, max.x - sides.right);
+        let (top, bottom) = (min.y + sides.top, max.y - sides.bottom);
+        let (x, width) = match left <= right {
+            true => (left, right - left),
+            false => ((left + right) / 2.0, 0.0),
+        };
+        let (y, height) = match top <= bottom {
+            true => (top, bottom - top),
+            false => ((top + bottom) / 2.0, 0.0),
+        };
+        Self {
+            pos: Pos2::new(x, y),
+            size: Vec2::new(width, height),
+        }
+    }
+}
+
+impl std::fmt::Display for Rect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} at ({}, {})",
+            self.size.x, self.size.y, self.pos.x, self.pos.y
+        )
+    }
+}
+
+/// `[x, y, width, height]`, to convert rectangles to and from other math libraries
+/// (`Pos2` and `Vec2` convert from and to `[f32; 2]` the same way).
+/// See the `mint` and `glam` features for direct conversions.
+///
+/// ```rust
+/// use dragonfly::{Pos2, Rect, Vec2};
+/// let rect = Rect::from_pos_size(Pos2::new(1.0, 2.0), Vec2::new(3.0, 4.0));
+/// let array: [f32; 4] = rect.into();
+/// assert_eq!(array, [1.0, 2.0, 3.0, 4.0]);
+/// assert_eq!(Rect::from(array), rect);
+/// let pos: [f32; 2] = rect.pos.into();
+/// assert_eq!(Pos2::from(pos), rect.pos);
+/// ```
+impl From<Rect> for [f32; 4] {
+    fn from(rect: Rect) -> Self {
+        [rect.pos.x, rect.pos.y, rect.size.x, rect.size.y]
+    }
+}
+
+impl From<[f32; 4]> for Rect {
+    fn from([x, y, width, height]: [f32; 4]) -> Self {
+        Self::from_pos_size(Pos2::new(x, y), Vec2::new(width, height))
+    }
+}
+
+/// Conversions to and from mint, for the geometry of layout nodes and display items.
+///
+/// A [`Rect`] converts from and to its top-left corner and its size.
+///
+/// ```rust
+/// use dragonfly::{Pos2, Rect, Vec2};
+/// let pos = Pos2::new(1.0, 2.0);
+/// let point: mint::Point2<f32> = pos.into();
+/// assert_eq!((point.x, point.y), (1.0, 2.0));
+/// assert_eq!(Pos2::from(point), pos);
+///
+/// let size = Vec2::new(3.0, 4.0);
+/// let vector: mint::Vector2<f32> = size.into();
+/// assert_eq!(Vec2::from(vector), size);
+///
+/// let rect = Rect::from_pos_size(pos, size);
+/// let (corner, extent): (mint::Point2<f32>, mint::Vector2<f32>) = rect.into();
+/// assert_eq!(Rect::from((corner, extent)), rect);
+/// ```
+#[cfg(feature = "mint")]
+mod mint_conversions {
+    use super::{Pos2, Rect, Vec2};
+
+    impl From<mint::Point2<f32>> for Pos2 {
+        fn from(p: mint::Point2<f32>) -> Self {
+            Self::new(p.x, p.y)
+        }
+    }
+
+    impl From<Pos2> for mint::Point2<f32> {
+        fn from(p: Pos2) -> Self {
+            Self { x: p.x, y: p.y }
+        }
+    }
+
+    impl From<mint::Vector2<f32>> for Vec2 {
+        fn from(v: mint::Vector2<f32>) -> Self {
+            Self::new(v.x, v.y)
+        }
+    }
+
+    impl From<Vec2> for mint::Vector2<f32> {
+        fn from(v: Vec2) -> Self {
+            Self { x: v.x, y: v.y }
+        }
+    }
+
+    impl From<(mint::Point2<f32>, mint::Vector2<f32>)> for Rect {
+        fn from((pos, size): (mint::Point2<f32>, mint::Vector2<f32>)) -> Self {
+            Self::from_pos_size(pos.into(), size.into())
+        }
+    }
+
+    impl From<Rect> for (mint::Point2<f32>, mint::Vector2<f32>) {
+        fn from(rect: Rect) -> Self {
+            (rect.pos.into(), rect.size.into())
+        }
+    }
+}
+
+/// Conversions to and from glam, for the geometry of layout nodes and display items.
+///
+/// Both [`Pos2`] and [`Vec2`] convert to `glam::Vec2`; a [`Rect`] converts from and to
+/// its top-left corner and its size.
+///
+/// ```rust
+/// use dragonfly::{Pos2, Rect, Vec2};
+/// let pos = Pos2::new(1.0, 2.0);
+/// let v: glam::Vec2 = pos.into();
+/// assert_eq!(v, glam::Vec2::new(1.0, 2.0));
+/// assert_eq!(Pos2::from(v), pos);
+///
+/// let size = Vec2::new(3.0, 4.0);
+/// assert_eq!(Vec2::from(glam::Vec2::from(size)), size);
+///
+/// let rect = Rect::from_pos_size(pos, size);
+/// let (corner, extent): (glam::Vec2, glam::Vec2) = rect.into();
+/// assert_eq!((corner, extent), (glam::Vec2::new(1.0, 2.0), glam::Vec2::new(3.0, 4.0)));
+/// assert_eq!(Rect::from((corner, extent)), rect);
+/// ```
+#[cfg(feature = "glam")]
+mod glam_conversions {
+    use super::{Pos2, Rect, Vec2};
+
+    impl From<glam::Vec2> for Pos2 {
+        fn from(v: glam::Vec2) -> Self {
+            Self::new(v.x, v.y)
+        }
+    }
+
+    impl From<Pos2> for glam::Vec2 {
+        fn from(p: Pos2) -> Self {
+            Self::new(p.x, p.y)
+        }
+    }
+
+    impl From<glam::Vec2> for Vec2 {
+        fn from(v: glam::Vec2) -> Self {
+            Self::new(v.x, v.y)
+        }
+    }
+
+    impl From<Vec2> for glam::Vec2 {
+        fn from(v: Vec2) -> Self {
+            Self::new(v.x, v.y)
+        }
+    }
+
+    impl From<(glam::Vec2, glam::Vec2)> for Rect {
+        fn from((pos, size): (glam::Vec2, glam::Vec2)) -> Self {
+            Self::from_pos_size(pos.into(), size.into())
+        }
+    }
+
+    impl From<Rect> for (glam::Vec2, glam::Vec2) {
+        fn from(rect: Rect) -> Self {
+            (rect.pos.into(), rect.size.into())
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Rect {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut rect = serializer.serialize_struct("Rect", 4)?;
+        rect.serialize_field("x", &self.pos.x)?;
+        rect.serialize_field("y", &self.pos.y)?;
+        rect.serialize_field("width", &self.size.x)?;
+        rect.serialize_field("height", &self.size.y)?;
+        rect.end()
+    }
+}
+
+/// A side of a box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+/// A value for each side of a box (margins, padding, border widths...), in the CSS order:
+/// top, right, bottom, left.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{Side, Sides};
+/// // the 1, 2, 3 and 4-value forms of the CSS shorthands
+/// assert_eq!(Sides::from_shorthand(&[1]), Some(Sides::new(1, 1, 1, 1)));
+/// assert_eq!(Sides::from_shorthand(&[1, 2]), Some(Sides::new(1, 2, 1, 2)));
+/// assert_eq!(Sides::from_shorthand(&[1, 2, 3]), Some(Sides::new(1, 2, 3, 2)));
+/// assert_eq!(Sides::from_shorthand(&[1, 2, 3, 4]), Some(Sides::new(1, 2, 3, 4)));
+/// assert_eq!(Sides::<i32>::from_shorthand(&[]), None);
+/// assert_eq!(Sides::from_shorthand(&[1, 2, 3, 4, 5]), None);
+///
+/// let mut sides = Sides::splat(0);
+/// sides.set(Side::Left, 4);
+/// sides.bottom = 2;
+/// assert_eq!(sides[Side::Left], 4);
+/// assert_eq!(sides.map(|v| v * 10), Sides::new(0, 0, 20, 40));
+/// assert_eq!(sides.into_iter().collect::<Vec<_>>(), [0, 0, 2, 4]);
+/// assert_eq!(sides.horizontal(), 4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Sides<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+impl<T> Sides<T> {
+    pub const fn new(top: T, right: T, bottom: T, left: T) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    /// The same value on every side.
+    pub fn splat(value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::new(value.clone(), value.clone(), value.clone(), value)
+    }
+
+    /// Expand the values of a CSS shorthand (`margin: 1px 2px 3px` for example): one value
+    /// applies to every side, two are the vertical and horizontal sides, three are the top,
+    /// the horizontal sides and the bottom, four are the top, right, bottom and left sides.
+    ///
+    /// Returns `None` if there are no values or more than four.
+    pub fn from_shorthand(values: &[T]) -> Option<Self>
+    where
+        T: Clone,
+    {
+        let value = |i: usize| values[i].clone();
+        match values.len() {
+            1 => Some(Self::splat(value(0))),
+            2 => Some(Self::new(value(0), value(1), value(0), value(1))),
+            3 => Some(Self::new(value(0), value(1), value(2), value(1))),
+            4 => Some(Self::new(value(0), value(1), value(2), value(3))),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, side: Side, value: T) {
+        self[side] = value;
+    }
+
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Sides<U> {
+        Sides::new(f(self.top), f(self.right), f(self.bottom), f(self.left))
+    }
+
+    /// Values in the CSS order (top, right, bottom, left).
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        [&self.top, &self.right, &self.bottom, &self.left].into_iter()
+    }
+
+    /// Mutable values in the CSS order (top, right, bottom, left).
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        [
+            &mut self.top,
+            &mut self.right,
+            &mut self.bottom,
+            &mut self.left,
+        ]
+        .into_iter()
+    }
+}
+
+impl<T: std::ops::Add<Output = T> + Copy> Sides<T> {
+    /// Sum of the left and right values.
+    #[inline]
+    pub fn horizontal(&self) -> T {
+        self.left + self.right
+    }
+
+    /// Sum of the top and bottom values.
+    #[inline]
+    pub fn vertical(&self) -> T {
+        self.top + self.bottom
+    }
+}
+
+impl<T> std::ops::Index<Side> for Sides<T> {
+    type Output = T;
+
+    fn index(&self, side: Side) -> &T {
+        match side {
+            Side::Top => &self.top,
+            Side::Right => &self.right,
+            Side::Bottom => &self.bottom,
+            Side::Left => &self.left,
+        }
+    }
+}
+
+impl<T> std::ops::IndexMut<Side> for Sides<T> {
+    fn index_mut(&mut self, side: Side) -> &mut T {
+        match side {
+            Side::Top => &mut self.top,
+            Side::Right => &mut self.right,
+            Side::Bottom => &mut self.bottom,
+            Side::Left => &mut self.left,
+        }
+    }
+}
+
+impl<T> IntoIterator for Sides<T> {
+    type Item = T;
+    type IntoIter = std::array::IntoIter<T, 4>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        [self.top, self.right, self.bottom, self.left].into_iter()
+    }
+}
+
+/// Resolve a URL from an attribute (`href`, `src`, `action`...) against a base URL, the way
+/// browsers do: leading and trailing ASCII whitespace is trimmed, tabs and newlines inside
+/// the URL are removed, and backslashes are slashes in `http`, `https` and `file` URLs.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{resolve_url, url::Url};
+/// let base = Url::parse("https://example.com/docs/page.html").unwrap();
+/// assert_eq!(resolve_url(&base, "../x").unwrap().as_str(), "https://example.com/x");
+/// assert_eq!(resolve_url(&base, "//cdn.example.com/x").unwrap().as_str(), "https://cdn.example.com/x");
+/// assert_eq!(resolve_url(&base, "#top").unwrap().as_str(), "https://example.com/docs/page.html#top");
+/// assert_eq!(resolve_url(&base, " \n a.html\t").unwrap().as_str(), "https://example.com/docs/a.html");
+/// assert_eq!(resolve_url(&base, "/im\nag\tes/a.png").unwrap().as_str(), "https://example.com/images/a.png");
+/// assert_eq!(resolve_url(&base, "\\\\cdn.example.com\\x").unwrap().as_str(), "https://cdn.example.com/x");
+/// assert!(resolve_url(&base, "https://[::1").is_err());
+/// ```
+pub fn resolve_url(base: &Url, raw: &str) -> Result<Url, UrlResolveError> {
+    Ok(base.join(raw.trim_matches(|c: char| c.is_ascii_whitespace()))?)
+}
+
+/// Whether a resource can be pulled from the URL (`javascript:`, `data:` or `mailto:` URLs can't).
+pub fn is_fetchable(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https" | "file")
+}
+
+/// Resolve a URL against a base URL like [`resolve_url`], failing with
+/// [`UrlResolveError::Unfetchable`] if it can't be fetched (see [`is_fetchable`]).
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::{resolve_fetchable_url, url::Url, UrlResolveError};
+/// let base = Url::parse("https://example.com/").unwrap();
+/// assert!(resolve_fetchable_url(&base, "style.css").is_ok());
+/// assert!(matches!(resolve_fetchable_url(&base, " javascript:void(0)"), Err(UrlResolveError::Unfetchable(_))));
+/// assert!(matches!(resolve_fetchable_url(&base, "data:,x"), Err(UrlResolveError::Unfetchable(_))));
+/// ```
+pub fn resolve_fetchable_url(base: &Url, raw: &str) -> Result<Url, UrlResolveError> {
+    let url = resolve_url(base, raw)?;
+    if !is_fetchable(&url) {
+        return Err(UrlResolveError::Unfetchable(url));
+    }
+    Ok(url)
+}
+
+/// Decode the `%XX` escapes of a URL component, invalid UTF-8 is replaced.
+///
+/// # Example
+///
+/// ```rust
+/// use dragonfly::percent_decode;
+/// assert_eq!(percent_decode("caf%C3%A9%20au%20lait"), "café au lait");
+/// assert_eq!(percent_decode("100%"), "100%");
+/// ```
+pub fn percent_decode(s: &str) -> std::borrow::Cow<'_, str> {
+    if !s.contains('%') {
+        return s.into();
+    }
+    String::from_utf8_lossy(&percent_decode_bytes(s))
+        .into_owned()
+        .into()
+}
+
+/// Decode the `%XX` escapes of a URL component to bytes.
+pub(crate) fn percent_decode_bytes(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| Some(hex(*bytes.get(i + 1)?)? * 16 + hex(*bytes.get(i + 2)?)?))
+            .flatten();
+        match escape {
+            Some(byte) => {
+                out.push(byte as u8);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
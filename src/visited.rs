@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// URLs of the visited pages, that `:visited` links are matched against.
+///
+/// Clones share the same URLs: a [`BrowserEngine`](crate::BrowserEngine) and the contexts it
+/// creates see the pages visited by each other. With the `serde` feature, the URLs can be
+/// persisted (they're serialized as a sorted list of strings).
+///
+/// ```
+/// use dragonfly::VisitedLinks;
+/// use url::Url;
+/// let visited = VisitedLinks::default();
+/// visited.mark(&Url::parse("https://example.com:443/page#section").unwrap());
+/// assert!(visited.contains(&Url::parse("https://example.com/page").unwrap()));
+/// assert!(!visited.contains(&Url::parse("https://example.com/other").unwrap()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VisitedLinks {
+    urls: Arc<Mutex<HashSet<String>>>,
+}
+
+impl VisitedLinks {
+    /// Key of a URL in the store: the fragment is stripped. Default ports (`:443` for `https`)
+    /// are already removed when URLs are parsed, so equivalent URLs have the same key.
+    pub fn normalize(url: &Url) -> String {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        url.into()
+    }
+
+    /// Mark a URL as visited.
+    pub fn mark(&self, url: &Url) {
+        self.urls.lock().unwrap().insert(Self::normalize(url));
+    }
+
+    /// Whether a URL was visited.
+    pub fn contains(&self, url: &Url) -> bool {
+        self.urls.lock().unwrap().contains(&Self::normalize(url))
+    }
+
+    /// Visited URLs (normalized), sorted.
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls: Vec<String> = self.urls.lock().unwrap().iter().cloned().collect();
+        urls.sort();
+        urls
+    }
+
+    pub fn len(&self) -> usize {
+        self.urls.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all visited URLs.
+    pub fn clear(&self) {
+        self.urls.lock().unwrap().clear();
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for VisitedLinks {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.urls())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for VisitedLinks {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let urls: Vec<String> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Self {
+            urls: Arc::new(Mutex::new(urls.into_iter().collect())),
+        })
+    }
+}
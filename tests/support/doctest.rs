@@ -0,0 +1,104 @@
+// Helpers shared by the doctests, included with
+// `# include!("../tests/support/doctest.rs");` (paths are relative to the source file).
+#[allow(dead_code)]
+mod support {
+    use std::future::Future;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    /// Run a future to completion on a single-threaded runtime.
+    pub fn block_on<F: Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    /// A response of [`serve`].
+    #[derive(Clone)]
+    pub struct Response {
+        pub status: u16,
+        pub headers: Vec<(&'static str, String)>,
+        pub body: Vec<u8>,
+        /// Time to wait before answering
+        pub delay: Duration,
+    }
+
+    impl Response {
+        pub fn ok(content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+            Self {
+                status: 200,
+                headers: vec![("Content-Type", content_type.to_string())],
+                body: body.into(),
+                delay: Duration::ZERO,
+            }
+        }
+
+        pub fn status(status: u16) -> Self {
+            Self {
+                status,
+                headers: vec![("Content-Type", "text/html".to_string())],
+                body: format!("<h1>error {status}</h1>").into_bytes(),
+                delay: Duration::ZERO,
+            }
+        }
+
+        pub fn redirect(location: &str) -> Self {
+            Self {
+                status: 302,
+                headers: vec![("Location", location.to_string())],
+                body: vec![],
+                delay: Duration::ZERO,
+            }
+        }
+
+        pub fn delayed(self, delay: Duration) -> Self {
+            Self { delay, ..self }
+        }
+    }
+
+    /// Serve HTTP/1.1 on a local port from a background thread, until the test exits.
+    /// `route` gives the response of a request path (with its query); paths it returns
+    /// `None` for are answered with a 404. Returns the base URL of the server, with a
+    /// trailing slash.
+    pub fn serve(route: impl Fn(&str) -> Option<Response> + Send + Sync + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/", listener.local_addr().unwrap());
+        let route = std::sync::Arc::new(route);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let route = route.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).ok();
+                    // skip the headers
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).is_ok_and(|len| len > 2) {
+                        line.clear();
+                    }
+                    let path = request_line.split(' ').nth(1).unwrap_or("/");
+                    let response = route(path).unwrap_or_else(|| Response::status(404));
+                    std::thread::sleep(response.delay);
+                    let mut head = format!(
+                        "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        response.status,
+                        response.body.len()
+                    );
+                    for (name, value) in &response.headers {
+                        head.push_str(&format!("{name}: {value}\r\n"));
+                    }
+                    head.push_str("\r\n");
+                    stream.write_all(head.as_bytes()).ok();
+                    stream.write_all(&response.body).ok();
+                });
+            }
+        });
+        base
+    }
+}
+#[allow(unused_imports)]
+use support::*;